@@ -0,0 +1,95 @@
+//! Local cache for values the CLI computes itself
+//!
+//! Lives in the user's cache directory, separate from forScore's database,
+//! so it can be deleted at any time and is rebuilt on demand.
+
+use crate::db::documents_path;
+use crate::error::{ForScoreError, Result};
+use crate::models::score::{score_page_count, Score};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Get the path to the cache database
+pub fn cache_db_path() -> Result<PathBuf> {
+    let dir = dirs::cache_dir()
+        .ok_or_else(|| ForScoreError::Other("Cannot find cache directory".into()))?;
+    Ok(dir.join("forscore-cli").join("cache.sqlite"))
+}
+
+/// Open (creating if needed) the cache database
+pub fn open_cache() -> Result<Connection> {
+    let path = cache_db_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let conn = Connection::open(&path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS page_counts (
+            path TEXT PRIMARY KEY,
+            size INTEGER NOT NULL,
+            mtime INTEGER NOT NULL,
+            pages INTEGER NOT NULL
+        )",
+    )?;
+    Ok(conn)
+}
+
+/// Count the pages in a PDF, reusing the cached count while the file's size and mtime match
+pub fn pdf_page_count(cache: &Connection, pdf: &Path) -> Option<i32> {
+    let meta = std::fs::metadata(pdf).ok()?;
+    let size = meta.len() as i64;
+    let mtime = meta
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs() as i64;
+    let key = pdf.to_string_lossy();
+
+    let cached = cache
+        .query_row(
+            "SELECT pages FROM page_counts WHERE path = ? AND size = ? AND mtime = ?",
+            rusqlite::params![key, size, mtime],
+            |row| row.get(0),
+        )
+        .ok();
+    if cached.is_some() {
+        return cached;
+    }
+
+    let pages = lopdf::Document::load(pdf).ok()?.get_pages().len() as i32;
+    // A failed cache write only means counting again next time
+    let _ = cache.execute(
+        "INSERT OR REPLACE INTO page_counts (path, size, mtime, pages) VALUES (?, ?, ?, ?)",
+        rusqlite::params![key, size, mtime, pages],
+    );
+    Some(pages)
+}
+
+/// Fill in `pages` for each item: bookmarks from their page range, scores from
+/// the PDF (cached), falling back to the pages forScore has recorded
+pub fn load_page_counts(conn: &Connection, scores: &mut [Score]) -> Result<()> {
+    let cache = open_cache().ok();
+    let documents = documents_path().ok();
+
+    for score in scores.iter_mut() {
+        if let (Some(start), Some(end)) = (score.start_page, score.end_page) {
+            score.pages = Some(end - start + 1);
+            continue;
+        }
+
+        let from_pdf = match (&cache, &documents) {
+            (Some(cache), Some(documents)) => pdf_page_count(cache, &documents.join(&score.path)),
+            _ => None,
+        };
+
+        score.pages = match from_pdf {
+            Some(pages) => Some(pages),
+            None => score_page_count(conn, score)?,
+        };
+    }
+
+    Ok(())
+}
@@ -1,10 +1,37 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "forscore")]
 #[command(version)]
 #[command(about = "CLI tool for managing forScore metadata", long_about = None)]
 pub struct Cli {
+    /// Use this library.4sl instead of forScore's (e.g. a copy on Linux or Windows)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub db: Option<PathBuf>,
+    /// Use this Sync folder (default: the Sync folder next to the database)
+    #[arg(long, global = true, value_name = "DIR")]
+    pub sync_dir: Option<PathBuf>,
+    /// Seconds to keep retrying while the database is locked, e.g. by forScore
+    /// saving or another forscore-cli command (default 5)
+    #[arg(long, global = true, value_name = "SECS")]
+    pub wait: Option<u64>,
+    /// Output format for listings (a command's --json still means JSON)
+    #[arg(long, global = true, value_enum, default_value = "table")]
+    pub format: OutputFormat,
+    /// Print each listed item as a line of text with {field} placeholders,
+    /// e.g. '{title} — {composer} ({key})'
+    #[arg(
+        long,
+        global = true,
+        value_name = "TEMPLATE",
+        conflicts_with = "format"
+    )]
+    pub template: Option<String>,
+    /// Report time spent opening the database, per SQL statement, on ITM
+    /// files and rendering (on stderr, after the command)
+    #[arg(long, global = true)]
+    pub profile: bool,
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -41,6 +68,11 @@ pub enum Commands {
         #[command(subcommand)]
         command: TagsCommand,
     },
+    /// Copy genres, tags, labels and difficulty levels between libraries
+    Meta {
+        #[command(subcommand)]
+        command: MetaCommand,
+    },
     /// Export data
     Export {
         #[command(subcommand)]
@@ -56,8 +88,51 @@ pub enum Commands {
         #[command(subcommand)]
         command: BookmarksCommand,
     },
+    /// Audio linked to scores (tracks and recordings)
+    Tracks {
+        #[command(subcommand)]
+        command: TracksCommand,
+    },
+    /// Deleted scores, bookmarks and setlists, restorable for 30 days
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+    /// Find scores, bookmarks, setlists, composers and libraries by name
+    Find {
+        /// Search query
+        query: String,
+        /// Maximum results per type
+        #[arg(long, default_value = "25")]
+        limit: usize,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Show library statistics
-    Info,
+    ///
+    /// With --min-complete (or a per-field minimum), exits with an error when
+    /// fewer scores than that percentage have a rating, key or composer.
+    Info {
+        /// Show where the database, Sync folder and PDFs were found, and why
+        #[arg(long, conflicts_with_all = ["json", "min_complete", "min_rating", "min_key", "min_composer"])]
+        paths: bool,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Minimum percentage of scores with a rating, a key and a composer
+        #[arg(long, value_name = "PERCENT")]
+        min_complete: Option<f64>,
+        /// Minimum percentage of scores with a rating (overrides --min-complete)
+        #[arg(long, value_name = "PERCENT")]
+        min_rating: Option<f64>,
+        /// Minimum percentage of scores with a key (overrides --min-complete)
+        #[arg(long, value_name = "PERCENT")]
+        min_key: Option<f64>,
+        /// Minimum percentage of scores with a composer (overrides --min-complete)
+        #[arg(long, value_name = "PERCENT")]
+        min_composer: Option<f64>,
+    },
     /// Backup the database
     Backup {
         /// Output file path
@@ -74,6 +149,179 @@ pub enum Commands {
         #[command(subcommand)]
         command: FixesCommand,
     },
+    /// Work with ITM sync files directly
+    Itm {
+        #[command(subcommand)]
+        command: ItmCommand,
+    },
+    /// Build test libraries for trying out commands and scripts
+    Fixture {
+        #[command(subcommand)]
+        command: FixtureCommand,
+    },
+    /// Copy metadata, bookmarks and setlists from another forScore library
+    ///
+    /// Scores are matched by UUID, then by path, then by PDF contents (when
+    /// the other library's Documents folder sits next to its Library folder,
+    /// as in a copied forScore container). Only fields that are empty here
+    /// are filled in; bookmarks and setlists are added when missing. The
+    /// changes are made in one transaction.
+    Migrate {
+        /// The other library's database (library.4sl)
+        #[arg(long)]
+        from: PathBuf,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Short names for scores, bookmarks and setlists, usable anywhere an
+    /// identifier is (stored in config.toml under [aliases])
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommand,
+    },
+    /// Read or write a single database column directly, for fields the
+    /// other commands don't cover yet
+    ///
+    /// Any column can be read. Only columns known to be safe to change can be
+    /// written (see `raw columns`); writes mark the row modified and update
+    /// the ITM file where the value is mirrored there.
+    Raw {
+        #[command(subcommand)]
+        command: RawCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RawCommand {
+    /// Print one column of one row
+    Get {
+        /// Table, e.g. ZITEM
+        table: String,
+        /// Row's primary key (Z_PK)
+        pk: i64,
+        /// Column, e.g. ZBPM
+        column: String,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Change one column of one row
+    Set {
+        /// Table, e.g. ZITEM
+        table: String,
+        /// Row's primary key (Z_PK)
+        pk: i64,
+        /// Column, e.g. ZBPM
+        column: String,
+        /// New value
+        #[arg(required_unless_present = "null")]
+        value: Option<String>,
+        /// Set the column to NULL
+        #[arg(long, conflicts_with = "value")]
+        null: bool,
+        /// Preview the change without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// List the columns `raw set` can write
+    Columns,
+}
+
+#[derive(Subcommand)]
+pub enum TrashCommand {
+    /// List deleted items, newest first
+    Ls {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Put a deleted item back with its setlist entries, metadata links and
+    /// files
+    ///
+    /// Links to setlists, libraries or metadata deleted since are left out.
+    Restore {
+        /// Trash ID, as shown by `trash ls`
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TracksCommand {
+    /// Cross-check linked audio: scores with none, linked files missing from
+    /// disk, and audio files in the Sync folder nothing links to
+    ///
+    /// Tracks from the Music library have no file and aren't checked. Files
+    /// are looked for in the Sync folder and in Documents.
+    Report {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AliasCommand {
+    /// List aliases and what they stand for
+    Ls {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add or change an alias
+    Set {
+        /// The short name, e.g. bb5
+        name: String,
+        /// Score, bookmark or setlist it stands for (ID, path, or title)
+        target: String,
+    },
+    /// Remove an alias
+    Remove {
+        /// The short name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum FixtureCommand {
+    /// Write a small synthetic library with forScore's schema, or with
+    /// --anonymize a copy of the current library with every title, name,
+    /// path and note replaced by a placeholder
+    ///
+    /// Only the database is written; PDFs and sync files aren't. Point the
+    /// CLI at the result with --db.
+    Create {
+        /// Output .4sl file
+        output: String,
+        /// Number of scores to generate
+        #[arg(long, default_value = "10", conflicts_with = "anonymize")]
+        scores: usize,
+        /// Copy and scrub the current library (or --db) instead of generating one
+        #[arg(long)]
+        anonymize: bool,
+        /// Replace the output file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ItmCommand {
+    /// Convert ITM files to readable JSON (one file per score), e.g. to track them in git
+    Dump {
+        /// Directory to write the JSON files to
+        #[arg(long)]
+        out: PathBuf,
+        /// Only dump ITMs of scores matching these filters (default: every ITM file)
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
 }
 
 #[derive(Subcommand)]
@@ -86,6 +334,29 @@ pub enum SyncCommand {
     },
     /// Trigger a sync (requires accessibility permissions)
     Trigger,
+    /// List every file recorded in .syncFolderState with its size, sync
+    /// time and type, sorted by path
+    ///
+    /// With --format csv or json, the output can be saved and diffed over
+    /// time or joined against `export csv`.
+    ExportState {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List scores whose ITM file was modified well after their database
+    /// row: edits synced from another device that forScore hasn't imported
+    ///
+    /// Worth checking before bulk edits, which would write the database's
+    /// older values back over them.
+    StaleDb {
+        /// Seconds the ITM must be ahead of the database to count
+        #[arg(long, default_value = "60", value_name = "SECONDS")]
+        threshold: i64,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -110,6 +381,42 @@ pub enum ScoresCommand {
         /// Only show scores (exclude bookmarks)
         #[arg(long)]
         scores_only: bool,
+        /// Only show favorites
+        #[arg(long)]
+        favorites: bool,
+        #[command(flatten)]
+        dates: DateArgs,
+        /// Show a Libraries column
+        #[arg(long)]
+        libraries: bool,
+        #[command(flatten)]
+        columns: ColumnArgs,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Scores added, played or modified lately, newest first
+    ///
+    /// Shorthand for `scores ls --sort <field> --desc` with a date filter.
+    Recent {
+        /// By when they were added (the default)
+        #[arg(long, conflicts_with_all = ["played", "modified"])]
+        added: bool,
+        /// By when they were last played
+        #[arg(long, conflicts_with = "modified")]
+        played: bool,
+        /// By when they were last modified
+        #[arg(long)]
+        modified: bool,
+        /// How many days back to look
+        #[arg(long, default_value = "14")]
+        days: u32,
+        /// Limit number of results
+        #[arg(long, default_value = "25")]
+        limit: usize,
+        /// Only show scores (exclude bookmarks)
+        #[arg(long)]
+        scores_only: bool,
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -118,42 +425,131 @@ pub enum ScoresCommand {
     Search {
         /// Search query (matches title or composer)
         query: Option<String>,
-        /// Search by title only
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Full-text search: ranked results, words matched in any order and
+        /// as prefixes, across title, composer, genre, tags and labels
+        #[arg(long, requires = "query")]
+        fts: bool,
+        /// Run a search saved under [searches] in config.toml (a query
+        /// expression, as for `scores query`); other filters narrow it
+        #[arg(long, value_name = "NAME")]
+        saved: Option<String>,
+        /// Limit number of results
+        #[arg(long, default_value = "25")]
+        limit: usize,
+        /// Show a Libraries column
         #[arg(long)]
-        title: Option<String>,
-        /// Search by composer
+        libraries: bool,
+        #[command(flatten)]
+        columns: ColumnArgs,
+        /// Output as JSON
         #[arg(long)]
-        composer: Option<String>,
-        /// Search by genre
+        json: bool,
+    },
+    /// Search with a query expression, e.g.
+    /// 'rating>=4 AND key="E Minor" AND NOT genre:Baroque'
+    ///
+    /// Fields: title, composer, genre, tag, label, library, key, rating,
+    /// difficulty, bpm, added, modified, played. Operators: = != < <= > >=
+    /// and : (contains). Combine with AND, OR, NOT and parentheses. Dates
+    /// are YYYY, YYYY-MM or YYYY-MM-DD (added>=2024-06 AND played<2025),
+    /// today, yesterday or "N days ago" (played>="2 weeks ago"); "none"
+    /// matches an unset field (composer=none).
+    Query {
+        /// Query expression
+        expr: String,
+        /// Limit number of results
+        #[arg(long, default_value = "25")]
+        limit: usize,
+        /// Show a Libraries column
         #[arg(long)]
-        genre: Option<String>,
-        /// Search by key (e.g., "C Major", "F# Minor")
+        libraries: bool,
+        #[command(flatten)]
+        columns: ColumnArgs,
+        /// Output as JSON
         #[arg(long)]
-        key: Option<String>,
-        /// Find items with no key set
+        json: bool,
+    },
+    /// Remap difficulty levels in bulk, e.g. after changing a grading scheme
+    RecalibrateDifficulty {
+        /// Old level to new level for each level that changes (e.g. "1=2,2=3,3=4");
+        /// unlisted levels are left alone
         #[arg(long)]
-        no_key: bool,
-        /// Filter by minimum rating (1-6)
+        map: String,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Preview changes without applying
         #[arg(long)]
-        rating: Option<i32>,
-        /// Find items with no rating set
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Convert ratings from one rating scale to another
+    ConvertRatings {
+        /// Scale the current ratings use (e.g. 5 for 1-5 stars)
         #[arg(long)]
-        no_rating: bool,
-        /// Filter by difficulty (1-5)
+        from_scale: i32,
+        /// Scale to convert to (at most 6, forScore's scale)
+        #[arg(long, default_value = "6")]
+        to_scale: i32,
+        /// Explicit mapping instead of linear scaling (e.g. "1=1,2=2,3=4,4=5,5=6")
         #[arg(long)]
-        difficulty: Option<i32>,
-        /// Limit number of results
-        #[arg(long, default_value = "25")]
-        limit: usize,
-        /// Only show scores (exclude bookmarks)
+        map: Option<String>,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Preview changes without applying
         #[arg(long)]
-        scores_only: bool,
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Group scores by key, ordered around the circle of fifths
+    ByKey {
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Only minor keys
+        #[arg(long, conflicts_with = "major")]
+        minor: bool,
+        /// Only major keys
+        #[arg(long)]
+        major: bool,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List scores and bookmarks that appear in multiple setlists
+    Shared {
+        /// Minimum number of setlists an item must belong to
+        #[arg(long, default_value = "2")]
+        min: usize,
         /// Output as JSON
         #[arg(long)]
         json: bool,
     },
     /// Show detailed info for a score
     Show {
+        /// Score ID, path, or title
+        identifier: String,
+        /// Dump every database column and ITM key, including ones the CLI
+        /// doesn't otherwise use (e.g. forScore Pro settings)
+        #[arg(long)]
+        all_fields: bool,
+        /// Compare each field between the database and the ITM file, and
+        /// show which was modified more recently
+        #[arg(long, conflicts_with = "all_fields")]
+        provenance: bool,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List every setlist and library a score is in, e.g. before deleting
+    /// or renaming it
+    ///
+    /// Setlists that hold one of the score's bookmarks are listed too.
+    Memberships {
         /// Score ID, path, or title
         identifier: String,
         /// Output as JSON
@@ -187,19 +583,454 @@ pub enum ScoresCommand {
         /// Set difficulty (1-5)
         #[arg(long)]
         difficulty: Option<i32>,
+        /// Remove the key
+        #[arg(long, conflicts_with = "key")]
+        clear_key: bool,
+        /// Remove the rating
+        #[arg(long, conflicts_with = "rating")]
+        clear_rating: bool,
+        /// Remove the difficulty
+        #[arg(long, conflicts_with = "difficulty")]
+        clear_difficulty: bool,
+        /// Remove all composers
+        #[arg(long, conflicts_with_all = ["composer", "add_composer"])]
+        clear_composer: bool,
+        /// Remove all genres
+        #[arg(long, conflicts_with_all = ["genre", "add_genre"])]
+        clear_genre: bool,
+        /// Remove all tags
+        #[arg(long, conflicts_with_all = ["tags", "add_tag"])]
+        clear_tags: bool,
+        /// Remove all labels
+        #[arg(long, conflicts_with = "add_label")]
+        clear_labels: bool,
         /// Set tags (comma-separated)
         #[arg(long)]
         tags: Option<String>,
+        /// Add a composer, keeping the others (repeatable)
+        #[arg(long)]
+        add_composer: Vec<String>,
+        /// Remove a composer, keeping the others (repeatable)
+        #[arg(long)]
+        remove_composer: Vec<String>,
+        /// Add a genre, keeping the others (repeatable)
+        #[arg(long)]
+        add_genre: Vec<String>,
+        /// Remove a genre, keeping the others (repeatable)
+        #[arg(long)]
+        remove_genre: Vec<String>,
+        /// Add a tag, keeping the others (repeatable)
+        #[arg(long)]
+        add_tag: Vec<String>,
+        /// Remove a tag, keeping the others (repeatable)
+        #[arg(long)]
+        remove_tag: Vec<String>,
+        /// Add a label, keeping the others (repeatable)
+        #[arg(long)]
+        add_label: Vec<String>,
+        /// Remove a label, keeping the others (repeatable)
+        #[arg(long)]
+        remove_label: Vec<String>,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Add a PDF to the library (copied into forScore's Documents folder)
+    Add {
+        /// PDF file to add
+        pdf: PathBuf,
+        /// Title (default: the file name without .pdf)
+        #[arg(long)]
+        title: Option<String>,
+        /// Composer
+        #[arg(long)]
+        composer: Option<String>,
+        /// Genre
+        #[arg(long)]
+        genre: Option<String>,
+        /// Key (e.g., "C Major", "F# Minor")
+        #[arg(long)]
+        key: Option<String>,
+        /// Rating (1-6)
+        #[arg(long)]
+        rating: Option<i32>,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Delete a score with its bookmarks, pages and setlist entries
+    Delete {
+        /// Score ID, path, or title
+        identifier: String,
+        /// Also delete the PDF and its .itm sync file
+        #[arg(long)]
+        with_files: bool,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Retire scores into an archive library, removing them from their other
+    /// libraries and setlists (remembered for unarchive)
+    Archive {
+        /// Score ID, path, or title (or use filters)
+        identifier: Option<String>,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Library to archive into (created if needed)
+        #[arg(long, default_value = "Archive")]
+        archive_library: String,
         /// Preview changes without applying
         #[arg(long)]
         dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Restore archived scores to the libraries and setlists they were in
+    Unarchive {
+        /// Score ID, path, or title (or use filters)
+        identifier: Option<String>,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Library the scores were archived into
+        #[arg(long, default_value = "Archive")]
+        archive_library: String,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Edit every score matching a search, after previewing and confirming
+    #[command(group(ArgGroup::new("set").required(true).multiple(true).args(
+        ["set_composer", "set_genre", "set_key", "set_rating", "set_difficulty"]
+    )))]
+    BulkEdit {
+        /// Search query (matches title or composer)
+        query: Option<String>,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Set composer
+        #[arg(long)]
+        set_composer: Option<String>,
+        /// Set genre
+        #[arg(long)]
+        set_genre: Option<String>,
+        /// Set key (e.g., "C Major", "F# Minor")
+        #[arg(long)]
+        set_key: Option<String>,
+        /// Set rating (1-6)
+        #[arg(long)]
+        set_rating: Option<i32>,
+        /// Set difficulty (1-5)
+        #[arg(long)]
+        set_difficulty: Option<i32>,
+        /// Apply without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+        /// Preview changes without applying
+        #[arg(long, conflicts_with = "yes")]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Step through matching scores one at a time, rating, grading and
+    /// tagging each with single keystrokes
+    ///
+    /// Keys: 1-6 rate, d then 1-5 difficulty, o open in forScore, Enter or
+    /// right arrow next, p or left arrow back, q quit. Each change is written
+    /// as soon as its key is pressed.
+    Review {
+        /// Search query (matches title or composer)
+        query: Option<String>,
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// A key that toggles a tag, e.g. w=Warmup (repeatable)
+        #[arg(long = "tag-key", value_name = "KEY=TAG")]
+        tag_keys: Vec<String>,
+        /// Open each score in forScore as it comes up
+        #[arg(long)]
+        open: bool,
+    },
+    /// Mark or unmark a score as a favorite (flags its first page in forScore)
+    #[command(group(ArgGroup::new("flag").required(true).args(["favorite", "unfavorite"])))]
+    Flag {
+        /// Score ID, path, or title
+        identifier: String,
+        /// Mark as a favorite
+        #[arg(long)]
+        favorite: bool,
+        /// Remove from favorites
+        #[arg(long)]
+        unfavorite: bool,
+    },
+    /// Show or edit a score's notes
+    Notes {
+        #[command(subcommand)]
+        command: NotesCommand,
+    },
+    /// Work groups: scores that belong together as one work (orchestra
+    /// parts, movements in separate PDFs), marked by a "Work/<name>" tag
+    Group {
+        #[command(subcommand)]
+        command: GroupCommand,
+    },
+}
+
+/// A score's note is the note on its first page (a bookmark's, on its start page)
+#[derive(Subcommand)]
+pub enum NotesCommand {
+    /// Show the note, plus notes on other pages
+    Show {
+        /// Score ID, path, or title
+        identifier: String,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Replace the note (an empty string clears it)
+    Set {
+        /// Score ID, path, or title
+        identifier: String,
+        /// Note text
+        text: String,
+    },
+    /// Add a line to the end of the note
+    Append {
+        /// Score ID, path, or title
+        identifier: String,
+        /// Text to add
+        text: String,
     },
 }
 
+#[derive(Subcommand)]
+pub enum GroupCommand {
+    /// List work groups
+    Ls {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Create a work group from scores
+    Create {
+        /// Name of the work, e.g. "Symphony No. 5"
+        name: String,
+        /// Scores to put in it (ID, path, or title)
+        #[arg(long, required = true, num_args = 1..)]
+        add: Vec<String>,
+    },
+    /// Show the scores in a work group
+    Show {
+        /// Group name
+        name: String,
+        #[command(flatten)]
+        columns: ColumnArgs,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add scores to a work group
+    Add {
+        /// Group name
+        name: String,
+        /// Score ID, path, or title
+        #[arg(required = true)]
+        scores: Vec<String>,
+    },
+    /// Take scores out of a work group
+    Remove {
+        /// Group name
+        name: String,
+        /// Score ID, path, or title
+        #[arg(required = true)]
+        scores: Vec<String>,
+    },
+}
+
+/// Output formats for `--format`
+#[derive(Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Tables and plain text
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    /// Comma-separated, with a header row
+    Csv,
+    /// Tab-separated, with a header row
+    Tsv,
+    /// One JSON object per line (jq and log tools read it a line at a time);
+    /// `scores ls` and `scores search` stream it as rows are read
+    Ndjson,
+}
+
+/// How `import csv` finds the score for each row
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum CsvMatch {
+    /// The `id` column (Z_PK), which only holds on the device that exported
+    Id,
+    /// The `uuid` column, which is the same on every device
+    Uuid,
+    /// The `path` column (the PDF's file name)
+    Path,
+    /// The `title` column; titles shared by several scores don't match
+    Title,
+}
+
+/// Built-in layouts for `setlists export`
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ProgramFormat {
+    /// Aligned plain text, for email
+    Txt,
+    /// A Markdown table
+    Md,
+    /// Comma-separated, with a header row
+    Csv,
+}
+
+/// Display modes for `setlists show`
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SetlistFormat {
+    Compact,
+    Detailed,
+}
+
+/// Filters shared by commands that select scores
+#[derive(Args, Clone, Default)]
+pub struct FilterArgs {
+    /// Search by title only
+    #[arg(long)]
+    pub title: Option<String>,
+    /// Search by composer
+    #[arg(long)]
+    pub composer: Option<String>,
+    /// Search by genre
+    #[arg(long)]
+    pub genre: Option<String>,
+    /// Search by tag (a parent tag also matches nested tags)
+    #[arg(long)]
+    pub tag: Option<String>,
+    /// Search by key (e.g., "C Major", "F# Minor")
+    #[arg(long)]
+    pub key: Option<String>,
+    /// Find items with no key set
+    #[arg(long)]
+    pub no_key: bool,
+    /// Filter by minimum rating (1-6)
+    #[arg(long)]
+    pub rating: Option<i32>,
+    /// Find items with no rating set
+    #[arg(long)]
+    pub no_rating: bool,
+    /// Filter by difficulty (1-5)
+    #[arg(long)]
+    pub difficulty: Option<i32>,
+    /// Only search within a library (name or ID)
+    #[arg(long)]
+    pub library: Option<String>,
+    /// Only show scores (exclude bookmarks)
+    #[arg(long)]
+    pub scores_only: bool,
+    /// Only show favorites
+    #[arg(long)]
+    pub favorites: bool,
+    #[command(flatten)]
+    pub dates: DateArgs,
+}
+
+impl FilterArgs {
+    /// Whether no filter was given
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.composer.is_none()
+            && self.genre.is_none()
+            && self.tag.is_none()
+            && self.key.is_none()
+            && !self.no_key
+            && self.rating.is_none()
+            && !self.no_rating
+            && self.difficulty.is_none()
+            && self.library.is_none()
+            && !self.scores_only
+            && !self.favorites
+            && self.dates.is_empty()
+    }
+}
+
+/// Table columns for commands that list scores
+#[derive(Args, Clone, Default)]
+pub struct ColumnArgs {
+    /// Table columns, comma-separated: id, title, composer, genre, key,
+    /// rating, difficulty, bpm, pages, tags, labels, libraries, path, added,
+    /// modified, played (default: [output] columns in config.toml, else
+    /// id,title,composer,key,rating,pages)
+    #[arg(long, value_name = "LIST")]
+    pub columns: Option<String>,
+    /// Add Added, Modified and Played columns
+    #[arg(long)]
+    pub show_dates: bool,
+}
+
+/// Filters on when items were added, modified and last played. Dates are
+/// YYYY-MM-DD (or YYYY-MM, YYYY), today, yesterday or "N days ago" (or
+/// weeks, months, years)
+#[derive(Args, Clone, Default)]
+pub struct DateArgs {
+    /// Only items added on or after a date (e.g. "2 weeks ago")
+    #[arg(long, value_name = "DATE")]
+    pub added_since: Option<String>,
+    /// Only items added before a date
+    #[arg(long, value_name = "DATE")]
+    pub added_before: Option<String>,
+    /// Only items modified on or after a date
+    #[arg(long, value_name = "DATE")]
+    pub modified_since: Option<String>,
+    /// Only items modified before a date (e.g. 2024-01-01)
+    #[arg(long, value_name = "DATE")]
+    pub modified_before: Option<String>,
+    /// Only items last played on or after a date
+    #[arg(long, value_name = "DATE")]
+    pub played_since: Option<String>,
+    /// Only items last played before a date
+    #[arg(long, value_name = "DATE")]
+    pub played_before: Option<String>,
+    /// Only items that have never been played
+    #[arg(long, conflicts_with_all = ["played_since", "played_before"])]
+    pub never_played: bool,
+}
+
+impl DateArgs {
+    /// Whether no date filter was given
+    pub fn is_empty(&self) -> bool {
+        self.added_since.is_none()
+            && self.added_before.is_none()
+            && self.modified_since.is_none()
+            && self.modified_before.is_none()
+            && self.played_since.is_none()
+            && self.played_before.is_none()
+            && !self.never_played
+    }
+}
+
 #[derive(Subcommand)]
 pub enum SetlistsCommand {
     /// List all setlists
     Ls {
+        /// Show menu order and the index/sort fields, in menu order
+        #[arg(long, short)]
+        verbose: bool,
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -208,14 +1039,38 @@ pub enum SetlistsCommand {
     Show {
         /// Setlist ID or name
         identifier: String,
+        /// Display mode: compact (one line per item) or detailed (composer, pages, duration, notes)
+        #[arg(long, value_enum)]
+        layout: Option<SetlistFormat>,
+        #[command(flatten)]
+        columns: ColumnArgs,
         /// Output as JSON
         #[arg(long)]
         json: bool,
     },
+    /// Export a setlist's running order (title, composer, key, pages) as
+    /// text, Markdown or CSV, or render it through a Handlebars template
+    /// (e.g. a concert program)
+    Export {
+        /// Setlist ID or name
+        identifier: String,
+        /// Built-in layout: txt, md or csv
+        #[arg(long, value_enum, required_unless_present = "template")]
+        layout: Option<ProgramFormat>,
+        /// Template file (.hbs); HTML-escaped only if its name contains ".htm"
+        #[arg(long, conflicts_with = "layout")]
+        template: Option<String>,
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
     /// Create a new setlist
     Create {
         /// Setlist name
         name: String,
+        /// Fill the setlist with the results of a saved search
+        #[arg(long, value_name = "SEARCH")]
+        from_saved: Option<String>,
     },
     /// Rename a setlist
     Rename {
@@ -229,12 +1084,42 @@ pub enum SetlistsCommand {
         /// Setlist ID or name
         identifier: String,
     },
+    /// Mark a setlist as played now (e.g. after a gig)
+    Play {
+        /// Setlist ID or name
+        identifier: String,
+        /// Also open the first item in forScore
+        #[arg(long)]
+        open: bool,
+    },
+    /// Open a setlist in forScore, optionally at one of its items
+    Open {
+        /// Setlist ID or name
+        identifier: String,
+        /// Jump to this item (1 = first), as numbered by `setlists show`
+        #[arg(long, value_name = "N")]
+        item: Option<usize>,
+    },
     /// Add a score to a setlist
     AddScore {
         /// Setlist ID or name
         setlist: String,
         /// Score ID, path, or title
         score: String,
+        /// Add even if the setlist already has the same piece under another
+        /// entry (the full score vs one of its bookmarks, or a duplicate score)
+        #[arg(long)]
+        allow_duplicate: bool,
+    },
+    /// Add every score in a work group to a setlist, in title order
+    AddGroup {
+        /// Setlist ID or name
+        setlist: String,
+        /// Work group name (see `scores group ls`)
+        group: String,
+        /// Add scores the setlist already has
+        #[arg(long)]
+        allow_duplicate: bool,
     },
     /// Remove a score from a setlist
     RemoveScore {
@@ -243,18 +1128,166 @@ pub enum SetlistsCommand {
         /// Score ID, path, or title
         score: String,
     },
-    /// Reorder a score within a setlist
-    Reorder {
+    /// Follow the keys through a setlist, flagging awkward key changes
+    ///
+    /// A change is awkward when the tonic moves by a tritone or the key
+    /// signatures are five or more steps apart on the circle of fifths.
+    Analyze {
+        /// Setlist ID or name
+        setlist: String,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Play only part of a score in a setlist, e.g. one section of a chart
+    /// in a medley
+    ///
+    /// The range is written to the entry in the setlist's sync file (its
+    /// First Page and Last Page); forScore's database has no columns for it.
+    /// Bookmarks already cover a range of their own, so only score entries
+    /// take one.
+    SetPages {
         /// Setlist ID or name
         setlist: String,
         /// Score ID, path, or title
         score: String,
+        /// First page to play
+        #[arg(long, required_unless_present = "clear", requires = "to")]
+        from: Option<i64>,
+        /// Last page to play
+        #[arg(long, requires = "from")]
+        to: Option<i64>,
+        /// Remove the range, so the whole score plays again
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        clear: bool,
+    },
+    /// Create a setlist from a text file of score titles, one per line
+    ///
+    /// Lines are resolved like other score identifiers, falling back to the
+    /// closest title. Blank lines and lines starting with # are skipped;
+    /// lines that match nothing are listed at the end.
+    Import {
+        /// Name of the new setlist
+        name: String,
+        /// Text file with one score title per line
+        #[arg(long)]
+        from: String,
+        /// Preview without creating the setlist
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Create setlists from a folder of .txt files (one score title per line)
+    ImportDir {
+        /// Folder containing .txt files; each file becomes a setlist named after it
+        folder: String,
+        /// Preview without creating setlists
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Draft a setlist from matching scores, alternating genres
+    ///
+    /// Picks scores in title order, taking one from each genre in turn, and
+    /// writes the setlist and its sync file for you to reorder.
+    Generate {
+        /// Name of the new setlist
+        name: String,
+        /// Search query (matches title or composer)
+        #[arg(long)]
+        from_search: Option<String>,
+        #[command(flatten)]
+        filter: Box<FilterArgs>,
+        /// At most this many pieces by one composer
+        #[arg(long)]
+        max_per_composer: Option<usize>,
+        /// Stop at about this total duration (e.g. "45m", "1h30m"); pieces
+        /// without a duration are left out
+        #[arg(long)]
+        target_duration: Option<String>,
+        /// Avoid consecutive pieces in the same key
+        #[arg(long)]
+        spread_keys: bool,
+        /// Preview the program without creating the setlist
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Set where a setlist appears in forScore's setlist menu
+    #[command(group(ArgGroup::new("order").required(true).multiple(true).args(
+        ["menu_index", "index", "sort"]
+    )))]
+    SetOrder {
+        /// Setlist ID or name
+        identifier: String,
+        /// Position in the setlist menu (0 is first), within the setlist's
+        /// folder; other setlists in the folder move down to make room
+        #[arg(long)]
+        menu_index: Option<usize>,
+        /// Raw ZINDEX value
+        #[arg(long)]
+        index: Option<i64>,
+        /// Raw ZSORT value
+        #[arg(long)]
+        sort: Option<i64>,
+    },
+    /// Reorder a score within a setlist, or with --interactive the whole
+    /// setlist in a terminal UI
+    ///
+    /// In the UI, up/down choose an item, space picks it up or puts it down,
+    /// and up/down move a picked-up item. Enter saves the new order (database
+    /// and sync file together); q or Esc leaves without saving.
+    Reorder {
+        /// Setlist ID or name
+        setlist: String,
+        /// Score ID, path, or title
+        #[arg(required_unless_present = "interactive")]
+        score: Option<String>,
         /// New position (1-based)
+        #[arg(long, required_unless_present = "interactive")]
+        position: Option<usize>,
+        /// Rearrange every item in a terminal UI
+        #[arg(long, conflicts_with_all = ["score", "position"])]
+        interactive: bool,
+    },
+    /// Sort a setlist's items by a field, in the database and sync file
+    ///
+    /// Items missing the field go last; ties are broken by title.
+    Sort {
+        /// Setlist ID or name
+        identifier: String,
+        /// Field to sort by
+        #[arg(long, value_enum)]
+        by: SetlistSortField,
+        /// Sort descending
+        #[arg(long)]
+        reverse: bool,
+        /// Preview the new order without applying
         #[arg(long)]
-        position: usize,
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
     },
 }
 
+/// Fields `setlists sort` can order by
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SetlistSortField {
+    Title,
+    /// First composer's name
+    Composer,
+    /// Key, from C up; majors before minors on the same note
+    Key,
+    Difficulty,
+}
+
 #[derive(Subcommand)]
 pub enum LibrariesCommand {
     /// List all libraries
@@ -267,6 +1300,8 @@ pub enum LibrariesCommand {
     Show {
         /// Library ID or name
         identifier: String,
+        #[command(flatten)]
+        columns: ColumnArgs,
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -312,6 +1347,31 @@ pub enum ComposersCommand {
         /// Target composer name
         target: String,
     },
+    /// Delete a composer
+    ///
+    /// A composer still linked to scores or bookmarks is only deleted with
+    /// --force, which unlinks it first and removes it from their ITM files.
+    Delete {
+        /// Composer name
+        name: String,
+        /// Unlink the composer from its scores and bookmarks first
+        #[arg(long)]
+        force: bool,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Set a composer's lifespan and nationality (kept in the sidecar store)
+    Edit {
+        /// Composer name
+        name: String,
+        /// Lifespan, e.g. 1685-1750 or 1962- (empty to clear)
+        #[arg(long)]
+        years: Option<String>,
+        /// Nationality, e.g. German (empty to clear)
+        #[arg(long)]
+        nationality: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -321,6 +1381,9 @@ pub enum GenresCommand {
         /// Show only unused genres
         #[arg(long)]
         unused: bool,
+        /// Show nested genres ("Jazz/Bebop") as a tree
+        #[arg(long)]
+        tree: bool,
         /// Output as JSON
         #[arg(long)]
         json: bool,
@@ -334,20 +1397,224 @@ pub enum TagsCommand {
         /// Show only unused tags
         #[arg(long)]
         unused: bool,
+        /// Show nested tags ("Jazz/Bebop") as a tree
+        #[arg(long)]
+        tree: bool,
         /// Output as JSON
         #[arg(long)]
         json: bool,
     },
+    /// Rename a tag and all tags nested beneath it
+    Rename {
+        /// Current tag name (e.g. "Jazz" also renames "Jazz/Bebop")
+        old_name: String,
+        /// New tag name
+        new_name: String,
+    },
+    /// Merge two tags (move all scores from source to target)
+    Merge {
+        /// Source tag name
+        source: String,
+        /// Target tag name
+        target: String,
+    },
+    /// Delete a tag
+    ///
+    /// A tag some scores still have is only deleted with --force, which
+    /// takes it off them first and removes it from their ITM files.
+    Delete {
+        /// Tag name
+        name: String,
+        /// Take the tag off its scores first
+        #[arg(long)]
+        force: bool,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Tag scores, creating the tag if it doesn't exist yet
+    ///
+    /// Name the scores, or tag every score matching --search and/or the
+    /// filters (bookmarks are left out).
+    Apply {
+        /// Tag name
+        #[arg(value_name = "TAG")]
+        name: String,
+        /// Score ID, path, or title
+        scores: Vec<String>,
+        /// Tag every score matching this query (title or composer)
+        #[arg(long, conflicts_with = "scores")]
+        search: Option<String>,
+        #[command(flatten)]
+        filter: Box<FilterArgs>,
+    },
+    /// Take a tag off scores
+    Remove {
+        /// Tag name
+        #[arg(value_name = "TAG")]
+        name: String,
+        /// Score ID, path, or title
+        #[arg(required = true)]
+        scores: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MetaCommand {
+    /// Write genres, tags, labels and difficulty levels to a JSON file
+    Export {
+        /// Output file
+        file: String,
+    },
+    /// Add the genres, tags, labels and difficulty levels from a JSON file
+    /// (existing entries are kept)
+    Import {
+        /// File written by `meta export`
+        file: String,
+        /// Preview without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
 }
 
 #[derive(Subcommand)]
 pub enum ExportCommand {
-    /// Export all scores to CSV
+    /// Export scores to CSV: all of them, or those matching the filters
     Csv {
         /// Output file path
         #[arg(short, long, default_value = "scores.csv")]
         output: String,
+        /// Only scores in this setlist (name or ID)
+        #[arg(long)]
+        setlist: Option<String>,
+        /// Only scores matching this query (title or composer)
+        #[arg(long)]
+        search: Option<String>,
+        #[command(flatten)]
+        filter: Box<FilterArgs>,
+        #[command(flatten)]
+        format: CsvFormatArgs,
+        /// Start the file with a byte order mark (lets Excel detect UTF-8)
+        #[arg(long)]
+        bom: bool,
+        #[command(flatten)]
+        snapshot: SnapshotArgs,
+        /// Byte-stable output for version control: rows sorted by UUID,
+        /// list fields sorted, LF line endings and whole-second dates
+        #[arg(long)]
+        stable: bool,
+    },
+    /// Export the whole library as JSON: scores with their metadata and
+    /// bookmarks, and setlists (read back by `import json`)
+    Json {
+        /// Output file path
+        #[arg(short, long, default_value = "library.json")]
+        output: String,
+        #[command(flatten)]
+        snapshot: SnapshotArgs,
+        /// Byte-stable output for version control: scores, bookmarks and
+        /// setlists sorted by UUID, list fields sorted and whole-second dates
+        #[arg(long)]
+        stable: bool,
+    },
+    /// Export the library as an Excel workbook with Scores, Setlists and
+    /// Bookmarks sheets
+    Xlsx {
+        /// Output file path
+        #[arg(short, long, default_value = "library.xlsx")]
+        output: String,
+        #[command(flatten)]
+        snapshot: SnapshotArgs,
+    },
+    /// Save a portable snapshot of the whole library (scores and their
+    /// metadata, bookmarks, setlists and libraries), read back by `import
+    /// snapshot` on this or another Mac
+    ///
+    /// Given a directory, writes a forscore-snapshot-*.json.gz timestamped in
+    /// UTC into it (a missing path ending in / or without an extension is
+    /// created as one); given a file, writes that file (plain JSON if it ends
+    /// in .json). Existing files aren't overwritten. The database is always
+    /// read from one consistent snapshot.
+    Snapshot {
+        /// Directory or file to write
+        target: PathBuf,
+    },
+    /// Summarize what changed between two exports of the same kind (`export
+    /// csv`, `export bookmarks-csv` or `export json`)
+    ///
+    /// Rows are matched by UUID, or by path and title where there's none;
+    /// lists the added, removed and changed scores, bookmarks and setlists.
+    Diff {
+        /// The earlier export
+        old: PathBuf,
+        /// The later export
+        new: PathBuf,
+        #[command(flatten)]
+        format: CsvFormatArgs,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Export all bookmarks to CSV, one row per bookmark with its score's path
+    BookmarksCsv {
+        /// Output file path
+        #[arg(short, long, default_value = "bookmarks.csv")]
+        output: String,
+        #[command(flatten)]
+        format: CsvFormatArgs,
+        /// Start the file with a byte order mark (lets Excel detect UTF-8)
+        #[arg(long)]
+        bom: bool,
+        #[command(flatten)]
+        snapshot: SnapshotArgs,
+    },
+    /// List scores whose PDF is missing from the Documents folder, as a
+    /// worklist of what to re-scan (CSV, or a Markdown checklist)
+    ///
+    /// Scores in setlists come first, since they're needed soonest.
+    MissingReport {
+        /// Output file path (default missing.csv, or missing.md with --markdown)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Write a Markdown checklist instead of CSV
+        #[arg(long)]
+        markdown: bool,
+        #[command(flatten)]
+        format: CsvFormatArgs,
+        #[command(flatten)]
+        snapshot: SnapshotArgs,
     },
+    /// Export the library as a static website (e.g. for GitHub Pages)
+    Site {
+        /// Output directory
+        #[arg(long, default_value = "site")]
+        out: String,
+        #[command(flatten)]
+        snapshot: SnapshotArgs,
+    },
+}
+
+/// How an export reads the database
+#[derive(Args, Clone)]
+pub struct SnapshotArgs {
+    /// Read the whole export from one snapshot of the database, so
+    /// changes forScore saves meanwhile can't leave it half old, half new
+    #[arg(long)]
+    pub snapshot: bool,
+}
+
+/// CSV delimiter and text encoding, e.g. for Excel in European locales
+#[derive(Args, Clone)]
+pub struct CsvFormatArgs {
+    /// Field delimiter (e.g. ';')
+    #[arg(long, default_value = ",")]
+    pub delimiter: char,
+    /// Text encoding (e.g. utf-8, windows-1252, iso-8859-15)
+    #[arg(long, default_value = "utf-8")]
+    pub encoding: String,
 }
 
 #[derive(Subcommand)]
@@ -356,9 +1623,88 @@ pub enum ImportCommand {
     Csv {
         /// Input CSV file
         file: String,
+        #[command(flatten)]
+        format: CsvFormatArgs,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+        /// Only check the file for problems (headers, ranges, keys, IDs)
+        #[arg(long)]
+        validate_only: bool,
+        /// Column that identifies each row's score; use uuid for an export
+        /// taken on another device, path or title for a sheet without IDs
+        #[arg(long, value_enum, default_value_t = CsvMatch::Id)]
+        match_by: CsvMatch,
+        /// Update the rows that match a score and report the others,
+        /// instead of refusing the whole file when a row doesn't match
+        #[arg(long)]
+        upsert: bool,
+        /// TOML file that renames another tool's columns (and rewrites
+        /// their values) to the ones import csv reads
+        #[arg(long)]
+        mapping: Option<PathBuf>,
+    },
+    /// Update scores and bookmarks from an `export json` file
+    ///
+    /// Scores are matched by UUID, then by path; bookmarks by UUID. Titles,
+    /// keys, ratings, difficulties, tempos, composers, genres, tags, labels
+    /// and bookmark page ranges are set to the file's values, and scores
+    /// join the file's libraries where this library has one of that name.
+    /// Setlists are reported as skipped; `import snapshot` creates them.
+    Json {
+        /// Input JSON file
+        file: String,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Restore or merge an `export snapshot` into this library
+    ///
+    /// Scores are matched by UUID, then by path. By default their metadata
+    /// and their bookmarks' titles, pages, composers, genres and ratings are
+    /// set to the snapshot's (restore);
+    /// with --merge only fields this library leaves empty are filled in.
+    /// Either way, bookmarks, libraries, library memberships and setlists
+    /// this library lacks are created; nothing is removed.
+    Snapshot {
+        /// Snapshot file, or a directory to take the newest snapshot from
+        source: PathBuf,
+        /// Only fill in what this library is missing
+        #[arg(long)]
+        merge: bool,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Create and update bookmarks from CSV (the columns `export
+    /// bookmarks-csv` writes)
+    ///
+    /// Rows with an id update that bookmark; rows without one create a
+    /// bookmark in the score at `path`, unless it already has one with the
+    /// same title and first page.
+    BookmarksCsv {
+        /// Input CSV file
+        file: String,
+        #[command(flatten)]
+        format: CsvFormatArgs,
         /// Preview changes without applying
         #[arg(long)]
         dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+        /// Only check the file for problems (headers, pages, ratings, paths)
+        #[arg(long)]
+        validate_only: bool,
     },
 }
 
@@ -380,6 +1726,23 @@ pub enum BookmarksCommand {
         #[arg(long)]
         json: bool,
     },
+    /// Create a bookmark in a score
+    Create {
+        /// Score ID, path, or title
+        score: String,
+        /// Bookmark title
+        #[arg(long)]
+        title: String,
+        /// Page range, e.g. 5-9 (or 5 for a single page)
+        #[arg(long)]
+        pages: String,
+        /// Preview the bookmark without creating it
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
     /// Edit bookmark metadata
     Edit {
         /// Bookmark ID
@@ -402,15 +1765,103 @@ pub enum BookmarksCommand {
         /// Set difficulty (1-5)
         #[arg(long)]
         difficulty: Option<i32>,
+        /// Set the first page
+        #[arg(long)]
+        start_page: Option<i32>,
+        /// Set the last page
+        #[arg(long)]
+        end_page: Option<i32>,
+        /// Remove the key
+        #[arg(long, conflicts_with = "key")]
+        clear_key: bool,
+        /// Remove the rating
+        #[arg(long, conflicts_with = "rating")]
+        clear_rating: bool,
+        /// Remove the difficulty
+        #[arg(long, conflicts_with = "difficulty")]
+        clear_difficulty: bool,
+        /// Remove all composers
+        #[arg(long, conflicts_with = "composer")]
+        clear_composer: bool,
+        /// Remove all genres
+        #[arg(long, conflicts_with = "genre")]
+        clear_genre: bool,
         /// Preview changes without applying
         #[arg(long)]
         dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
     },
     /// Delete a bookmark
     Delete {
         /// Bookmark ID
         id: i64,
     },
+    /// Split a score into evenly sized bookmarks
+    Split {
+        /// Score ID, path, or title
+        score: String,
+        /// Number of pages per bookmark
+        #[arg(long)]
+        every: i32,
+        /// Bookmark title prefix (numbered from 1)
+        #[arg(long, default_value = "Part")]
+        prefix: String,
+        /// Total page count (defaults to the page count known to forScore)
+        #[arg(long)]
+        pages: Option<i32>,
+        /// Preview bookmarks without creating them
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Move bookmark page ranges after pages were inserted into or removed
+    /// from the PDF
+    ///
+    /// With a positive --by, that many pages were inserted before
+    /// --from-page; with a negative one, that many pages were removed
+    /// starting at --from-page. Bookmarks that only covered removed pages
+    /// are left alone and reported.
+    Shift {
+        /// Score ID, path, or title
+        score: String,
+        /// Number of pages inserted (positive) or removed (negative)
+        #[arg(long, allow_hyphen_values = true)]
+        by: i32,
+        /// First page affected by the insertion or removal
+        #[arg(long, default_value_t = 1)]
+        from_page: i32,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Create a bookmark for each entry in the PDF's table of contents
+    ///
+    /// Each bookmark runs until the next entry at the same or a higher
+    /// level, so a section spans its pieces. Entries already bookmarked
+    /// with the same title and first page are skipped.
+    FromToc {
+        /// Score ID, path, or title
+        score: String,
+        /// Deepest outline level to include (1 = top-level entries only)
+        #[arg(long)]
+        depth: Option<usize>,
+        /// Create the bookmarks without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+        /// Preview bookmarks without creating them
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -421,4 +1872,114 @@ pub enum FixesCommand {
         #[arg(long)]
         apply: bool,
     },
+    /// Fix score paths that differ from the real file only by case or percent-encoding
+    PathNormalization {
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Rederive every sort title from its title with the `[sort_title]`
+    /// rules in the config file, e.g. after changing them
+    SortTitles {
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Reconcile bookmarks in the database with the bookmarks in ITM files
+    ///
+    /// Bookmarks are matched by identifier (UUID). By default, bookmarks
+    /// missing on either side are created there; with --prefer, that side
+    /// wins and the other side's extra bookmarks are removed. The changes
+    /// are listed and confirmed before anything is written.
+    BookmarkSync {
+        /// Side to treat as correct
+        #[arg(long, value_enum)]
+        prefer: Option<BookmarkSource>,
+        /// Apply without asking for confirmation
+        #[arg(long, conflicts_with = "dry_run")]
+        yes: bool,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Find bookmarks whose score no longer exists, and delete them or move
+    /// them to another score
+    ///
+    /// Their setlist entries and anything left of them in ITM files are
+    /// updated too.
+    OrphanedBookmarks {
+        /// Reattach them to this score (ID, path, or title) instead of
+        /// deleting them
+        #[arg(long, value_name = "SCORE")]
+        reattach_to: Option<String>,
+        /// Preview changes without applying
+        #[arg(long)]
+        dry_run: bool,
+        /// Print dry-run changes as JSON patch objects
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Find scores whose titles collide (the same ignoring case, or a typo
+    /// apart), which makes resolving them by title ambiguous, and rename all
+    /// but the oldest of each group
+    ///
+    /// Titles that differ only in their numbers ("Etude 1", "Etude 2") are
+    /// not treated as colliding.
+    AmbiguousTitles {
+        /// Largest edit distance at which two titles collide (0 = case only)
+        #[arg(long, default_value_t = 1)]
+        distance: usize,
+        /// What to append to renamed titles; composer falls back to a number
+        /// when the composers don't tell the scores apart
+        #[arg(long, value_enum, default_value = "composer")]
+        suffix: TitleSuffix,
+        /// Actually rename the scores
+        #[arg(long)]
+        apply: bool,
+        /// Output the groups as JSON
+        #[arg(long, conflicts_with = "apply")]
+        json: bool,
+    },
+    /// Find composers entered under different spellings ("J.S. Bach", "JS
+    /// Bach", "Bach, Johann Sebastian") and merge each group into the name
+    /// used most
+    ///
+    /// Names match on surname (allowing a typo in longer ones) and initials.
+    /// A bare surname only joins a group when it can't belong to another,
+    /// so "Bach" isn't merged while there's both a J.S. and a C.P.E. Bach.
+    DuplicateComposers {
+        /// Merge without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+        /// Output the groups as JSON
+        #[arg(long, conflicts_with = "yes")]
+        json: bool,
+    },
+}
+
+/// What `fixes ambiguous-titles` appends to a title
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum TitleSuffix {
+    /// " (Composer)"
+    Composer,
+    /// " (2)", " (3)", ...
+    Number,
+}
+
+/// Where bookmarks are stored, for `fixes bookmark-sync --prefer`
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum BookmarkSource {
+    /// The forScore database
+    Db,
+    /// The .itm sync files
+    Itm,
 }
@@ -0,0 +1,102 @@
+use crate::cli::AliasCommand;
+use crate::config::{config_path, load_config, write_alias};
+use crate::db::open_readonly;
+use crate::error::{ForScoreError, Result};
+use crate::models::score::{resolve_bookmark, resolve_score_exact, resolve_score_strict};
+use crate::models::setlist::{resolve_setlist_exact, resolve_setlist_strict};
+use crate::output::{output, AliasEntry};
+use rusqlite::Connection;
+
+pub fn handle(cmd: AliasCommand) -> Result<()> {
+    match cmd {
+        AliasCommand::Ls { json } => {
+            let aliases = load_config()?.aliases;
+            if aliases.is_empty() && !json {
+                println!("No aliases (add one with `alias set`)");
+                return Ok(());
+            }
+            // Aliases are still worth listing without a library to check them against
+            let conn = open_readonly().ok();
+            let entries: Vec<AliasEntry> = aliases
+                .into_iter()
+                .map(|(name, target)| AliasEntry {
+                    resolves_to: conn
+                        .as_ref()
+                        .and_then(|conn| resolve_exact(conn, &target))
+                        .map(|(kind, id, _)| format!("{} {}", kind, id)),
+                    name,
+                    target,
+                })
+                .collect();
+            output(&entries, json);
+        }
+
+        AliasCommand::Set { name, target } => {
+            let name = name.trim();
+            if name.is_empty() || name.parse::<i64>().is_ok() {
+                return Err(ForScoreError::Other(
+                    "An alias can't be empty or a number (numbers are IDs)".into(),
+                ));
+            }
+            let conn = open_readonly()?;
+            let (kind, id, title) = resolve_target(&conn, &target)?;
+            // Keep what was typed when it names the item exactly; another
+            // alias is stored as the title it stands for
+            let stored = if resolve_exact(&conn, &target).is_some() {
+                target.as_str()
+            } else {
+                title.as_str()
+            };
+            write_alias(name, Some(stored))?;
+            println!("{} -> {} '{}' (ID {})", name, kind, title, id);
+        }
+
+        AliasCommand::Remove { name } => {
+            if write_alias(&name, None)? {
+                println!("Removed alias '{}'", name);
+            } else {
+                return Err(ForScoreError::Other(format!(
+                    "No alias '{}' in {}",
+                    name,
+                    config_path()?.display()
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The score, bookmark or setlist an identifier names exactly, as (kind, ID, title)
+fn resolve_exact(conn: &Connection, identifier: &str) -> Option<(&'static str, i64, String)> {
+    if let Ok(score) = resolve_score_exact(conn, identifier) {
+        return Some(("score", score.id, score.title));
+    }
+    if let Ok(bookmark) = resolve_bookmark(conn, identifier) {
+        return Some(("bookmark", bookmark.id, bookmark.title));
+    }
+    resolve_setlist_exact(conn, identifier)
+        .ok()
+        .map(|setlist| ("setlist", setlist.id, setlist.title))
+}
+
+/// Like `resolve_exact`, also following aliases; the closest score titles
+/// and setlist names are suggested, never used (an alias isn't pointed at a
+/// guess)
+fn resolve_target(conn: &Connection, identifier: &str) -> Result<(&'static str, i64, String)> {
+    if let Some(found) = resolve_exact(conn, identifier) {
+        return Ok(found);
+    }
+    let score = match resolve_score_strict(conn, identifier) {
+        Ok(score) => return Ok(("score", score.id, score.title)),
+        Err(e) => e,
+    };
+    resolve_setlist_strict(conn, identifier)
+        .map(|setlist| ("setlist", setlist.id, setlist.title))
+        .map_err(|setlist| {
+            ForScoreError::Other(format!(
+                "No score, bookmark or setlist matches '{}' ({}; {})",
+                identifier, score, setlist
+            ))
+        })
+}
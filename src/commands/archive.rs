@@ -0,0 +1,318 @@
+use crate::cli::FilterArgs;
+use crate::commands::scores::build_filter;
+use crate::commands::setlists::setlist_file_items;
+use crate::db::{entity, open_readonly, open_readwrite, warn_if_running};
+use crate::dry_run::{DryRun, Patch};
+use crate::error::{ForScoreError, Result};
+use crate::models::library::{
+    add_score_to_library, create_library, list_item_libraries, list_libraries,
+    remove_score_from_library, Library,
+};
+use crate::models::score::{resolve_score, search_scores};
+use crate::models::setlist::{
+    add_item_to_setlist, list_item_setlist_entries, list_setlists, remove_score_from_setlist,
+    reorder_score_in_setlist, Setlist,
+};
+use crate::models::Score;
+use crate::progress::{Progress, Summary};
+use crate::setlist_sync::{remove_item_from_setlist_file, reorder_setlist_file};
+use crate::store::{
+    archived_memberships, clear_archived_memberships, open_store, record_archived_membership,
+    ArchivedMembership,
+};
+use crate::touch::touch_item;
+use rusqlite::Connection;
+
+/// Move scores into the archive library, out of their other libraries and
+/// setlists, remembering where they were
+pub fn archive(
+    identifier: Option<String>,
+    args: &FilterArgs,
+    archive_library: &str,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    if !dry_run {
+        warn_if_running();
+    }
+    let conn = if dry_run {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+    let store = open_store()?;
+
+    let scores = select_scores(&conn, identifier, args)?;
+    let mut archive = find_library(&conn, archive_library)?;
+
+    let progress = if dry_run {
+        Progress::hidden()
+    } else {
+        Progress::new(scores.len(), "Archiving")
+    };
+    let mut preview = DryRun::new(json);
+    let mut summary = Summary::default();
+
+    for score in &scores {
+        progress.inc();
+
+        let Some(uuid) = &score.uuid else {
+            summary.skip(&score.title, "no UUID to remember it by");
+            continue;
+        };
+        let libraries = list_item_libraries(&conn, score.id)?;
+        if libraries
+            .iter()
+            .any(|l| l.title.eq_ignore_ascii_case(archive_library))
+        {
+            summary.skip(&score.title, "already archived");
+            continue;
+        }
+        let entries = list_item_setlist_entries(&conn, score.id)?;
+
+        if dry_run {
+            let mut patch = Patch::update("score", score.id, &score.title);
+            patch.change("libraries", join(&libraries, |l| &l.title), archive_library);
+            if !entries.is_empty() {
+                patch.change("setlists", join(&entries, |e| &e.setlist.title), "");
+            }
+            preview.add(patch);
+            continue;
+        }
+
+        for library in &libraries {
+            let membership = ArchivedMembership {
+                kind: "library".into(),
+                name: library.title.clone(),
+                setlist_uuid: None,
+                position: None,
+            };
+            record_archived_membership(&store, uuid, &membership)?;
+            remove_score_from_library(&conn, library.id, score.id)?;
+        }
+        for entry in &entries {
+            let membership = ArchivedMembership {
+                kind: "setlist".into(),
+                name: entry.setlist.title.clone(),
+                setlist_uuid: entry.setlist.uuid.clone(),
+                position: Some(entry.position),
+            };
+            record_archived_membership(&store, uuid, &membership)?;
+            remove_score_from_setlist(&conn, entry.setlist.id, score.id)?;
+            if let Some(entry_uuid) = &entry.uuid {
+                if let Err(e) = remove_item_from_setlist_file(&entry.setlist.title, entry_uuid) {
+                    summary.error(
+                        &score.title,
+                        format!("setlist '{}' sync file: {}", entry.setlist.title, e),
+                    );
+                }
+            }
+        }
+
+        let library = match &archive {
+            Some(library) => library.id,
+            None => {
+                let library = create_library(&conn, archive_library)?;
+                let id = library.id;
+                archive = Some(library);
+                id
+            }
+        };
+        add_score_to_library(&conn, library, score.id)?;
+        touch_item(&conn, score.id)?;
+
+        progress.println(format!(
+            "Archived: {} ({} libraries, {} setlists)",
+            score.title,
+            libraries.len(),
+            entries.len()
+        ));
+        summary.updated += 1;
+    }
+
+    progress.finish();
+    if dry_run {
+        preview.finish();
+    } else {
+        summary.print("Archived");
+    }
+
+    Ok(())
+}
+
+/// Take scores out of the archive library and put them back in the libraries
+/// and setlists they were archived from
+pub fn unarchive(
+    identifier: Option<String>,
+    args: &FilterArgs,
+    archive_library: &str,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    if !dry_run {
+        warn_if_running();
+    }
+    let conn = if dry_run {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+    let store = open_store()?;
+
+    let scores = select_scores(&conn, identifier, args)?;
+    let archive = find_library(&conn, archive_library)?;
+    let setlists = list_setlists(&conn)?;
+
+    let progress = if dry_run {
+        Progress::hidden()
+    } else {
+        Progress::new(scores.len(), "Unarchiving")
+    };
+    let mut preview = DryRun::new(json);
+    let mut summary = Summary::default();
+
+    for score in &scores {
+        progress.inc();
+
+        let memberships = match &score.uuid {
+            Some(uuid) => archived_memberships(&store, uuid)?,
+            None => Vec::new(),
+        };
+        let archived = match &archive {
+            Some(library) => list_item_libraries(&conn, score.id)?
+                .iter()
+                .any(|l| l.id == library.id),
+            None => false,
+        };
+        if !archived && memberships.is_empty() {
+            summary.skip(&score.title, "not archived");
+            continue;
+        }
+
+        let (libraries, entries): (Vec<_>, Vec<_>) =
+            memberships.iter().partition(|m| m.kind == "library");
+
+        if dry_run {
+            let mut patch = Patch::update("score", score.id, &score.title);
+            let old = if archived { archive_library } else { "" };
+            patch.change("libraries", old, join(&libraries, |m| &m.name));
+            if !entries.is_empty() {
+                patch.change("setlists", "", join(&entries, |m| &m.name));
+            }
+            preview.add(patch);
+            continue;
+        }
+
+        if let (Some(library), true) = (&archive, archived) {
+            remove_score_from_library(&conn, library.id, score.id)?;
+        }
+        for membership in &libraries {
+            match find_library(&conn, &membership.name)? {
+                Some(library) => add_score_to_library(&conn, library.id, score.id)?,
+                None => summary.error(
+                    &score.title,
+                    format!("library '{}' no longer exists", membership.name),
+                ),
+            }
+        }
+        for membership in &entries {
+            let Some(setlist) = find_setlist(&setlists, membership) else {
+                summary.error(
+                    &score.title,
+                    format!("setlist '{}' no longer exists", membership.name),
+                );
+                continue;
+            };
+            if let Err(e) = restore_setlist_entry(&conn, setlist, score, membership.position) {
+                summary.error(&score.title, format!("setlist '{}': {}", setlist.title, e));
+            }
+        }
+
+        if let Some(uuid) = &score.uuid {
+            clear_archived_memberships(&store, uuid)?;
+        }
+        touch_item(&conn, score.id)?;
+
+        progress.println(format!(
+            "Unarchived: {} ({} libraries, {} setlists)",
+            score.title,
+            libraries.len(),
+            entries.len()
+        ));
+        summary.updated += 1;
+    }
+
+    progress.finish();
+    if dry_run {
+        preview.finish();
+    } else {
+        summary.print("Unarchived");
+    }
+
+    Ok(())
+}
+
+/// The score named by `identifier`, or every score matching the filters
+fn select_scores(
+    conn: &Connection,
+    identifier: Option<String>,
+    args: &FilterArgs,
+) -> Result<Vec<Score>> {
+    if let Some(identifier) = identifier {
+        return Ok(vec![resolve_score(conn, &identifier)?]);
+    }
+    if args.is_empty() {
+        return Err(ForScoreError::Other(
+            "Give a score or at least one filter".into(),
+        ));
+    }
+
+    let mut filter = build_filter(conn, None, args)?;
+    filter.scores_only = true;
+    search_scores(conn, &filter, usize::MAX)
+}
+
+/// A library with exactly this name (ignoring case)
+fn find_library(conn: &Connection, name: &str) -> Result<Option<Library>> {
+    Ok(list_libraries(conn)?
+        .into_iter()
+        .find(|l| l.title.eq_ignore_ascii_case(name)))
+}
+
+/// The setlist a membership was recorded in, by UUID or else by name, so a
+/// renamed setlist is still found
+fn find_setlist<'a>(
+    setlists: &'a [Setlist],
+    membership: &ArchivedMembership,
+) -> Option<&'a Setlist> {
+    membership
+        .setlist_uuid
+        .as_ref()
+        .and_then(|uuid| setlists.iter().find(|s| s.uuid.as_ref() == Some(uuid)))
+        .or_else(|| setlists.iter().find(|s| s.title == membership.name))
+}
+
+/// Add a score back to a setlist at its old position and rebuild the .set file
+fn restore_setlist_entry(
+    conn: &Connection,
+    setlist: &Setlist,
+    score: &Score,
+    position: Option<i64>,
+) -> Result<()> {
+    add_item_to_setlist(conn, setlist.id, score.id, entity::score())?;
+    if let Some(position) = position {
+        reorder_score_in_setlist(conn, setlist.id, score.id, position.max(1) as usize)?;
+    }
+    let items = setlist_file_items(conn, setlist.id)?;
+    reorder_setlist_file(&setlist.title, &items)?;
+    Ok(())
+}
+
+fn join<T>(items: &[T], name: impl Fn(&T) -> &String) -> String {
+    items
+        .iter()
+        .map(name)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ")
+}
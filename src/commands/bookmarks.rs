@@ -1,11 +1,25 @@
 use crate::cli::BookmarksCommand;
-use crate::db::{mark_modified, open_readonly, open_readwrite, warn_if_running};
-use crate::error::Result;
-use crate::itm::{delete_bookmark_from_itm, update_bookmark_in_itm, ItmBookmarkUpdate};
+use crate::commands::scores::confirm;
+use crate::db::{documents_path, open_readonly, open_readwrite, warn_if_running};
+use crate::dry_run::{DryRun, Patch};
+use crate::error::{ForScoreError, Result};
+use crate::itm::{
+    add_bookmarks_to_itm, delete_bookmark_from_itm, update_bookmark_in_itm, ItmBookmarkUpdate,
+    ItmNewBookmark,
+};
 use crate::models::key::MusicalKey;
-use crate::models::meta::{get_or_create_composer, get_or_create_genre};
-use crate::models::score::{get_bookmark_by_id, list_bookmarks, resolve_score};
+use crate::models::meta::{
+    get_or_create_composer, get_or_create_genre, set_item_links, COMPOSER_LINKS, GENRE_LINKS,
+};
+use crate::models::score::{
+    create_bookmark, get_bookmark_by_id, get_score_by_path, list_bookmarks, resolve_score,
+    score_page_count, Bookmark,
+};
 use crate::output::output;
+use crate::progress::Summary;
+use crate::sort_title::sort_title;
+use crate::touch::touch_item;
+use crate::trash::Bundle;
 
 pub fn handle(cmd: BookmarksCommand) -> Result<()> {
     match cmd {
@@ -67,7 +81,15 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
             key,
             rating,
             difficulty,
+            start_page,
+            end_page,
+            clear_key,
+            clear_rating,
+            clear_difficulty,
+            clear_composer,
+            clear_genre,
             dry_run,
+            json,
         } => {
             if !dry_run {
                 warn_if_running();
@@ -81,16 +103,14 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
 
             let bookmark = get_bookmark_by_id(&conn, id)?;
 
-            if dry_run {
-                println!("Dry run - would update bookmark ID {}:", bookmark.id);
-            }
+            let mut patch = Patch::update("bookmark", bookmark.id, &bookmark.title);
 
             // Update title
             if let Some(new_title) = &title {
                 if dry_run {
-                    println!("  Title: {} -> {}", bookmark.title, new_title);
+                    patch.change("title", bookmark.title.as_str(), new_title.as_str());
                 } else {
-                    let sort_title = new_title.to_lowercase();
+                    let sort_title = sort_title(new_title);
                     conn.execute(
                         "UPDATE ZITEM SET ZTITLE = ?, ZSORTTITLE = ? WHERE Z_PK = ?",
                         rusqlite::params![new_title, sort_title, bookmark.id],
@@ -102,10 +122,14 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
             if let Some(key_str) = &key {
                 let key_obj = MusicalKey::from_string(key_str)?;
                 if dry_run {
-                    println!(
-                        "  Key: {} -> {}",
-                        bookmark.key.map(|k| k.display()).unwrap_or_default(),
-                        key_obj.display()
+                    patch.change(
+                        "key",
+                        bookmark
+                            .key
+                            .as_ref()
+                            .map(|k| k.display())
+                            .unwrap_or_default(),
+                        key_obj.display(),
                     );
                 } else {
                     conn.execute(
@@ -117,11 +141,15 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
 
             // Update rating
             if let Some(r) = rating {
-                if r < 1 || r > 6 {
+                if !(1..=6).contains(&r) {
                     return Err(crate::error::ForScoreError::InvalidRating(r));
                 }
                 if dry_run {
-                    println!("  Rating: {} -> {}", bookmark.rating.unwrap_or(0), r);
+                    patch.change(
+                        "rating",
+                        bookmark.rating.map(|r| r.to_string()).unwrap_or_default(),
+                        r.to_string(),
+                    );
                 } else {
                     conn.execute(
                         "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
@@ -132,14 +160,17 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
 
             // Update difficulty
             if let Some(d) = difficulty {
-                if d < 1 || d > 5 {
+                if !(1..=5).contains(&d) {
                     return Err(crate::error::ForScoreError::InvalidDifficulty(d));
                 }
                 if dry_run {
-                    println!(
-                        "  Difficulty: {} -> {}",
-                        bookmark.difficulty.unwrap_or(0),
-                        d
+                    patch.change(
+                        "difficulty",
+                        bookmark
+                            .difficulty
+                            .map(|d| d.to_string())
+                            .unwrap_or_default(),
+                        d.to_string(),
                     );
                 } else {
                     conn.execute(
@@ -149,13 +180,44 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
                 }
             }
 
+            // Update page range, keeping whichever end isn't given
+            let pages = if start_page.is_some() || end_page.is_some() {
+                let start = start_page.or(bookmark.start_page).unwrap_or(1);
+                let end = end_page.or(bookmark.end_page).unwrap_or(start);
+                if start < 1 || end < start {
+                    return Err(ForScoreError::Other(format!(
+                        "Invalid page range {}-{}",
+                        start, end
+                    )));
+                }
+                if let Some(score) = get_score_by_path(&conn, &bookmark.path)? {
+                    if let Some(total) = score_page_count(&conn, &score)?.filter(|t| end > *t) {
+                        return Err(ForScoreError::Other(format!(
+                            "'{}' has {} pages; the bookmark can't end on page {}",
+                            score.title, total, end
+                        )));
+                    }
+                }
+                if dry_run {
+                    patch.change("pages", page_range(&bookmark), format!("{}-{}", start, end));
+                } else {
+                    conn.execute(
+                        "UPDATE ZITEM SET ZSTARTPAGE = ?, ZENDPAGE = ? WHERE Z_PK = ?",
+                        rusqlite::params![start, end, bookmark.id],
+                    )?;
+                }
+                Some((start, end))
+            } else {
+                None
+            };
+
             // Update composer
             if let Some(composer_name) = &composer {
                 if dry_run {
-                    println!(
-                        "  Composer: {} -> {}",
+                    patch.change(
+                        "composer",
                         bookmark.composers.first().cloned().unwrap_or_default(),
-                        composer_name
+                        composer_name.as_str(),
                     );
                 } else {
                     let composer_id = get_or_create_composer(&conn, composer_name)?;
@@ -177,10 +239,10 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
             // Update genre
             if let Some(genre_name) = &genre {
                 if dry_run {
-                    println!(
-                        "  Genre: {} -> {}",
+                    patch.change(
+                        "genre",
                         bookmark.genres.first().cloned().unwrap_or_default(),
-                        genre_name
+                        genre_name.as_str(),
                     );
                 } else {
                     let genre_id = get_or_create_genre(&conn, genre_name)?;
@@ -196,33 +258,102 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
                 }
             }
 
-            if !dry_run {
-                // Mark the bookmark as modified
-                mark_modified(&conn, bookmark.id)?;
+            // Clear fields
+            let mut removed_itm_keys = Vec::new();
+            let cleared = [
+                (
+                    clear_key,
+                    "key",
+                    "ZKEY",
+                    "Key",
+                    bookmark.key.as_ref().map(|k| k.display()),
+                ),
+                (
+                    clear_rating,
+                    "rating",
+                    "ZRATING",
+                    "Rating",
+                    bookmark.rating.map(|r| r.to_string()),
+                ),
+                (
+                    clear_difficulty,
+                    "difficulty",
+                    "ZDIFFICULTY",
+                    "Difficulty",
+                    bookmark.difficulty.map(|d| d.to_string()),
+                ),
+            ];
+            for (clear, field, column, itm_key, current) in cleared {
+                if !clear {
+                    continue;
+                }
+                if dry_run {
+                    patch.change(field, current.unwrap_or_default(), "");
+                    continue;
+                }
+                conn.execute(
+                    &format!("UPDATE ZITEM SET {} = NULL WHERE Z_PK = ?", column),
+                    [bookmark.id],
+                )?;
+                removed_itm_keys.push(itm_key);
+            }
+            let cleared_links = [
+                (
+                    clear_composer,
+                    "composer",
+                    "Composer",
+                    COMPOSER_LINKS,
+                    &bookmark.composers,
+                ),
+                (clear_genre, "genre", "Genre", GENRE_LINKS, &bookmark.genres),
+            ];
+            for (clear, field, itm_key, links, current) in cleared_links {
+                if !clear {
+                    continue;
+                }
+                if dry_run {
+                    patch.change(field, current.join(", "), "");
+                    continue;
+                }
+                set_item_links(&conn, links, bookmark.id, current, &[])?;
+                removed_itm_keys.push(itm_key);
+            }
 
-                // Also update the ITM file for sync
-                let mut itm_update = ItmBookmarkUpdate::new();
-                itm_update.title = title.clone();
-                itm_update.composer = composer.clone();
-                itm_update.genre = genre.clone();
-                if let Some(key_str) = &key {
-                    if let Ok(key_obj) = MusicalKey::from_string(key_str) {
-                        itm_update.key = Some(key_obj.code as i64);
-                    }
+            if dry_run {
+                let mut preview = DryRun::new(json);
+                preview.add(patch);
+                preview.finish();
+                return Ok(());
+            }
+
+            // Mark the bookmark as modified
+            touch_item(&conn, bookmark.id)?;
+
+            // Also update the ITM file for sync
+            let mut itm_update = ItmBookmarkUpdate::new();
+            itm_update.remove = removed_itm_keys;
+            itm_update.title = title.clone();
+            itm_update.composer = composer.clone();
+            itm_update.genre = genre.clone();
+            if let Some(key_str) = &key {
+                if let Ok(key_obj) = MusicalKey::from_string(key_str) {
+                    itm_update.key = Some(key_obj.code as i64);
                 }
-                itm_update.rating = rating.map(|r| r as i64);
-                itm_update.difficulty = difficulty.map(|d| d as i64);
+            }
+            itm_update.rating = rating.map(|r| r as i64);
+            itm_update.difficulty = difficulty.map(|d| d as i64);
+            itm_update.first_page = pages.map(|(start, _)| start as i64);
+            itm_update.last_page = pages.map(|(_, end)| end as i64);
 
-                // Get the bookmark's UUID for matching in ITM
-                let uuid = bookmark.uuid.as_deref();
+            // Get the bookmark's UUID for matching in ITM
+            let uuid = bookmark.uuid.as_deref();
 
-                match update_bookmark_in_itm(&bookmark.path, uuid, &itm_update) {
-                    Ok(true) => println!("Updated bookmark and ITM: {}", bookmark.title),
-                    Ok(false) => println!("Updated bookmark: {} (no ITM match)", bookmark.title),
-                    Err(e) => {
-                        println!("Updated bookmark: {}", bookmark.title);
-                        eprintln!("Warning: Failed to update ITM file: {}", e);
-                    }
+            match update_bookmark_in_itm(&bookmark.path, uuid, &itm_update) {
+                Ok(true) => println!("Updated bookmark and ITM: {}", bookmark.title),
+                Ok(false) => println!("Updated bookmark: {} (no ITM match)", bookmark.title),
+                Err(e) => {
+                    println!("Updated bookmark: {}", bookmark.title);
+                    eprintln!("Warning: Failed to update ITM file: {}", e);
                 }
             }
         }
@@ -232,6 +363,13 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
 
             let conn = open_readwrite()?;
             let bookmark = get_bookmark_by_id(&conn, id)?;
+            let mut trash = Bundle::new("bookmark", id, &bookmark.title);
+            trash.snapshot(&conn, "ZITEM", "Z_PK", id)?;
+            trash.snapshot(&conn, "Z_4COMPOSERS", "Z_4ITEMS1", id)?;
+            trash.snapshot(&conn, "Z_4GENRES", "Z_4ITEMS4", id)?;
+            trash.save()?;
+            // Before the row goes, so its score is marked too
+            touch_item(&conn, id)?;
 
             // Delete from database
             conn.execute("DELETE FROM ZITEM WHERE Z_PK = ?", [id])?;
@@ -252,8 +390,479 @@ pub fn handle(cmd: BookmarksCommand) -> Result<()> {
                     eprintln!("Warning: Failed to update ITM file: {}", e);
                 }
             }
+            println!("Restore with: forscore trash restore {}", trash.id());
+        }
+
+        BookmarksCommand::Create {
+            score,
+            title,
+            pages,
+            dry_run,
+            json,
+        } => {
+            let (start, end) = parse_pages(&pages)?;
+            if title.trim().is_empty() {
+                return Err(ForScoreError::Other("The title can't be empty".into()));
+            }
+
+            if !dry_run {
+                warn_if_running();
+            }
+
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            let score = resolve_score(&conn, &score)?;
+            if let Some(total) = score_page_count(&conn, &score)?.filter(|t| end > *t) {
+                return Err(ForScoreError::Other(format!(
+                    "'{}' has {} pages; the bookmark can't end on page {}",
+                    score.title, total, end
+                )));
+            }
+
+            if dry_run {
+                let mut preview = DryRun::new(json);
+                let mut patch = Patch::create("bookmark", &title);
+                patch.change("score", "", score.title.as_str());
+                patch.change("pages", "", format!("{}-{}", start, end));
+                preview.add(patch);
+                preview.finish();
+                return Ok(());
+            }
+
+            let bookmark = create_bookmark(&conn, &score, &title, start, end)?;
+            touch_item(&conn, score.id)?;
+
+            let itm_bookmark = ItmNewBookmark {
+                title: title.clone(),
+                identifier: bookmark.uuid.clone().unwrap_or_default(),
+                first_page: start as i64,
+                last_page: end as i64,
+            };
+            match add_bookmarks_to_itm(&score.path, &[itm_bookmark]) {
+                Ok(true) => println!(
+                    "Created bookmark '{}' (ID {}) in '{}' + ITM",
+                    title, bookmark.id, score.title
+                ),
+                Ok(false) => println!(
+                    "Created bookmark '{}' (ID {}) in '{}' (no ITM file)",
+                    title, bookmark.id, score.title
+                ),
+                Err(e) => {
+                    println!(
+                        "Created bookmark '{}' (ID {}) in '{}'",
+                        title, bookmark.id, score.title
+                    );
+                    eprintln!("Warning: Failed to update ITM file: {}", e);
+                }
+            }
+        }
+
+        BookmarksCommand::Split {
+            score,
+            every,
+            prefix,
+            pages,
+            dry_run,
+            json,
+        } => {
+            if every < 1 {
+                return Err(ForScoreError::Other("--every must be at least 1".into()));
+            }
+
+            if !dry_run {
+                warn_if_running();
+            }
+
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            let score = resolve_score(&conn, &score)?;
+
+            let total = match pages {
+                Some(p) => p,
+                None => score_page_count(&conn, &score)?.ok_or_else(|| {
+                    ForScoreError::Other(format!(
+                        "Page count unknown for '{}'; pass --pages",
+                        score.title
+                    ))
+                })?,
+            };
+
+            let ranges = split_ranges(total, every);
+
+            if dry_run {
+                let mut preview = DryRun::new(json);
+                for (i, (start, end)) in ranges.iter().enumerate() {
+                    let mut patch = Patch::create("bookmark", &format!("{} {}", prefix, i + 1));
+                    patch.change("score", "", score.title.as_str());
+                    patch.change("pages", "", format!("{}-{}", start, end));
+                    preview.add(patch);
+                }
+                preview.finish();
+                return Ok(());
+            }
+
+            let mut itm_bookmarks = Vec::new();
+            for (i, (start, end)) in ranges.iter().enumerate() {
+                let title = format!("{} {}", prefix, i + 1);
+                let bookmark = create_bookmark(&conn, &score, &title, *start, *end)?;
+                itm_bookmarks.push(ItmNewBookmark {
+                    title,
+                    identifier: bookmark.uuid.unwrap_or_default(),
+                    first_page: *start as i64,
+                    last_page: *end as i64,
+                });
+            }
+            touch_item(&conn, score.id)?;
+
+            match add_bookmarks_to_itm(&score.path, &itm_bookmarks) {
+                Ok(true) => println!(
+                    "Created {} bookmarks in '{}' + ITM",
+                    ranges.len(),
+                    score.title
+                ),
+                Ok(false) => println!(
+                    "Created {} bookmarks in '{}' (no ITM file)",
+                    ranges.len(),
+                    score.title
+                ),
+                Err(e) => {
+                    println!("Created {} bookmarks in '{}'", ranges.len(), score.title);
+                    eprintln!("Warning: Failed to update ITM file: {}", e);
+                }
+            }
+        }
+
+        BookmarksCommand::Shift {
+            score,
+            by,
+            from_page,
+            dry_run,
+            json,
+        } => {
+            if by == 0 {
+                return Err(ForScoreError::Other("--by can't be 0".into()));
+            }
+            if from_page < 1 {
+                return Err(ForScoreError::Other(
+                    "--from-page must be at least 1".into(),
+                ));
+            }
+
+            if !dry_run {
+                warn_if_running();
+            }
+
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            let score = resolve_score(&conn, &score)?;
+            let bookmarks = list_bookmarks(&conn, score.id)?;
+
+            let mut preview = DryRun::new(json);
+            let mut summary = Summary::default();
+            for bookmark in &bookmarks {
+                let (Some(start), Some(end)) = (bookmark.start_page, bookmark.end_page) else {
+                    summary.skip(&bookmark.title, "no page range");
+                    continue;
+                };
+                let Some((new_start, new_end)) = shift_range(start, end, by, from_page) else {
+                    summary.skip(&bookmark.title, "all of its pages were removed");
+                    continue;
+                };
+                if (new_start, new_end) == (start, end) {
+                    continue;
+                }
+
+                if dry_run {
+                    let mut patch = Patch::update("bookmark", bookmark.id, &bookmark.title);
+                    patch.change(
+                        "pages",
+                        page_range(bookmark),
+                        format!("{}-{}", new_start, new_end),
+                    );
+                    preview.add(patch);
+                    continue;
+                }
+
+                conn.execute(
+                    "UPDATE ZITEM SET ZSTARTPAGE = ?, ZENDPAGE = ? WHERE Z_PK = ?",
+                    rusqlite::params![new_start, new_end, bookmark.id],
+                )?;
+                touch_item(&conn, bookmark.id)?;
+
+                let mut itm_update = ItmBookmarkUpdate::new();
+                itm_update.first_page = Some(new_start as i64);
+                itm_update.last_page = Some(new_end as i64);
+                if let Err(e) =
+                    update_bookmark_in_itm(&bookmark.path, bookmark.uuid.as_deref(), &itm_update)
+                {
+                    summary.error(&bookmark.title, format!("ITM not updated: {}", e));
+                }
+                summary.updated += 1;
+            }
+
+            if dry_run {
+                for (title, reason) in &summary.skipped {
+                    eprintln!("Skipping '{}': {}", title, reason);
+                }
+                preview.finish();
+            } else {
+                summary.print("Shifted");
+            }
+        }
+
+        BookmarksCommand::FromToc {
+            score,
+            depth,
+            yes,
+            dry_run,
+            json,
+        } => {
+            let conn = open_readonly()?;
+            let score = resolve_score(&conn, &score)?;
+
+            let pdf = documents_path()?.join(&score.path);
+            let doc = lopdf::Document::load(&pdf).map_err(|e| {
+                ForScoreError::Other(format!("Can't read {}: {}", pdf.display(), e))
+            })?;
+            let toc = doc.get_toc().map_err(|_| {
+                ForScoreError::Other(format!("'{}' has no table of contents", score.title))
+            })?;
+            let total = doc.get_pages().len() as i32;
+
+            let entries: Vec<(usize, String, i32)> = toc
+                .toc
+                .into_iter()
+                .filter(|entry| depth.is_none_or(|depth| entry.level <= depth))
+                .map(|entry| (entry.level, entry.title, entry.page as i32))
+                .collect();
+
+            let existing = list_bookmarks(&conn, score.id)?;
+            let ranges: Vec<(String, i32, i32)> = toc_ranges(&entries, total)
+                .into_iter()
+                .filter(|(title, start, _)| {
+                    !existing.iter().any(|bm| {
+                        bm.title.eq_ignore_ascii_case(title) && bm.start_page == Some(*start)
+                    })
+                })
+                .collect();
+
+            if ranges.is_empty() {
+                println!(
+                    "No new bookmarks in the table of contents of '{}'",
+                    score.title
+                );
+                return Ok(());
+            }
+
+            if dry_run {
+                let mut preview = DryRun::new(json);
+                for (title, start, end) in &ranges {
+                    let mut patch = Patch::create("bookmark", title);
+                    patch.change("score", "", score.title.as_str());
+                    patch.change("pages", "", format!("{}-{}", start, end));
+                    preview.add(patch);
+                }
+                preview.finish();
+                return Ok(());
+            }
+
+            println!(
+                "Bookmarks from the table of contents of '{}':\n",
+                score.title
+            );
+            for (title, start, end) in &ranges {
+                println!("  {:>9}  {}", format!("{}-{}", start, end), title);
+            }
+            println!();
+            if !yes && !confirm(&format!("Create {} bookmark(s)?", ranges.len()))? {
+                println!("Nothing changed.");
+                return Ok(());
+            }
+
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let mut itm_bookmarks = Vec::new();
+            for (title, start, end) in &ranges {
+                let bookmark = create_bookmark(&conn, &score, title, *start, *end)?;
+                itm_bookmarks.push(ItmNewBookmark {
+                    title: title.clone(),
+                    identifier: bookmark.uuid.unwrap_or_default(),
+                    first_page: *start as i64,
+                    last_page: *end as i64,
+                });
+            }
+            touch_item(&conn, score.id)?;
+
+            match add_bookmarks_to_itm(&score.path, &itm_bookmarks) {
+                Ok(true) => println!(
+                    "Created {} bookmarks in '{}' + ITM",
+                    ranges.len(),
+                    score.title
+                ),
+                Ok(false) => println!(
+                    "Created {} bookmarks in '{}' (no ITM file)",
+                    ranges.len(),
+                    score.title
+                ),
+                Err(e) => {
+                    println!("Created {} bookmarks in '{}'", ranges.len(), score.title);
+                    eprintln!("Warning: Failed to update ITM file: {}", e);
+                }
+            }
         }
     }
 
     Ok(())
 }
+
+/// Page ranges for table of contents entries (level, title, first page):
+/// each runs until the next entry at the same or a higher level, the last
+/// ones to the end of the PDF
+fn toc_ranges(entries: &[(usize, String, i32)], total: i32) -> Vec<(String, i32, i32)> {
+    let mut entries: Vec<&(usize, String, i32)> = entries
+        .iter()
+        .filter(|(_, title, page)| !title.trim().is_empty() && (1..=total).contains(page))
+        .collect();
+    entries.sort_by_key(|(_, _, page)| *page);
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, (level, title, start))| {
+            let end = entries[i + 1..]
+                .iter()
+                .find(|(next_level, _, _)| next_level <= level)
+                .map(|(_, _, next)| (next - 1).max(*start))
+                .unwrap_or(total);
+            (title.trim().to_string(), *start, end)
+        })
+        .collect()
+}
+
+/// A bookmark's page range as shown in dry-run changes
+fn page_range(bookmark: &Bookmark) -> String {
+    match (bookmark.start_page, bookmark.end_page) {
+        (Some(start), Some(end)) => format!("{}-{}", start, end),
+        _ => String::new(),
+    }
+}
+
+/// Move a page range after `by` pages were inserted before page `from`
+/// (or, with a negative `by`, removed starting at `from`). Returns None when
+/// every page in the range was removed
+fn shift_range(start: i32, end: i32, by: i32, from: i32) -> Option<(i32, i32)> {
+    let shift = |page: i32, is_end: bool| {
+        if page < from {
+            page
+        } else if by >= 0 || page >= from - by {
+            page + by
+        } else if is_end {
+            // The last page was removed: end just before the gap
+            from - 1
+        } else {
+            // The first page was removed: start just after the gap
+            from
+        }
+    };
+    let (start, end) = (shift(start, false), shift(end, true));
+    (start <= end).then_some((start, end))
+}
+
+/// A page range as given to --pages: "5-9", or "5" for one page
+fn parse_pages(spec: &str) -> Result<(i32, i32)> {
+    let invalid = || {
+        ForScoreError::Other(format!(
+            "Invalid page range '{}': use first-last, e.g. 5-9",
+            spec
+        ))
+    };
+    let page = |s: &str| s.trim().parse::<i32>().map_err(|_| invalid());
+    let (start, end) = match spec.split_once('-') {
+        Some((start, end)) => (page(start)?, page(end)?),
+        None => {
+            let single = page(spec)?;
+            (single, single)
+        }
+    };
+    if start < 1 || end < start {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+/// Split pages 1..=total into consecutive ranges of `every` pages
+fn split_ranges(total: i32, every: i32) -> Vec<(i32, i32)> {
+    (1..=total)
+        .step_by(every as usize)
+        .map(|start| (start, (start + every - 1).min(total)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pages() {
+        assert_eq!(parse_pages("5-9").unwrap(), (5, 9));
+        assert_eq!(parse_pages(" 3 - 3 ").unwrap(), (3, 3));
+        assert_eq!(parse_pages("12").unwrap(), (12, 12));
+        assert!(parse_pages("9-5").is_err());
+        assert!(parse_pages("0-2").is_err());
+        assert!(parse_pages("a-b").is_err());
+    }
+
+    #[test]
+    fn test_split_ranges() {
+        assert_eq!(split_ranges(10, 4), vec![(1, 4), (5, 8), (9, 10)]);
+        assert_eq!(split_ranges(6, 3), vec![(1, 3), (4, 6)]);
+        assert_eq!(split_ranges(0, 3), vec![]);
+    }
+
+    #[test]
+    fn test_shift_range() {
+        // Two pages inserted before page 5
+        assert_eq!(shift_range(1, 4, 2, 5), Some((1, 4)));
+        assert_eq!(shift_range(5, 9, 2, 5), Some((7, 11)));
+        assert_eq!(shift_range(3, 6, 2, 5), Some((3, 8)));
+        // Pages 5-6 removed
+        assert_eq!(shift_range(7, 9, -2, 5), Some((5, 7)));
+        assert_eq!(shift_range(3, 6, -2, 5), Some((3, 4)));
+        assert_eq!(shift_range(6, 9, -2, 5), Some((5, 7)));
+        assert_eq!(shift_range(5, 6, -2, 5), None);
+        assert_eq!(shift_range(1, 4, -2, 5), Some((1, 4)));
+    }
+
+    #[test]
+    fn test_toc_ranges() {
+        let entries = vec![
+            (1, "Part I".to_string(), 3),
+            (2, "Etude 1".to_string(), 3),
+            (2, "Etude 2".to_string(), 6),
+            (1, "Part II".to_string(), 10),
+            (2, " ".to_string(), 11),
+            (2, "Missing".to_string(), 40),
+        ];
+        assert_eq!(
+            toc_ranges(&entries, 12),
+            vec![
+                ("Part I".to_string(), 3, 9),
+                ("Etude 1".to_string(), 3, 5),
+                ("Etude 2".to_string(), 6, 9),
+                ("Part II".to_string(), 10, 12),
+            ]
+        );
+    }
+}
@@ -1,55 +1,812 @@
-use crate::cli::ExportCommand;
-use crate::db::open_readonly;
-use crate::error::Result;
-use crate::models::score::list_scores_with_metadata;
-use csv::Writer;
-use std::fs::File;
+use crate::cache::load_page_counts;
+use crate::cli::{ExportCommand, SnapshotArgs};
+use crate::commands::scores::build_filter;
+use crate::csv_format::CsvFormat;
+use crate::db::{documents_path, open_readonly, open_snapshot};
+use crate::error::{ForScoreError, Result};
+use crate::export_diff::{csv_records, diff_records, json_records, Change, Record, RecordDiff};
+use crate::library_json::{read_library, stabilize_score, stable_key};
+use crate::models::score::{
+    list_all_bookmarks, list_scores_in_setlist, list_scores_with_metadata, load_metadata_batch,
+    search_scores, Bookmark, Score,
+};
+use crate::models::setlist::{list_shared_items, resolve_setlist, setlists_by_score};
+use crate::snapshot::{read_snapshot, write_snapshot};
+use crate::store::{all_composer_info, open_store, ComposerInfo};
+use chrono::{DateTime, SecondsFormat, Utc};
+use csv::{ReaderBuilder, Terminator, WriterBuilder};
+use rusqlite::Connection;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+const SITE_CSS: &str = "body { font-family: -apple-system, Helvetica, sans-serif; max-width: 48em; margin: 2em auto; padding: 0 1em; color: #222; }
+a { color: #0b5fa5; text-decoration: none; }
+a:hover { text-decoration: underline; }
+h2 { border-bottom: 1px solid #ddd; padding-bottom: 0.2em; margin-top: 2em; }
+h3 { margin-bottom: 0.3em; }
+ul { margin-top: 0.3em; }
+table { border-collapse: collapse; }
+th { text-align: left; padding: 0.2em 1em 0.2em 0; color: #666; font-weight: normal; }
+";
+
+/// Columns of `export csv` and the Scores sheet of `export xlsx`
+const SCORE_COLUMNS: [&str; 19] = [
+    "id",
+    "uuid",
+    "path",
+    "title",
+    "composer",
+    "genre",
+    "key",
+    "rating",
+    "difficulty",
+    "bpm",
+    "keywords",
+    "labels",
+    "libraries",
+    "pages",
+    "added",
+    "modified",
+    "last_played",
+    "composer_years",
+    "composer_nationality",
+];
+
+/// Columns of `export bookmarks-csv`, also read by `import bookmarks-csv`
+pub const BOOKMARK_COLUMNS: [&str; 9] = [
+    "id",
+    "uuid",
+    "path",
+    "title",
+    "start_page",
+    "end_page",
+    "composer",
+    "genre",
+    "rating",
+];
+
+/// Columns of the Setlists sheet of `export xlsx`, one row per item
+const SETLIST_COLUMNS: [&str; 6] = [
+    "setlist",
+    "position",
+    "title",
+    "path",
+    "start_page",
+    "end_page",
+];
+
+/// Workbook columns written as numbers rather than text
+const NUMBER_COLUMNS: &[&str] = &[
+    "id",
+    "rating",
+    "difficulty",
+    "bpm",
+    "pages",
+    "position",
+    "start_page",
+    "end_page",
+];
+
+/// The connection an export reads through: a snapshot with `--snapshot`
+fn open_export(args: &SnapshotArgs) -> Result<Connection> {
+    if args.snapshot {
+        open_snapshot()
+    } else {
+        open_readonly()
+    }
+}
 
 pub fn handle(cmd: ExportCommand) -> Result<()> {
     match cmd {
-        ExportCommand::Csv { output } => {
-            let conn = open_readonly()?;
-            let scores = list_scores_with_metadata(&conn)?;
-
-            let file = File::create(&output)?;
-            let mut wtr = Writer::from_writer(file);
-
-            // Write header
-            wtr.write_record([
-                "id",
-                "path",
-                "title",
-                "composer",
-                "genre",
-                "key",
-                "rating",
-                "difficulty",
-                "bpm",
-                "keywords",
-                "labels",
-            ])?;
-
-            // Write rows
+        ExportCommand::Csv {
+            output,
+            setlist,
+            search,
+            filter,
+            format,
+            bom,
+            snapshot,
+            stable,
+        } => {
+            let format = CsvFormat::from_args(&format)?;
+            let conn = open_export(&snapshot)?;
+            let mut scores = if search.is_some() || !filter.is_empty() {
+                let mut filter = build_filter(&conn, search, &filter)?;
+                filter.scores_only = true;
+                let mut scores = search_scores(&conn, &filter, usize::MAX)?;
+                load_metadata_batch(&conn, &mut scores)?;
+                scores
+            } else {
+                list_scores_with_metadata(&conn)?
+            };
+            if let Some(setlist) = &setlist {
+                let setlist = resolve_setlist(&conn, setlist)?;
+                let in_setlist: HashSet<i64> = list_scores_in_setlist(&conn, setlist.id)?
+                    .iter()
+                    .map(|s| s.id)
+                    .collect();
+                scores.retain(|s| in_setlist.contains(&s.id));
+            }
+            load_page_counts(&conn, &mut scores)?;
+            let composer_info = all_composer_info(&open_store()?)?;
+            if stable {
+                scores.sort_by_key(|s| stable_key(&s.uuid, &s.path));
+                scores.iter_mut().for_each(stabilize_score);
+            }
+
+            // Written to memory first, to be encoded as a whole
+            let mut wtr = WriterBuilder::new()
+                .delimiter(format.delimiter)
+                .terminator(if stable {
+                    Terminator::Any(b'\n')
+                } else {
+                    Terminator::CRLF
+                })
+                .from_writer(Vec::new());
+
+            wtr.write_record(SCORE_COLUMNS)?;
             for score in &scores {
+                wtr.write_record(score_row(score, &composer_info))?;
+            }
+
+            let csv = wtr
+                .into_inner()
+                .map_err(|e| ForScoreError::Other(e.to_string()))?;
+            let bytes = format.encode(&String::from_utf8_lossy(&csv), bom)?;
+            fs::write(&output, bytes)?;
+            println!("Exported {} scores to {}", scores.len(), output);
+        }
+
+        ExportCommand::Json {
+            output,
+            snapshot,
+            stable,
+        } => {
+            let conn = open_export(&snapshot)?;
+            let mut library = read_library(&conn)?;
+            if stable {
+                library.stabilize();
+            }
+            let mut text = serde_json::to_string_pretty(&library)?;
+            text.push('\n');
+            fs::write(&output, text)?;
+            println!(
+                "Exported {} scores, {} bookmarks and {} setlists to {}",
+                library.scores.len(),
+                library
+                    .scores
+                    .iter()
+                    .map(|s| s.bookmarks.len())
+                    .sum::<usize>(),
+                library.setlists.len(),
+                output
+            );
+        }
+
+        ExportCommand::Diff {
+            old,
+            new,
+            format,
+            json,
+        } => {
+            let format = CsvFormat::from_args(&format)?;
+            let (old_json, old_records) = read_export(&old, &format)?;
+            let (new_json, new_records) = read_export(&new, &format)?;
+            if old_json != new_json {
+                return Err(ForScoreError::Other(
+                    "Can't compare a JSON export with a CSV one".to_string(),
+                ));
+            }
+            let diffs = diff_records(&old_records, &new_records);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&diffs)?);
+            } else {
+                print_diff(&diffs);
+            }
+        }
+
+        ExportCommand::Snapshot { target } => {
+            let conn = open_snapshot()?;
+            let snapshot = read_snapshot(&conn)?;
+            let path = write_snapshot(&target, &snapshot)?;
+            println!(
+                "Saved {} scores, {} bookmarks, {} setlists and {} libraries to {}",
+                snapshot.scores.len(),
+                snapshot.bookmark_count(),
+                snapshot.setlists.len(),
+                snapshot.libraries.len(),
+                path.display()
+            );
+        }
+
+        ExportCommand::Xlsx { output, snapshot } => {
+            let conn = open_export(&snapshot)?;
+            let library = read_library(&conn)?;
+            let composer_info = all_composer_info(&open_store()?)?;
+
+            let scores: Vec<Vec<String>> = library
+                .scores
+                .iter()
+                .map(|s| score_row(&s.score, &composer_info))
+                .collect();
+            let bookmarks: Vec<Vec<String>> = library
+                .scores
+                .iter()
+                .flat_map(|s| s.bookmarks.iter().map(bookmark_row))
+                .collect();
+            let setlists: Vec<Vec<String>> = library
+                .setlists
+                .iter()
+                .flat_map(|setlist| {
+                    setlist.items.iter().enumerate().map(|(i, item)| {
+                        vec![
+                            setlist.title.clone(),
+                            (i + 1).to_string(),
+                            item.title.clone(),
+                            item.path.clone(),
+                            item.start_page.map(|p| p.to_string()).unwrap_or_default(),
+                            item.end_page.map(|p| p.to_string()).unwrap_or_default(),
+                        ]
+                    })
+                })
+                .collect();
+
+            let mut workbook = Workbook::new();
+            add_sheet(&mut workbook, "Scores", &SCORE_COLUMNS, &scores)?;
+            add_sheet(&mut workbook, "Setlists", &SETLIST_COLUMNS, &setlists)?;
+            add_sheet(&mut workbook, "Bookmarks", &BOOKMARK_COLUMNS, &bookmarks)?;
+            workbook.save(&output).map_err(xlsx_error)?;
+            println!(
+                "Exported {} scores, {} bookmarks and {} setlists to {}",
+                scores.len(),
+                bookmarks.len(),
+                library.setlists.len(),
+                output
+            );
+        }
+
+        ExportCommand::BookmarksCsv {
+            output,
+            format,
+            bom,
+            snapshot,
+        } => {
+            let format = CsvFormat::from_args(&format)?;
+            let conn = open_export(&snapshot)?;
+            let mut bookmarks = list_all_bookmarks(&conn)?;
+            for bookmark in &mut bookmarks {
+                bookmark.load_metadata(&conn)?;
+            }
+
+            let mut wtr = WriterBuilder::new()
+                .delimiter(format.delimiter)
+                .from_writer(Vec::new());
+            wtr.write_record(BOOKMARK_COLUMNS)?;
+            for bookmark in &bookmarks {
+                wtr.write_record(bookmark_row(bookmark))?;
+            }
+
+            let csv = wtr
+                .into_inner()
+                .map_err(|e| ForScoreError::Other(e.to_string()))?;
+            let bytes = format.encode(&String::from_utf8_lossy(&csv), bom)?;
+            fs::write(&output, bytes)?;
+            println!("Exported {} bookmarks to {}", bookmarks.len(), output);
+        }
+
+        ExportCommand::MissingReport {
+            output,
+            markdown,
+            format,
+            snapshot,
+        } => {
+            let format = CsvFormat::from_args(&format)?;
+            let conn = open_export(&snapshot)?;
+            let documents = documents_path()?;
+            let mut scores = list_scores_with_metadata(&conn)?;
+            scores.retain(|s| !documents.join(&s.path).exists());
+            load_page_counts(&conn, &mut scores)?;
+            let setlists = setlists_by_score(&conn)?;
+            sort_worklist(&mut scores, &setlists);
+
+            let output = output.unwrap_or_else(|| {
+                if markdown {
+                    "missing.md"
+                } else {
+                    "missing.csv"
+                }
+                .to_string()
+            });
+            if markdown {
+                fs::write(&output, missing_markdown(&scores, &setlists))?;
+            } else {
+                let mut wtr = WriterBuilder::new()
+                    .delimiter(format.delimiter)
+                    .from_writer(Vec::new());
                 wtr.write_record([
-                    &score.id.to_string(),
-                    &score.path,
-                    &score.title,
-                    &score.composers.join("; "),
-                    &score.genres.join("; "),
-                    &score.key.as_ref().map(|k| k.display()).unwrap_or_default(),
-                    &score.rating.map(|r| r.to_string()).unwrap_or_default(),
-                    &score.difficulty.map(|d| d.to_string()).unwrap_or_default(),
-                    &score.bpm.map(|b| b.to_string()).unwrap_or_default(),
-                    &score.keywords.join("; "),
-                    &score.labels.join("; "),
+                    "id",
+                    "title",
+                    "composer",
+                    "path",
+                    "pages",
+                    "setlists",
+                    "libraries",
                 ])?;
+                for score in &scores {
+                    wtr.write_record([
+                        &score.id.to_string(),
+                        &score.title,
+                        &score.composers.join("; "),
+                        &score.path,
+                        &score.pages.map(|p| p.to_string()).unwrap_or_default(),
+                        &setlists
+                            .get(&score.id)
+                            .map(|s| s.join("; "))
+                            .unwrap_or_default(),
+                        &score.libraries.join("; "),
+                    ])?;
+                }
+                let csv = wtr
+                    .into_inner()
+                    .map_err(|e| ForScoreError::Other(e.to_string()))?;
+                fs::write(
+                    &output,
+                    format.encode(&String::from_utf8_lossy(&csv), false)?,
+                )?;
             }
 
-            wtr.flush()?;
-            println!("Exported {} scores to {}", scores.len(), output);
+            let in_setlists = scores
+                .iter()
+                .filter(|s| setlists.contains_key(&s.id))
+                .count();
+            println!(
+                "{} scores are missing their PDF ({} in setlists); wrote {}",
+                scores.len(),
+                in_setlists,
+                output
+            );
+        }
+
+        ExportCommand::Site { out, snapshot } => {
+            let conn = open_export(&snapshot)?;
+            let mut scores = list_scores_with_metadata(&conn)?;
+            load_page_counts(&conn, &mut scores)?;
+            let composer_info = all_composer_info(&open_store()?)?;
+
+            // Setlist membership by item ID
+            let setlists: HashMap<i64, Vec<String>> = list_shared_items(&conn, 1)?
+                .into_iter()
+                .map(|item| (item.id, item.setlists))
+                .collect();
+
+            write_site(Path::new(&out), &scores, &setlists, &composer_info)?;
+            println!(
+                "Exported {} scores to {}/index.html",
+                scores.len(),
+                out.trim_end_matches('/')
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Scores in the most setlists first, then by title
+fn sort_worklist(scores: &mut [Score], setlists: &HashMap<i64, Vec<String>>) {
+    let count = |s: &Score| setlists.get(&s.id).map_or(0, Vec::len);
+    scores.sort_by(|a, b| {
+        count(b)
+            .cmp(&count(a))
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    });
+}
+
+/// The missing-file worklist as a Markdown checklist
+fn missing_markdown(scores: &[Score], setlists: &HashMap<i64, Vec<String>>) -> String {
+    let mut out = format!("# Scores to re-scan\n\n{} missing PDFs\n\n", scores.len());
+    for score in scores {
+        let mut details = Vec::new();
+        if !score.composers.is_empty() {
+            details.push(score.composers.join(", "));
         }
+        if let Some(pages) = score.pages {
+            details.push(format!("{} pages", pages));
+        }
+        details.push(format!("`{}`", score.path));
+        if let Some(names) = setlists.get(&score.id) {
+            details.push(format!("setlists: {}", names.join(", ")));
+        }
+        out.push_str(&format!(
+            "- [ ] **{}** — {}\n",
+            score.title,
+            details.join(" · ")
+        ));
     }
+    out
+}
 
+/// Add a worksheet with a bold, frozen header row
+fn add_sheet(
+    workbook: &mut Workbook,
+    name: &str,
+    headers: &[&str],
+    rows: &[Vec<String>],
+) -> Result<()> {
+    let bold = Format::new().set_bold();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(name).map_err(xlsx_error)?;
+    for (col, header) in headers.iter().enumerate() {
+        sheet
+            .write_string_with_format(0, col as u16, *header, &bold)
+            .map_err(xlsx_error)?;
+    }
+    for (row, cells) in rows.iter().enumerate() {
+        let row = row as u32 + 1;
+        for (col, cell) in cells.iter().enumerate() {
+            if cell.is_empty() {
+                continue;
+            }
+            let number = cell.parse::<f64>().ok();
+            match number.filter(|_| NUMBER_COLUMNS.contains(&headers[col])) {
+                Some(number) => sheet.write_number(row, col as u16, number),
+                None => sheet.write_string(row, col as u16, cell),
+            }
+            .map_err(xlsx_error)?;
+        }
+    }
+    sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
     Ok(())
 }
+
+fn xlsx_error(e: XlsxError) -> ForScoreError {
+    ForScoreError::Other(format!("Can't write workbook: {}", e))
+}
+
+/// Read an export as diff records: JSON if the file looks like it, CSV
+/// otherwise (the bool says which)
+fn read_export(path: &Path, format: &CsvFormat) -> Result<(bool, Vec<Record>)> {
+    let text = format.decode(&fs::read(path)?);
+    if text.trim_start().starts_with('{') {
+        let records = json_records(&text)
+            .map_err(|e| ForScoreError::Other(format!("{}: {}", path.display(), e)))?;
+        return Ok((true, records));
+    }
+
+    let mut rdr = ReaderBuilder::new()
+        .delimiter(format.delimiter)
+        .from_reader(text.as_bytes());
+    let headers = rdr.headers()?.clone();
+    let rows = rdr.records().collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok((false, csv_records(&headers, &rows)))
+}
+
+fn print_diff(diffs: &[RecordDiff]) {
+    if diffs.is_empty() {
+        println!("No differences");
+        return;
+    }
+    for (kind, heading) in [
+        ("score", "Scores"),
+        ("bookmark", "Bookmarks"),
+        ("setlist", "Setlists"),
+    ] {
+        let diffs: Vec<&RecordDiff> = diffs.iter().filter(|d| d.kind == kind).collect();
+        if diffs.is_empty() {
+            continue;
+        }
+        let count = |change: Change| diffs.iter().filter(|d| d.change == change).count();
+        println!(
+            "{}: {} added, {} removed, {} changed",
+            heading,
+            count(Change::Added),
+            count(Change::Removed),
+            count(Change::Changed)
+        );
+        for diff in diffs {
+            let mark = match diff.change {
+                Change::Added => '+',
+                Change::Removed => '-',
+                Change::Changed => '~',
+            };
+            println!("  {} {}", mark, diff.label);
+            for field in &diff.fields {
+                let text = |s: &str| {
+                    if s.is_empty() {
+                        "(none)".to_string()
+                    } else {
+                        s.to_string()
+                    }
+                };
+                println!(
+                    "      {}: {} -> {}",
+                    field.field,
+                    text(&field.old),
+                    text(&field.new)
+                );
+            }
+        }
+    }
+}
+
+/// A score's cells, in `SCORE_COLUMNS` order
+fn score_row(score: &Score, composer_info: &HashMap<String, ComposerInfo>) -> Vec<String> {
+    vec![
+        score.id.to_string(),
+        score.uuid.clone().unwrap_or_default(),
+        score.path.clone(),
+        score.title.clone(),
+        score.composers.join("; "),
+        score.genres.join("; "),
+        score.key.as_ref().map(|k| k.display()).unwrap_or_default(),
+        score.rating.map(|r| r.to_string()).unwrap_or_default(),
+        score.difficulty.map(|d| d.to_string()).unwrap_or_default(),
+        score.bpm.map(|b| b.to_string()).unwrap_or_default(),
+        score.keywords.join("; "),
+        score.labels.join("; "),
+        score.libraries.join("; "),
+        score.pages.map(|p| p.to_string()).unwrap_or_default(),
+        date_column(score.added),
+        date_column(score.modified),
+        date_column(score.last_played),
+        composer_column(&score.composers, composer_info, |i| i.years()),
+        composer_column(&score.composers, composer_info, |i| i.nationality.clone()),
+    ]
+}
+
+/// A bookmark's cells, in `BOOKMARK_COLUMNS` order
+pub fn bookmark_row(bookmark: &Bookmark) -> Vec<String> {
+    vec![
+        bookmark.id.to_string(),
+        bookmark.uuid.clone().unwrap_or_default(),
+        bookmark.path.clone(),
+        bookmark.title.clone(),
+        bookmark
+            .start_page
+            .map(|p| p.to_string())
+            .unwrap_or_default(),
+        bookmark.end_page.map(|p| p.to_string()).unwrap_or_default(),
+        bookmark.composers.join("; "),
+        bookmark.genres.join("; "),
+        bookmark.rating.map(|r| r.to_string()).unwrap_or_default(),
+    ]
+}
+
+/// A date as RFC 3339 in UTC, or empty
+fn date_column(date: Option<DateTime<Utc>>) -> String {
+    date.map(|d| d.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+/// One value per composer, aligned with the "; "-joined composer column
+/// (empty if no composer has a value)
+fn composer_column(
+    composers: &[String],
+    info: &HashMap<String, ComposerInfo>,
+    field: impl Fn(&ComposerInfo) -> Option<String>,
+) -> String {
+    let values: Vec<String> = composers
+        .iter()
+        .map(|c| {
+            info.get(&c.to_lowercase())
+                .and_then(&field)
+                .unwrap_or_default()
+        })
+        .collect();
+    if values.iter().all(|v| v.is_empty()) {
+        String::new()
+    } else {
+        values.join("; ")
+    }
+}
+
+/// A composer's name with lifespan and nationality, e.g. "J.S. Bach (1685-1750, German)"
+fn composer_with_details(name: &str, info: &HashMap<String, ComposerInfo>) -> String {
+    let details: Vec<String> = info
+        .get(&name.to_lowercase())
+        .map(|i| i.years().into_iter().chain(i.nationality.clone()).collect())
+        .unwrap_or_default();
+    if details.is_empty() {
+        name.to_string()
+    } else {
+        format!("{} ({})", name, details.join(", "))
+    }
+}
+
+/// Write index.html, one page per score, and a stylesheet into `out`
+fn write_site(
+    out: &Path,
+    scores: &[Score],
+    setlists: &HashMap<i64, Vec<String>>,
+    composer_info: &HashMap<String, ComposerInfo>,
+) -> Result<()> {
+    fs::create_dir_all(out.join("scores"))?;
+    fs::write(out.join("style.css"), SITE_CSS)?;
+
+    let mut by_composer: BTreeMap<String, Vec<&Score>> = BTreeMap::new();
+    let mut by_genre: BTreeMap<String, Vec<&Score>> = BTreeMap::new();
+
+    for score in scores {
+        let composers = if score.composers.is_empty() {
+            vec!["Unknown Composer".to_string()]
+        } else {
+            score.composers.clone()
+        };
+        for composer in composers {
+            by_composer
+                .entry(composer_with_details(&composer, composer_info))
+                .or_default()
+                .push(score);
+        }
+        for genre in &score.genres {
+            by_genre.entry(genre.clone()).or_default().push(score);
+        }
+
+        let no_setlists = Vec::new();
+        let page = score_page(
+            score,
+            setlists.get(&score.id).unwrap_or(&no_setlists),
+            composer_info,
+        );
+        fs::write(out.join("scores").join(format!("{}.html", score.id)), page)?;
+    }
+
+    let mut body = format!("<h1>Repertoire</h1>\n<p>{} scores</p>\n", scores.len());
+    body.push_str("<h2>By Composer</h2>\n");
+    body.push_str(&index_section(&by_composer));
+    if !by_genre.is_empty() {
+        body.push_str("<h2>By Genre</h2>\n");
+        body.push_str(&index_section(&by_genre));
+    }
+
+    fs::write(out.join("index.html"), html_page("Repertoire", "", &body))?;
+    Ok(())
+}
+
+/// Render grouped score links as headings with lists
+fn index_section(groups: &BTreeMap<String, Vec<&Score>>) -> String {
+    let mut html = String::new();
+    for (name, scores) in groups {
+        html.push_str(&format!("<h3>{}</h3>\n<ul>\n", escape_html(name)));
+        for score in scores {
+            html.push_str(&format!(
+                "<li><a href=\"scores/{}.html\">{}</a></li>\n",
+                score.id,
+                escape_html(&score.title)
+            ));
+        }
+        html.push_str("</ul>\n");
+    }
+    html
+}
+
+/// Render the page for a single score
+fn score_page(
+    score: &Score,
+    setlists: &[String],
+    composer_info: &HashMap<String, ComposerInfo>,
+) -> String {
+    let mut rows = Vec::new();
+    let mut row = |label: &str, value: String| {
+        if !value.is_empty() {
+            rows.push(format!(
+                "<tr><th>{}</th><td>{}</td></tr>",
+                label,
+                escape_html(&value)
+            ));
+        }
+    };
+
+    let composers: Vec<String> = score
+        .composers
+        .iter()
+        .map(|c| composer_with_details(c, composer_info))
+        .collect();
+    row("Composer", composers.join(", "));
+    row("Genre", score.genres.join(", "));
+    row(
+        "Key",
+        score.key.as_ref().map(|k| k.display()).unwrap_or_default(),
+    );
+    row(
+        "Rating",
+        score
+            .rating
+            .map(|r| "★".repeat(r as usize))
+            .unwrap_or_default(),
+    );
+    row(
+        "Difficulty",
+        score.difficulty.map(|d| d.to_string()).unwrap_or_default(),
+    );
+    row("BPM", score.bpm.map(|b| b.to_string()).unwrap_or_default());
+    row(
+        "Pages",
+        score.pages.map(|p| p.to_string()).unwrap_or_default(),
+    );
+    row("Tags", score.keywords.join(", "));
+    row("Labels", score.labels.join(", "));
+
+    let mut body = format!(
+        "<p><a href=\"../index.html\">&larr; All scores</a></p>\n<h1>{}</h1>\n<table>\n{}\n</table>\n",
+        escape_html(&score.title),
+        rows.join("\n")
+    );
+
+    if !setlists.is_empty() {
+        body.push_str("<h2>Setlists</h2>\n<ul>\n");
+        for setlist in setlists {
+            body.push_str(&format!("<li>{}</li>\n", escape_html(setlist)));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    html_page(&score.title, "../", &body)
+}
+
+/// Wrap a body in a complete HTML document; `root` is the relative path to the site root
+fn html_page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{}</title>
+<link rel=\"stylesheet\" href=\"{}style.css\">
+</head>
+<body>
+{}</body>
+</html>
+",
+        escape_html(title),
+        root,
+        body
+    )
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(id: i64, title: &str) -> Score {
+        Score {
+            id,
+            path: format!("{}.pdf", title),
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_missing_markdown() {
+        let mut scores = vec![score(1, "Waltz"), score(2, "Air"), score(3, "Prelude")];
+        scores[2].composers = vec!["J.S. Bach".into()];
+        scores[2].pages = Some(4);
+        let setlists = HashMap::from([(3, vec!["Gig".to_string(), "Recital".to_string()])]);
+        sort_worklist(&mut scores, &setlists);
+        assert_eq!(
+            missing_markdown(&scores, &setlists),
+            "# Scores to re-scan\n\n3 missing PDFs\n\n\
+             - [ ] **Prelude** — J.S. Bach · 4 pages · `Prelude.pdf` · setlists: Gig, Recital\n\
+             - [ ] **Air** — `Air.pdf`\n\
+             - [ ] **Waltz** — `Waltz.pdf`\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("Rock & Roll <\"live\">"),
+            "Rock &amp; Roll &lt;&quot;live&quot;&gt;"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::db::open_readonly;
+use crate::error::Result;
+use crate::models::library::list_libraries;
+use crate::models::meta::list_composers;
+use crate::models::score::{load_metadata_batch, search_scores, ScoreFilter};
+use crate::models::setlist::list_setlists;
+use serde::Serialize;
+
+/// One match from `find`
+#[derive(Debug, Serialize)]
+pub struct FindResult {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub id: i64,
+    pub name: String,
+    /// Extra context: a score's composers, a bookmark's pages, a count of scores
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Group headings, in display order
+const GROUPS: &[(&str, &str)] = &[
+    ("score", "Scores"),
+    ("bookmark", "Bookmarks"),
+    ("setlist", "Setlists"),
+    ("composer", "Composers"),
+    ("library", "Libraries"),
+];
+
+pub fn find(query: String, limit: usize, json: bool) -> Result<()> {
+    let conn = open_readonly()?;
+    let mut results = Vec::new();
+
+    // Scores and bookmarks match on title or composer, like `scores search`
+    let filter = ScoreFilter {
+        query: Some(query.clone()),
+        ..Default::default()
+    };
+    let mut items = search_scores(&conn, &filter, limit)?;
+    load_metadata_batch(&conn, &mut items)?;
+    for item in items {
+        let (kind, detail) = match (item.start_page, item.end_page) {
+            (Some(start), Some(end)) => (
+                "bookmark",
+                Some(format!("{}, pp. {}-{}", item.path, start, end)),
+            ),
+            _ => (
+                "score",
+                Some(item.composers.join(", ")).filter(|c| !c.is_empty()),
+            ),
+        };
+        results.push(FindResult {
+            kind,
+            id: item.id,
+            name: item.title,
+            detail,
+        });
+    }
+
+    let count = |n: i32| Some(format!("{} scores", n));
+
+    for setlist in list_setlists(&conn)? {
+        if matches_query(&setlist.title, &query) {
+            results.push(FindResult {
+                kind: "setlist",
+                id: setlist.id,
+                name: setlist.title,
+                detail: count(setlist.score_count),
+            });
+        }
+    }
+
+    for composer in list_composers(&conn, false)? {
+        if matches_query(&composer.name, &query) {
+            results.push(FindResult {
+                kind: "composer",
+                id: composer.id,
+                name: composer.name,
+                detail: count(composer.score_count),
+            });
+        }
+    }
+
+    for library in list_libraries(&conn)? {
+        if matches_query(&library.title, &query) {
+            results.push(FindResult {
+                kind: "library",
+                id: library.id,
+                name: library.title,
+                detail: count(library.score_count),
+            });
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+
+    if results.is_empty() {
+        println!("No matches for '{}'", query);
+        return Ok(());
+    }
+
+    for (kind, heading) in GROUPS {
+        let group: Vec<&FindResult> = results.iter().filter(|r| r.kind == *kind).collect();
+        if group.is_empty() {
+            continue;
+        }
+        println!("{} ({}):", heading, group.len());
+        for result in group.iter().take(limit) {
+            match &result.detail {
+                Some(detail) => println!("  {:>5}  {}  ({})", result.id, result.name, detail),
+                None => println!("  {:>5}  {}", result.id, result.name),
+            }
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Case-insensitive match where the query's words appear in order, the same
+/// rule `scores search` uses ("op 28" matches "Op. 28")
+fn matches_query(text: &str, query: &str) -> bool {
+    let text = text.to_lowercase();
+    let mut rest = text.as_str();
+    for word in query.to_lowercase().split_whitespace() {
+        match rest.find(word) {
+            Some(pos) => rest = &rest[pos + word.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_query() {
+        assert!(matches_query("Preludes, Op. 28", "op 28"));
+        assert!(matches_query("Nimrod", "nim"));
+        assert!(!matches_query("Op. 28 Preludes", "28 op"));
+        assert!(!matches_query("Nimrod", "enigma"));
+    }
+}
@@ -1,8 +1,32 @@
-use crate::cli::FixesCommand;
-use crate::db::{entity, open_readonly, open_readwrite, warn_if_running};
+use crate::cli::{BookmarkSource, FixesCommand, TitleSuffix};
+use crate::commands::scores::confirm;
+use crate::db::{documents_path, entity, open_readonly, open_readwrite, warn_if_running};
+use crate::dry_run::{DryRun, Patch};
 use crate::error::Result;
-use crate::itm::delete_bookmark_from_itm;
+use crate::fuzzy::edit_distance_within;
+use crate::itm::{
+    add_bookmarks_to_itm, delete_bookmark_from_itm, itm_bookmarks, itm_path_for_score, read_itm,
+    rename_composer_in_all_itm, rename_itm, set_bookmark_identifier_in_itm, update_itm,
+    ItmBookmark, ItmNewBookmark, ItmUpdate,
+};
+use crate::models::meta::{composer_items, list_composers, merge_composers, Composer};
+use crate::models::score::{
+    create_bookmark_with_uuid, delete_item, list_bookmarks, load_metadata_batch, merge_score_into,
+    resolve_score, score_page_count, search_scores, Bookmark, ScoreFilter,
+};
+use crate::models::setlist::list_item_setlist_entries;
+use crate::models::Score;
+use crate::progress::{Progress, Summary};
+use crate::setlist_sync::{
+    all_setlist_file_paths, remove_item_from_setlist_file, rewrite_file_paths_in_all_setlists,
+};
+use crate::sort_title::sort_title;
+use crate::store::{open_store, rename_composer_info};
+use crate::touch::touch_item;
 use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
 
 pub fn handle(cmd: FixesCommand) -> Result<()> {
     match cmd {
@@ -36,16 +60,1244 @@ pub fn handle(cmd: FixesCommand) -> Result<()> {
 
             if apply {
                 println!();
+                let progress = Progress::new(duplicates.len(), "Deleting duplicates");
+                let mut summary = Summary::default();
                 for dup in &duplicates {
-                    delete_bookmark(&conn, dup)?;
+                    progress.inc();
+                    delete_bookmark(&conn, dup, &progress, &mut summary)?;
                 }
-                println!("\nDeleted {} duplicate bookmark(s).", duplicates.len());
+                progress.finish();
+                println!();
+                summary.print("Deleted");
             } else {
                 println!("\nRun with --apply to delete duplicates.");
             }
         }
+
+        FixesCommand::PathNormalization { dry_run, json } => {
+            if !dry_run {
+                warn_if_running();
+            }
+
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            fix_path_normalization(&conn, dry_run.then(|| DryRun::new(json)))?;
+        }
+
+        FixesCommand::SortTitles { dry_run, json } => {
+            if !dry_run {
+                warn_if_running();
+            }
+
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            fix_sort_titles(&conn, dry_run.then(|| DryRun::new(json)))?;
+        }
+
+        FixesCommand::BookmarkSync {
+            prefer,
+            yes,
+            dry_run,
+            json,
+        } => {
+            if dry_run {
+                let mut preview = DryRun::new(json);
+                fix_bookmark_sync(&open_readonly()?, prefer, Some(&mut preview))?;
+                preview.finish();
+                return Ok(());
+            }
+
+            if !yes {
+                let planned =
+                    fix_bookmark_sync(&open_readonly()?, prefer, Some(&mut DryRun::new(false)))?;
+                if planned == 0 {
+                    return Ok(());
+                }
+                println!();
+                if !confirm(&format!("Apply {} change(s)?", planned))? {
+                    println!("Nothing changed.");
+                    return Ok(());
+                }
+            }
+            warn_if_running();
+            fix_bookmark_sync(&open_readwrite()?, prefer, None)?;
+        }
+
+        FixesCommand::OrphanedBookmarks {
+            reattach_to,
+            dry_run,
+            json,
+        } => {
+            if !dry_run {
+                warn_if_running();
+            }
+
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            fix_orphaned_bookmarks(
+                &conn,
+                reattach_to.as_deref(),
+                dry_run.then(|| DryRun::new(json)),
+            )?;
+        }
+
+        FixesCommand::AmbiguousTitles {
+            distance,
+            suffix,
+            apply,
+            json,
+        } => {
+            if apply {
+                warn_if_running();
+            }
+
+            let conn = if apply {
+                open_readwrite()?
+            } else {
+                open_readonly()?
+            };
+
+            fix_ambiguous_titles(&conn, distance, suffix, apply, json)?;
+        }
+
+        FixesCommand::DuplicateComposers { yes, json } => {
+            fix_duplicate_composers(yes, json)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Bookmarks only in the database and only in the ITM file, matched by identifier
+fn unmatched_bookmarks<'a>(
+    db: &'a [Bookmark],
+    itm: &'a [ItmBookmark],
+) -> (Vec<&'a Bookmark>, Vec<&'a ItmBookmark>) {
+    let itm_ids: HashSet<&str> = itm.iter().filter_map(|b| b.identifier.as_deref()).collect();
+    let db_ids: HashSet<&str> = db.iter().filter_map(|b| b.uuid.as_deref()).collect();
+
+    let db_only = db
+        .iter()
+        .filter(|b| !b.uuid.as_deref().is_some_and(|id| itm_ids.contains(id)))
+        .collect();
+    let itm_only = itm
+        .iter()
+        .filter(|b| {
+            !b.identifier
+                .as_deref()
+                .is_some_and(|id| db_ids.contains(id))
+        })
+        .collect();
+    (db_only, itm_only)
+}
+
+/// Compare each score's bookmarks with its ITM file and reconcile them; with
+/// `preview` set, only report the planned changes
+fn fix_bookmark_sync(
+    conn: &Connection,
+    prefer: Option<BookmarkSource>,
+    mut preview: Option<&mut DryRun>,
+) -> Result<usize> {
+    let filter = ScoreFilter {
+        scores_only: true,
+        ..Default::default()
+    };
+    let scores = search_scores(conn, &filter, usize::MAX)?;
+
+    let progress = if preview.is_some() {
+        Progress::hidden()
+    } else {
+        Progress::new(scores.len(), "Checking bookmarks")
+    };
+    let mut summary = Summary::default();
+    let mut without_itm = 0;
+
+    for score in &scores {
+        progress.inc();
+
+        let itm_path = itm_path_for_score(&score.path)?;
+        if !itm_path.exists() {
+            without_itm += 1;
+            continue;
+        }
+        let itm = match read_itm(&itm_path) {
+            Ok(value) => itm_bookmarks(&value),
+            Err(e) => {
+                summary.error(&score.title, e.to_string());
+                continue;
+            }
+        };
+        let db = list_bookmarks(conn, score.id)?;
+        let (db_only, itm_only) = unmatched_bookmarks(&db, &itm);
+
+        // Bookmarks only in the database: add to the ITM, or delete if the ITM wins
+        let mut to_itm = Vec::new();
+        for bookmark in db_only {
+            let name = format!("{}: {}", score.title, bookmark.title);
+            if prefer == Some(BookmarkSource::Itm) {
+                if let Some(preview) = preview.as_deref_mut() {
+                    let mut patch =
+                        Patch::new("delete", "bookmark", Some(bookmark.id), &bookmark.title);
+                    patch.change("score", score.title.as_str(), "");
+                    patch.change("only in", "database", "");
+                    preview.add(patch);
+                } else {
+                    delete_item(conn, bookmark.id)?;
+                    progress.println(format!("Deleted from database: {}", name));
+                }
+                summary.updated += 1;
+                continue;
+            }
+
+            let (Some(first), Some(last), Some(uuid)) = (
+                bookmark.start_page,
+                bookmark.end_page,
+                bookmark.uuid.as_ref(),
+            ) else {
+                summary.skip(name, "no page range or UUID in the database");
+                continue;
+            };
+            if let Some(preview) = preview.as_deref_mut() {
+                let mut patch = Patch::create("bookmark", &bookmark.title);
+                patch.change("score", "", score.title.as_str());
+                patch.change("add to", "", "ITM");
+                preview.add(patch);
+            } else {
+                to_itm.push(ItmNewBookmark {
+                    title: bookmark.title.clone(),
+                    identifier: uuid.clone(),
+                    first_page: first as i64,
+                    last_page: last as i64,
+                });
+                progress.println(format!("Added to ITM: {}", name));
+            }
+            summary.updated += 1;
+        }
+        if let Err(e) = add_bookmarks_to_itm(&score.path, &to_itm) {
+            summary.error(&score.title, format!("failed to update ITM: {}", e));
+        }
+
+        // Bookmarks only in the ITM: create in the database, or remove if the database wins
+        for bookmark in itm_only {
+            let name = format!("{}: {}", score.title, bookmark.title);
+            if prefer == Some(BookmarkSource::Db) {
+                let Some(identifier) = bookmark.identifier.as_deref() else {
+                    summary.skip(name, "no identifier in the ITM");
+                    continue;
+                };
+                if let Some(preview) = preview.as_deref_mut() {
+                    let mut patch = Patch::new("delete", "bookmark", None, &bookmark.title);
+                    patch.change("score", score.title.as_str(), "");
+                    patch.change("only in", "ITM", "");
+                    preview.add(patch);
+                } else {
+                    match delete_bookmark_from_itm(&score.path, Some(identifier)) {
+                        Ok(_) => progress.println(format!("Removed from ITM: {}", name)),
+                        Err(e) => {
+                            summary.error(name, format!("failed to update ITM: {}", e));
+                            continue;
+                        }
+                    }
+                }
+                summary.updated += 1;
+                continue;
+            }
+
+            let (Some(first), Some(last)) = (bookmark.first_page, bookmark.last_page) else {
+                summary.skip(name, "no page range in the ITM");
+                continue;
+            };
+            if let Some(preview) = preview.as_deref_mut() {
+                let mut patch = Patch::create("bookmark", &bookmark.title);
+                patch.change("score", "", score.title.as_str());
+                patch.change("add to", "", "database");
+                preview.add(patch);
+            } else {
+                // An ITM entry without an identifier gets one first, so the
+                // row created for it matches it on the next run
+                let uuid = match &bookmark.identifier {
+                    Some(identifier) => identifier.clone(),
+                    None => {
+                        let uuid = uuid::Uuid::new_v4().to_string().to_uppercase();
+                        match set_bookmark_identifier_in_itm(&score.path, bookmark, &uuid) {
+                            Ok(true) => uuid,
+                            Ok(false) => {
+                                summary.skip(name, "ITM entry not found to add an identifier");
+                                continue;
+                            }
+                            Err(e) => {
+                                summary.error(name, format!("failed to update ITM: {}", e));
+                                continue;
+                            }
+                        }
+                    }
+                };
+                create_bookmark_with_uuid(
+                    conn,
+                    score,
+                    &bookmark.title,
+                    first as i32,
+                    last as i32,
+                    &uuid,
+                )?;
+                touch_item(conn, score.id)?;
+                progress.println(format!("Added to database: {}", name));
+            }
+            summary.updated += 1;
+        }
+    }
+    progress.finish();
+
+    match &preview {
+        Some(preview) if preview.is_json() => {}
+        Some(_) => {
+            println!();
+            summary.print("Would change");
+        }
+        None => {
+            println!();
+            summary.print("Changed");
+        }
+    }
+    if without_itm > 0 {
+        eprintln!(
+            "{} score(s) without an ITM file were not checked",
+            without_itm
+        );
+    }
+
+    Ok(summary.updated)
+}
+
+/// Normalize a path for comparison: percent-decoded and lowercased
+fn normalize_path(path: &str) -> String {
+    urlencoding::decode(path)
+        .map(|p| p.into_owned())
+        .unwrap_or_else(|_| path.to_string())
+        .to_lowercase()
+}
+
+/// A bookmark whose ZSCORE doesn't point at a score
+struct OrphanedBookmark {
+    id: i64,
+    title: String,
+    /// Path of the score it belonged to
+    path: Option<String>,
+    uuid: Option<String>,
+    score_id: Option<i64>,
+    start_page: Option<i32>,
+    end_page: Option<i32>,
+}
+
+fn find_orphaned_bookmarks(conn: &Connection) -> Result<Vec<OrphanedBookmark>> {
+    let mut stmt = conn.prepare(
+        "SELECT b.Z_PK, b.ZTITLE, b.ZPATH, b.ZUUID, b.ZSCORE, b.ZSTARTPAGE, b.ZENDPAGE
+         FROM ZITEM b
+         WHERE b.Z_ENT = ?1
+           AND NOT EXISTS (SELECT 1 FROM ZITEM s WHERE s.Z_PK = b.ZSCORE AND s.Z_ENT = ?2)
+         ORDER BY b.Z_PK",
+    )?;
+    let orphans = stmt
+        .query_map([entity::bookmark(), entity::score()], |row| {
+            Ok(OrphanedBookmark {
+                id: row.get(0)?,
+                title: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                path: row.get(2)?,
+                uuid: row.get(3)?,
+                score_id: row.get(4)?,
+                start_page: row.get(5)?,
+                end_page: row.get(6)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(orphans)
+}
+
+/// Delete bookmarks whose score is gone, or reattach them to `reattach_to`;
+/// with `preview` set, only report the planned changes
+fn fix_orphaned_bookmarks(
+    conn: &Connection,
+    reattach_to: Option<&str>,
+    preview: Option<DryRun>,
+) -> Result<()> {
+    let orphans = find_orphaned_bookmarks(conn)?;
+    let target = reattach_to.map(|s| resolve_score(conn, s)).transpose()?;
+    let missing = |o: &OrphanedBookmark| match o.score_id {
+        Some(id) => format!("ID {} (missing)", id),
+        None => "(none)".to_string(),
+    };
+
+    if let Some(mut preview) = preview {
+        for orphan in &orphans {
+            let patch = match &target {
+                Some(score) => {
+                    let mut patch = Patch::update("bookmark", orphan.id, &orphan.title);
+                    patch.change(
+                        "score",
+                        missing(orphan),
+                        format!("{} (ID {})", score.title, score.id),
+                    );
+                    patch
+                }
+                None => {
+                    let mut patch =
+                        Patch::new("delete", "bookmark", Some(orphan.id), &orphan.title);
+                    patch.change("score", missing(orphan), "");
+                    patch
+                }
+            };
+            preview.add(patch);
+        }
+        preview.finish();
+        return Ok(());
+    }
+
+    if orphans.is_empty() {
+        println!("No orphaned bookmarks found.");
+        return Ok(());
+    }
+
+    let page_count = match &target {
+        Some(score) => score_page_count(conn, score)?,
+        None => None,
+    };
+    let label = if target.is_some() {
+        "Reattaching bookmarks"
+    } else {
+        "Deleting bookmarks"
+    };
+    let progress = Progress::new(orphans.len(), label);
+    let mut summary = Summary::default();
+    let mut moved_paths = HashSet::new();
+    for orphan in &orphans {
+        progress.inc();
+        let name = format!("{} (ID {})", orphan.title, orphan.id);
+        let entries = list_item_setlist_entries(conn, orphan.id)?;
+
+        match &target {
+            Some(score) => {
+                if let (Some(end), Some(pages)) = (orphan.end_page, page_count) {
+                    if end > pages {
+                        summary.skip(
+                            name,
+                            format!("ends on page {}, past the end of '{}'", end, score.title),
+                        );
+                        continue;
+                    }
+                }
+                conn.execute(
+                    "UPDATE ZITEM SET ZSCORE = ?, ZPATH = ? WHERE Z_PK = ?",
+                    rusqlite::params![score.id, score.path, orphan.id],
+                )?;
+                touch_item(conn, orphan.id)?;
+                if let Some(old) = orphan.path.as_deref().filter(|p| *p != score.path) {
+                    moved_paths.insert(old.to_string());
+                }
+                progress.println(format!("Reattached: {} to {}", name, score.title));
+            }
+            None => {
+                delete_item(conn, orphan.id)?;
+                for entry in &entries {
+                    let Some(uuid) = entry.uuid.as_deref() else {
+                        continue;
+                    };
+                    if let Err(e) = remove_item_from_setlist_file(&entry.setlist.title, uuid) {
+                        summary.error(&name, format!("failed to update setlist sync file: {}", e));
+                    }
+                }
+                progress.println(format!("Deleted: {}", name));
+            }
+        }
+        summary.updated += 1;
+
+        // Whatever's left of it in the old score's ITM
+        if let Some(old) = orphan.path.as_deref() {
+            let same_itm = target.as_ref().is_some_and(|s| s.path == old);
+            if !same_itm {
+                if let Err(e) = delete_bookmark_from_itm(old, orphan.uuid.as_deref()) {
+                    summary.error(&name, format!("failed to update old ITM: {}", e));
+                }
+            }
+        }
+        if let (Some(score), Some(uuid), Some(first), Some(last)) = (
+            &target,
+            orphan.uuid.as_ref(),
+            orphan.start_page,
+            orphan.end_page,
+        ) {
+            let bookmark = ItmNewBookmark {
+                title: orphan.title.clone(),
+                identifier: uuid.clone(),
+                first_page: first as i64,
+                last_page: last as i64,
+            };
+            if let Err(e) = add_bookmarks_to_itm(&score.path, &[bookmark]) {
+                summary.error(&name, format!("failed to update ITM: {}", e));
+            }
+        }
+    }
+    progress.finish();
+
+    // Point setlist entries at the new score's file, unless a score still
+    // uses the old one
+    if let Some(score) = &target {
+        let mut unused = HashSet::new();
+        for path in moved_paths {
+            if !path_in_use(conn, &path)? {
+                unused.insert(path);
+            }
+        }
+        if !unused.is_empty() {
+            if let Err(e) = rewrite_file_paths_in_all_setlists(|p| {
+                unused.contains(p).then(|| score.path.clone())
+            }) {
+                summary.error(
+                    &score.title,
+                    format!("failed to update setlist sync files: {}", e),
+                );
+            }
+        }
+    }
+
+    println!();
+    summary.print(if target.is_some() {
+        "Reattached"
+    } else {
+        "Deleted"
+    });
+    Ok(())
+}
+
+/// Whether a score has this path
+fn path_in_use(conn: &Connection, path: &str) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM ZITEM WHERE Z_ENT = ? AND ZPATH = ?)",
+        rusqlite::params![entity::score(), path],
+        |row| row.get(0),
+    )?)
+}
+
+/// Set each score's and bookmark's sort title to what the current rules
+/// derive from its title
+fn fix_sort_titles(conn: &Connection, preview: Option<DryRun>) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT Z_PK, Z_ENT, ZTITLE, ZSORTTITLE FROM ZITEM
+         WHERE Z_ENT IN (?, ?) AND ZTITLE IS NOT NULL ORDER BY Z_PK",
+    )?;
+    let changes: Vec<(i64, &'static str, String, Option<String>, String)> = stmt
+        .query_map([entity::score(), entity::bookmark()], |row| {
+            let ent: i32 = row.get(1)?;
+            let title: String = row.get(2)?;
+            Ok((
+                row.get(0)?,
+                if ent == entity::bookmark() {
+                    "bookmark"
+                } else {
+                    "score"
+                },
+                title.clone(),
+                row.get::<_, Option<String>>(3)?,
+                sort_title(&title),
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .filter(|(_, _, _, old, new)| old.as_deref() != Some(new.as_str()))
+        .collect();
+
+    if let Some(mut preview) = preview {
+        for (id, kind, title, old, new) in &changes {
+            let mut patch = Patch::update(kind, *id, title);
+            patch.change("sort title", old.clone().unwrap_or_default(), new.as_str());
+            preview.add(patch);
+        }
+        preview.finish();
+        return Ok(());
+    }
+
+    if changes.is_empty() {
+        println!("All sort titles already follow the rules.");
+        return Ok(());
+    }
+
+    let progress = Progress::new(changes.len(), "Updating sort titles");
+    for (id, _, _, _, new) in &changes {
+        progress.inc();
+        conn.execute(
+            "UPDATE ZITEM SET ZSORTTITLE = ? WHERE Z_PK = ?",
+            rusqlite::params![new, id],
+        )?;
+        touch_item(conn, *id)?;
+    }
+    progress.finish();
+    println!("Updated {} sort title(s)", changes.len());
+    Ok(())
+}
+
+/// Normalize score paths; with `preview` set, only report the planned changes
+fn fix_path_normalization(conn: &Connection, preview: Option<DryRun>) -> Result<()> {
+    // Canonical spellings by normalized path: files on disk win over .set references
+    let mut canonical: HashMap<String, String> = HashMap::new();
+    for path in all_setlist_file_paths().unwrap_or_default() {
+        canonical.insert(normalize_path(&path), path);
+    }
+    if let Ok(dir) = documents_path() {
+        for entry in fs::read_dir(dir)?.flatten() {
+            if let Some(name) = entry.file_name().to_str() {
+                canonical.insert(normalize_path(name), name.to_string());
+            }
+        }
+    }
+
+    // Group scores whose paths normalize to the same value
+    let mut stmt = conn.prepare(
+        "SELECT Z_PK, ZPATH, ZTITLE FROM ZITEM WHERE Z_ENT = ? AND ZPATH IS NOT NULL ORDER BY Z_PK",
+    )?;
+    let mut groups: BTreeMap<String, Vec<(i64, String, String)>> = BTreeMap::new();
+    let rows = stmt.query_map([entity::score()], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+        ))
+    })?;
+    for row in rows.flatten() {
+        groups.entry(normalize_path(&row.1)).or_default().push(row);
+    }
+
+    let mut renames = Vec::new();
+    let mut merges = Vec::new();
+
+    for (normalized, scores) in &groups {
+        let target = match canonical.get(normalized) {
+            Some(t) => t.clone(),
+            None if scores.len() > 1 => scores[0].1.clone(),
+            None => continue,
+        };
+        canonical.insert(normalized.clone(), target.clone());
+
+        let keep = scores
+            .iter()
+            .find(|(_, path, _)| *path == target)
+            .unwrap_or(&scores[0]);
+
+        if keep.1 != target {
+            renames.push((keep.clone(), target.clone()));
+        }
+        for dup in scores.iter().filter(|s| s.0 != keep.0) {
+            merges.push((dup.clone(), keep.clone()));
+        }
+    }
+
+    // .set entries that reference a non-canonical spelling
+    let set_fixes: Vec<(String, String)> = all_setlist_file_paths()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|path| {
+            let target = canonical.get(&normalize_path(&path))?;
+            (*target != path).then(|| (path, target.clone()))
+        })
+        .collect();
+
+    if let Some(mut preview) = preview {
+        for ((id, path, title), target) in &renames {
+            let mut patch = Patch::update("score", *id, title);
+            patch.change("path", path.as_str(), target.as_str());
+            preview.add(patch);
+        }
+        for ((dup_id, _, dup_title), (keep_id, _, _)) in &merges {
+            let mut patch = Patch::new("merge", "score", Some(*dup_id), dup_title);
+            patch.change("id", dup_id.to_string(), keep_id.to_string());
+            preview.add(patch);
+        }
+        for (path, target) in &set_fixes {
+            let mut patch = Patch::new("update", "setlist-entry", None, path);
+            patch.change("path", path.as_str(), target.as_str());
+            preview.add(patch);
+        }
+        preview.finish();
+        return Ok(());
+    }
+
+    if renames.is_empty() && merges.is_empty() && set_fixes.is_empty() {
+        println!("All paths are already normalized.");
+        return Ok(());
+    }
+
+    for ((id, path, title), target) in &renames {
+        println!("Rename: {} (ID {}): {} -> {}", title, id, path, target);
+    }
+
+    let mut summary = Summary::default();
+
+    for ((id, path, title), target) in &renames {
+        // Bookmarks share their score's path
+        conn.execute(
+            "UPDATE ZITEM SET ZPATH = ? WHERE ZPATH = ?",
+            rusqlite::params![target, path],
+        )?;
+        touch_item(conn, *id)?;
+
+        if let Err(e) = rename_itm(path, target) {
+            summary.error(
+                format!("{} (ID {})", title, id),
+                format!("failed to rename ITM: {}", e),
+            );
+        }
+        summary.updated += 1;
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    for ((dup_id, _, _), (keep_id, _, _)) in &merges {
+        merge_score_into(&tx, *dup_id, *keep_id)?;
+        touch_item(&tx, *keep_id)?;
+    }
+    tx.commit()?;
+    for ((dup_id, _, dup_title), (keep_id, _, _)) in &merges {
+        println!("Merged: {} (ID {}) into ID {}", dup_title, dup_id, keep_id);
+        summary.updated += 1;
+    }
+
+    if !set_fixes.is_empty() {
+        let targets: HashMap<&str, &str> = set_fixes
+            .iter()
+            .map(|(p, t)| (p.as_str(), t.as_str()))
+            .collect();
+        match rewrite_file_paths_in_all_setlists(|p| targets.get(p).map(|t| t.to_string())) {
+            Ok((files, entries)) => {
+                println!("Updated {} setlist files ({} entries)", files, entries)
+            }
+            Err(e) => eprintln!("Warning: Failed to update setlist files: {}", e),
+        }
+    }
+
+    summary.print("Fixed");
+    Ok(())
+}
+
+/// A score in a group of colliding titles, and its planned new title
+#[derive(Serialize)]
+struct CollidingTitle {
+    id: i64,
+    title: String,
+    composer: Option<String>,
+    new_title: Option<String>,
+}
+
+/// A title as resolution compares it: lowercased, whitespace collapsed
+fn title_key(title: &str) -> String {
+    title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The runs of digits in a title, e.g. ["9", "2"] for "Op. 9 No. 2"
+fn digit_runs(title: &str) -> Vec<&str> {
+    title
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|run| !run.is_empty())
+        .collect()
+}
+
+/// Whether two titles differ in a word of one or two letters, usually a key
+/// ("Prelude in C", "Prelude in C#"); punctuation is ignored ("Op." is "Op")
+fn short_word_differs(a: &str, b: &str) -> bool {
+    let words = |title: &str| -> Vec<String> {
+        title
+            .split_whitespace()
+            .map(|w| {
+                w.chars()
+                    .filter(|c| c.is_alphanumeric() || *c == '#')
+                    .collect()
+            })
+            .filter(|w: &String| !w.is_empty())
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    a.len() == b.len()
+        && a.iter()
+            .zip(&b)
+            .any(|(x, y)| x != y && x.chars().count().min(y.chars().count()) <= 2)
+}
+
+/// Whether two title keys collide: equal, or within `distance` edits and
+/// neither numbered nor keyed differently
+fn titles_collide(a: &str, b: &str, distance: usize) -> bool {
+    a == b
+        || (digit_runs(a) == digit_runs(b)
+            && !short_word_differs(a, b)
+            && edit_distance_within(a, b, distance).is_some())
+}
+
+/// Indexes of titles that collide, directly or through a chain of near
+/// misses, grouped; groups and members keep the input order
+fn collision_groups(titles: &[&str], distance: usize) -> Vec<Vec<usize>> {
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut parent: Vec<usize> = (0..titles.len()).collect();
+
+    // Identical keys are grouped directly; only one of each is compared
+    let keys: Vec<String> = titles.iter().map(|t| title_key(t)).collect();
+    let mut first_with_key: HashMap<&str, usize> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        let first = *first_with_key.entry(key.as_str()).or_insert(i);
+        let (a, b) = (root(&mut parent, first), root(&mut parent, i));
+        parent[b] = a;
+    }
+
+    if distance > 0 {
+        // By length, so each key is only compared with keys close in length
+        let mut distinct: Vec<(usize, &str)> =
+            first_with_key.iter().map(|(key, i)| (*i, *key)).collect();
+        distinct.sort_by_key(|(i, key)| (key.chars().count(), *i));
+        let lengths: Vec<usize> = distinct.iter().map(|(_, k)| k.chars().count()).collect();
+        for x in 0..distinct.len() {
+            for y in x + 1..distinct.len() {
+                if lengths[y] - lengths[x] > distance {
+                    break;
+                }
+                let ((i, a), (j, b)) = (distinct[x], distinct[y]);
+                if titles_collide(a, b, distance) {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[b] = a;
+                }
+            }
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..titles.len() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(i);
+    }
+    let mut groups: Vec<Vec<usize>> = groups.into_values().filter(|g| g.len() > 1).collect();
+    groups.sort_by_key(|g| g[0]);
+    groups
+}
+
+/// New titles for a group of (title, composer), oldest first. The first keeps
+/// its title; the rest get their composer appended when it tells them apart
+/// from every other member and isn't in the title already, or else their
+/// position in the group
+fn plan_renames(members: &[(&str, Option<&str>)], suffix: TitleSuffix) -> Vec<Option<String>> {
+    let composer_count = |composer: &str| {
+        members
+            .iter()
+            .filter(|(_, c)| c.is_some_and(|c| c.eq_ignore_ascii_case(composer)))
+            .count()
+    };
+    members
+        .iter()
+        .enumerate()
+        .map(|(position, (title, composer))| {
+            if position == 0 {
+                return None;
+            }
+            let distinct_composer = composer.filter(|c| {
+                suffix == TitleSuffix::Composer
+                    && composer_count(c) == 1
+                    && !title.to_lowercase().contains(&c.to_lowercase())
+            });
+            Some(match distinct_composer {
+                Some(c) => format!("{} ({})", title, c),
+                None => format!("{} ({})", title, position + 1),
+            })
+        })
+        .collect()
+}
+
+/// List scores whose titles collide and, with `apply`, rename them
+fn fix_ambiguous_titles(
+    conn: &Connection,
+    distance: usize,
+    suffix: TitleSuffix,
+    apply: bool,
+    json: bool,
+) -> Result<()> {
+    let filter = ScoreFilter {
+        scores_only: true,
+        ..Default::default()
+    };
+    let mut scores: Vec<Score> = search_scores(conn, &filter, usize::MAX)?;
+    load_metadata_batch(conn, &mut scores)?;
+    scores.sort_by_key(|s| s.id);
+    let titles: Vec<&str> = scores.iter().map(|s| s.title.as_str()).collect();
+
+    let groups: Vec<Vec<CollidingTitle>> = collision_groups(&titles, distance)
+        .into_iter()
+        .map(|group| {
+            let members: Vec<&Score> = group.iter().map(|i| &scores[*i]).collect();
+            let named: Vec<(&str, Option<&str>)> = members
+                .iter()
+                .map(|s| (s.title.as_str(), s.composers.first().map(String::as_str)))
+                .collect();
+            members
+                .iter()
+                .zip(plan_renames(&named, suffix))
+                .map(|(score, new_title)| CollidingTitle {
+                    id: score.id,
+                    title: score.title.clone(),
+                    composer: score.composers.first().cloned(),
+                    new_title,
+                })
+                .collect()
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+    if groups.is_empty() {
+        println!("No colliding titles found.");
+        return Ok(());
+    }
+
+    println!("Found {} group(s) of colliding titles:\n", groups.len());
+    for group in &groups {
+        for member in group {
+            let composer = member
+                .composer
+                .as_ref()
+                .map(|c| format!(", {}", c))
+                .unwrap_or_default();
+            match &member.new_title {
+                Some(new_title) => println!(
+                    "  \"{}\" (ID {}{}) -> \"{}\"",
+                    member.title, member.id, composer, new_title
+                ),
+                None => println!("  \"{}\" (ID {}{})", member.title, member.id, composer),
+            }
+        }
+        println!();
+    }
+
+    if !apply {
+        println!("Run with --apply to rename.");
+        return Ok(());
+    }
+
+    let renames: Vec<&CollidingTitle> = groups
+        .iter()
+        .flatten()
+        .filter(|m| m.new_title.is_some())
+        .collect();
+    let progress = Progress::new(renames.len(), "Renaming");
+    let mut summary = Summary::default();
+    for member in renames {
+        progress.inc();
+        let Some(new_title) = &member.new_title else {
+            continue;
+        };
+        conn.execute(
+            "UPDATE ZITEM SET ZTITLE = ?, ZSORTTITLE = ? WHERE Z_PK = ?",
+            rusqlite::params![new_title, sort_title(new_title), member.id],
+        )?;
+        touch_item(conn, member.id)?;
+        summary.updated += 1;
+
+        let Some(score) = scores.iter().find(|s| s.id == member.id) else {
+            continue;
+        };
+        let mut itm_update = ItmUpdate::new();
+        itm_update.title = Some(new_title.clone());
+        if let Err(e) = update_itm(&score.path, &itm_update) {
+            summary.error(
+                format!("{} (ID {})", member.title, member.id),
+                format!("failed to update ITM: {}", e),
+            );
+        }
+    }
+    progress.finish();
+    summary.print("Renamed");
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct SimilarComposer {
+    id: i64,
+    name: String,
+    score_count: i32,
+    /// The name it would be merged into; none for the one kept
+    merge_into: Option<String>,
+}
+
+/// Words that end a surname rather than start one ("Strauss II")
+const NAME_SUFFIXES: &[&str] = &["jr", "sr", "ii", "iii", "iv"];
+/// Particles left out of initials ("Ludwig van Beethoven" is L.)
+const NAME_PARTICLES: &[&str] = &["van", "von", "de", "der", "di", "da", "du", "la", "le"];
+
+/// Lowercase, with common accents dropped ("Dvořák" -> "dvorak")
+fn fold_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' => 'a',
+            'é' | 'è' | 'ê' | 'ë' | 'ě' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'ø' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' | 'ů' => 'u',
+            'ñ' | 'ň' => 'n',
+            'ç' | 'č' => 'c',
+            'ř' => 'r',
+            'š' => 's',
+            'ž' => 'z',
+            'ý' => 'y',
+            _ => c,
+        })
+        .collect()
+}
+
+/// A composer name as (surname, initials): "J.S. Bach", "JS Bach" and
+/// "Bach, Johann Sebastian" are all ("bach", "js")
+fn composer_key(name: &str) -> Option<(String, String)> {
+    let words = |s: &str| -> Vec<String> {
+        s.split(|c: char| c.is_whitespace() || c == '.')
+            .map(|w| {
+                w.chars()
+                    .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '\'')
+                    .collect::<String>()
+            })
+            .filter(|w| !w.is_empty())
+            .collect()
+    };
+    let (surname, given) = match name.split_once(',') {
+        Some((surname, given)) => (words(surname), words(given)),
+        None => {
+            let mut all = words(name);
+            let mut surname = Vec::new();
+            while all
+                .last()
+                .is_some_and(|w| NAME_SUFFIXES.contains(&fold_name(w).as_str()))
+                && all.len() > 1
+            {
+                surname.insert(0, all.pop()?);
+            }
+            surname.insert(0, all.pop()?);
+            (surname, all)
+        }
+    };
+    if surname.is_empty() {
+        return None;
     }
 
+    let mut initials = String::new();
+    for word in &given {
+        if NAME_PARTICLES.contains(&fold_name(word).as_str()) {
+            continue;
+        }
+        // "JS" is two initials, "Jean-Philippe" and "J-P" are two as well
+        let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+        if (2..=3).contains(&letters.len()) && letters.iter().all(|c| c.is_uppercase()) {
+            initials.extend(letters.iter().flat_map(|c| c.to_lowercase()));
+            continue;
+        }
+        for part in word.split('-') {
+            if let Some(c) = fold_name(part).chars().find(|c| c.is_alphabetic()) {
+                initials.push(c);
+            }
+        }
+    }
+    Some((fold_name(&surname.join(" ")), initials))
+}
+
+/// Indexes of composer names that look like the same person, grouped;
+/// groups and members keep the input order
+fn composer_groups(names: &[&str]) -> Vec<Vec<usize>> {
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let keys: Vec<Option<(String, String)>> = names.iter().map(|n| composer_key(n)).collect();
+
+    // Surnames a typo apart count as one, when they're long enough for a
+    // typo to be more likely than another person
+    let mut surnames: Vec<&str> = keys.iter().flatten().map(|(s, _)| s.as_str()).collect();
+    surnames.sort();
+    surnames.dedup();
+    let mut surname_parent: Vec<usize> = (0..surnames.len()).collect();
+    for x in 0..surnames.len() {
+        for y in x + 1..surnames.len() {
+            let (a, b) = (surnames[x], surnames[y]);
+            if a.chars().count().min(b.chars().count()) >= 6
+                && edit_distance_within(a, b, 1).is_some()
+            {
+                let (a, b) = (root(&mut surname_parent, x), root(&mut surname_parent, y));
+                surname_parent[b] = a;
+            }
+        }
+    }
+
+    // Names by surname, then by initials
+    let mut by_surname: BTreeMap<usize, BTreeMap<&str, Vec<usize>>> = BTreeMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        let Some((surname, initials)) = key else {
+            continue;
+        };
+        let Ok(s) = surnames.binary_search(&surname.as_str()) else {
+            continue;
+        };
+        by_surname
+            .entry(root(&mut surname_parent, s))
+            .or_default()
+            .entry(initials.as_str())
+            .or_default()
+            .push(i);
+    }
+
+    let mut parent: Vec<usize> = (0..names.len()).collect();
+    for initials in by_surname.values() {
+        // Longest initials first, so shorter ones ("J.", none) join a group
+        // only when every longer name they could abbreviate is in it
+        let mut ordered: Vec<(&str, &Vec<usize>)> = initials.iter().map(|(k, v)| (*k, v)).collect();
+        ordered.sort_by_key(|(k, _)| std::cmp::Reverse(k.len()));
+        let mut placed: Vec<(&str, usize)> = Vec::new();
+        for (key, members) in ordered {
+            for &i in &members[1..] {
+                let (a, b) = (root(&mut parent, members[0]), root(&mut parent, i));
+                parent[b] = a;
+            }
+            let mut roots: Vec<usize> = placed
+                .iter()
+                .filter(|(longer, _)| longer.starts_with(key))
+                .map(|(_, i)| root(&mut parent, *i))
+                .collect();
+            roots.sort();
+            roots.dedup();
+            if let [only] = roots[..] {
+                let own = root(&mut parent, members[0]);
+                parent[own] = only;
+            }
+            placed.push((key, members[0]));
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..names.len() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(i);
+    }
+    let mut groups: Vec<Vec<usize>> = groups.into_values().filter(|g| g.len() > 1).collect();
+    groups.sort_by_key(|g| g[0]);
+    groups
+}
+
+/// List composers that look like duplicates and, once confirmed, merge each
+/// group into its most used name (the longest on a tie)
+fn fix_duplicate_composers(yes: bool, json: bool) -> Result<()> {
+    let composers = list_composers(&open_readonly()?, false)?;
+    let names: Vec<&str> = composers.iter().map(|c| c.name.as_str()).collect();
+
+    let groups: Vec<Vec<SimilarComposer>> = composer_groups(&names)
+        .into_iter()
+        .map(|group| {
+            let members: Vec<&Composer> = group.iter().map(|i| &composers[*i]).collect();
+            let keep = members
+                .iter()
+                .max_by_key(|c| {
+                    (
+                        c.score_count,
+                        c.name.chars().count(),
+                        std::cmp::Reverse(c.id),
+                    )
+                })
+                .map(|c| c.name.clone());
+            members
+                .iter()
+                .map(|c| SimilarComposer {
+                    id: c.id,
+                    name: c.name.clone(),
+                    score_count: c.score_count,
+                    merge_into: keep.clone().filter(|k| *k != c.name),
+                })
+                .collect()
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+    if groups.is_empty() {
+        println!("No similar composer names found.");
+        return Ok(());
+    }
+
+    println!("Found {} group(s) of similar composers:\n", groups.len());
+    for group in &groups {
+        for member in group {
+            match &member.merge_into {
+                Some(target) => println!(
+                    "  \"{}\" (ID {}, {} item(s)) -> \"{}\"",
+                    member.name, member.id, member.score_count, target
+                ),
+                None => println!(
+                    "  \"{}\" (ID {}, {} item(s))",
+                    member.name, member.id, member.score_count
+                ),
+            }
+        }
+        println!();
+    }
+
+    let merges: Vec<&SimilarComposer> = groups
+        .iter()
+        .flatten()
+        .filter(|m| m.merge_into.is_some())
+        .collect();
+    if !yes && !confirm(&format!("Merge {} composer name(s)?", merges.len()))? {
+        println!("Nothing changed.");
+        return Ok(());
+    }
+    warn_if_running();
+    let conn = open_readwrite()?;
+    let store = open_store()?;
+
+    let progress = Progress::new(merges.len(), "Merging");
+    let mut summary = Summary::default();
+    for member in merges {
+        progress.inc();
+        let Some(target) = &member.merge_into else {
+            continue;
+        };
+        let items = composer_items(&conn, member.id)?;
+        merge_composers(&conn, &member.name, target)?;
+        for (item_id, _, _) in &items {
+            touch_item(&conn, *item_id)?;
+        }
+        rename_composer_info(&store, &member.name, target)?;
+        summary.updated += 1;
+
+        if let Err(e) = rename_composer_in_all_itm(&member.name, target) {
+            summary.error(&member.name, format!("failed to update ITM files: {}", e));
+        }
+    }
+    progress.finish();
+    summary.print("Merged");
     Ok(())
 }
 
@@ -94,7 +1346,7 @@ fn find_duplicate_bookmarks(conn: &Connection) -> Result<Vec<DuplicateBookmark>>
 
     let duplicates = stmt
         .query_map(
-            [entity::BOOKMARK, entity::BOOKMARK, entity::BOOKMARK],
+            [entity::bookmark(), entity::bookmark(), entity::bookmark()],
             |row| {
                 Ok(DuplicateBookmark {
                     id: row.get("id")?,
@@ -116,29 +1368,184 @@ fn find_duplicate_bookmarks(conn: &Connection) -> Result<Vec<DuplicateBookmark>>
     Ok(duplicates)
 }
 
-fn delete_bookmark(conn: &Connection, bookmark: &DuplicateBookmark) -> Result<()> {
-    // Delete from database
-    conn.execute("DELETE FROM ZITEM WHERE Z_PK = ?", [bookmark.id])?;
-
-    // Delete composer links
-    conn.execute(
+fn delete_bookmark(
+    conn: &Connection,
+    bookmark: &DuplicateBookmark,
+    progress: &Progress,
+    summary: &mut Summary,
+) -> Result<()> {
+    // Delete from database, with its composer and genre links
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM ZITEM WHERE Z_PK = ?", [bookmark.id])?;
+    tx.execute(
         "DELETE FROM Z_4COMPOSERS WHERE Z_4ITEMS1 = ?",
         [bookmark.id],
     )?;
-
-    // Delete genre links
-    conn.execute("DELETE FROM Z_4GENRES WHERE Z_4ITEMS4 = ?", [bookmark.id])?;
+    tx.execute("DELETE FROM Z_4GENRES WHERE Z_4ITEMS4 = ?", [bookmark.id])?;
+    tx.commit()?;
 
     // Delete from ITM file
     let uuid = bookmark.uuid.as_deref();
     match delete_bookmark_from_itm(&bookmark.path, uuid) {
-        Ok(true) => println!("Deleted: {} (ID {}) + ITM", bookmark.title, bookmark.id),
-        Ok(false) => println!("Deleted: {} (ID {})", bookmark.title, bookmark.id),
+        Ok(true) => {
+            progress.println(format!(
+                "Deleted: {} (ID {}) + ITM",
+                bookmark.title, bookmark.id
+            ));
+            summary.updated += 1;
+        }
+        Ok(false) => {
+            progress.println(format!("Deleted: {} (ID {})", bookmark.title, bookmark.id));
+            summary.updated += 1;
+        }
         Err(e) => {
-            println!("Deleted: {} (ID {})", bookmark.title, bookmark.id);
-            eprintln!("  Warning: Failed to update ITM: {}", e);
+            progress.println(format!(
+                "Deleted: {} (ID {}) (database only)",
+                bookmark.title, bookmark.id
+            ));
+            summary.error(
+                format!("{} (ID {})", bookmark.title, bookmark.id),
+                format!(
+                    "deleted from the database only; failed to update ITM: {}",
+                    e
+                ),
+            );
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("Song.PDF"), "song.pdf");
+        assert_eq!(normalize_path("My%20Song.pdf"), "my song.pdf");
+        assert_eq!(normalize_path("Caf%C3%A9.pdf"), normalize_path("café.PDF"));
+    }
+
+    #[test]
+    fn test_collision_groups() {
+        let titles = [
+            "Prelude in C",
+            "Etude 1",
+            "prelude  in c",
+            "Etude 2",
+            "Nocturne Op. 9",
+            "Nocturne Op 9",
+            "Prelude in C#",
+            "Prelud in C",
+        ];
+        assert_eq!(
+            collision_groups(&titles, 1),
+            vec![vec![0, 2, 7], vec![4, 5]]
+        );
+        assert_eq!(collision_groups(&titles, 0), vec![vec![0, 2]]);
+    }
+
+    #[test]
+    fn test_composer_key() {
+        let key = |name| composer_key(name).map(|(s, i)| format!("{}/{}", s, i));
+        assert_eq!(key("J.S. Bach").as_deref(), Some("bach/js"));
+        assert_eq!(key("JS Bach").as_deref(), Some("bach/js"));
+        assert_eq!(key("Bach, Johann Sebastian").as_deref(), Some("bach/js"));
+        assert_eq!(key("Ludwig van Beethoven").as_deref(), Some("beethoven/l"));
+        assert_eq!(key("Jean-Philippe Rameau").as_deref(), Some("rameau/jp"));
+        assert_eq!(key("Johann Strauss II").as_deref(), Some("strauss ii/j"));
+        assert_eq!(key("Antonín Dvořák").as_deref(), Some("dvorak/a"));
+        assert_eq!(key("Chopin").as_deref(), Some("chopin/"));
+        assert_eq!(key(" , "), None);
+    }
+
+    #[test]
+    fn test_composer_groups() {
+        let names = [
+            "J.S. Bach",
+            "Chopin",
+            "Bach, Johann Sebastian",
+            "Frédéric Chopin",
+            "JS Bach",
+            "Bach",
+            "C.P.E. Bach",
+            "Schuman, Robert",
+            "Robert Schumann",
+            "Johann Strauss II",
+            "Richard Strauss",
+            "J. Strauss II",
+        ];
+        assert_eq!(
+            composer_groups(&names),
+            vec![vec![0, 2, 4], vec![1, 3], vec![7, 8], vec![9, 11]]
+        );
+    }
+
+    #[test]
+    fn test_plan_renames() {
+        let members = [
+            ("Prelude", Some("Bach")),
+            ("prelude", Some("Chopin")),
+            ("Prelude", None),
+            ("Prelude (Bach)", Some("Bach")),
+        ];
+        assert_eq!(
+            plan_renames(&members, TitleSuffix::Composer),
+            vec![
+                None,
+                Some("prelude (Chopin)".to_string()),
+                Some("Prelude (3)".to_string()),
+                Some("Prelude (Bach) (4)".to_string()),
+            ]
+        );
+        assert_eq!(
+            plan_renames(&members[..2], TitleSuffix::Number),
+            vec![None, Some("prelude (2)".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_unmatched_bookmarks() {
+        let db_bookmark = |id: i64, uuid: Option<&str>| Bookmark {
+            id,
+            path: "Book.pdf".to_string(),
+            title: format!("DB {}", id),
+            uuid: uuid.map(str::to_string),
+            start_page: Some(1),
+            end_page: Some(2),
+            rating: None,
+            difficulty: None,
+            key: None,
+            composers: Vec::new(),
+            genres: Vec::new(),
+        };
+        let itm_bookmark = |identifier: Option<&str>| ItmBookmark {
+            identifier: identifier.map(str::to_string),
+            title: "ITM".to_string(),
+            first_page: Some(1),
+            last_page: Some(2),
+        };
+
+        let db = vec![
+            db_bookmark(1, Some("A")),
+            db_bookmark(2, Some("B")),
+            db_bookmark(3, None),
+        ];
+        let itm = vec![
+            itm_bookmark(Some("A")),
+            itm_bookmark(Some("C")),
+            itm_bookmark(None),
+        ];
+        let (db_only, itm_only) = unmatched_bookmarks(&db, &itm);
+
+        assert_eq!(db_only.iter().map(|b| b.id).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(
+            itm_only
+                .iter()
+                .map(|b| b.identifier.as_deref())
+                .collect::<Vec<_>>(),
+            vec![Some("C"), None]
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use crate::db::{core_data_timestamp, database_path, entity, open_readonly};
+use crate::error::{ForScoreError, Result};
+use crate::hooks::discard_changes;
+use crate::models::library::{add_score_to_library, create_library};
+use crate::models::meta::{
+    get_or_create_difficulty, get_or_create_rating, link_item, COMPOSER_LINKS, GENRE_LINKS,
+    KEYWORD_LINKS, LABEL_LINKS,
+};
+use crate::models::score::{create_bookmark, create_score};
+use crate::models::setlist::{add_item_to_setlist, create_setlist};
+use crate::schema::create_known_schema;
+use rusqlite::Connection;
+use std::fs;
+use std::path::Path;
+
+const FORMS: &[&str] = &[
+    "Prelude",
+    "Etude",
+    "Nocturne",
+    "Sonata",
+    "Waltz",
+    "Invention",
+    "Mazurka",
+];
+const COMPOSERS: &[&str] = &[
+    "J.S. Bach",
+    "Frédéric Chopin",
+    "Claude Debussy",
+    "W.A. Mozart",
+    "Johannes Brahms",
+];
+const GENRES: &[&str] = &["Baroque", "Romantic", "Impressionist", "Classical"];
+/// C Major, E Minor, G Minor, A Minor, D Major, F# Major, A# Minor, F Major
+const KEYS: &[i32] = &[100, 301, 501, 601, 200, 410, 611, 400];
+
+const DAY: f64 = 86400.0;
+
+/// Write a synthetic library with `scores` scores, or an anonymized copy of
+/// the current one, to `output`
+pub fn create(output: &str, scores: usize, anonymize: bool, force: bool) -> Result<()> {
+    let output = Path::new(output);
+    if let Ok(live) = database_path() {
+        if fs::canonicalize(&live).ok() == fs::canonicalize(output).ok() {
+            return Err(ForScoreError::Other(
+                "Refusing to overwrite the library itself".into(),
+            ));
+        }
+    }
+    if output.exists() {
+        if !force {
+            return Err(ForScoreError::Other(format!(
+                "{} already exists (use --force to replace it)",
+                output.display()
+            )));
+        }
+        fs::remove_file(output)?;
+        for extension in ["4sl-wal", "4sl-shm"] {
+            let _ = fs::remove_file(output.with_extension(extension));
+        }
+    }
+
+    if anonymize {
+        let source = database_path()?;
+        let conn = open_readonly()?;
+        conn.execute("VACUUM INTO ?", [output.to_string_lossy()])?;
+        drop(conn);
+
+        let copy = Connection::open(output)?;
+        let items = anonymize_library(&copy)?;
+        println!(
+            "Anonymized copy of {} written to {} ({} scores and bookmarks)",
+            source.display(),
+            output.display(),
+            items
+        );
+    } else {
+        let conn = Connection::open(output)?;
+        let tx = conn.unchecked_transaction()?;
+        create_known_schema(&tx)?;
+        generate(&tx, scores)?;
+        tx.commit()?;
+        println!(
+            "Created {} with {} scores, 2 bookmarks, 1 library and 1 setlist",
+            output.display(),
+            scores
+        );
+    }
+
+    // The changes went to the fixture, not the library hooks are about
+    discard_changes();
+    println!("Use it with: forscore --db {} ...", output.display());
+
+    Ok(())
+}
+
+/// Fill an empty library with scores cycling through a few composers, genres,
+/// keys and ratings, so every list and filter has something to show
+fn generate(conn: &Connection, count: usize) -> Result<()> {
+    let now = core_data_timestamp();
+    let library = create_library(conn, "Repertoire")?;
+    let setlist = create_setlist(conn, "Practice")?;
+
+    for i in 0..count {
+        let title = format!("{} No. {}", FORMS[i % FORMS.len()], i / FORMS.len() + 1);
+        let score = create_score(conn, &format!("{}.pdf", title), &title)?;
+
+        // Every third score is unrated and every other one never played
+        let rating = match i % 3 {
+            2 => None,
+            _ => Some(get_or_create_rating(conn, (i % 6) as i32 + 1)?),
+        };
+        let difficulty = get_or_create_difficulty(conn, (i % 5) as i32 + 1)?;
+        let played = (i % 2 == 0).then_some(now - (i as f64 + 0.5) * DAY);
+        conn.execute(
+            "UPDATE ZITEM SET ZKEY = ?, ZRATING = ?, ZDIFFICULTY = ?, ZBPM = ?,
+                              ZADDED = ?, ZMODIFIED = ?, ZLASTPLAYED = ?
+             WHERE Z_PK = ?",
+            rusqlite::params![
+                KEYS[i % KEYS.len()],
+                rating,
+                difficulty,
+                60 + (i * 7) % 80,
+                now - (i + 1) as f64 * DAY,
+                now - (i as f64 + 0.5) * DAY,
+                played,
+                score.id
+            ],
+        )?;
+
+        link_item(
+            conn,
+            COMPOSER_LINKS,
+            score.id,
+            COMPOSERS[i % COMPOSERS.len()],
+        )?;
+        link_item(conn, GENRE_LINKS, score.id, GENRES[i % GENRES.len()])?;
+        if i % 4 == 0 {
+            link_item(conn, KEYWORD_LINKS, score.id, "Warmup")?;
+        }
+        if i == 0 {
+            link_item(conn, LABEL_LINKS, score.id, "Red")?;
+        }
+        add_score_to_library(conn, library.id, score.id)?;
+
+        if i < 3 {
+            add_item_to_setlist(conn, setlist.id, score.id, entity::score())?;
+        }
+        if i == 0 {
+            let bookmark = create_bookmark(conn, &score, "Part 1", 1, 2)?;
+            create_bookmark(conn, &score, "Part 2", 3, 4)?;
+            add_item_to_setlist(conn, setlist.id, bookmark.id, entity::bookmark())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace every title, name, path and free-text field in a copy of a
+/// library with a placeholder built from its ID. Ratings, keys, dates,
+/// page ranges and the links between rows are kept. Returns the number of
+/// scores and bookmarks
+fn anonymize_library(conn: &Connection) -> Result<i64> {
+    let bookmark = entity::bookmark();
+    let meta_name = format!(
+        "CASE Z_ENT WHEN {} THEN 'Composer ' WHEN {} THEN 'Difficulty ' WHEN {} THEN 'Tag ' \
+         WHEN {} THEN 'Label ' ELSE 'Meta ' END || Z_PK",
+        entity::composer(),
+        entity::difficulty(),
+        entity::keyword(),
+        entity::label()
+    );
+    let item_title =
+        format!("CASE Z_ENT WHEN {bookmark} THEN 'Bookmark ' ELSE 'Score ' END || Z_PK");
+    let scrubbed: Vec<(&str, &str, String)> = vec![
+        ("ZITEM", "ZTITLE", item_title.clone()),
+        ("ZITEM", "ZSORTTITLE", format!("LOWER({})", item_title)),
+        (
+            "ZITEM",
+            "ZPATH",
+            format!("'Score ' || CASE Z_ENT WHEN {bookmark} THEN ZSCORE ELSE Z_PK END || '.pdf'"),
+        ),
+        ("ZITEM", "ZREFERENCE", "NULL".into()),
+        ("ZITEM", "ZSHORTCUT", "NULL".into()),
+        ("ZITEM", "ZTOC", "NULL".into()),
+        ("ZITEM", "ZVERSIONS", "NULL".into()),
+        (
+            "ZMETA",
+            "ZVALUE",
+            format!("CASE WHEN ZVALUE IS NULL THEN NULL ELSE {} END", meta_name),
+        ),
+        (
+            "ZMETA",
+            "ZVALUE2",
+            "CASE WHEN ZVALUE2 IS NULL THEN NULL ELSE 'Genre ' || Z_PK END".into(),
+        ),
+        ("ZMETA", "ZVALUE3", "NULL".into()),
+        ("ZMETA", "ZVALUE4", "NULL".into()),
+        ("ZMETA", "ZLAST", "NULL".into()),
+        ("ZLIBRARY", "ZTITLE", "'Library ' || Z_PK".into()),
+        ("ZLIBRARY", "ZCURRENTBOOKMARK", "NULL".into()),
+        ("ZLIBRARY", "ZCURRENTFILEPATH", "NULL".into()),
+        ("ZLIBRARY", "ZCURRENTSETLIST", "NULL".into()),
+        ("ZSETLIST", "ZTITLE", "'Setlist ' || Z_PK".into()),
+        ("ZFOLDER", "ZTITLE", "'Folder ' || Z_PK".into()),
+        ("ZTAB", "ZTITLE", "NULL".into()),
+        ("ZTAB", "ZCURRENTFILEPATH", "NULL".into()),
+        ("ZTAB", "ZPREFERREDBOOKMARK", "NULL".into()),
+        (
+            "ZPAGE",
+            "ZNOTE",
+            "CASE WHEN ZNOTE IS NULL THEN NULL ELSE 'Note' END".into(),
+        ),
+        ("ZTEXTANNOTATION", "ZTEXT", "'Text'".into()),
+        (
+            "ZRECORDING",
+            "ZFILENAME",
+            "'Recording ' || Z_PK || '.m4a'".into(),
+        ),
+        ("ZTRACK", "ZTITLE", "'Track ' || Z_PK".into()),
+        ("ZTRACK", "ZALBUM", "NULL".into()),
+        ("ZTRACK", "ZARTIST", "NULL".into()),
+        ("ZTRACK", "ZPERSISTENTIDENTIFIER", "NULL".into()),
+        ("ZBUTTON", "ZLABEL", "NULL".into()),
+        ("ZBUTTON", "ZVALUE", "NULL".into()),
+        ("ZPRESET", "ZTITLE", "'Preset ' || Z_PK".into()),
+    ];
+
+    let tx = conn.unchecked_transaction()?;
+    let tables: Vec<String> = tx
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")?
+        .query_map([], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    for table in &tables {
+        let columns: Vec<String> = tx
+            .prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?
+            .query_map([], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        for column in &columns {
+            // Archived plists of settings forScore doesn't model may hold anything
+            let value = if column.starts_with("ZUNKNOWNFIELDS") {
+                Some("NULL")
+            } else {
+                scrubbed
+                    .iter()
+                    .find(|(t, c, _)| t == table && c == column)
+                    .map(|(_, _, value)| value.as_str())
+            };
+            if let Some(value) = value {
+                tx.execute(&format!("UPDATE {} SET {} = {}", table, column, value), [])?;
+            }
+        }
+    }
+    tx.commit()?;
+    conn.execute_batch("VACUUM")?;
+
+    Ok(conn.query_row(
+        "SELECT COUNT(*) FROM ZITEM WHERE Z_ENT IN (?, ?)",
+        [entity::score(), entity::bookmark()],
+        |row| row.get(0),
+    )?)
+}
@@ -0,0 +1,121 @@
+use crate::cache::load_page_counts;
+use crate::cli::GroupCommand;
+use crate::db::{open_readonly, open_readwrite, warn_if_running};
+use crate::error::{ForScoreError, Result};
+use crate::itm::{update_itm, ItmUpdate};
+use crate::models::group::{
+    add_to_group, group_members, group_tag, list_groups, remove_from_group, resolve_group,
+};
+use crate::models::score::{load_metadata_batch, resolve_score_strict};
+use crate::output::{is_structured, output_scores, table_columns};
+use crate::touch::touch_item;
+use rusqlite::Connection;
+
+pub fn handle(cmd: GroupCommand) -> Result<()> {
+    match cmd {
+        GroupCommand::Ls { json } => {
+            let conn = open_readonly()?;
+            let groups = list_groups(&conn)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&groups)?);
+            } else if groups.is_empty() {
+                println!("No work groups (create one with `scores group create`)");
+            } else {
+                for group in &groups {
+                    println!("{} ({} scores)", group.name, group.score_count);
+                }
+            }
+        }
+
+        GroupCommand::Create { name, add } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            if !group_members(&conn, &name)?.is_empty() {
+                return Err(ForScoreError::Other(format!(
+                    "Work group '{}' already exists (use `scores group add` to extend it)",
+                    name
+                )));
+            }
+            let added = change_members(&conn, &name, &add, true)?;
+            println!(
+                "Created work group '{}' with {} scores (tag '{}')",
+                name,
+                added,
+                group_tag(&name)
+            );
+        }
+
+        GroupCommand::Show {
+            name,
+            columns,
+            json,
+        } => {
+            let conn = open_readonly()?;
+            let mut scores = resolve_group(&conn, &name)?;
+            load_page_counts(&conn, &mut scores)?;
+            if !is_structured(json) {
+                println!("Work group: {} ({} scores)\n", name, scores.len());
+            }
+            output_scores(&scores, json, &table_columns(&columns, false, false)?);
+        }
+
+        GroupCommand::Add { name, scores } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let added = change_members(&conn, &name, &scores, true)?;
+            println!("Added {} scores to work group '{}'", added, name);
+        }
+
+        GroupCommand::Remove { name, scores } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let removed = change_members(&conn, &name, &scores, false)?;
+            println!("Removed {} scores from work group '{}'", removed, name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Add scores to a group, or take them out, in the database and their ITM
+/// files. Returns how many changed
+fn change_members(
+    conn: &Connection,
+    name: &str,
+    identifiers: &[String],
+    add: bool,
+) -> Result<usize> {
+    // Resolve them all first so a typo doesn't leave the group half-changed
+    let mut scores = identifiers
+        .iter()
+        .map(|identifier| resolve_score_strict(conn, identifier))
+        .collect::<Result<Vec<_>>>()?;
+    load_metadata_batch(conn, &mut scores)?;
+
+    let mut changed = 0;
+    for score in &scores {
+        let keywords = if add {
+            add_to_group(conn, score, name)?
+        } else {
+            remove_from_group(conn, score, name)?
+        };
+        let Some(keywords) = keywords else {
+            let state = if add { "already" } else { "not" };
+            println!("  '{}' is {} in the group", score.title, state);
+            continue;
+        };
+        touch_item(conn, score.id)?;
+        changed += 1;
+
+        let mut itm_update = ItmUpdate::new();
+        itm_update.keywords = Some(keywords);
+        match update_itm(&score.path, &itm_update) {
+            Ok(_) => println!("  {}", score.title),
+            Err(e) => {
+                println!("  {} (database only)", score.title);
+                eprintln!("Warning: Failed to update ITM file: {}", e);
+            }
+        }
+    }
+    Ok(changed)
+}
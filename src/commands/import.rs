@@ -1,79 +1,184 @@
-use crate::cli::ImportCommand;
-use crate::db::{mark_modified, open_readonly, open_readwrite, warn_if_running};
+use crate::cli::{CsvFormatArgs, CsvMatch, ImportCommand};
+use crate::commands::export::BOOKMARK_COLUMNS;
+use crate::commands::migrate::{
+    copy_metadata, create_setlist_with_items, itm_update, match_scores, metadata_patch, MatchedBy,
+    PLANNED,
+};
+use crate::csv_format::CsvFormat;
+use crate::csv_mapping::ColumnMapping;
+use crate::db::{entity, open_readonly, open_readwrite, warn_if_running};
+use crate::dry_run::{DryRun, Patch};
 use crate::error::{ForScoreError, Result};
+use crate::itm::{
+    add_bookmarks_to_itm, update_bookmark_in_itm, update_itm, ItmBookmarkUpdate, ItmNewBookmark,
+    ItmUpdate,
+};
+use crate::library_json::parse_library;
 use crate::models::key::MusicalKey;
-use crate::models::meta::{get_or_create_composer, get_or_create_genre};
-use crate::models::score::get_score_by_id;
-use csv::Reader;
-use std::fs::File;
+use crate::models::library::{add_score_to_library, create_library, list_libraries};
+use crate::models::meta::{
+    get_or_create_composer, get_or_create_difficulty, get_or_create_genre, get_or_create_rating,
+    set_item_links, COMPOSER_LINKS, GENRE_LINKS, KEYWORD_LINKS, LABEL_LINKS,
+};
+use crate::models::score::{
+    create_bookmark, get_bookmark_by_id, get_score_by_id, get_score_by_path, get_score_by_title,
+    get_score_by_uuid, list_bookmarks, list_scores_with_metadata, Bookmark,
+};
+use crate::models::setlist::list_setlists;
+use crate::models::Score;
+use crate::progress::{Progress, Summary};
+use crate::snapshot::load_snapshot;
+use crate::sort_title::sort_title;
+use crate::touch::touch_item;
+use chrono::Local;
+use csv::{ReaderBuilder, StringRecord};
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Columns understood by `import csv` (the same set `export csv` writes;
+/// uuid and path only identify the score, and bpm, keywords, labels, libraries, pages, the dates and
+/// the composer_* columns are read-only)
+const KNOWN_COLUMNS: &[&str] = &[
+    "id",
+    "uuid",
+    "path",
+    "title",
+    "composer",
+    "genre",
+    "key",
+    "rating",
+    "difficulty",
+    "bpm",
+    "keywords",
+    "labels",
+    "libraries",
+    "pages",
+    "added",
+    "modified",
+    "last_played",
+    "composer_years",
+    "composer_nationality",
+];
 
 pub fn handle(cmd: ImportCommand) -> Result<()> {
     match cmd {
-        ImportCommand::Csv { file, dry_run } => {
-            if !dry_run {
+        ImportCommand::Csv {
+            file,
+            format,
+            dry_run,
+            json,
+            validate_only,
+            match_by,
+            upsert,
+            mapping,
+        } => {
+            let read_only = dry_run || validate_only;
+            if !read_only {
                 warn_if_running();
             }
 
-            let conn = if dry_run {
+            let conn = if read_only {
                 open_readonly()?
             } else {
                 open_readwrite()?
             };
 
-            let csv_file = File::open(&file)?;
-            let mut rdr = Reader::from_reader(csv_file);
+            let format = CsvFormat::from_args(&format)?;
+            let text = format.decode(&fs::read(&file)?);
+            let mut rdr = ReaderBuilder::new()
+                .delimiter(format.delimiter)
+                .from_reader(text.as_bytes());
 
             let headers = rdr.headers()?.clone();
+            let records: Vec<StringRecord> = rdr.records().collect::<csv::Result<_>>()?;
+            let (headers, records) = match mapping {
+                Some(path) => ColumnMapping::load(&path)?.apply(&headers, records),
+                None => (headers, records),
+            };
+
+            // Validate everything before writing anything
+            let problems = validate(&conn, &headers, &records, match_by, upsert);
+            if !problems.is_empty() {
+                println!("Validation failed ({} problems):", problems.len());
+                for problem in &problems {
+                    println!("  {}", problem);
+                }
+                return Err(ForScoreError::Other(format!(
+                    "{} has {} validation problems; nothing was imported",
+                    file,
+                    problems.len()
+                )));
+            }
+
+            if validate_only {
+                println!("Validation passed: {} rows OK", records.len());
+                return Ok(());
+            }
 
             // Find column indices
-            let id_idx = headers.iter().position(|h| h == "id");
-            let title_idx = headers.iter().position(|h| h == "title");
+            let match_idx = headers.iter().position(|h| h == match_column(match_by));
+            // A title that identifies the score isn't also a new title for it
+            let title_idx = headers
+                .iter()
+                .position(|h| h == "title")
+                .filter(|_| match_by != CsvMatch::Title);
             let composer_idx = headers.iter().position(|h| h == "composer");
             let genre_idx = headers.iter().position(|h| h == "genre");
             let key_idx = headers.iter().position(|h| h == "key");
             let rating_idx = headers.iter().position(|h| h == "rating");
             let difficulty_idx = headers.iter().position(|h| h == "difficulty");
 
-            let id_idx =
-                id_idx.ok_or_else(|| ForScoreError::Other("CSV must have 'id' column".into()))?;
+            let match_idx = match_idx.ok_or_else(|| {
+                ForScoreError::Other(format!("CSV must have '{}' column", match_column(match_by)))
+            })?;
 
-            let mut updated = 0;
-            let mut errors = 0;
+            let mut summary = Summary::default();
+            let mut preview = DryRun::new(json);
+            let mut itm_files = 0;
+
+            // Dry runs print per-row previews, so only show a bar for real imports
+            let progress = if dry_run {
+                Progress::hidden()
+            } else {
+                Progress::new(records.len(), "Importing")
+            };
 
-            for result in rdr.records() {
-                let record = result?;
+            for record in &records {
+                progress.inc();
+                let line = record.position().map(|p| p.line()).unwrap_or(0);
 
-                let id: i64 = match record.get(id_idx).and_then(|s| s.parse().ok()) {
-                    Some(id) => id,
-                    None => {
-                        errors += 1;
+                let value = record.get(match_idx).unwrap_or("");
+                let score = match row_score(&conn, match_by, value) {
+                    Ok(score) => score,
+                    Err(problem) if upsert => {
+                        summary.skip(format!("Line {}", line), problem);
+                        continue;
+                    }
+                    Err(problem) => {
+                        summary.error(format!("Line {}", line), problem);
                         continue;
                     }
                 };
+                let id = score.id;
 
-                // Verify score exists
-                if get_score_by_id(&conn, id).is_err() {
-                    eprintln!("Score ID {} not found, skipping", id);
-                    errors += 1;
-                    continue;
-                }
-
-                if dry_run {
-                    println!("Would update score ID {}:", id);
-                }
+                let mut patch = Patch::update("score", id, &score.title);
+                let mut itm_update = ItmUpdate::new();
 
                 // Update title
                 if let Some(idx) = title_idx {
                     if let Some(title) = record.get(idx) {
                         if !title.is_empty() {
                             if dry_run {
-                                println!("  title = {}", title);
+                                patch.change("title", score.title.as_str(), title);
                             } else {
-                                let sort_title = title.to_lowercase();
+                                let sort_title = sort_title(title);
                                 conn.execute(
                                     "UPDATE ZITEM SET ZTITLE = ?, ZSORTTITLE = ? WHERE Z_PK = ?",
                                     rusqlite::params![title, sort_title, id],
                                 )?;
+                                itm_update.title = Some(title.to_string());
                             }
                         }
                     }
@@ -85,12 +190,17 @@ pub fn handle(cmd: ImportCommand) -> Result<()> {
                         if !key_str.is_empty() {
                             if let Ok(key) = MusicalKey::from_string(key_str) {
                                 if dry_run {
-                                    println!("  key = {}", key.display());
+                                    patch.change(
+                                        "key",
+                                        score.key.map(|k| k.display()).unwrap_or_default(),
+                                        key.display(),
+                                    );
                                 } else {
                                     conn.execute(
                                         "UPDATE ZITEM SET ZKEY = ? WHERE Z_PK = ?",
                                         [key.code as i64, id],
                                     )?;
+                                    itm_update.key = Some(key.code as i64);
                                 }
                             }
                         }
@@ -101,14 +211,19 @@ pub fn handle(cmd: ImportCommand) -> Result<()> {
                 if let Some(idx) = rating_idx {
                     if let Some(rating_str) = record.get(idx) {
                         if let Ok(rating) = rating_str.parse::<i32>() {
-                            if rating >= 1 && rating <= 6 {
+                            if (1..=6).contains(&rating) {
                                 if dry_run {
-                                    println!("  rating = {}", rating);
+                                    patch.change(
+                                        "rating",
+                                        score.rating.map(|r| r.to_string()).unwrap_or_default(),
+                                        rating.to_string(),
+                                    );
                                 } else {
                                     conn.execute(
                                         "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
                                         [rating as i64, id],
                                     )?;
+                                    itm_update.rating = Some(rating as i64);
                                 }
                             }
                         }
@@ -119,14 +234,19 @@ pub fn handle(cmd: ImportCommand) -> Result<()> {
                 if let Some(idx) = difficulty_idx {
                     if let Some(diff_str) = record.get(idx) {
                         if let Ok(diff) = diff_str.parse::<i32>() {
-                            if diff >= 1 && diff <= 5 {
+                            if (1..=5).contains(&diff) {
                                 if dry_run {
-                                    println!("  difficulty = {}", diff);
+                                    patch.change(
+                                        "difficulty",
+                                        score.difficulty.map(|d| d.to_string()).unwrap_or_default(),
+                                        diff.to_string(),
+                                    );
                                 } else {
                                     conn.execute(
                                         "UPDATE ZITEM SET ZDIFFICULTY = ? WHERE Z_PK = ?",
                                         [diff as i64, id],
                                     )?;
+                                    itm_update.difficulty = Some(diff as i64);
                                 }
                             }
                         }
@@ -138,7 +258,11 @@ pub fn handle(cmd: ImportCommand) -> Result<()> {
                     if let Some(composer) = record.get(idx) {
                         if !composer.is_empty() {
                             if dry_run {
-                                println!("  composer = {}", composer);
+                                patch.change(
+                                    "composer",
+                                    score.composers.first().cloned().unwrap_or_default(),
+                                    composer,
+                                );
                             } else {
                                 let composer_id = get_or_create_composer(&conn, composer)?;
                                 conn.execute("DELETE FROM Z_4COMPOSERS WHERE Z_4ITEMS1 = ?", [id])?;
@@ -146,6 +270,7 @@ pub fn handle(cmd: ImportCommand) -> Result<()> {
                                     "INSERT INTO Z_4COMPOSERS (Z_4ITEMS1, Z_10COMPOSERS) VALUES (?, ?)",
                                     [id, composer_id],
                                 )?;
+                                itm_update.composer = Some(composer.to_string());
                             }
                         }
                     }
@@ -156,7 +281,11 @@ pub fn handle(cmd: ImportCommand) -> Result<()> {
                     if let Some(genre) = record.get(idx) {
                         if !genre.is_empty() {
                             if dry_run {
-                                println!("  genre = {}", genre);
+                                patch.change(
+                                    "genre",
+                                    score.genres.first().cloned().unwrap_or_default(),
+                                    genre,
+                                );
                             } else {
                                 let genre_id = get_or_create_genre(&conn, genre)?;
                                 conn.execute("DELETE FROM Z_4GENRES WHERE Z_4ITEMS4 = ?", [id])?;
@@ -164,29 +293,1242 @@ pub fn handle(cmd: ImportCommand) -> Result<()> {
                                     "INSERT INTO Z_4GENRES (Z_4ITEMS4, Z_12GENRES) VALUES (?, ?)",
                                     [id, genre_id],
                                 )?;
+                                itm_update.genre = Some(genre.to_string());
                             }
                         }
                     }
                 }
 
                 // Mark score as modified (update timestamp and version)
-                if !dry_run {
-                    mark_modified(&conn, id)?;
+                if dry_run {
+                    preview.add(patch);
+                } else {
+                    touch_item(&conn, id)?;
+
+                    // Also update the ITM file, so the edits sync
+                    match update_itm(&score.path, &itm_update) {
+                        Ok(true) => itm_files += 1,
+                        Ok(false) => {}
+                        Err(e) => {
+                            summary.error(&score.title, format!("ITM not updated: {}", e));
+                        }
+                    }
                 }
 
-                updated += 1;
+                summary.updated += 1;
             }
+            progress.finish();
 
             if dry_run {
-                println!(
-                    "\nDry run complete. Would update {} scores ({} errors)",
-                    updated, errors
-                );
+                if !preview.is_json() {
+                    println!();
+                    summary.print("Would update");
+                }
+                preview.finish();
+            } else {
+                println!("Updated {} ITM file(s)", itm_files);
+                summary.print("Updated");
+            }
+        }
+
+        ImportCommand::Json {
+            file,
+            dry_run,
+            json,
+        } => import_json(&file, dry_run, json)?,
+
+        ImportCommand::Snapshot {
+            source,
+            merge,
+            dry_run,
+            json,
+        } => import_snapshot(&source, merge, dry_run, json)?,
+
+        ImportCommand::BookmarksCsv {
+            file,
+            format,
+            dry_run,
+            json,
+            validate_only,
+        } => import_bookmarks(&file, &format, dry_run, json, validate_only)?,
+    }
+
+    Ok(())
+}
+
+/// Set matched scores and bookmarks to an `export json` document's values
+fn import_json(file: &str, dry_run: bool, json: bool) -> Result<()> {
+    let library = parse_library(&fs::read_to_string(file)?)?;
+
+    if !dry_run {
+        warn_if_running();
+    }
+    let conn = if dry_run {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+
+    let targets = list_scores_with_metadata(&conn)?;
+    let sources: Vec<Score> = library.scores.iter().map(|s| s.score.clone()).collect();
+    let matches = match_scores(&sources, &targets);
+    let library_ids: HashMap<String, i64> = list_libraries(&conn)?
+        .into_iter()
+        .map(|l| (l.title.to_lowercase(), l.id))
+        .collect();
+
+    let progress = if dry_run {
+        Progress::hidden()
+    } else {
+        Progress::new(matches.len(), "Importing")
+    };
+    let mut preview = DryRun::new(json);
+    let mut summary = Summary::default();
+    let (mut scores_updated, mut bookmarks_updated) = (0, 0);
+
+    for &(si, ti, _) in &matches {
+        progress.inc();
+        let (src, tgt) = (&library.scores[si], &targets[ti]);
+
+        let mut patch = score_patch(&src.score, tgt);
+        let fields_changed = !patch.changes.is_empty();
+
+        // Libraries are only joined, and only ones this library has
+        let mut new_libraries = Vec::new();
+        for name in &src.score.libraries {
+            if tgt.libraries.iter().any(|t| t.eq_ignore_ascii_case(name)) {
+                continue;
+            }
+            match library_ids.get(&name.to_lowercase()) {
+                Some(&id) => new_libraries.push((name, id)),
+                None => summary.skip(
+                    format!("{}: library '{}'", tgt.title, name),
+                    "no library of that name here",
+                ),
+            }
+        }
+        if !new_libraries.is_empty() {
+            let mut libraries = tgt.libraries.clone();
+            libraries.extend(new_libraries.iter().map(|(name, _)| name.to_string()));
+            patch.change("libraries", tgt.libraries.join(", "), libraries.join(", "));
+        }
+
+        if !patch.changes.is_empty() {
+            scores_updated += 1;
+            if dry_run {
+                preview.add(patch);
             } else {
-                println!("Updated {} scores ({} errors)", updated, errors);
+                if fields_changed {
+                    let itm_update = write_score(&conn, &src.score, tgt)?;
+                    if let Err(e) = update_itm(&tgt.path, &itm_update) {
+                        summary.error(&tgt.title, format!("ITM not updated: {}", e));
+                    }
+                }
+                for (_, id) in &new_libraries {
+                    add_score_to_library(&conn, *id, tgt.id)?;
+                }
+                touch_item(&conn, tgt.id)?;
+            }
+        }
+
+        // Bookmarks are only matched by UUID; titles and pages can both change
+        let existing = bookmarks_with_metadata(&conn, tgt.id)?;
+        for bm in &src.bookmarks {
+            let Some(current) = existing
+                .iter()
+                .find(|e| e.uuid.is_some() && e.uuid == bm.uuid)
+            else {
+                continue;
+            };
+
+            let (patch, itm_update) = bookmark_patch(bm, current);
+            if patch.changes.is_empty() {
+                continue;
+            }
+
+            bookmarks_updated += 1;
+            if dry_run {
+                preview.add(patch);
+                continue;
+            }
+            write_bookmark(&conn, bm, current, &itm_update)?;
+            if let Err(e) =
+                update_bookmark_in_itm(&current.path, current.uuid.as_deref(), &itm_update)
+            {
+                summary.error(&current.title, format!("ITM not updated: {}", e));
             }
         }
     }
+    progress.finish();
+
+    let matched: HashSet<usize> = matches.iter().map(|&(si, _, _)| si).collect();
+    for (si, src) in sources.iter().enumerate() {
+        if !matched.contains(&si) {
+            summary.skip(&src.path, "not in this library");
+        }
+    }
+    for setlist in &library.setlists {
+        summary.skip(
+            &setlist.title,
+            "setlists aren't imported from JSON (import snapshot creates them)",
+        );
+    }
+    summary.updated = scores_updated + bookmarks_updated;
 
+    if dry_run && preview.is_json() {
+        preview.finish();
+        return Ok(());
+    }
+    println!(
+        "{} {} score(s) and {} bookmark(s)",
+        if dry_run { "Would update" } else { "Updated" },
+        scores_updated,
+        bookmarks_updated
+    );
+    summary.print(if dry_run { "Would update" } else { "Updated" });
+    if dry_run {
+        preview.finish();
+    }
     Ok(())
 }
+
+/// Restore (or with `merge`, fill in) this library from a snapshot
+fn import_snapshot(source: &Path, merge: bool, dry_run: bool, json: bool) -> Result<()> {
+    let (path, snapshot) = load_snapshot(source)?;
+
+    if !dry_run {
+        warn_if_running();
+    }
+    let conn = if dry_run {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+
+    let mut preview = DryRun::new(json);
+    let mut summary = Summary::default();
+    if !preview.is_json() {
+        println!(
+            "Snapshot {} taken {}",
+            path.display(),
+            snapshot
+                .created
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+        );
+    }
+
+    // Libraries this one doesn't have (by name)
+    let mut library_ids: HashMap<String, i64> = list_libraries(&conn)?
+        .into_iter()
+        .map(|l| (l.title.to_lowercase(), l.id))
+        .collect();
+    let mut libraries_created = 0;
+    for name in &snapshot.libraries {
+        if library_ids.contains_key(&name.to_lowercase()) {
+            continue;
+        }
+        libraries_created += 1;
+        let id = if dry_run {
+            preview.add(Patch::create("library", name));
+            PLANNED
+        } else {
+            create_library(&conn, name)?.id
+        };
+        library_ids.insert(name.to_lowercase(), id);
+    }
+
+    let targets = list_scores_with_metadata(&conn)?;
+    let sources: Vec<Score> = snapshot.scores.iter().map(|s| s.score.clone()).collect();
+    let matches = match_scores(&sources, &targets);
+
+    let progress = if dry_run {
+        Progress::hidden()
+    } else {
+        Progress::new(matches.len(), "Importing")
+    };
+    let (mut by_uuid, mut by_path) = (0, 0);
+    let (mut scores_updated, mut bookmarks_updated, mut bookmarks_created) = (0, 0, 0);
+
+    // Snapshot items -> this library's item IDs, for setlist entries: by
+    // UUID, and by (path, start page, title) for items without one
+    let mut items_by_uuid: HashMap<String, i64> = HashMap::new();
+    let mut items_by_place: HashMap<(String, Option<i32>, String), i64> = HashMap::new();
+    let mut map_item = |uuid: &Option<String>, place: (&str, Option<i32>, &str), id: i64| {
+        if let Some(uuid) = uuid {
+            items_by_uuid.insert(uuid.clone(), id);
+        }
+        items_by_place.insert((place.0.to_string(), place.1, place.2.to_lowercase()), id);
+    };
+
+    for &(si, ti, how) in &matches {
+        progress.inc();
+        let (src, tgt) = (&snapshot.scores[si], &targets[ti]);
+        map_item(
+            &src.score.uuid,
+            (&src.score.path, None, &src.score.title),
+            tgt.id,
+        );
+        match how {
+            MatchedBy::Uuid => by_uuid += 1,
+            // `match_scores` never matches by content
+            MatchedBy::Path | MatchedBy::Content => by_path += 1,
+        }
+
+        let mut patch = if merge {
+            metadata_patch(&src.score, tgt)
+        } else {
+            score_patch(&src.score, tgt)
+        };
+        let fields_changed = !patch.changes.is_empty();
+        let new_libraries: Vec<&String> = src
+            .score
+            .libraries
+            .iter()
+            .filter(|l| !tgt.libraries.iter().any(|t| t.eq_ignore_ascii_case(l)))
+            .collect();
+        if !new_libraries.is_empty() {
+            let mut libraries = tgt.libraries.clone();
+            libraries.extend(new_libraries.iter().map(|l| l.to_string()));
+            patch.change("libraries", tgt.libraries.join(", "), libraries.join(", "));
+        }
+
+        if !patch.changes.is_empty() {
+            scores_updated += 1;
+            if dry_run {
+                preview.add(patch);
+            } else {
+                if fields_changed {
+                    let itm_update = if merge {
+                        copy_metadata(&conn, &src.score, tgt)?;
+                        itm_update(&src.score, tgt)
+                    } else {
+                        write_score(&conn, &src.score, tgt)?
+                    };
+                    if let Err(e) = update_itm(&tgt.path, &itm_update) {
+                        summary.error(&tgt.title, format!("ITM not updated: {}", e));
+                    }
+                }
+                for name in &new_libraries {
+                    add_score_to_library(&conn, library_ids[&name.to_lowercase()], tgt.id)?;
+                }
+                touch_item(&conn, tgt.id)?;
+            }
+        }
+
+        // Bookmarks match by UUID, then by title and start page; the rest
+        // are created
+        let existing = bookmarks_with_metadata(&conn, tgt.id)?;
+        let mut new_bookmarks = Vec::new();
+        for bm in &src.bookmarks {
+            let current = existing
+                .iter()
+                .find(|e| e.uuid.is_some() && e.uuid == bm.uuid)
+                .or_else(|| {
+                    existing.iter().find(|e| {
+                        e.title.eq_ignore_ascii_case(&bm.title) && e.start_page == bm.start_page
+                    })
+                });
+            let place = (src.score.path.as_str(), bm.start_page, bm.title.as_str());
+
+            if let Some(current) = current {
+                map_item(&bm.uuid, place, current.id);
+                if merge {
+                    continue;
+                }
+                let (patch, itm_update) = bookmark_patch(bm, current);
+                if patch.changes.is_empty() {
+                    continue;
+                }
+                bookmarks_updated += 1;
+                if dry_run {
+                    preview.add(patch);
+                    continue;
+                }
+                write_bookmark(&conn, bm, current, &itm_update)?;
+                if let Err(e) =
+                    update_bookmark_in_itm(&current.path, current.uuid.as_deref(), &itm_update)
+                {
+                    summary.error(&current.title, format!("ITM not updated: {}", e));
+                }
+                continue;
+            }
+
+            let (Some(start), Some(end)) = (bm.start_page, bm.end_page) else {
+                summary.skip(&bm.title, "bookmark has no page range");
+                continue;
+            };
+            bookmarks_created += 1;
+            if dry_run {
+                let mut patch = Patch::create("bookmark", &bm.title);
+                patch.change("score", "", tgt.title.as_str());
+                patch.change("pages", "", format!("{}-{}", start, end));
+                preview.add(patch);
+                map_item(&bm.uuid, place, PLANNED);
+            } else {
+                let created = create_bookmark(&conn, tgt, &bm.title, start, end)?;
+                map_item(&bm.uuid, place, created.id);
+                new_bookmarks.push(ItmNewBookmark {
+                    title: bm.title.clone(),
+                    identifier: created.uuid.unwrap_or_default(),
+                    first_page: start as i64,
+                    last_page: end as i64,
+                });
+            }
+        }
+        if !new_bookmarks.is_empty() {
+            if let Err(e) = add_bookmarks_to_itm(&tgt.path, &new_bookmarks) {
+                summary.error(&tgt.title, format!("ITM not updated: {}", e));
+            }
+        }
+    }
+    progress.finish();
+
+    let matched: HashSet<usize> = matches.iter().map(|&(si, _, _)| si).collect();
+    for (si, src) in sources.iter().enumerate() {
+        if !matched.contains(&si) {
+            summary.skip(&src.path, "not in this library");
+        }
+    }
+
+    // Setlists this library doesn't have (by name)
+    let existing: HashSet<String> = list_setlists(&conn)?
+        .into_iter()
+        .map(|s| s.title.to_lowercase())
+        .collect();
+    let mut setlists_created = 0;
+    for setlist in &snapshot.setlists {
+        if existing.contains(&setlist.title.to_lowercase()) {
+            continue;
+        }
+
+        let mut items = Vec::new();
+        for item in &setlist.items {
+            let id = item
+                .uuid
+                .as_ref()
+                .and_then(|uuid| items_by_uuid.get(uuid))
+                .or_else(|| {
+                    items_by_place.get(&(
+                        item.path.clone(),
+                        item.start_page,
+                        item.title.to_lowercase(),
+                    ))
+                });
+            match id {
+                Some(&id) => items.push((id, item)),
+                None => summary.skip(
+                    format!("{}: {}", setlist.title, item.title),
+                    "not in this library",
+                ),
+            }
+        }
+
+        setlists_created += 1;
+        if dry_run {
+            let mut patch = Patch::create("setlist", &setlist.title);
+            for (i, (_, item)) in items.iter().enumerate() {
+                patch.change(&format!("item {}", i + 1), "", item.title.as_str());
+            }
+            preview.add(patch);
+            continue;
+        }
+        let entries: Vec<(i64, Option<i32>, Option<i32>)> = items
+            .iter()
+            .map(|(id, item)| (*id, item.start_page, item.end_page))
+            .collect();
+        create_setlist_with_items(&conn, &setlist.title, &entries, &mut summary)?;
+    }
+
+    summary.updated = scores_updated
+        + bookmarks_updated
+        + bookmarks_created
+        + setlists_created
+        + libraries_created;
+
+    if dry_run && preview.is_json() {
+        preview.finish();
+        return Ok(());
+    }
+
+    println!();
+    println!(
+        "Matched {} of {} scores ({} by UUID, {} by path); {} not in this library",
+        matches.len(),
+        sources.len(),
+        by_uuid,
+        by_path,
+        sources.len() - matches.len()
+    );
+    let verb = if dry_run { "Would" } else { "Did" };
+    println!(
+        "{} update {} score(s) and {} bookmark(s); create {} bookmark(s), {} setlist(s) and {} librar{}",
+        verb,
+        scores_updated,
+        bookmarks_updated,
+        bookmarks_created,
+        setlists_created,
+        libraries_created,
+        if libraries_created == 1 { "y" } else { "ies" }
+    );
+    println!();
+    summary.print(if dry_run { "Would change" } else { "Changed" });
+
+    if dry_run {
+        preview.finish();
+    }
+    Ok(())
+}
+
+/// The bookmark fields where `current` differs from the imported `bm`
+fn bookmark_patch(bm: &Bookmark, current: &Bookmark) -> (Patch, ItmBookmarkUpdate) {
+    let mut patch = Patch::update("bookmark", current.id, &current.title);
+    let mut itm_update = ItmBookmarkUpdate::new();
+    if bm.title != current.title {
+        patch.change("title", current.title.as_str(), bm.title.as_str());
+        itm_update.title = Some(bm.title.clone());
+    }
+    if let (Some(start), Some(end)) = (bm.start_page, bm.end_page) {
+        if (bm.start_page, bm.end_page) != (current.start_page, current.end_page) {
+            patch.change(
+                "pages",
+                match (current.start_page, current.end_page) {
+                    (Some(s), Some(e)) => format!("{}-{}", s, e),
+                    _ => String::new(),
+                },
+                format!("{}-{}", start, end),
+            );
+            itm_update.first_page = Some(start as i64);
+            itm_update.last_page = Some(end as i64);
+        }
+    }
+    for (field, key, names, current_names, itm_names) in [
+        (
+            "composers",
+            "Composer",
+            &bm.composers,
+            &current.composers,
+            &mut itm_update.composer,
+        ),
+        (
+            "genres",
+            "Genre",
+            &bm.genres,
+            &current.genres,
+            &mut itm_update.genre,
+        ),
+    ] {
+        if same_names(names, current_names) {
+            continue;
+        }
+        patch.change(field, current_names.join(", "), names.join(", "));
+        if names.is_empty() {
+            itm_update.remove.push(key);
+        } else {
+            *itm_names = Some(names.join(", "));
+        }
+    }
+    if bm.rating != current.rating {
+        let number = |n: Option<i32>| n.map(|n| n.to_string()).unwrap_or_default();
+        patch.change("rating", number(current.rating), number(bm.rating));
+        match bm.rating {
+            Some(rating) => itm_update.rating = Some(rating as i64),
+            None => itm_update.remove.push("Rating"),
+        }
+    }
+    (patch, itm_update)
+}
+
+/// A score's bookmarks with their composers and genres
+fn bookmarks_with_metadata(conn: &Connection, score_id: i64) -> Result<Vec<Bookmark>> {
+    let mut bookmarks = list_bookmarks(conn, score_id)?;
+    for bookmark in &mut bookmarks {
+        bookmark.load_metadata(conn)?;
+    }
+    Ok(bookmarks)
+}
+
+/// Write what `bookmark_patch` found to the database (the ITM is left to
+/// the caller)
+fn write_bookmark(
+    conn: &Connection,
+    bm: &Bookmark,
+    current: &Bookmark,
+    update: &ItmBookmarkUpdate,
+) -> Result<()> {
+    if let Some(title) = &update.title {
+        conn.execute(
+            "UPDATE ZITEM SET ZTITLE = ?, ZSORTTITLE = ? WHERE Z_PK = ?",
+            rusqlite::params![title, sort_title(title), current.id],
+        )?;
+    }
+    if let (Some(start), Some(end)) = (update.first_page, update.last_page) {
+        conn.execute(
+            "UPDATE ZITEM SET ZSTARTPAGE = ?, ZENDPAGE = ? WHERE Z_PK = ?",
+            [start, end, current.id],
+        )?;
+    }
+    if !same_names(&bm.composers, &current.composers) {
+        set_item_links(
+            conn,
+            COMPOSER_LINKS,
+            current.id,
+            &current.composers,
+            &bm.composers,
+        )?;
+    }
+    if !same_names(&bm.genres, &current.genres) {
+        set_item_links(conn, GENRE_LINKS, current.id, &current.genres, &bm.genres)?;
+    }
+    if bm.rating != current.rating {
+        let rating_id = bm
+            .rating
+            .map(|r| get_or_create_rating(conn, r))
+            .transpose()?;
+        conn.execute(
+            "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
+            rusqlite::params![rating_id, current.id],
+        )?;
+    }
+    touch_item(conn, current.id)
+}
+
+/// Whether two name lists hold the same names, ignoring case and order
+fn same_names(a: &[String], b: &[String]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .all(|n| b.iter().any(|m| m.eq_ignore_ascii_case(n)))
+}
+
+/// The fields where `tgt` differs from the imported `src`
+fn score_patch(src: &Score, tgt: &Score) -> Patch {
+    let mut patch = Patch::update("score", tgt.id, &tgt.title);
+    let number = |n: Option<i32>| n.map(|n| n.to_string()).unwrap_or_default();
+    let key = |k: &Option<MusicalKey>| k.as_ref().map(|k| k.display()).unwrap_or_default();
+
+    if src.title != tgt.title {
+        patch.change("title", tgt.title.as_str(), src.title.as_str());
+    }
+    if key(&src.key) != key(&tgt.key) {
+        patch.change("key", key(&tgt.key), key(&src.key));
+    }
+    if src.rating != tgt.rating {
+        patch.change("rating", number(tgt.rating), number(src.rating));
+    }
+    if src.difficulty != tgt.difficulty {
+        patch.change("difficulty", number(tgt.difficulty), number(src.difficulty));
+    }
+    if src.bpm != tgt.bpm {
+        patch.change("bpm", number(tgt.bpm), number(src.bpm));
+    }
+    for (field, s, t) in [
+        ("composers", &src.composers, &tgt.composers),
+        ("genres", &src.genres, &tgt.genres),
+        ("tags", &src.keywords, &tgt.keywords),
+        ("labels", &src.labels, &tgt.labels),
+    ] {
+        if !same_names(s, t) {
+            patch.change(field, t.join(", "), s.join(", "));
+        }
+    }
+    patch
+}
+
+/// Write the fields `score_patch` found different, returning the ITM side
+fn write_score(conn: &Connection, src: &Score, tgt: &Score) -> Result<ItmUpdate> {
+    let mut itm_update = ItmUpdate::new();
+
+    if src.title != tgt.title {
+        conn.execute(
+            "UPDATE ZITEM SET ZTITLE = ?, ZSORTTITLE = ? WHERE Z_PK = ?",
+            rusqlite::params![src.title, sort_title(&src.title), tgt.id],
+        )?;
+        itm_update.title = Some(src.title.clone());
+    }
+    let key_code = |k: &Option<MusicalKey>| k.as_ref().map(|k| k.code);
+    if key_code(&src.key) != key_code(&tgt.key) {
+        conn.execute(
+            "UPDATE ZITEM SET ZKEY = ? WHERE Z_PK = ?",
+            rusqlite::params![key_code(&src.key), tgt.id],
+        )?;
+        match key_code(&src.key) {
+            Some(code) => itm_update.key = Some(code as i64),
+            None => itm_update.remove.push("key"),
+        }
+    }
+    if src.rating != tgt.rating {
+        let rating_id = src
+            .rating
+            .map(|r| get_or_create_rating(conn, r))
+            .transpose()?;
+        conn.execute(
+            "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
+            rusqlite::params![rating_id, tgt.id],
+        )?;
+        match src.rating {
+            Some(rating) => itm_update.rating = Some(rating as i64),
+            None => itm_update.remove.push("rating"),
+        }
+    }
+    if src.difficulty != tgt.difficulty {
+        let difficulty_id = src
+            .difficulty
+            .map(|d| get_or_create_difficulty(conn, d))
+            .transpose()?;
+        conn.execute(
+            "UPDATE ZITEM SET ZDIFFICULTY = ? WHERE Z_PK = ?",
+            rusqlite::params![difficulty_id, tgt.id],
+        )?;
+        match src.difficulty {
+            Some(difficulty) => itm_update.difficulty = Some(difficulty as i64),
+            None => itm_update.remove.push("difficulty"),
+        }
+    }
+    // The ITM has no tempo field
+    if src.bpm != tgt.bpm {
+        conn.execute(
+            "UPDATE ZITEM SET ZBPM = ? WHERE Z_PK = ?",
+            rusqlite::params![src.bpm, tgt.id],
+        )?;
+    }
+
+    if !same_names(&src.composers, &tgt.composers) {
+        set_item_links(conn, COMPOSER_LINKS, tgt.id, &tgt.composers, &src.composers)?;
+        itm_update.composer = Some(src.composers.join(", "));
+    }
+    if !same_names(&src.genres, &tgt.genres) {
+        set_item_links(conn, GENRE_LINKS, tgt.id, &tgt.genres, &src.genres)?;
+        itm_update.genre = Some(src.genres.join(", "));
+    }
+    if !same_names(&src.keywords, &tgt.keywords) {
+        set_item_links(conn, KEYWORD_LINKS, tgt.id, &tgt.keywords, &src.keywords)?;
+        itm_update.keywords = Some(src.keywords.clone());
+    }
+    if !same_names(&src.labels, &tgt.labels) {
+        set_item_links(conn, LABEL_LINKS, tgt.id, &tgt.labels, &src.labels)?;
+        itm_update.labels = Some(src.labels.clone());
+    }
+
+    Ok(itm_update)
+}
+
+/// A row of `import bookmarks-csv`; empty cells are None
+struct BookmarkRow {
+    id: Option<i64>,
+    path: String,
+    title: Option<String>,
+    start_page: Option<i32>,
+    end_page: Option<i32>,
+    /// The names in a "; "-separated cell, as `export bookmarks-csv` writes them
+    composers: Option<Vec<String>>,
+    genres: Option<Vec<String>>,
+    rating: Option<i32>,
+}
+
+/// What a bookmark row applies to: an existing bookmark, or a new one in a score
+enum BookmarkTarget {
+    Update(Bookmark),
+    Create(Score),
+}
+
+fn parse_bookmark_row(
+    headers: &StringRecord,
+    record: &StringRecord,
+) -> std::result::Result<BookmarkRow, String> {
+    let field = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .and_then(|i| record.get(i))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+    let number = |name: &str| -> std::result::Result<Option<i32>, String> {
+        field(name)
+            .map(|v| {
+                v.parse::<i32>()
+                    .map_err(|_| format!("invalid {} '{}'", name, v))
+            })
+            .transpose()
+    };
+
+    let id = field("id")
+        .map(|v| v.parse::<i64>().map_err(|_| format!("invalid id '{}'", v)))
+        .transpose()?;
+    let start_page = number("start_page")?;
+    let end_page = number("end_page")?;
+    if start_page.is_some_and(|p| p < 1) || end_page.is_some_and(|p| p < 1) {
+        return Err("pages start at 1".to_string());
+    }
+    let rating = number("rating")?;
+    if rating.is_some_and(|r| !(1..=6).contains(&r)) {
+        return Err(format!(
+            "rating '{}' must be 1-6",
+            rating.unwrap_or_default()
+        ));
+    }
+
+    Ok(BookmarkRow {
+        id,
+        path: field("path").unwrap_or_default().to_string(),
+        title: field("title").map(str::to_string),
+        start_page,
+        end_page,
+        composers: field("composer").map(name_list),
+        genres: field("genre").map(name_list),
+        rating,
+    })
+}
+
+/// The names in a list cell, split on ";"
+fn name_list(cell: &str) -> Vec<String> {
+    cell.split(';')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The bookmark a row updates, or the score it adds one to
+fn bookmark_target(
+    conn: &Connection,
+    row: &BookmarkRow,
+) -> std::result::Result<BookmarkTarget, String> {
+    if let Some(id) = row.id {
+        let bookmark =
+            get_bookmark_by_id(conn, id).map_err(|_| format!("bookmark ID {} not found", id))?;
+        let start = row.start_page.or(bookmark.start_page).unwrap_or(1);
+        let end = row.end_page.or(bookmark.end_page).unwrap_or(start);
+        if end < start {
+            return Err(format!("end_page {} is before start_page {}", end, start));
+        }
+        return Ok(BookmarkTarget::Update(bookmark));
+    }
+
+    if row.path.is_empty() {
+        return Err("no id, and no path for a new bookmark".to_string());
+    }
+    let score = get_score_by_path(conn, &row.path)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("no score with path '{}'", row.path))?;
+    if row.title.is_none() {
+        return Err("a new bookmark needs a title".to_string());
+    }
+    let Some(start) = row.start_page else {
+        return Err("a new bookmark needs a start_page".to_string());
+    };
+    if row.end_page.is_some_and(|end| end < start) {
+        return Err(format!(
+            "end_page {} is before start_page {}",
+            row.end_page.unwrap_or_default(),
+            start
+        ));
+    }
+    Ok(BookmarkTarget::Create(score))
+}
+
+fn import_bookmarks(
+    file: &str,
+    format: &CsvFormatArgs,
+    dry_run: bool,
+    json: bool,
+    validate_only: bool,
+) -> Result<()> {
+    let read_only = dry_run || validate_only;
+    if !read_only {
+        warn_if_running();
+    }
+
+    let conn = if read_only {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+
+    let format = CsvFormat::from_args(format)?;
+    let text = format.decode(&fs::read(file)?);
+    let mut rdr = ReaderBuilder::new()
+        .delimiter(format.delimiter)
+        .from_reader(text.as_bytes());
+
+    let headers = rdr.headers()?.clone();
+    let records: Vec<StringRecord> = rdr.records().collect::<csv::Result<_>>()?;
+
+    // Validate everything before writing anything
+    let mut problems: Vec<String> = headers
+        .iter()
+        .filter(|h| !BOOKMARK_COLUMNS.contains(h))
+        .map(|h| format!("Header: unknown column '{}'", h))
+        .collect();
+    let mut rows = Vec::new();
+    for record in &records {
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+        let resolved = parse_bookmark_row(&headers, record)
+            .and_then(|row| bookmark_target(&conn, &row).map(|target| (row, target)));
+        match resolved {
+            Ok(row) => rows.push(row),
+            Err(problem) => problems.push(format!("Line {}: {}", line, problem)),
+        }
+    }
+    if !problems.is_empty() {
+        println!("Validation failed ({} problems):", problems.len());
+        for problem in &problems {
+            println!("  {}", problem);
+        }
+        return Err(ForScoreError::Other(format!(
+            "{} has {} validation problems; nothing was imported",
+            file,
+            problems.len()
+        )));
+    }
+
+    if validate_only {
+        println!("Validation passed: {} rows OK", records.len());
+        return Ok(());
+    }
+
+    let mut summary = Summary::default();
+    let mut preview = DryRun::new(json);
+    let mut created = 0;
+
+    let progress = if dry_run {
+        Progress::hidden()
+    } else {
+        Progress::new(rows.len(), "Importing")
+    };
+
+    for (row, target) in rows {
+        progress.inc();
+
+        let (mut patch, bookmark, is_new) = match target {
+            BookmarkTarget::Update(bookmark) => (
+                Patch::update("bookmark", bookmark.id, &bookmark.title),
+                Some(bookmark),
+                false,
+            ),
+            BookmarkTarget::Create(score) => {
+                let title = row.title.clone().unwrap_or_default();
+                let start = row.start_page.unwrap_or(1);
+                let end = row.end_page.unwrap_or(start);
+                let existing = list_bookmarks(&conn, score.id)?;
+                if existing
+                    .iter()
+                    .any(|bm| bm.title.eq_ignore_ascii_case(&title) && bm.start_page == Some(start))
+                {
+                    summary.skip(&title, format!("'{}' already has it", score.title));
+                    continue;
+                }
+
+                let mut patch = Patch::create("bookmark", &title);
+                patch.change("score", "", score.title.as_str());
+                patch.change("pages", "", format!("{}-{}", start, end));
+                if dry_run {
+                    (patch, None, true)
+                } else {
+                    let bookmark = create_bookmark(&conn, &score, &title, start, end)?;
+                    touch_item(&conn, score.id)?;
+                    let itm_bookmark = ItmNewBookmark {
+                        title,
+                        identifier: bookmark.uuid.clone().unwrap_or_default(),
+                        first_page: start as i64,
+                        last_page: end as i64,
+                    };
+                    if let Err(e) = add_bookmarks_to_itm(&score.path, &[itm_bookmark]) {
+                        summary.error(&bookmark.title, format!("ITM not updated: {}", e));
+                    }
+                    created += 1;
+                    (patch, Some(bookmark), true)
+                }
+            }
+        };
+        let current = bookmark.as_ref().filter(|_| !is_new);
+
+        // Fields that differ from the bookmark (all given ones for a new bookmark)
+        let mut itm_update = ItmBookmarkUpdate::new();
+        if let Some(bookmark) = current {
+            if let Some(title) = row.title.as_ref().filter(|t| **t != bookmark.title) {
+                patch.change("title", bookmark.title.as_str(), title.as_str());
+                itm_update.title = Some(title.clone());
+            }
+            let start = row.start_page.or(bookmark.start_page).unwrap_or(1);
+            let end = row.end_page.or(bookmark.end_page).unwrap_or(start);
+            if (Some(start), Some(end)) != (bookmark.start_page, bookmark.end_page) {
+                patch.change(
+                    "pages",
+                    match (bookmark.start_page, bookmark.end_page) {
+                        (Some(s), Some(e)) => format!("{}-{}", s, e),
+                        _ => String::new(),
+                    },
+                    format!("{}-{}", start, end),
+                );
+                itm_update.first_page = Some(start as i64);
+                itm_update.last_page = Some(end as i64);
+            }
+        }
+        let no_names = Vec::new();
+        let old_composers = current.map_or(&no_names, |b| &b.composers);
+        let composers = row
+            .composers
+            .as_ref()
+            .filter(|names| !same_names(names, old_composers));
+        if let Some(names) = composers {
+            patch.change("composer", old_composers.join(", "), names.join(", "));
+            itm_update.composer = Some(names.join(", "));
+        }
+        let old_genres = current.map_or(&no_names, |b| &b.genres);
+        let genres = row
+            .genres
+            .as_ref()
+            .filter(|names| !same_names(names, old_genres));
+        if let Some(names) = genres {
+            patch.change("genre", old_genres.join(", "), names.join(", "));
+            itm_update.genre = Some(names.join(", "));
+        }
+        let old_rating = current.and_then(|b| b.rating);
+        if let Some(rating) = row.rating.filter(|r| Some(*r) != old_rating) {
+            patch.change(
+                "rating",
+                old_rating.map(|r| r.to_string()).unwrap_or_default(),
+                rating.to_string(),
+            );
+            itm_update.rating = Some(rating as i64);
+        }
+
+        if !is_new && itm_update.is_empty() {
+            continue;
+        }
+        summary.updated += 1;
+        if dry_run {
+            preview.add(patch);
+            continue;
+        }
+        let Some(bookmark) = bookmark else {
+            continue;
+        };
+
+        let id = bookmark.id;
+        if let Some(title) = &itm_update.title {
+            conn.execute(
+                "UPDATE ZITEM SET ZTITLE = ?, ZSORTTITLE = ? WHERE Z_PK = ?",
+                rusqlite::params![title, sort_title(title), id],
+            )?;
+        }
+        if let (Some(start), Some(end)) = (itm_update.first_page, itm_update.last_page) {
+            conn.execute(
+                "UPDATE ZITEM SET ZSTARTPAGE = ?, ZENDPAGE = ? WHERE Z_PK = ?",
+                [start, end, id],
+            )?;
+        }
+        if let Some(names) = composers {
+            set_item_links(&conn, COMPOSER_LINKS, id, &bookmark.composers, names)?;
+        }
+        if let Some(names) = genres {
+            set_item_links(&conn, GENRE_LINKS, id, &bookmark.genres, names)?;
+        }
+        if let Some(rating) = itm_update.rating {
+            let rating_id = get_or_create_rating(&conn, rating as i32)?;
+            conn.execute(
+                "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
+                [rating_id, id],
+            )?;
+        }
+        touch_item(&conn, id)?;
+
+        if let Err(e) =
+            update_bookmark_in_itm(&bookmark.path, bookmark.uuid.as_deref(), &itm_update)
+        {
+            summary.error(&bookmark.title, format!("ITM not updated: {}", e));
+        }
+    }
+    progress.finish();
+
+    if dry_run {
+        if !preview.is_json() {
+            println!();
+            summary.print("Would import");
+        }
+        preview.finish();
+    } else {
+        println!(
+            "Created {} bookmark(s), updated {}",
+            created,
+            summary.updated - created
+        );
+        summary.print("Imported");
+    }
+
+    Ok(())
+}
+
+/// The column `--match-by` reads
+fn match_column(match_by: CsvMatch) -> &'static str {
+    match match_by {
+        CsvMatch::Id => "id",
+        CsvMatch::Uuid => "uuid",
+        CsvMatch::Path => "path",
+        CsvMatch::Title => "title",
+    }
+}
+
+/// The score a row's match column names, or what's wrong with it
+fn row_score(
+    conn: &Connection,
+    match_by: CsvMatch,
+    value: &str,
+) -> std::result::Result<Score, String> {
+    let value = value.trim();
+    match match_by {
+        CsvMatch::Id => {
+            let id: i64 = value
+                .parse()
+                .map_err(|_| format!("invalid id '{}'", value))?;
+            get_score_by_id(conn, id).map_err(|_| format!("score ID {} not found", id))
+        }
+        CsvMatch::Uuid if value.is_empty() => Err("no uuid".to_string()),
+        CsvMatch::Uuid => match get_score_by_uuid(conn, value) {
+            Ok(Some(score)) => Ok(score),
+            Ok(None) => Err(format!("no score with UUID {}", value)),
+            Err(e) => Err(e.to_string()),
+        },
+        CsvMatch::Path if value.is_empty() => Err("no path".to_string()),
+        CsvMatch::Path => match get_score_by_path(conn, value) {
+            Ok(Some(score)) => Ok(score),
+            Ok(None) => Err(format!("no score with path '{}'", value)),
+            Err(e) => Err(e.to_string()),
+        },
+        CsvMatch::Title if value.is_empty() => Err("no title".to_string()),
+        CsvMatch::Title => {
+            let count: i64 = conn
+                .query_row(
+                    "SELECT COUNT(*) FROM ZITEM WHERE LOWER(ZTITLE) = LOWER(?) AND Z_ENT = ?",
+                    rusqlite::params![value, entity::score()],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+            match count {
+                0 => Err(format!("no score titled '{}'", value)),
+                1 => get_score_by_title(conn, value).map_err(|e| e.to_string()),
+                n => Err(format!("{} scores are titled '{}'", n, value)),
+            }
+        }
+    }
+}
+
+/// Check headers and every row, returning a report line per problem
+fn validate(
+    conn: &Connection,
+    headers: &StringRecord,
+    records: &[StringRecord],
+    match_by: CsvMatch,
+    upsert: bool,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for header in headers.iter() {
+        if !KNOWN_COLUMNS.contains(&header) {
+            problems.push(format!("Header: unknown column '{}'", header));
+        }
+    }
+
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let match_idx = column(match_column(match_by));
+    let key_idx = column("key");
+    let rating_idx = column("rating");
+    let difficulty_idx = column("difficulty");
+
+    if match_idx.is_none() {
+        problems.push(format!(
+            "Header: missing required '{}' column",
+            match_column(match_by)
+        ));
+        return problems;
+    }
+
+    for record in records {
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+        let field = |idx: Option<usize>| idx.and_then(|i| record.get(i)).unwrap_or("").trim();
+
+        // With --upsert, unmatched rows are reported after the import instead
+        if let Err(problem) = row_score(conn, match_by, field(match_idx)) {
+            if !upsert {
+                problems.push(format!("Line {}: {}", line, problem));
+            }
+        }
+
+        let key = field(key_idx);
+        if !key.is_empty() && MusicalKey::from_string(key).is_err() {
+            problems.push(format!("Line {}: unrecognized key '{}'", line, key));
+        }
+
+        let rating = field(rating_idx);
+        if !rating.is_empty() && !matches!(rating.parse::<i32>(), Ok(r) if (1..=6).contains(&r)) {
+            problems.push(format!("Line {}: rating '{}' must be 1-6", line, rating));
+        }
+
+        let difficulty = field(difficulty_idx);
+        if !difficulty.is_empty()
+            && !matches!(difficulty.parse::<i32>(), Ok(d) if (1..=5).contains(&d))
+        {
+            problems.push(format!(
+                "Line {}: difficulty '{}' must be 1-5",
+                line, difficulty
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bookmark_row() {
+        let headers = StringRecord::from(vec!["id", "path", "title", "start_page", "rating"]);
+
+        let row = parse_bookmark_row(
+            &headers,
+            &StringRecord::from(vec!["", "a.pdf", " Coda ", "12", ""]),
+        )
+        .unwrap();
+        assert_eq!(row.id, None);
+        assert_eq!(row.path, "a.pdf");
+        assert_eq!(row.title.as_deref(), Some("Coda"));
+        assert_eq!(row.start_page, Some(12));
+        assert_eq!(row.end_page, None);
+        assert_eq!(row.rating, None);
+
+        let bad = |cells: Vec<&str>| parse_bookmark_row(&headers, &StringRecord::from(cells));
+        assert!(bad(vec!["x", "", "", "", ""]).is_err());
+        assert!(bad(vec!["", "", "", "0", ""]).is_err());
+        assert!(bad(vec!["", "", "", "", "7"]).is_err());
+    }
+
+    #[test]
+    fn test_bookmark_csv_round_trip() {
+        let bookmark = Bookmark {
+            id: 7,
+            path: "a.pdf".to_string(),
+            title: "Coda".to_string(),
+            uuid: Some("BM".to_string()),
+            start_page: Some(3),
+            end_page: Some(4),
+            rating: Some(5),
+            difficulty: None,
+            key: None,
+            composers: vec!["Bach".to_string(), "Busoni".to_string()],
+            genres: vec!["Baroque".to_string()],
+        };
+        let headers = StringRecord::from(BOOKMARK_COLUMNS.to_vec());
+        let record = StringRecord::from(crate::commands::export::bookmark_row(&bookmark));
+
+        let row = parse_bookmark_row(&headers, &record).unwrap();
+        assert_eq!(row.id, Some(7));
+        assert_eq!(row.composers.as_deref(), Some(&bookmark.composers[..]));
+        assert_eq!(row.genres.as_deref(), Some(&bookmark.genres[..]));
+        assert_eq!((row.start_page, row.end_page), (Some(3), Some(4)));
+        assert_eq!(row.rating, Some(5));
+        assert!(same_names(&row.composers.unwrap(), &bookmark.composers));
+    }
+}
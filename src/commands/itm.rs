@@ -0,0 +1,101 @@
+use crate::cli::ItmCommand;
+use crate::commands::scores::build_filter;
+use crate::db::open_readonly;
+use crate::error::{ForScoreError, Result};
+use crate::itm::{itm_path_for_score, plist_to_json, read_itm, sync_folder_path};
+use crate::models::score::search_scores;
+use crate::progress::{Progress, Summary};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn handle(cmd: ItmCommand) -> Result<()> {
+    match cmd {
+        ItmCommand::Dump { out, filter } => {
+            // (name for the JSON file, ITM path)
+            let itms: Vec<(String, PathBuf)> = if filter.is_empty() {
+                all_itm_files()?
+            } else {
+                let conn = open_readonly()?;
+                let filter = build_filter(&conn, None, &filter)?;
+                let paths: BTreeSet<String> = search_scores(&conn, &filter, i64::MAX as usize)?
+                    .into_iter()
+                    .map(|s| s.path)
+                    .collect();
+                paths
+                    .into_iter()
+                    .map(|path| Ok((path.clone(), itm_path_for_score(&path)?)))
+                    .collect::<Result<_>>()?
+            };
+
+            dump(&out, &itms)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Every .itm file in the sync folder, named by the PDF it belongs to
+fn all_itm_files() -> Result<Vec<(String, PathBuf)>> {
+    let sync_folder = sync_folder_path()?;
+    let mut files: Vec<(String, PathBuf)> = fs::read_dir(&sync_folder)
+        .map_err(|e| ForScoreError::Other(format!("Cannot read sync folder: {}", e)))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let name = path
+                .file_name()?
+                .to_str()?
+                .strip_suffix(".itm")?
+                .to_string();
+            Some((name, path))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Write `<out>/<pdf path>.json` for each ITM, leaving unchanged files alone
+/// so the tree diffs cleanly
+fn dump(out: &Path, itms: &[(String, PathBuf)]) -> Result<()> {
+    let progress = Progress::new(itms.len(), "Dumping ITM files");
+    let mut summary = Summary::default();
+    let mut unchanged = 0;
+
+    for (name, itm_path) in itms {
+        progress.inc();
+
+        if !itm_path.exists() {
+            summary.skip(name, "no ITM file");
+            continue;
+        }
+        let value = match read_itm(itm_path) {
+            Ok(value) => value,
+            Err(e) => {
+                summary.error(name, e.to_string());
+                continue;
+            }
+        };
+
+        let json_path = out.join(format!("{}.json", name));
+        let json = serde_json::to_string_pretty(&plist_to_json(&value))? + "\n";
+        if fs::read_to_string(&json_path).is_ok_and(|existing| existing == json) {
+            unchanged += 1;
+            continue;
+        }
+        if let Some(parent) = json_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&json_path, json)?;
+        summary.updated += 1;
+    }
+
+    progress.finish();
+    summary.print("Written");
+    if unchanged > 0 {
+        println!("{} unchanged", unchanged);
+    }
+    println!("JSON files in {}", out.display());
+
+    Ok(())
+}
@@ -1,11 +1,15 @@
+use crate::cache::load_page_counts;
 use crate::cli::LibrariesCommand;
 use crate::db::{open_readonly, open_readwrite, warn_if_running};
 use crate::error::Result;
 use crate::models::library::{
     add_score_to_library, list_libraries, remove_score_from_library, resolve_library,
 };
-use crate::models::score::{list_scores_in_library, resolve_score};
-use crate::output::output;
+use crate::models::score::{
+    list_scores_in_library, load_metadata_batch, resolve_score, resolve_score_strict,
+};
+use crate::output::{is_structured, output, output_scores, table_columns};
+use crate::touch::touch_item;
 
 pub fn handle(cmd: LibrariesCommand) -> Result<()> {
     match cmd {
@@ -15,21 +19,26 @@ pub fn handle(cmd: LibrariesCommand) -> Result<()> {
             output(&libraries, json);
         }
 
-        LibrariesCommand::Show { identifier, json } => {
+        LibrariesCommand::Show {
+            identifier,
+            columns,
+            json,
+        } => {
             let conn = open_readonly()?;
             let library = resolve_library(&conn, &identifier)?;
             let mut scores = list_scores_in_library(&conn, library.id)?;
 
             // Load metadata (composers, genres, etc.) for each score
-            for score in &mut scores {
-                score.load_metadata(&conn)?;
-            }
+            load_metadata_batch(&conn, &mut scores)?;
+            load_page_counts(&conn, &mut scores)?;
 
-            println!(
-                "Library: {} ({} scores)\n",
-                library.title, library.score_count
-            );
-            output(&scores, json);
+            if !is_structured(json) {
+                println!(
+                    "Library: {} ({} scores)\n",
+                    library.title, library.score_count
+                );
+            }
+            output_scores(&scores, json, &table_columns(&columns, false, false)?);
         }
 
         LibrariesCommand::AddScore { library, score } => {
@@ -38,6 +47,7 @@ pub fn handle(cmd: LibrariesCommand) -> Result<()> {
             let lib = resolve_library(&conn, &library)?;
             let sc = resolve_score(&conn, &score)?;
             add_score_to_library(&conn, lib.id, sc.id)?;
+            touch_item(&conn, sc.id)?;
             println!("Added '{}' to library '{}'", sc.title, lib.title);
         }
 
@@ -45,8 +55,9 @@ pub fn handle(cmd: LibrariesCommand) -> Result<()> {
             warn_if_running();
             let conn = open_readwrite()?;
             let lib = resolve_library(&conn, &library)?;
-            let sc = resolve_score(&conn, &score)?;
+            let sc = resolve_score_strict(&conn, &score)?;
             remove_score_from_library(&conn, lib.id, sc.id)?;
+            touch_item(&conn, sc.id)?;
             println!("Removed '{}' from library '{}'", sc.title, lib.title);
         }
     }
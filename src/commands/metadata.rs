@@ -1,17 +1,42 @@
-use crate::cli::{ComposersCommand, GenresCommand, TagsCommand};
-use crate::db::{open_readonly, open_readwrite, warn_if_running};
-use crate::error::Result;
-use crate::itm::rename_composer_in_all_itm;
+use crate::cli::{ComposersCommand, GenresCommand, MetaCommand, TagsCommand};
+use crate::commands::scores::build_filter;
+use crate::db::{entity, open_readonly, open_readwrite, warn_if_running};
+use crate::dry_run::{DryRun, Patch};
+use crate::error::{ForScoreError, Result};
+use crate::itm::{
+    remove_keyword_from_all_itm, rename_composer_in_all_itm, rename_keywords_in_all_itm,
+    update_bookmark_in_itm, update_itm, ItmBookmarkUpdate, ItmUpdate,
+};
 use crate::models::meta::{
-    list_composers, list_genres, list_keywords, merge_composers, rename_composer,
+    composer_items, create_difficulty_level, create_vocabulary_entry, delete_composer,
+    delete_keyword, get_composer_by_name, get_keyword_by_name, keyword_items, list_composers,
+    list_difficulty_levels, list_genres, list_keywords, list_vocabulary, merge_composers,
+    merge_keywords, rename_composer, rename_in_subtree, rename_keyword_subtree,
+    set_difficulty_label, tag_score, untag_score, DifficultyLevel, Vocabulary, GENRES, KEYWORDS,
+    LABELS,
+};
+use crate::models::score::{
+    get_bookmark_by_id, get_score_by_id, load_metadata_batch, resolve_score, resolve_score_strict,
+    search_scores,
+};
+use crate::models::Score;
+use crate::output::{output, render_tree};
+use crate::progress::Progress;
+use crate::store::{
+    get_composer_info, load_composer_info, open_store, parse_years, rename_composer_info,
+    set_composer_info, ComposerInfo,
 };
-use crate::output::output;
+use crate::touch::touch_item;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 pub fn handle_composers(cmd: ComposersCommand) -> Result<()> {
     match cmd {
         ComposersCommand::Ls { unused, json } => {
             let conn = open_readonly()?;
-            let composers = list_composers(&conn, unused)?;
+            let mut composers = list_composers(&conn, unused)?;
+            load_composer_info(&mut composers)?;
             output(&composers, json);
         }
 
@@ -19,6 +44,7 @@ pub fn handle_composers(cmd: ComposersCommand) -> Result<()> {
             warn_if_running();
             let conn = open_readwrite()?;
             rename_composer(&conn, &old_name, &new_name)?;
+            rename_composer_info(&open_store()?, &old_name, &new_name)?;
 
             // Also update ITM files (both score-level and bookmark-level)
             match rename_composer_in_all_itm(&old_name, &new_name) {
@@ -42,6 +68,7 @@ pub fn handle_composers(cmd: ComposersCommand) -> Result<()> {
             warn_if_running();
             let conn = open_readwrite()?;
             merge_composers(&conn, &source, &target)?;
+            rename_composer_info(&open_store()?, &source, &target)?;
 
             // Also update ITM files (rename source to target)
             match rename_composer_in_all_itm(&source, &target) {
@@ -60,17 +87,154 @@ pub fn handle_composers(cmd: ComposersCommand) -> Result<()> {
                 }
             }
         }
+
+        ComposersCommand::Delete {
+            name,
+            force,
+            dry_run,
+        } => {
+            if !dry_run {
+                warn_if_running();
+            }
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+            let composer = get_composer_by_name(&conn, &name)?;
+            let items = composer_items(&conn, composer.id)?;
+            if !items.is_empty() && !force {
+                return Err(ForScoreError::Other(format!(
+                    "'{}' is the composer of {} score(s) or bookmark(s); use --force to unlink and delete it",
+                    composer.name,
+                    items.len()
+                )));
+            }
+
+            if dry_run {
+                let mut preview = DryRun::new(false);
+                for (id, ent, title) in &items {
+                    let kind = if *ent == entity::bookmark() {
+                        "bookmark"
+                    } else {
+                        "score"
+                    };
+                    let mut patch = Patch::update(kind, *id, title);
+                    patch.change("composer", composer.name.as_str(), "");
+                    preview.add(patch);
+                }
+                preview.add(Patch::new(
+                    "delete",
+                    "composer",
+                    Some(composer.id),
+                    &composer.name,
+                ));
+                preview.finish();
+                return Ok(());
+            }
+
+            delete_composer(&conn, composer.id)?;
+            for (id, _, _) in &items {
+                touch_item(&conn, *id)?;
+            }
+            set_composer_info(&open_store()?, &composer.name, &ComposerInfo::default())?;
+
+            println!("Deleted '{}'", composer.name);
+            if items.is_empty() {
+                return Ok(());
+            }
+            println!("Unlinked {} score(s) and bookmark(s)", items.len());
+
+            // Each item's ITM file gets the composers it has left
+            let mut synced = 0;
+            for (id, ent, title) in &items {
+                match write_composers_to_itm(&conn, *id, *ent) {
+                    Ok(true) => synced += 1,
+                    Ok(false) => {}
+                    Err(e) => eprintln!("Warning: Failed to update ITM for '{}': {}", title, e),
+                }
+            }
+            if synced > 0 {
+                println!("Updated {} of them in ITM files", synced);
+            }
+        }
+
+        ComposersCommand::Edit {
+            name,
+            years,
+            nationality,
+        } => {
+            if years.is_none() && nationality.is_none() {
+                return Err(ForScoreError::Other(
+                    "Nothing to change (use --years and/or --nationality)".into(),
+                ));
+            }
+
+            // Use the name as forScore spells it so lookups by name line up
+            let conn = open_readonly()?;
+            let composer = get_composer_by_name(&conn, &name)?;
+
+            let store = open_store()?;
+            let mut info = get_composer_info(&store, &composer.name)?;
+            if let Some(years) = years {
+                (info.born, info.died) = if years.trim().is_empty() {
+                    (None, None)
+                } else {
+                    parse_years(&years)?
+                };
+            }
+            if let Some(nationality) = nationality {
+                let nationality = nationality.trim();
+                info.nationality = (!nationality.is_empty()).then(|| nationality.to_string());
+            }
+            set_composer_info(&store, &composer.name, &info)?;
+
+            let details: Vec<String> = info.years().into_iter().chain(info.nationality).collect();
+            if details.is_empty() {
+                println!("Cleared details for '{}'", composer.name);
+            } else {
+                println!("Updated '{}': {}", composer.name, details.join(", "));
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Write a score's or bookmark's composers, as the database now has them, to
+/// its ITM file
+fn write_composers_to_itm(conn: &Connection, id: i64, ent: i32) -> Result<bool> {
+    if ent == entity::bookmark() {
+        let bookmark = get_bookmark_by_id(conn, id)?;
+        let mut update = ItmBookmarkUpdate::new();
+        if bookmark.composers.is_empty() {
+            update.remove.push("Composer");
+        } else {
+            update.composer = Some(bookmark.composers.join(", "));
+        }
+        update_bookmark_in_itm(&bookmark.path, bookmark.uuid.as_deref(), &update)
+    } else {
+        let score = get_score_by_id(conn, id)?;
+        let mut update = ItmUpdate::new();
+        update.composer = Some(score.composers.join(", "));
+        update_itm(&score.path, &update)
+    }
+}
+
 pub fn handle_genres(cmd: GenresCommand) -> Result<()> {
     match cmd {
-        GenresCommand::Ls { unused, json } => {
+        GenresCommand::Ls { unused, tree, json } => {
             let conn = open_readonly()?;
             let genres = list_genres(&conn, unused)?;
-            output(&genres, json);
+            if tree && !json {
+                let names: Vec<_> = genres
+                    .iter()
+                    .map(|g| (g.name.clone(), g.score_count))
+                    .collect();
+                print!("{}", render_tree(&names));
+            } else {
+                output(&genres, json);
+            }
         }
     }
 
@@ -79,12 +243,399 @@ pub fn handle_genres(cmd: GenresCommand) -> Result<()> {
 
 pub fn handle_tags(cmd: TagsCommand) -> Result<()> {
     match cmd {
-        TagsCommand::Ls { unused, json } => {
+        TagsCommand::Ls { unused, tree, json } => {
             let conn = open_readonly()?;
             let keywords = list_keywords(&conn, unused)?;
-            output(&keywords, json);
+            if tree && !json {
+                let names: Vec<_> = keywords
+                    .iter()
+                    .map(|k| (k.name.clone(), k.score_count))
+                    .collect();
+                print!("{}", render_tree(&names));
+            } else {
+                output(&keywords, json);
+            }
+        }
+
+        TagsCommand::Rename { old_name, new_name } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let renames = rename_keyword_subtree(&conn, &old_name, &new_name)?;
+
+            for (old, new) in &renames {
+                println!("Renamed '{}' to '{}'", old, new);
+            }
+
+            // Also update ITM files
+            match rename_keywords_in_all_itm(|name| rename_in_subtree(name, &old_name, &new_name)) {
+                Ok((files, fixes)) => {
+                    if files > 0 {
+                        println!("Updated {} ITM files ({} tags)", files, fixes);
+                    }
+                }
+                Err(e) => eprintln!("Warning: Failed to update ITM files: {}", e),
+            }
+        }
+
+        TagsCommand::Merge { source, target } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let source = get_keyword_by_name(&conn, &source)?;
+            let target = get_keyword_by_name(&conn, &target)?;
+            if source.id == target.id {
+                return Err(ForScoreError::Other(format!(
+                    "'{}' and '{}' are the same tag",
+                    source.name, target.name
+                )));
+            }
+            let items = keyword_items(&conn, source.id)?;
+            merge_keywords(&conn, source.id, target.id)?;
+            for (id, _, _) in &items {
+                touch_item(&conn, *id)?;
+            }
+
+            let rename = |name: &str| {
+                name.eq_ignore_ascii_case(&source.name)
+                    .then(|| target.name.clone())
+            };
+            match rename_keywords_in_all_itm(rename) {
+                Ok((files, fixes)) => {
+                    println!("Merged '{}' into '{}'", source.name, target.name);
+                    if files > 0 {
+                        println!("Updated {} ITM files ({} tags)", files, fixes);
+                    }
+                }
+                Err(e) => {
+                    println!(
+                        "Merged '{}' into '{}' (database only)",
+                        source.name, target.name
+                    );
+                    eprintln!("Warning: Failed to update ITM files: {}", e);
+                }
+            }
+        }
+
+        TagsCommand::Delete {
+            name,
+            force,
+            dry_run,
+        } => {
+            if !dry_run {
+                warn_if_running();
+            }
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+            let keyword = get_keyword_by_name(&conn, &name)?;
+            let items = keyword_items(&conn, keyword.id)?;
+            if !items.is_empty() && !force {
+                return Err(ForScoreError::Other(format!(
+                    "{} score(s) or bookmark(s) are tagged '{}'; use --force to untag them and delete it",
+                    items.len(),
+                    keyword.name
+                )));
+            }
+
+            if dry_run {
+                let mut preview = DryRun::new(false);
+                for (id, ent, title) in &items {
+                    let kind = if *ent == entity::bookmark() {
+                        "bookmark"
+                    } else {
+                        "score"
+                    };
+                    let mut patch = Patch::update(kind, *id, title);
+                    patch.change("tag", keyword.name.as_str(), "");
+                    preview.add(patch);
+                }
+                preview.add(Patch::new("delete", "tag", Some(keyword.id), &keyword.name));
+                preview.finish();
+                return Ok(());
+            }
+
+            delete_keyword(&conn, keyword.id)?;
+            for (id, _, _) in &items {
+                touch_item(&conn, *id)?;
+            }
+
+            match remove_keyword_from_all_itm(&keyword.name) {
+                Ok((files, fixes)) => {
+                    println!("Deleted '{}'", keyword.name);
+                    if !items.is_empty() {
+                        println!("Untagged {} score(s) and bookmark(s)", items.len());
+                    }
+                    if files > 0 {
+                        println!("Updated {} ITM files ({} tags)", files, fixes);
+                    }
+                }
+                Err(e) => {
+                    println!("Deleted '{}' (database only)", keyword.name);
+                    eprintln!("Warning: Failed to update ITM files: {}", e);
+                }
+            }
+        }
+
+        TagsCommand::Apply {
+            name,
+            scores,
+            search,
+            filter,
+        } => {
+            let by_search = search.is_some() || !filter.is_empty();
+            if scores.is_empty() && !by_search {
+                return Err(ForScoreError::Other(
+                    "Name the scores to tag, or give --search and/or filters".into(),
+                ));
+            }
+            if !scores.is_empty() && by_search {
+                return Err(ForScoreError::Other(
+                    "Name the scores to tag or search for them, not both".into(),
+                ));
+            }
+
+            warn_if_running();
+            let conn = open_readwrite()?;
+            // Use the tag as it's already spelled, if it exists
+            let tag = get_keyword_by_name(&conn, &name)
+                .map(|k| k.name)
+                .unwrap_or(name);
+            let scores = if by_search {
+                let mut filter = build_filter(&conn, search, &filter)?;
+                filter.scores_only = true;
+                search_scores(&conn, &filter, usize::MAX)?
+            } else {
+                resolve_scores(&conn, &scores, resolve_score)?
+            };
+            if scores.is_empty() {
+                println!("No scores match.");
+                return Ok(());
+            }
+            retag(&conn, &tag, scores, true)?;
+        }
+
+        TagsCommand::Remove { name, scores } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let tag = get_keyword_by_name(&conn, &name)?.name;
+            // Untagging only takes exact matches, not the closest title
+            let scores = resolve_scores(&conn, &scores, resolve_score_strict)?;
+            retag(&conn, &tag, scores, false)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve every identifier before changing anything, so a typo doesn't
+/// leave them half-tagged
+fn resolve_scores(
+    conn: &Connection,
+    identifiers: &[String],
+    resolve: fn(&Connection, &str) -> Result<Score>,
+) -> Result<Vec<Score>> {
+    identifiers
+        .iter()
+        .map(|identifier| resolve(conn, identifier))
+        .collect()
+}
+
+/// Tag scores, or untag them, in the database and their ITM files, then
+/// say how many changed and how many already were that way
+fn retag(conn: &Connection, tag: &str, mut scores: Vec<Score>, add: bool) -> Result<()> {
+    load_metadata_batch(conn, &mut scores)?;
+
+    let progress = Progress::new(scores.len(), if add { "Tagging" } else { "Untagging" });
+    let mut changed = 0;
+    let mut unchanged = 0;
+    let mut itm_errors = 0;
+    for score in &scores {
+        progress.inc();
+        let keywords = if add {
+            tag_score(conn, score, tag)?
+        } else {
+            untag_score(conn, score, tag)?
+        };
+        let Some(keywords) = keywords else {
+            unchanged += 1;
+            continue;
+        };
+        touch_item(conn, score.id)?;
+        changed += 1;
+
+        let mut itm_update = ItmUpdate::new();
+        itm_update.keywords = Some(keywords);
+        if let Err(e) = update_itm(&score.path, &itm_update) {
+            itm_errors += 1;
+            progress.println(format!(
+                "Warning: Failed to update ITM file of '{}': {}",
+                score.title, e
+            ));
+        }
+    }
+    progress.finish();
+
+    if add {
+        println!(
+            "Tagged {} score(s) '{}'; {} already had it",
+            changed, tag, unchanged
+        );
+    } else {
+        println!(
+            "Untagged {} score(s) '{}'; {} didn't have it",
+            changed, tag, unchanged
+        );
+    }
+    if itm_errors > 0 {
+        println!("{} of them changed in the database only", itm_errors);
+    }
+    Ok(())
+}
+
+/// The file format for `meta export` / `meta import`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Taxonomy {
+    #[serde(default)]
+    genres: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    difficulties: Vec<DifficultyLevel>,
+}
+
+pub fn handle_meta(cmd: MetaCommand) -> Result<()> {
+    match cmd {
+        MetaCommand::Export { file } => {
+            let conn = open_readonly()?;
+            let taxonomy = Taxonomy {
+                genres: list_vocabulary(&conn, GENRES)?,
+                tags: list_vocabulary(&conn, KEYWORDS)?,
+                labels: list_vocabulary(&conn, LABELS)?,
+                difficulties: list_difficulty_levels(&conn)?
+                    .into_iter()
+                    .map(|(_, level)| level)
+                    .collect(),
+            };
+
+            std::fs::write(&file, serde_json::to_string_pretty(&taxonomy)?)?;
+            println!(
+                "Exported {} genres, {} tags, {} labels and {} difficulty levels to {}",
+                taxonomy.genres.len(),
+                taxonomy.tags.len(),
+                taxonomy.labels.len(),
+                taxonomy.difficulties.len(),
+                file
+            );
+        }
+
+        MetaCommand::Import {
+            file,
+            dry_run,
+            json,
+        } => {
+            let taxonomy: Taxonomy = serde_json::from_str(&std::fs::read_to_string(&file)?)?;
+
+            if !dry_run {
+                warn_if_running();
+            }
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            let mut preview = DryRun::new(json);
+            let mut created = 0;
+
+            let vocabularies = [
+                ("genre", GENRES, &taxonomy.genres),
+                ("tag", KEYWORDS, &taxonomy.tags),
+                ("label", LABELS, &taxonomy.labels),
+            ];
+            for (kind, vocabulary, names) in vocabularies {
+                created +=
+                    import_vocabulary(&conn, kind, vocabulary, names, &mut preview, dry_run)?;
+            }
+
+            let existing = list_difficulty_levels(&conn)?;
+            let mut relabeled = 0;
+            for level in &taxonomy.difficulties {
+                match existing.iter().find(|(_, e)| e.level == level.level) {
+                    None => {
+                        if dry_run {
+                            let mut patch =
+                                Patch::create("difficulty", &format!("Level {}", level.level));
+                            patch.change("label", "", level.label.clone().unwrap_or_default());
+                            preview.add(patch);
+                        } else {
+                            create_difficulty_level(&conn, level)?;
+                        }
+                        created += 1;
+                    }
+                    // Only copy labels over; an unlabeled level in the file leaves ours alone
+                    Some((id, current))
+                        if level.label.is_some() && current.label != level.label =>
+                    {
+                        if dry_run {
+                            let mut patch =
+                                Patch::update("difficulty", *id, &format!("Level {}", level.level));
+                            patch.change(
+                                "label",
+                                current.label.clone().unwrap_or_default(),
+                                level.label.clone().unwrap_or_default(),
+                            );
+                            preview.add(patch);
+                        } else {
+                            set_difficulty_label(&conn, *id, level.label.as_deref())?;
+                        }
+                        relabeled += 1;
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if dry_run {
+                preview.finish();
+            } else {
+                println!(
+                    "Imported {}: {} entries created, {} difficulty labels updated",
+                    file, created, relabeled
+                );
+            }
         }
     }
 
     Ok(())
 }
+
+/// Create the names missing from a vocabulary, returning how many there were
+fn import_vocabulary(
+    conn: &Connection,
+    kind: &'static str,
+    vocabulary: Vocabulary,
+    names: &[String],
+    preview: &mut DryRun,
+    dry_run: bool,
+) -> Result<usize> {
+    let mut existing: HashSet<String> = list_vocabulary(conn, vocabulary)?.into_iter().collect();
+    let mut created = 0;
+
+    for name in names {
+        let name = name.trim();
+        if name.is_empty() || existing.contains(name) {
+            continue;
+        }
+        if dry_run {
+            preview.add(Patch::create(kind, name));
+        } else {
+            create_vocabulary_entry(conn, vocabulary, name)?;
+        }
+        existing.insert(name.to_string());
+        created += 1;
+    }
+
+    Ok(created)
+}
@@ -0,0 +1,600 @@
+use crate::db::{documents_path, entity, open_readonly, open_readwrite, warn_if_running};
+use crate::dry_run::{DryRun, Patch};
+use crate::error::{ForScoreError, Result};
+use crate::itm::{add_bookmarks_to_itm, update_itm, ItmNewBookmark, ItmUpdate};
+use crate::models::meta::{
+    get_or_create_composer, get_or_create_difficulty, get_or_create_genre, get_or_create_rating,
+    get_or_create_vocabulary_entry, KEYWORDS, LABELS,
+};
+use crate::models::score::{
+    create_bookmark, list_bookmarks, list_scores_in_setlist, list_scores_with_metadata,
+};
+use crate::models::setlist::{add_item_to_setlist, create_setlist, list_setlists};
+use crate::models::Score;
+use crate::progress::{Progress, Summary};
+use crate::schema::Schema;
+use crate::setlist_sync::{add_item_to_setlist_file, create_setlist_file, SetlistItem};
+use crate::touch::touch_item;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+/// Placeholder target ID for bookmarks a dry run would create
+pub const PLANNED: i64 = -1;
+
+/// Copy metadata, bookmarks and setlists from another library into this one
+pub fn migrate(from: &Path, dry_run: bool, json: bool) -> Result<()> {
+    if !from.exists() {
+        return Err(ForScoreError::Other(format!(
+            "Library not found: {}",
+            from.display()
+        )));
+    }
+    let source = Connection::open_with_flags(from, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    if !dry_run {
+        warn_if_running();
+    }
+    let conn = if dry_run {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+
+    // Entity IDs are read from this library; the other one has to agree
+    if Schema::read(&source)?.entities != Schema::read(&conn)?.entities {
+        return Err(ForScoreError::Other(format!(
+            "{} is from a different forScore version (its entity IDs differ); open it in the same forScore version first",
+            from.display()
+        )));
+    }
+
+    let source_scores = list_scores_with_metadata(&source)?;
+    let target_scores = list_scores_with_metadata(&conn)?;
+    let mut matches = match_scores(&source_scores, &target_scores);
+    // PDFs renamed on one side are matched by content, where both
+    // libraries' Documents folders are at hand
+    let source_documents = from
+        .parent()
+        .and_then(Path::parent)
+        .and_then(Path::parent)
+        .map(|data| data.join("Documents"))
+        .filter(|path| path.exists());
+    if let (Some(source_documents), Ok(documents)) = (source_documents, documents_path()) {
+        let by_content = match_by_content(
+            &source_scores,
+            &target_scores,
+            &matches,
+            &source_documents,
+            &documents,
+        );
+        matches.extend(by_content);
+    }
+
+    // All or nothing: a failure part way leaves this library, and its ITM
+    // files, as they were
+    let tx = conn.unchecked_transaction()?;
+
+    let progress = if dry_run {
+        Progress::hidden()
+    } else {
+        Progress::new(matches.len(), "Migrating")
+    };
+    let mut preview = DryRun::new(json);
+    let mut summary = Summary::default();
+    let (mut by_uuid, mut by_path, mut by_content) = (0, 0, 0);
+    let (mut scores_updated, mut bookmarks_created) = (0, 0);
+
+    // Source item ID -> target item ID, for scores and bookmarks
+    let mut item_map: HashMap<i64, i64> = HashMap::new();
+    // ITM changes by target score index, written once the transaction commits
+    let mut itm_updates: Vec<(usize, ItmUpdate)> = Vec::new();
+    let mut itm_bookmarks: Vec<(usize, Vec<ItmNewBookmark>)> = Vec::new();
+
+    for &(si, ti, how) in &matches {
+        progress.inc();
+        let (src, tgt) = (&source_scores[si], &target_scores[ti]);
+        item_map.insert(src.id, tgt.id);
+        match how {
+            MatchedBy::Uuid => by_uuid += 1,
+            MatchedBy::Path => by_path += 1,
+            MatchedBy::Content => by_content += 1,
+        }
+
+        let patch = metadata_patch(src, tgt);
+        if !patch.changes.is_empty() {
+            scores_updated += 1;
+            if dry_run {
+                preview.add(patch);
+            } else {
+                copy_metadata(&tx, src, tgt)?;
+                itm_updates.push((ti, itm_update(src, tgt)));
+                progress.println(format!("Updated metadata: {}", tgt.title));
+            }
+        }
+
+        // Bookmarks the target score doesn't have (same title and start page)
+        let existing = list_bookmarks(&tx, tgt.id)?;
+        let mut new_bookmarks = Vec::new();
+        for bm in list_bookmarks(&source, src.id)? {
+            let same = existing
+                .iter()
+                .find(|e| e.title.eq_ignore_ascii_case(&bm.title) && e.start_page == bm.start_page);
+            if let Some(e) = same {
+                item_map.insert(bm.id, e.id);
+                continue;
+            }
+            let (Some(start), Some(end)) = (bm.start_page, bm.end_page) else {
+                summary.skip(&bm.title, "bookmark has no page range");
+                continue;
+            };
+
+            bookmarks_created += 1;
+            if dry_run {
+                let mut patch = Patch::create("bookmark", &bm.title);
+                patch.change("score", "", tgt.title.as_str());
+                patch.change("pages", "", format!("{}-{}", start, end));
+                preview.add(patch);
+                item_map.insert(bm.id, PLANNED);
+            } else {
+                let created = create_bookmark(&tx, tgt, &bm.title, start, end)?;
+                item_map.insert(bm.id, created.id);
+                new_bookmarks.push(ItmNewBookmark {
+                    title: bm.title.clone(),
+                    identifier: created.uuid.unwrap_or_default(),
+                    first_page: start as i64,
+                    last_page: end as i64,
+                });
+            }
+        }
+        if !new_bookmarks.is_empty() {
+            progress.println(format!(
+                "Created {} bookmark(s) in {}",
+                new_bookmarks.len(),
+                tgt.title
+            ));
+            itm_bookmarks.push((ti, new_bookmarks));
+        }
+    }
+    progress.finish();
+
+    // Setlists this library doesn't have (by name)
+    let existing: HashSet<String> = list_setlists(&tx)?
+        .into_iter()
+        .map(|s| s.title.to_lowercase())
+        .collect();
+    let mut setlists_created = 0;
+    for setlist in list_setlists(&source)? {
+        if existing.contains(&setlist.title.to_lowercase()) {
+            continue;
+        }
+
+        let mut items = Vec::new();
+        for item in list_scores_in_setlist(&source, setlist.id)? {
+            match item_map.get(&item.id) {
+                Some(&id) => items.push((id, item)),
+                None => summary.skip(
+                    format!("{}: {}", setlist.title, item.title),
+                    "not in this library",
+                ),
+            }
+        }
+
+        setlists_created += 1;
+        if dry_run {
+            let mut patch = Patch::create("setlist", &setlist.title);
+            for (i, (_, item)) in items.iter().enumerate() {
+                patch.change(&format!("item {}", i + 1), "", item.title.as_str());
+            }
+            preview.add(patch);
+            continue;
+        }
+
+        let entries: Vec<(i64, Option<i32>, Option<i32>)> = items
+            .iter()
+            .map(|(id, item)| (*id, item.start_page, item.end_page))
+            .collect();
+        create_setlist_with_items(&tx, &setlist.title, &entries, &mut summary)?;
+    }
+    tx.commit()?;
+
+    for (ti, update) in &itm_updates {
+        let tgt = &target_scores[*ti];
+        if let Err(e) = update_itm(&tgt.path, update) {
+            summary.error(&tgt.title, format!("failed to update ITM: {}", e));
+        }
+    }
+    for (ti, new_bookmarks) in &itm_bookmarks {
+        let tgt = &target_scores[*ti];
+        if let Err(e) = add_bookmarks_to_itm(&tgt.path, new_bookmarks) {
+            summary.error(&tgt.title, format!("failed to update ITM: {}", e));
+        }
+    }
+
+    summary.updated = scores_updated + bookmarks_created + setlists_created;
+    let unmatched = source_scores.len() - matches.len();
+
+    if dry_run && preview.is_json() {
+        preview.finish();
+        return Ok(());
+    }
+
+    println!();
+    println!(
+        "Matched {} of {} scores ({} by UUID, {} by path, {} by content); {} not in this library",
+        matches.len(),
+        source_scores.len(),
+        by_uuid,
+        by_path,
+        by_content,
+        unmatched
+    );
+    let verb = if dry_run { "Would" } else { "Did" };
+    println!(
+        "{} update {} score(s), create {} bookmark(s) and {} setlist(s)",
+        verb, scores_updated, bookmarks_created, setlists_created
+    );
+    println!();
+    summary.print(if dry_run { "Would change" } else { "Changed" });
+
+    if dry_run {
+        preview.finish();
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchedBy {
+    Uuid,
+    Path,
+    /// Same PDF contents under another path
+    Content,
+}
+
+/// Pair each source score with a target score: by UUID, then by path (exact,
+/// then ignoring case). Returns (source index, target index, how).
+pub fn match_scores(source: &[Score], target: &[Score]) -> Vec<(usize, usize, MatchedBy)> {
+    let by_uuid: HashMap<&str, usize> = target
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| Some((s.uuid.as_deref()?, i)))
+        .collect();
+    let by_path: HashMap<&str, usize> = target
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.path.as_str(), i))
+        .collect();
+    let by_lower_path: HashMap<String, usize> = target
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.path.to_lowercase(), i))
+        .collect();
+
+    let mut used = HashSet::new();
+    let mut matches = Vec::new();
+    for (si, src) in source.iter().enumerate() {
+        let found = src
+            .uuid
+            .as_deref()
+            .and_then(|u| by_uuid.get(u))
+            .map(|&ti| (ti, MatchedBy::Uuid))
+            .or_else(|| {
+                by_path
+                    .get(src.path.as_str())
+                    .map(|&ti| (ti, MatchedBy::Path))
+            })
+            .or_else(|| {
+                by_lower_path
+                    .get(&src.path.to_lowercase())
+                    .map(|&ti| (ti, MatchedBy::Path))
+            });
+        if let Some((ti, how)) = found {
+            if used.insert(ti) {
+                matches.push((si, ti, how));
+            }
+        }
+    }
+    matches
+}
+
+/// Fields the source has and the target lacks
+pub fn metadata_patch(src: &Score, tgt: &Score) -> Patch {
+    let mut patch = Patch::update("score", tgt.id, &tgt.title);
+    let missing = |t: &Option<i32>, s: &Option<i32>| t.is_none() && s.is_some();
+
+    if missing(&tgt.rating, &src.rating) {
+        patch.change("rating", "", src.rating.unwrap().to_string());
+    }
+    if missing(&tgt.difficulty, &src.difficulty) {
+        patch.change("difficulty", "", src.difficulty.unwrap().to_string());
+    }
+    if tgt.key.is_none() {
+        if let Some(key) = &src.key {
+            patch.change("key", "", key.display());
+        }
+    }
+    for (field, t, s) in [
+        ("composers", &tgt.composers, &src.composers),
+        ("genres", &tgt.genres, &src.genres),
+        ("tags", &tgt.keywords, &src.keywords),
+        ("labels", &tgt.labels, &src.labels),
+    ] {
+        if t.is_empty() && !s.is_empty() {
+            patch.change(field, "", s.join(", "));
+        }
+    }
+    patch
+}
+
+/// Write the fields `metadata_patch` found missing
+pub fn copy_metadata(conn: &Connection, src: &Score, tgt: &Score) -> Result<()> {
+    if tgt.rating.is_none() {
+        if let Some(rating) = src.rating {
+            let id = get_or_create_rating(conn, rating)?;
+            conn.execute("UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?", [id, tgt.id])?;
+        }
+    }
+    if tgt.difficulty.is_none() {
+        if let Some(difficulty) = src.difficulty {
+            let id = get_or_create_difficulty(conn, difficulty)?;
+            conn.execute(
+                "UPDATE ZITEM SET ZDIFFICULTY = ? WHERE Z_PK = ?",
+                [id, tgt.id],
+            )?;
+        }
+    }
+    if tgt.key.is_none() {
+        if let Some(key) = &src.key {
+            conn.execute(
+                "UPDATE ZITEM SET ZKEY = ? WHERE Z_PK = ?",
+                [key.code as i64, tgt.id],
+            )?;
+        }
+    }
+
+    if tgt.composers.is_empty() {
+        for name in &src.composers {
+            let id = get_or_create_composer(conn, name)?;
+            link(
+                conn,
+                "Z_4COMPOSERS",
+                "Z_4ITEMS1",
+                "Z_10COMPOSERS",
+                tgt.id,
+                id,
+            )?;
+        }
+    }
+    if tgt.genres.is_empty() {
+        for name in &src.genres {
+            let id = get_or_create_genre(conn, name)?;
+            link(conn, "Z_4GENRES", "Z_4ITEMS4", "Z_12GENRES", tgt.id, id)?;
+        }
+    }
+    if tgt.keywords.is_empty() {
+        for name in &src.keywords {
+            let id = get_or_create_vocabulary_entry(conn, KEYWORDS, name)?;
+            link(conn, "Z_4KEYWORDS", "Z_4ITEMS5", "Z_13KEYWORDS", tgt.id, id)?;
+        }
+    }
+    if tgt.labels.is_empty() {
+        for name in &src.labels {
+            let id = get_or_create_vocabulary_entry(conn, LABELS, name)?;
+            link(conn, "Z_4LABELS", "Z_4ITEMS2", "Z_14LABELS", tgt.id, id)?;
+        }
+    }
+
+    touch_item(conn, tgt.id)
+}
+
+fn link(
+    conn: &Connection,
+    table: &str,
+    item_column: &str,
+    meta_column: &str,
+    item_id: i64,
+    meta_id: i64,
+) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO {} ({}, {}) VALUES (?, ?)",
+            table, item_column, meta_column
+        ),
+        [item_id, meta_id],
+    )?;
+    Ok(())
+}
+
+/// The ITM side of `copy_metadata`
+pub fn itm_update(src: &Score, tgt: &Score) -> ItmUpdate {
+    let mut update = ItmUpdate::new();
+    if tgt.rating.is_none() {
+        update.rating = src.rating.map(|r| r as i64);
+    }
+    if tgt.difficulty.is_none() {
+        update.difficulty = src.difficulty.map(|d| d as i64);
+    }
+    if tgt.key.is_none() {
+        update.key = src.key.as_ref().map(|k| k.code as i64);
+    }
+    if tgt.composers.is_empty() && !src.composers.is_empty() {
+        update.composer = Some(src.composers.join(", "));
+    }
+    if tgt.genres.is_empty() {
+        update.genre = src.genres.first().cloned();
+    }
+    update
+}
+
+/// Create a setlist (and its sync file) holding `items`: this library's
+/// item IDs, with page ranges for bookmarks
+pub fn create_setlist_with_items(
+    conn: &Connection,
+    title: &str,
+    items: &[(i64, Option<i32>, Option<i32>)],
+    summary: &mut Summary,
+) -> Result<()> {
+    let created = create_setlist(conn, title)?;
+    let sync_file = match create_setlist_file(title) {
+        Ok(_) => true,
+        Err(e) => {
+            summary.error(title, format!("failed to create sync file: {}", e));
+            false
+        }
+    };
+    for &(id, start_page, end_page) in items {
+        let is_bookmark = start_page.is_some();
+        let ent = if is_bookmark {
+            entity::bookmark()
+        } else {
+            entity::score()
+        };
+        let identifier = add_item_to_setlist(conn, created.id, id, ent)?;
+        if sync_file {
+            let (file_path, item_title) = item_path_and_title(conn, id)?;
+            let entry = SetlistItem {
+                file_path,
+                title: item_title.clone(),
+                identifier,
+                is_bookmark,
+                first_page: start_page.map(|p| p as i64),
+                last_page: end_page.map(|p| p as i64),
+            };
+            if let Err(e) = add_item_to_setlist_file(title, &entry) {
+                summary.error(&item_title, format!("failed to update sync file: {}", e));
+            }
+        }
+    }
+    println!(
+        "Created setlist '{}' with {} items{}",
+        title,
+        items.len(),
+        if sync_file {
+            " + sync file"
+        } else {
+            " (database only)"
+        }
+    );
+    Ok(())
+}
+
+/// This library's path and title for an item (paths can differ when matched by UUID)
+fn item_path_and_title(conn: &Connection, item_id: i64) -> Result<(String, String)> {
+    Ok(conn.query_row(
+        "SELECT ZPATH, ZTITLE FROM ZITEM WHERE Z_PK = ?",
+        [item_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?)
+}
+
+/// Pair the scores `matched` leaves over whose PDFs have the same contents
+/// (files renamed on one device). Only files of the same size are hashed.
+fn match_by_content(
+    source: &[Score],
+    target: &[Score],
+    matched: &[(usize, usize, MatchedBy)],
+    source_documents: &Path,
+    target_documents: &Path,
+) -> Vec<(usize, usize, MatchedBy)> {
+    let size = |path: &Path| fs::metadata(path).ok().map(|m| m.len());
+    let mut used: HashSet<usize> = matched.iter().map(|&(_, ti, _)| ti).collect();
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (ti, score) in target.iter().enumerate() {
+        if used.contains(&ti) {
+            continue;
+        }
+        if let Some(len) = size(&target_documents.join(&score.path)) {
+            by_size.entry(len).or_default().push(ti);
+        }
+    }
+
+    let source_matched: HashSet<usize> = matched.iter().map(|&(si, _, _)| si).collect();
+    let mut target_hashes: HashMap<usize, Option<u64>> = HashMap::new();
+    let mut matches = Vec::new();
+    for (si, score) in source.iter().enumerate() {
+        if source_matched.contains(&si) {
+            continue;
+        }
+        let path = source_documents.join(&score.path);
+        let Some(candidates) = size(&path).and_then(|len| by_size.get(&len)) else {
+            continue;
+        };
+        let Some(hash) = content_hash(&path) else {
+            continue;
+        };
+        let found = candidates.iter().copied().find(|ti| {
+            !used.contains(ti)
+                && *target_hashes
+                    .entry(*ti)
+                    .or_insert_with(|| content_hash(&target_documents.join(&target[*ti].path)))
+                    == Some(hash)
+        });
+        if let Some(ti) = found {
+            used.insert(ti);
+            matches.push((si, ti, MatchedBy::Content));
+        }
+    }
+    matches
+}
+
+fn content_hash(path: &Path) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    fs::read(path).ok()?.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(id: i64, path: &str, uuid: Option<&str>) -> Score {
+        Score {
+            id,
+            path: path.to_string(),
+            title: path.to_string(),
+            uuid: uuid.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_match_scores() {
+        let source = vec![
+            score(1, "Renamed.pdf", Some("A")),
+            score(2, "Nimrod.PDF", None),
+            score(3, "Only Here.pdf", Some("C")),
+        ];
+        let target = vec![
+            score(10, "nimrod.pdf", Some("B")),
+            score(11, "Original.pdf", Some("A")),
+        ];
+        assert_eq!(
+            match_scores(&source, &target),
+            vec![(0, 1, MatchedBy::Uuid), (1, 0, MatchedBy::Path)]
+        );
+    }
+
+    #[test]
+    fn test_match_by_content() {
+        let dir = std::env::temp_dir().join(format!("forscore-migrate-{}", std::process::id()));
+        let (old, new) = (dir.join("old"), dir.join("new"));
+        fs::create_dir_all(&old).unwrap();
+        fs::create_dir_all(&new).unwrap();
+        fs::write(old.join("Nimrod.pdf"), "%PDF nimrod").unwrap();
+        fs::write(old.join("Air.pdf"), "%PDF air").unwrap();
+        fs::write(new.join("Elgar - Nimrod.pdf"), "%PDF nimrod").unwrap();
+        fs::write(new.join("Same Size.pdf"), "%PDF aaa").unwrap();
+
+        let source = vec![score(1, "Nimrod.pdf", None), score(2, "Air.pdf", None)];
+        let target = vec![
+            score(10, "Same Size.pdf", None),
+            score(11, "Elgar - Nimrod.pdf", None),
+        ];
+        let matches = match_by_content(&source, &target, &[], &old, &new);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(matches, vec![(0, 1, MatchedBy::Content)]);
+    }
+}
@@ -1,9 +1,21 @@
+pub mod alias;
+pub mod archive;
 pub mod bookmarks;
 pub mod export;
+pub mod find;
 pub mod fixes;
+pub mod fixture;
+pub mod groups;
 pub mod import;
+pub mod itm;
 pub mod libraries;
 pub mod metadata;
+pub mod migrate;
+pub mod raw;
+pub mod reorder;
+pub mod review;
 pub mod scores;
 pub mod setlists;
+pub mod tracks;
+pub mod trash;
 pub mod utils;
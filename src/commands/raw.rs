@@ -0,0 +1,396 @@
+//! `raw get|set`: one column of one row, for fields the other commands
+//! don't cover yet. Reads go to any column; writes only to the columns in
+//! `WRITABLE`, whose meaning and sync behaviour are known.
+
+use crate::cli::RawCommand;
+use crate::db::{entity, open_readonly, open_readwrite, warn_if_running};
+use crate::dry_run::{DryRun, Patch};
+use crate::error::{ForScoreError, Result};
+use crate::itm::{hex, update_bookmark_in_itm, update_itm, ItmBookmarkUpdate, ItmUpdate};
+use crate::models::key::MusicalKey;
+use crate::schema::Schema;
+use crate::sort_title::sort_title;
+use crate::touch::{touch_item, touch_setlist};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OptionalExtension};
+
+#[derive(Clone, Copy, PartialEq)]
+enum ColumnType {
+    Integer,
+    Text,
+}
+
+/// Where else a column's value is kept
+#[derive(Clone, Copy, PartialEq)]
+enum Mirror {
+    None,
+    ItmTitle,
+    ItmKey,
+}
+
+/// A column `raw set` can write: table, column, type and what it holds
+type WritableColumn = (&'static str, &'static str, ColumnType, &'static str);
+
+use ColumnType::{Integer, Text};
+
+const WRITABLE: &[WritableColumn] = &[
+    ("ZITEM", "ZTITLE", Text, "title"),
+    ("ZITEM", "ZSORTTITLE", Text, "title used for sorting"),
+    (
+        "ZITEM",
+        "ZKEY",
+        Integer,
+        "key code or name, e.g. 100 or \"C Major\"",
+    ),
+    ("ZITEM", "ZBPM", Integer, "metronome tempo"),
+    ("ZITEM", "ZMINUTES", Integer, "duration, minutes part"),
+    ("ZITEM", "ZSECONDS", Integer, "duration, seconds part"),
+    ("ZITEM", "ZSIGNATURETOP", Integer, "time signature beats"),
+    ("ZITEM", "ZSIGNATUREBOTTOM", Integer, "time signature unit"),
+    ("ZITEM", "ZPITCH", Text, "pitch pipe note"),
+    ("ZSETLIST", "ZINDEX", Integer, "raw index"),
+    ("ZSETLIST", "ZMENUINDEX", Integer, "setlist menu position"),
+    ("ZSETLIST", "ZSORT", Integer, "raw sort value"),
+    ("ZLIBRARY", "ZINDEX", Integer, "library menu position"),
+];
+
+/// forScore needs every item to have a title
+fn nullable(column: &WritableColumn) -> bool {
+    !(column.0 == "ZITEM" && column.1 == "ZTITLE")
+}
+
+/// Another column of the same row kept in step with this one, and its value
+/// for a new value of this one (as `scores edit` sets them)
+fn derived(column: &WritableColumn, value: &Value) -> Option<(&'static str, Value)> {
+    match (column.0, column.1, value) {
+        ("ZITEM", "ZTITLE", Value::Text(title)) => {
+            Some(("ZSORTTITLE", Value::Text(sort_title(title))))
+        }
+        _ => None,
+    }
+}
+
+fn mirror(column: &WritableColumn) -> Mirror {
+    match (column.0, column.1) {
+        ("ZITEM", "ZTITLE") => Mirror::ItmTitle,
+        ("ZITEM", "ZKEY") => Mirror::ItmKey,
+        _ => Mirror::None,
+    }
+}
+
+pub fn handle(cmd: RawCommand) -> Result<()> {
+    match cmd {
+        RawCommand::Get {
+            table,
+            pk,
+            column,
+            json,
+        } => {
+            let conn = open_readonly()?;
+            let (table, column) = existing_column(&conn, &table, &column)?;
+            let value = read_value(&conn, &table, pk, &column)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&json_value(&value))?);
+            } else {
+                println!("{}", display_value(&value));
+            }
+        }
+
+        RawCommand::Set {
+            table,
+            pk,
+            column,
+            value,
+            null: _,
+            dry_run,
+            json,
+        } => {
+            let target = writable_column(&table, &column)?;
+            let new = parse_value(target, value.as_deref())?;
+
+            if !dry_run {
+                warn_if_running();
+            }
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+            let old = read_value(&conn, target.0, pk, target.1)?;
+            let derived = match derived(target, &new) {
+                Some((column, value)) => {
+                    Some((column, read_value(&conn, target.0, pk, column)?, value))
+                }
+                None => None,
+            };
+            let kind = row_kind(&conn, target.0, pk)?;
+            let title: Option<String> =
+                read_value(&conn, target.0, pk, "ZTITLE")
+                    .ok()
+                    .and_then(|v| match v {
+                        Value::Text(t) => Some(t),
+                        _ => None,
+                    });
+            let title = title.unwrap_or_default();
+
+            if dry_run {
+                let mut preview = DryRun::new(json);
+                let mut patch = Patch::update(kind, pk, &title);
+                patch.change(target.1, display_value(&old), display_value(&new));
+                if let Some((column, old, new)) = &derived {
+                    if old != new {
+                        patch.change(column, display_value(old), display_value(new));
+                    }
+                }
+                preview.add(patch);
+                preview.finish();
+                return Ok(());
+            }
+            if old == new {
+                println!(
+                    "{}.{} of {} is already {}",
+                    target.0,
+                    target.1,
+                    pk,
+                    display_value(&new)
+                );
+                return Ok(());
+            }
+
+            conn.execute(
+                &format!("UPDATE {} SET {} = ? WHERE Z_PK = ?", target.0, target.1),
+                rusqlite::params![new, pk],
+            )?;
+            if let Some((column, _, value)) = &derived {
+                conn.execute(
+                    &format!("UPDATE {} SET {} = ? WHERE Z_PK = ?", target.0, column),
+                    rusqlite::params![value, pk],
+                )?;
+            }
+            match target.0 {
+                "ZITEM" => touch_item(&conn, pk)?,
+                "ZSETLIST" => touch_setlist(&conn, pk)?,
+                _ => {
+                    conn.execute(
+                        &format!("UPDATE {} SET Z_OPT = Z_OPT + 1 WHERE Z_PK = ?", target.0),
+                        [pk],
+                    )?;
+                }
+            }
+
+            let synced = match sync_mirror(&conn, mirror(target), pk, &new) {
+                Ok(true) => " + ITM",
+                Ok(false) => "",
+                Err(e) => {
+                    eprintln!("Warning: Failed to update ITM file: {}", e);
+                    " (database only)"
+                }
+            };
+            println!(
+                "{}.{} of {} {} '{}': {} -> {}{}",
+                target.0,
+                target.1,
+                kind,
+                pk,
+                title,
+                display_value(&old),
+                display_value(&new),
+                synced
+            );
+        }
+
+        RawCommand::Columns => {
+            for c in WRITABLE {
+                let null = if nullable(c) { "" } else { ", not null" };
+                let kind = match c.2 {
+                    Integer => "integer",
+                    Text => "text",
+                };
+                println!(
+                    "  {:<28} {} ({}{})",
+                    format!("{}.{}", c.0, c.1),
+                    c.3,
+                    kind,
+                    null
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A table and column as the database spells them, if it has them
+fn existing_column(conn: &Connection, table: &str, column: &str) -> Result<(String, String)> {
+    let schema = Schema::read(conn)?;
+    let (table, columns) = schema
+        .tables
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(table))
+        .ok_or_else(|| ForScoreError::Other(format!("No table '{}'", table)))?;
+    let column = columns
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(column))
+        .ok_or_else(|| ForScoreError::Other(format!("No column '{}' in {}", column, table)))?;
+    Ok((table.clone(), column.clone()))
+}
+
+fn writable_column(table: &str, column: &str) -> Result<&'static WritableColumn> {
+    WRITABLE
+        .iter()
+        .find(|c| c.0.eq_ignore_ascii_case(table) && c.1.eq_ignore_ascii_case(column))
+        .ok_or_else(|| {
+            ForScoreError::Other(format!(
+                "{}.{} can't be set with raw set (see `raw columns` for the ones that can)",
+                table.to_uppercase(),
+                column.to_uppercase()
+            ))
+        })
+}
+
+/// Parse a value for a column; None means NULL
+fn parse_value(column: &WritableColumn, value: Option<&str>) -> Result<Value> {
+    let name = format!("{}.{}", column.0, column.1);
+    match (value, column.2) {
+        (None, _) if nullable(column) => Ok(Value::Null),
+        (None, _) => Err(ForScoreError::Other(format!("{} can't be NULL", name))),
+        (Some(v), Integer) if column.0 == "ZITEM" && column.1 == "ZKEY" => {
+            let key = match v.trim().parse() {
+                // note digit, then sharp (0 or 1), then mode (0 major, 1 minor)
+                Ok(code) => MusicalKey::from_code(code)
+                    .filter(|_| code % 10 <= 1 && code / 10 % 10 <= 1)
+                    .ok_or_else(|| {
+                        ForScoreError::Other(format!("{} isn't a key code forScore uses", code))
+                    })?,
+                Err(_) => MusicalKey::from_string(v)?,
+            };
+            Ok(Value::Integer(key.code as i64))
+        }
+        (Some(v), Integer) => v.trim().parse().map(Value::Integer).map_err(|_| {
+            ForScoreError::Other(format!("{} takes a whole number, not '{}'", name, v))
+        }),
+        (Some(v), Text) => Ok(Value::Text(v.to_string())),
+    }
+}
+
+/// `table` and `column` must be names the database has
+fn read_value(conn: &Connection, table: &str, pk: i64, column: &str) -> Result<Value> {
+    conn.query_row(
+        &format!("SELECT {} FROM {} WHERE Z_PK = ?", column, table),
+        [pk],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| ForScoreError::Other(format!("No row with Z_PK {} in {}", pk, table)))
+}
+
+/// What a row is, for messages and patches
+fn row_kind(conn: &Connection, table: &str, pk: i64) -> Result<&'static str> {
+    Ok(match table {
+        "ZITEM" => {
+            let ent: Option<i32> = conn
+                .query_row("SELECT Z_ENT FROM ZITEM WHERE Z_PK = ?", [pk], |row| {
+                    row.get(0)
+                })
+                .optional()?;
+            if ent == Some(entity::bookmark()) {
+                "bookmark"
+            } else {
+                "score"
+            }
+        }
+        "ZSETLIST" => "setlist",
+        "ZLIBRARY" => "library",
+        _ => "row",
+    })
+}
+
+/// Copy a new value to the item's ITM file, if the column is mirrored there
+fn sync_mirror(conn: &Connection, mirror: Mirror, pk: i64, value: &Value) -> Result<bool> {
+    if mirror == Mirror::None {
+        return Ok(false);
+    }
+    let (ent, path, uuid): (i32, Option<String>, Option<String>) = conn.query_row(
+        "SELECT Z_ENT, ZPATH, ZUUID FROM ZITEM WHERE Z_PK = ?",
+        [pk],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    // Bookmarks share their score's path
+    let Some(path) = path else {
+        return Ok(false);
+    };
+
+    let (mut title, mut key, mut remove) = (None, None, Vec::new());
+    match (mirror, value) {
+        (Mirror::ItmTitle, Value::Text(t)) => title = Some(t.clone()),
+        (Mirror::ItmKey, Value::Integer(k)) => key = Some(*k),
+        (Mirror::ItmKey, Value::Null) => remove.push("key"),
+        _ => return Ok(false),
+    }
+
+    if ent == entity::bookmark() {
+        let mut update = ItmBookmarkUpdate::new();
+        update.title = title;
+        update.key = key;
+        update.remove = remove;
+        update_bookmark_in_itm(&path, uuid.as_deref(), &update)
+    } else {
+        let mut update = ItmUpdate::new();
+        update.title = title;
+        update.key = key;
+        update.remove = remove;
+        update_itm(&path, &update)
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(t) => t.clone(),
+        Value::Blob(b) => hex(b),
+    }
+}
+
+fn json_value(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(i) => (*i).into(),
+        Value::Real(f) => (*f).into(),
+        Value::Text(t) => t.clone().into(),
+        Value::Blob(b) => hex(b).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_value() {
+        let bpm = writable_column("zitem", "zbpm").unwrap();
+        assert_eq!(parse_value(bpm, Some(" 120")).unwrap(), Value::Integer(120));
+        assert_eq!(parse_value(bpm, None).unwrap(), Value::Null);
+        assert!(parse_value(bpm, Some("fast")).is_err());
+
+        let title = writable_column("ZITEM", "ZTITLE").unwrap();
+        assert_eq!(
+            parse_value(title, Some("Air")).unwrap(),
+            Value::Text("Air".into())
+        );
+        assert!(parse_value(title, None).is_err());
+
+        assert!(writable_column("ZITEM", "ZPATH").is_err());
+
+        let key = writable_column("ZITEM", "ZKEY").unwrap();
+        assert_eq!(parse_value(key, Some("100")).unwrap(), Value::Integer(100));
+        assert_eq!(
+            parse_value(key, Some("A minor")).unwrap(),
+            Value::Integer(MusicalKey::from_string("A minor").unwrap().code as i64)
+        );
+        assert!(parse_value(key, Some("7")).is_err());
+        assert!(parse_value(key, Some("105")).is_err());
+        assert!(parse_value(key, Some("H major")).is_err());
+    }
+}
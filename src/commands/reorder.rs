@@ -0,0 +1,203 @@
+//! `setlists reorder --interactive`: rearrange a whole setlist in the
+//! terminal, then write the new order to the database and sync file together.
+
+use crate::commands::setlists::setlist_file_items;
+use crate::db::{open_readwrite, warn_if_running};
+use crate::error::{ForScoreError, Result};
+use crate::models::score::list_scores_in_setlist;
+use crate::models::setlist::{resolve_setlist, set_setlist_item_order};
+use crate::models::Score;
+use crate::setlist_sync::reorder_setlist_file;
+use console::{Key, Term};
+use std::io::{self, IsTerminal};
+
+/// Lines kept for the header and help below the list
+const CHROME_LINES: usize = 5;
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Continue,
+    Save,
+    Cancel,
+}
+
+/// The order being edited: positions into the original item list, the
+/// selected row, and whether the selected item is picked up
+struct Arrangement {
+    order: Vec<usize>,
+    cursor: usize,
+    holding: bool,
+}
+
+impl Arrangement {
+    fn new(len: usize) -> Self {
+        Arrangement {
+            order: (0..len).collect(),
+            cursor: 0,
+            holding: false,
+        }
+    }
+
+    /// Move the cursor to `row`, carrying the item along when it's picked up
+    fn go_to(&mut self, row: usize) {
+        let row = row.min(self.order.len().saturating_sub(1));
+        if self.holding {
+            let item = self.order.remove(self.cursor);
+            self.order.insert(row, item);
+        }
+        self.cursor = row;
+    }
+
+    fn key(&mut self, key: &Key, page: usize) -> Outcome {
+        let page = page.max(1);
+        match key {
+            Key::ArrowUp | Key::Char('k') => self.go_to(self.cursor.saturating_sub(1)),
+            Key::ArrowDown | Key::Char('j') => self.go_to(self.cursor + 1),
+            Key::PageUp => self.go_to(self.cursor.saturating_sub(page)),
+            Key::PageDown => self.go_to(self.cursor + page),
+            Key::Home => self.go_to(0),
+            Key::End => self.go_to(self.order.len()),
+            Key::Char(' ') => self.holding = !self.holding,
+            Key::Enter => return Outcome::Save,
+            Key::Escape | Key::CtrlC | Key::Char('q') => return Outcome::Cancel,
+            _ => {}
+        }
+        Outcome::Continue
+    }
+
+    fn changed(&self) -> bool {
+        self.order.iter().enumerate().any(|(i, item)| i != *item)
+    }
+}
+
+/// Rearrange a setlist's items with the keyboard and save the result
+pub fn reorder_interactive(identifier: &str) -> Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() || !io::stdin().is_terminal() {
+        return Err(ForScoreError::Other(
+            "setlists reorder --interactive needs a terminal (use --position in scripts)".into(),
+        ));
+    }
+    warn_if_running();
+    let conn = open_readwrite()?;
+    let sl = resolve_setlist(&conn, identifier)?;
+    let items = list_scores_in_setlist(&conn, sl.id)?;
+    if items.len() < 2 {
+        println!("'{}' has nothing to reorder.", sl.title);
+        return Ok(());
+    }
+
+    let mut arrangement = Arrangement::new(items.len());
+    let outcome = loop {
+        let (rows, _) = term.size();
+        let visible = (rows as usize).saturating_sub(CHROME_LINES).max(3);
+        draw(&term, &sl.title, &items, &arrangement, visible)?;
+        let key = term.read_key()?;
+        match arrangement.key(&key, visible) {
+            Outcome::Continue => continue,
+            outcome => break outcome,
+        }
+    };
+    term.clear_screen()?;
+
+    if outcome == Outcome::Cancel {
+        println!("Left '{}' unchanged", sl.title);
+        return Ok(());
+    }
+    if !arrangement.changed() {
+        println!("'{}' is already in that order", sl.title);
+        return Ok(());
+    }
+
+    // The database change is only committed once the sync file is written,
+    // so the two can't disagree
+    let order: Vec<i64> = arrangement.order.iter().map(|i| items[*i].id).collect();
+    let tx = conn.unchecked_transaction()?;
+    set_setlist_item_order(&tx, sl.id, &order)?;
+    let file_items = setlist_file_items(&tx, sl.id)?;
+    let synced = reorder_setlist_file(&sl.title, &file_items)?;
+    tx.commit()?;
+
+    if synced {
+        println!("Saved the new order of '{}' + updated sync file", sl.title);
+    } else {
+        println!("Saved the new order of '{}' (no sync file)", sl.title);
+    }
+    Ok(())
+}
+
+/// Draw the rows around the cursor, marking the selected or picked-up item
+fn draw(
+    term: &Term,
+    title: &str,
+    items: &[Score],
+    arrangement: &Arrangement,
+    visible: usize,
+) -> Result<()> {
+    term.clear_screen()?;
+    println!("Reordering '{}' ({} items)\n", title, items.len());
+
+    let first = arrangement
+        .cursor
+        .saturating_sub(visible / 2)
+        .min(items.len().saturating_sub(visible));
+    for (row, item) in arrangement
+        .order
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+    {
+        let marker = match (row == arrangement.cursor, arrangement.holding) {
+            (true, true) => "»»",
+            (true, false) => "> ",
+            _ => "  ",
+        };
+        let key = items[*item]
+            .key
+            .as_ref()
+            .map(|k| format!("  [{}]", k.display()))
+            .unwrap_or_default();
+        println!("{} {:>3}. {}{}", marker, row + 1, items[*item].title, key);
+    }
+
+    println!();
+    if arrangement.holding {
+        println!("↑/↓ move item · space put down · Enter save · q cancel");
+    } else {
+        println!("↑/↓ select · space pick up · Enter save · q cancel");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrangement() {
+        let mut arrangement = Arrangement::new(4);
+        assert_eq!(arrangement.key(&Key::ArrowDown, 10), Outcome::Continue);
+        arrangement.key(&Key::Char(' '), 10);
+        arrangement.key(&Key::ArrowDown, 10);
+        arrangement.key(&Key::ArrowDown, 10);
+        assert_eq!(arrangement.order, vec![0, 2, 3, 1]);
+        assert_eq!(arrangement.cursor, 3);
+
+        // Moving past either end stops there
+        arrangement.key(&Key::ArrowDown, 10);
+        assert_eq!(arrangement.order, vec![0, 2, 3, 1]);
+        arrangement.key(&Key::Home, 10);
+        assert_eq!(arrangement.order, vec![1, 0, 2, 3]);
+
+        // Put down, the cursor moves on its own
+        arrangement.key(&Key::Char(' '), 10);
+        arrangement.key(&Key::End, 10);
+        assert_eq!(arrangement.order, vec![1, 0, 2, 3]);
+        assert_eq!(arrangement.cursor, 3);
+        assert!(arrangement.changed());
+
+        assert_eq!(arrangement.key(&Key::Enter, 10), Outcome::Save);
+        assert_eq!(arrangement.key(&Key::Char('q'), 10), Outcome::Cancel);
+    }
+}
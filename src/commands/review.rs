@@ -0,0 +1,315 @@
+//! `scores review`: step through matching scores one at a time, rating,
+//! grading and tagging each with single keystrokes. Every change is written
+//! (database and ITM file) as soon as its key is pressed.
+
+use crate::cli::FilterArgs;
+use crate::commands::scores::build_filter;
+use crate::db::{open_readwrite, warn_if_running};
+use crate::error::{ForScoreError, Result};
+use crate::itm::{update_itm, ItmUpdate};
+use crate::models::meta::{
+    get_or_create_difficulty, get_or_create_rating, link_item, unlink_item, KEYWORD_LINKS,
+};
+use crate::models::score::{get_score_by_id, search_scores};
+use crate::models::Score;
+use crate::output::output_score;
+use crate::platform::open_url;
+use crate::touch::touch_item;
+use console::{Key, Term};
+use rusqlite::Connection;
+use std::collections::HashSet;
+use std::io::{self, IsTerminal};
+
+/// Keys with a built-in meaning, which can't toggle a tag
+const RESERVED_KEYS: &str = "0123456789dnopq ";
+
+#[derive(Debug, PartialEq)]
+enum Action {
+    Rate(i32),
+    /// `d`: the next key is a difficulty
+    StartDifficulty,
+    Difficulty(i32),
+    /// Toggle the tag bound to this `--tag-key`
+    ToggleTag(usize),
+    Open,
+    Next,
+    Previous,
+    Quit,
+    Ignore,
+}
+
+/// Parse `--tag-key` values such as "w=Warmup"
+fn parse_tag_keys(specs: &[String]) -> Result<Vec<(char, String)>> {
+    let mut keys: Vec<(char, String)> = Vec::new();
+    for spec in specs {
+        let invalid = |reason: &str| {
+            ForScoreError::Other(format!("Invalid --tag-key '{}': {}", spec, reason))
+        };
+        let (key, tag) = spec
+            .split_once('=')
+            .ok_or_else(|| invalid("expected KEY=TAG, e.g. w=Warmup"))?;
+        let mut chars = key.trim().chars();
+        let (Some(key), None) = (chars.next(), chars.next()) else {
+            return Err(invalid("the key must be a single character"));
+        };
+        if RESERVED_KEYS.contains(key) {
+            return Err(invalid("digits and d, n, o, p, q and space are taken"));
+        }
+        if keys.iter().any(|(k, _)| *k == key) {
+            return Err(invalid("that key is already bound"));
+        }
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(invalid("no tag given"));
+        }
+        keys.push((key, tag.to_string()));
+    }
+    Ok(keys)
+}
+
+/// What a keystroke does; after `d`, only 1-5 mean anything
+fn action(key: &Key, awaiting_difficulty: bool, tag_keys: &[(char, String)]) -> Action {
+    let digit = |c: &char| c.to_digit(10).map(|d| d as i32);
+    if awaiting_difficulty {
+        return match key {
+            Key::Char(c) if (1..=5).contains(&digit(c).unwrap_or(0)) => {
+                Action::Difficulty(digit(c).unwrap_or(0))
+            }
+            _ => Action::Ignore,
+        };
+    }
+    match key {
+        Key::Char(c) if (1..=6).contains(&digit(c).unwrap_or(0)) => {
+            Action::Rate(digit(c).unwrap_or(0))
+        }
+        Key::Char('d') => Action::StartDifficulty,
+        Key::Char('o') => Action::Open,
+        Key::Enter | Key::ArrowRight | Key::Char(' ') | Key::Char('n') => Action::Next,
+        Key::ArrowLeft | Key::Backspace | Key::Char('p') => Action::Previous,
+        Key::Escape | Key::CtrlC | Key::Char('q') => Action::Quit,
+        Key::Char(c) => match tag_keys.iter().position(|(k, _)| k == c) {
+            Some(index) => Action::ToggleTag(index),
+            None => Action::Ignore,
+        },
+        _ => Action::Ignore,
+    }
+}
+
+/// Review the scores a search finds, writing each change immediately
+pub fn review(
+    query: Option<String>,
+    args: &FilterArgs,
+    tag_keys: &[String],
+    open: bool,
+) -> Result<()> {
+    let tag_keys = parse_tag_keys(tag_keys)?;
+    let term = Term::stdout();
+    if !term.is_term() || !io::stdin().is_terminal() {
+        return Err(ForScoreError::Other(
+            "scores review needs a terminal (use scores bulk-edit in scripts)".into(),
+        ));
+    }
+    warn_if_running();
+    let conn = open_readwrite()?;
+
+    let mut filter = build_filter(&conn, query, args)?;
+    filter.scores_only = true;
+    let ids: Vec<i64> = search_scores(&conn, &filter, usize::MAX)?
+        .into_iter()
+        .map(|s| s.id)
+        .collect();
+    if ids.is_empty() {
+        println!("No matching scores.");
+        return Ok(());
+    }
+
+    let help = help_line(&tag_keys);
+    let mut index = 0;
+    let mut changed = HashSet::new();
+    let mut awaiting_difficulty = false;
+    let mut opened = None;
+    let mut status = String::new();
+    loop {
+        let score = get_score_by_id(&conn, ids[index])?;
+        if open && opened != Some(score.id) {
+            opened = Some(score.id);
+            if let Err(e) = open_score(&score) {
+                status = e.to_string();
+            }
+        }
+
+        term.clear_screen()?;
+        println!("Reviewing {} of {}\n", index + 1, ids.len());
+        output_score(&score, false);
+        println!();
+        if !status.is_empty() {
+            println!("{}", status);
+        }
+        if awaiting_difficulty {
+            println!("Difficulty? 1-5 (any other key cancels)");
+        } else {
+            println!("{}", help);
+        }
+
+        let key = term.read_key()?;
+        let was_awaiting = std::mem::take(&mut awaiting_difficulty);
+        status.clear();
+        let result = match action(&key, was_awaiting, &tag_keys) {
+            Action::Rate(rating) => set_rating(&conn, &score, rating),
+            Action::StartDifficulty => {
+                awaiting_difficulty = true;
+                continue;
+            }
+            Action::Difficulty(difficulty) => set_difficulty(&conn, &score, difficulty),
+            Action::ToggleTag(i) => toggle_tag(&conn, &score, &tag_keys[i].1),
+            Action::Open => {
+                if let Err(e) = open_score(&score) {
+                    status = e.to_string();
+                }
+                continue;
+            }
+            Action::Next if index + 1 == ids.len() => break,
+            Action::Next => {
+                index += 1;
+                continue;
+            }
+            Action::Previous => {
+                index = index.saturating_sub(1);
+                continue;
+            }
+            Action::Quit => break,
+            Action::Ignore => continue,
+        };
+        status = result?;
+        changed.insert(score.id);
+    }
+
+    term.clear_screen()?;
+    println!(
+        "Reviewed {} of {} scores; changed {}",
+        index + 1,
+        ids.len(),
+        changed.len()
+    );
+    Ok(())
+}
+
+fn help_line(tag_keys: &[(char, String)]) -> String {
+    let mut keys = vec!["1-6 rate".to_string(), "d 1-5 difficulty".to_string()];
+    keys.extend(tag_keys.iter().map(|(key, tag)| format!("{} {}", key, tag)));
+    keys.extend(
+        ["o open", "Enter next", "p back", "q quit"]
+            .iter()
+            .map(|k| k.to_string()),
+    );
+    keys.join(" · ")
+}
+
+fn open_score(score: &Score) -> Result<()> {
+    open_url(&format!(
+        "forscore://open?path={}",
+        urlencoding::encode(&score.path)
+    ))
+}
+
+/// Write an ITM change, saying where the change went
+fn write_itm(score: &Score, update: &ItmUpdate, done: String) -> String {
+    match update_itm(&score.path, update) {
+        Ok(_) => done,
+        Err(e) => format!("{} (database only: {})", done, e),
+    }
+}
+
+fn set_rating(conn: &Connection, score: &Score, rating: i32) -> Result<String> {
+    let rating_id = get_or_create_rating(conn, rating)?;
+    conn.execute(
+        "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
+        [rating_id, score.id],
+    )?;
+    touch_item(conn, score.id)?;
+    let mut update = ItmUpdate::new();
+    update.rating = Some(rating as i64);
+    Ok(write_itm(score, &update, format!("Rated {}", rating)))
+}
+
+fn set_difficulty(conn: &Connection, score: &Score, difficulty: i32) -> Result<String> {
+    let difficulty_id = get_or_create_difficulty(conn, difficulty)?;
+    conn.execute(
+        "UPDATE ZITEM SET ZDIFFICULTY = ? WHERE Z_PK = ?",
+        [difficulty_id, score.id],
+    )?;
+    touch_item(conn, score.id)?;
+    let mut update = ItmUpdate::new();
+    update.difficulty = Some(difficulty as i64);
+    Ok(write_itm(
+        score,
+        &update,
+        format!("Difficulty {}", difficulty),
+    ))
+}
+
+fn toggle_tag(conn: &Connection, score: &Score, tag: &str) -> Result<String> {
+    let has_tag = score.keywords.iter().any(|k| k.eq_ignore_ascii_case(tag));
+    let keywords: Vec<String> = if has_tag {
+        unlink_item(conn, KEYWORD_LINKS, score.id, tag)?;
+        score
+            .keywords
+            .iter()
+            .filter(|k| !k.eq_ignore_ascii_case(tag))
+            .cloned()
+            .collect()
+    } else {
+        link_item(conn, KEYWORD_LINKS, score.id, tag)?;
+        score
+            .keywords
+            .iter()
+            .cloned()
+            .chain([tag.to_string()])
+            .collect()
+    };
+    touch_item(conn, score.id)?;
+    let mut update = ItmUpdate::new();
+    update.keywords = Some(keywords);
+    let done = if has_tag {
+        format!("Removed tag '{}'", tag)
+    } else {
+        format!("Tagged '{}'", tag)
+    };
+    Ok(write_itm(score, &update, done))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tag_keys() {
+        let keys = parse_tag_keys(&["w=Warmup".into(), "R = Repertoire".into()]).unwrap();
+        assert_eq!(
+            keys,
+            vec![('w', "Warmup".to_string()), ('R', "Repertoire".to_string())]
+        );
+        for bad in ["Warmup", "ww=Warmup", "3=Warmup", "q=Quiet", "w="] {
+            assert!(parse_tag_keys(&[bad.into()]).is_err(), "{}", bad);
+        }
+        assert!(parse_tag_keys(&["w=Warmup".into(), "w=Other".into()]).is_err());
+    }
+
+    #[test]
+    fn test_action() {
+        let tags = vec![('w', "Warmup".to_string())];
+        assert_eq!(action(&Key::Char('4'), false, &tags), Action::Rate(4));
+        assert_eq!(action(&Key::Char('7'), false, &tags), Action::Ignore);
+        assert_eq!(
+            action(&Key::Char('d'), false, &tags),
+            Action::StartDifficulty
+        );
+        assert_eq!(action(&Key::Char('4'), true, &tags), Action::Difficulty(4));
+        assert_eq!(action(&Key::Char('6'), true, &tags), Action::Ignore);
+        assert_eq!(action(&Key::Char('w'), false, &tags), Action::ToggleTag(0));
+        assert_eq!(action(&Key::Char('w'), true, &tags), Action::Ignore);
+        assert_eq!(action(&Key::Enter, false, &tags), Action::Next);
+        assert_eq!(action(&Key::ArrowLeft, false, &tags), Action::Previous);
+        assert_eq!(action(&Key::Char('q'), false, &tags), Action::Quit);
+    }
+}
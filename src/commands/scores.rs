@@ -1,16 +1,78 @@
-use crate::cli::ScoresCommand;
-use crate::db::{mark_modified, open_readonly, open_readwrite, warn_if_running};
-use crate::error::Result;
-use crate::itm::{update_itm, ItmUpdate};
+use crate::cache::load_page_counts;
+use crate::cli::{DateArgs, FilterArgs, NotesCommand, ScoresCommand};
+use crate::commands::archive::{archive, unarchive};
+use crate::commands::{groups, review};
+use crate::db::{
+    core_data_to_datetime, documents_path, open_readonly, open_readwrite, warn_if_running,
+};
+use crate::dry_run::{DryRun, Patch};
+use crate::error::{ForScoreError, Result};
+use crate::fts::fts_search;
+use crate::itm::{
+    create_itm, itm_path_for_score, plist_to_json, read_itm, update_bookmark_in_itm, update_itm,
+    ItmBookmarkUpdate, ItmUpdate,
+};
 use crate::models::key::MusicalKey;
-use crate::models::library::resolve_library;
-use crate::models::meta::{get_or_create_composer, get_or_create_genre};
+use crate::models::library::{list_item_libraries, resolve_library};
+use crate::models::meta::{
+    edit_list, get_or_create_composer, get_or_create_difficulty, get_or_create_genre,
+    get_or_create_rating, set_item_links, COMPOSER_LINKS, GENRE_LINKS, KEYWORD_LINKS, LABEL_LINKS,
+};
 use crate::models::score::{
-    list_scores, list_scores_in_library, list_scores_in_setlist, resolve_score, search_scores,
+    create_score, delete_score, favorite_item_ids, get_bookmark_by_id, get_score_by_path,
+    item_modified, item_page, list_bookmarks, list_scores, list_scores_each,
+    list_scores_in_library, list_scores_in_setlist, load_metadata_batch, page_notes, path_shared,
+    raw_item_fields, resolve_score, resolve_score_or_bookmark, resolve_score_strict, search_scores,
+    search_scores_each, set_page_flag, set_page_note, setlist_entries_for_score, ScoreFilter,
+    ScoreOrBookmark,
+};
+use crate::models::setlist::{list_item_setlist_entries, list_shared_items, resolve_setlist};
+use crate::models::Score;
+use crate::output::{
+    is_ndjson, output, output_score, output_scores, relative_day, table_columns, truncate,
+    write_ndjson,
 };
-use crate::models::setlist::resolve_setlist;
-use crate::output::{output, output_score};
-use std::process::Command;
+use crate::platform::open_url;
+use crate::progress::{Progress, Summary};
+use crate::query::{self, Expr, Field, Op};
+use crate::setlist_sync::remove_item_from_setlist_file;
+use crate::sort_title::sort_title;
+use crate::touch::touch_item;
+use crate::trash::Bundle;
+use chrono::{DateTime, Local, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+
+/// Scores `stream_ndjson` loads metadata for and writes at a time
+const NDJSON_CHUNK: usize = 500;
+
+/// Write the scores `read` hands over as NDJSON, loading their metadata and
+/// page counts a chunk at a time, so a large library is never held in memory
+fn stream_ndjson(
+    conn: &Connection,
+    read: impl FnOnce(&mut dyn FnMut(Score) -> Result<()>) -> Result<()>,
+) -> Result<()> {
+    let write = |chunk: &mut Vec<Score>| -> Result<()> {
+        load_metadata_batch(conn, chunk)?;
+        load_page_counts(conn, chunk)?;
+        write_ndjson(chunk.drain(..));
+        Ok(())
+    };
+
+    let mut chunk = Vec::with_capacity(NDJSON_CHUNK);
+    read(&mut |score| {
+        chunk.push(score);
+        if chunk.len() == NDJSON_CHUNK {
+            write(&mut chunk)?;
+        }
+        Ok(())
+    })?;
+    write(&mut chunk)
+}
 
 pub fn handle(cmd: ScoresCommand) -> Result<()> {
     match cmd {
@@ -21,11 +83,30 @@ pub fn handle(cmd: ScoresCommand) -> Result<()> {
             sort,
             desc,
             scores_only,
+            favorites,
+            dates,
+            libraries,
+            columns,
             json,
         } => {
             let conn = open_readonly()?;
+            let date_expr = date_filter(&dates)?;
 
             let is_filtered = setlist.is_some() || library.is_some();
+            if !is_filtered && is_ndjson(json) {
+                return stream_ndjson(&conn, |f| {
+                    list_scores_each(
+                        &conn,
+                        &sort,
+                        desc,
+                        limit,
+                        scores_only,
+                        favorites,
+                        date_expr.as_ref(),
+                        f,
+                    )
+                });
+            }
 
             let mut scores = if let Some(setlist_id) = setlist {
                 let sl = resolve_setlist(&conn, &setlist_id)?;
@@ -34,71 +115,286 @@ pub fn handle(cmd: ScoresCommand) -> Result<()> {
                 let lib = resolve_library(&conn, &library_id)?;
                 list_scores_in_library(&conn, lib.id)?
             } else {
-                list_scores(&conn, &sort, desc, limit, scores_only)?
+                list_scores(
+                    &conn,
+                    &sort,
+                    desc,
+                    limit,
+                    scores_only,
+                    favorites,
+                    date_expr.as_ref(),
+                )?
             };
 
+            if is_filtered && favorites {
+                let favorite_ids = favorite_item_ids(&conn)?;
+                scores.retain(|s| favorite_ids.contains(&s.id));
+            }
+            if is_filtered && date_expr.is_some() {
+                let filter = ScoreFilter {
+                    expr: date_expr.clone(),
+                    ..Default::default()
+                };
+                let dated: HashSet<i64> = search_scores(&conn, &filter, usize::MAX)?
+                    .into_iter()
+                    .map(|s| s.id)
+                    .collect();
+                scores.retain(|s| dated.contains(&s.id));
+            }
+
             // Apply limit for setlist/library views (they don't support it natively)
             if is_filtered {
                 scores.truncate(limit);
             }
 
             // Load metadata for each score
-            for score in &mut scores {
-                let _ = score.load_metadata(&conn);
-            }
+            load_metadata_batch(&conn, &mut scores)?;
+            load_page_counts(&conn, &mut scores)?;
 
-            output(&scores, json);
+            let columns = table_columns(&columns, date_expr.is_some(), libraries)?;
+            output_scores(&scores, json, &columns);
+        }
+
+        ScoresCommand::Recent {
+            added: _,
+            played,
+            modified,
+            days,
+            limit,
+            scores_only,
+            json,
+        } => {
+            let field = if played {
+                Field::Played
+            } else if modified {
+                Field::Modified
+            } else {
+                Field::Added
+            };
+            recent(field, days, limit, scores_only, json)?;
         }
 
         ScoresCommand::Search {
             query,
-            title,
-            composer,
-            genre,
-            key,
-            no_key,
-            rating,
-            no_rating,
-            difficulty,
+            filter,
+            fts,
+            saved,
             limit,
-            scores_only,
+            libraries,
+            columns,
             json,
         } => {
             let conn = open_readonly()?;
+            let expr = saved.as_deref().map(query::saved_search).transpose()?;
+            let columns = table_columns(&columns, !filter.dates.is_empty(), libraries)?;
 
-            let key_code = if let Some(k) = key {
-                Some(MusicalKey::from_string(&k)?.code)
+            let mut scores = if fts {
+                // Rank by the index, keeping only items the filters allow
+                let ranked = fts_search(&conn, query.as_deref().unwrap_or_default())?;
+                let mut filter = build_filter(&conn, None, &filter)?;
+                filter.expr = Expr::all(expr.into_iter().chain(filter.expr));
+                let mut allowed: HashMap<i64, Score> = search_scores(&conn, &filter, usize::MAX)?
+                    .into_iter()
+                    .map(|s| (s.id, s))
+                    .collect();
+                ranked
+                    .into_iter()
+                    .filter_map(|id| allowed.remove(&id))
+                    .take(limit)
+                    .collect()
             } else {
-                None
+                let mut filter = build_filter(&conn, query, &filter)?;
+                filter.expr = Expr::all(expr.into_iter().chain(filter.expr));
+                if is_ndjson(json) {
+                    return stream_ndjson(&conn, |f| search_scores_each(&conn, &filter, limit, f));
+                }
+                search_scores(&conn, &filter, limit)?
             };
 
-            let mut scores = search_scores(
-                &conn,
-                query.as_deref(),
-                title.as_deref(),
-                composer.as_deref(),
-                genre.as_deref(),
-                key_code,
-                no_key,
-                rating,
-                no_rating,
-                difficulty,
-                limit,
-                scores_only,
-            )?;
-
             // Load metadata for each score
-            for score in &mut scores {
-                let _ = score.load_metadata(&conn);
+            load_metadata_batch(&conn, &mut scores)?;
+            load_page_counts(&conn, &mut scores)?;
+
+            output_scores(&scores, json, &columns);
+        }
+
+        ScoresCommand::Query {
+            expr,
+            limit,
+            libraries,
+            columns,
+            json,
+        } => {
+            let conn = open_readonly()?;
+            let filter = ScoreFilter {
+                expr: Some(query::parse(&expr)?),
+                ..Default::default()
+            };
+            let mut scores = search_scores(&conn, &filter, limit)?;
+
+            load_metadata_batch(&conn, &mut scores)?;
+            load_page_counts(&conn, &mut scores)?;
+
+            let columns = table_columns(&columns, false, libraries)?;
+            output_scores(&scores, json, &columns);
+        }
+
+        ScoresCommand::ConvertRatings {
+            from_scale,
+            to_scale,
+            map,
+            filter,
+            dry_run,
+            json,
+        } => {
+            let mapping = match map {
+                Some(spec) => parse_rating_map(&spec)?,
+                None => linear_rating_map(from_scale, to_scale)?,
+            };
+
+            if !dry_run {
+                warn_if_running();
+            }
+
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            let filter = build_filter(&conn, None, &filter)?;
+            let scores: Vec<_> = search_scores(&conn, &filter, usize::MAX)?
+                .into_iter()
+                .filter(|s| s.rating.is_some())
+                .collect();
+
+            let mut summary = Summary::default();
+            let mut preview = DryRun::new(json);
+            let progress = if dry_run {
+                Progress::hidden()
+            } else {
+                Progress::new(scores.len(), "Converting ratings")
+            };
+
+            for score in &scores {
+                progress.inc();
+                let old = score.rating.unwrap_or(0);
+
+                let new = match mapping.iter().find(|(from, _)| *from == old) {
+                    Some((_, to)) => *to,
+                    None => {
+                        summary.skip(&score.title, format!("rating {} not in mapping", old));
+                        continue;
+                    }
+                };
+                if new == old {
+                    summary.skip(&score.title, "unchanged");
+                    continue;
+                }
+
+                let bookmark = get_bookmark_by_id(&conn, score.id).ok();
+
+                if dry_run {
+                    let kind = if bookmark.is_some() {
+                        "bookmark"
+                    } else {
+                        "score"
+                    };
+                    let mut patch = Patch::update(kind, score.id, &score.title);
+                    patch.change("rating", old.to_string(), new.to_string());
+                    preview.add(patch);
+                    summary.updated += 1;
+                    continue;
+                }
+
+                conn.execute(
+                    "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
+                    [new as i64, score.id],
+                )?;
+                touch_item(&conn, score.id)?;
+
+                // Bookmarks live in their parent's ITM bookmarks array
+                let itm_result = match bookmark {
+                    Some(bookmark) => {
+                        let mut update = ItmBookmarkUpdate::new();
+                        update.rating = Some(new as i64);
+                        update_bookmark_in_itm(&bookmark.path, bookmark.uuid.as_deref(), &update)
+                    }
+                    None => {
+                        let mut update = ItmUpdate::new();
+                        update.rating = Some(new as i64);
+                        update_itm(&score.path, &update)
+                    }
+                };
+                if let Err(e) = itm_result {
+                    progress.println(format!(
+                        "Warning: Failed to update ITM file for {}: {}",
+                        score.title, e
+                    ));
+                }
+
+                summary.updated += 1;
+            }
+            progress.finish();
+
+            if dry_run {
+                if !preview.is_json() {
+                    println!();
+                    summary.print("Would convert");
+                }
+                preview.finish();
+            } else {
+                summary.print("Converted");
+            }
+        }
+
+        ScoresCommand::RecalibrateDifficulty {
+            map,
+            filter,
+            dry_run,
+            json,
+        } => recalibrate_difficulty(&parse_difficulty_map(&map)?, &filter, dry_run, json)?,
+
+        ScoresCommand::ByKey {
+            filter,
+            minor,
+            major,
+            json,
+        } => by_key(&filter, minor, major, json)?,
+
+        ScoresCommand::Shared { min, json } => {
+            let conn = open_readonly()?;
+            let items = list_shared_items(&conn, min)?;
+
+            if items.is_empty() {
+                println!("No items appear in {} or more setlists", min);
+            } else {
+                output(&items, json);
             }
+        }
 
-            output(&scores, json);
+        ScoresCommand::Show {
+            identifier,
+            all_fields,
+            provenance,
+            json,
+        } => {
+            let conn = open_readonly()?;
+            let mut score = resolve_score(&conn, &identifier)?;
+            if all_fields {
+                show_all_fields(&conn, &score, json)?;
+            } else if provenance {
+                show_provenance(&conn, &score, json)?;
+            } else {
+                load_page_counts(&conn, std::slice::from_mut(&mut score))?;
+                output_score(&score, json);
+            }
         }
 
-        ScoresCommand::Show { identifier, json } => {
+        ScoresCommand::Memberships { identifier, json } => {
             let conn = open_readonly()?;
             let score = resolve_score(&conn, &identifier)?;
-            output_score(&score, json);
+            show_memberships(&conn, &score, json)?;
         }
 
         ScoresCommand::Open { identifier } => {
@@ -108,7 +404,7 @@ pub fn handle(cmd: ScoresCommand) -> Result<()> {
             // Use forScore URL scheme
             let url = format!("forscore://open?path={}", urlencoding::encode(&score.path));
 
-            Command::new("open").arg(&url).spawn()?;
+            open_url(&url)?;
             println!("Opening {} in forScore...", score.title);
         }
 
@@ -120,8 +416,24 @@ pub fn handle(cmd: ScoresCommand) -> Result<()> {
             key,
             rating,
             difficulty,
-            tags: _,
+            clear_key,
+            clear_rating,
+            clear_difficulty,
+            clear_composer,
+            clear_genre,
+            clear_tags,
+            clear_labels,
+            tags,
+            add_composer,
+            remove_composer,
+            add_genre,
+            remove_genre,
+            add_tag,
+            remove_tag,
+            add_label,
+            remove_label,
             dry_run,
+            json,
         } => {
             if !dry_run {
                 warn_if_running();
@@ -135,16 +447,14 @@ pub fn handle(cmd: ScoresCommand) -> Result<()> {
 
             let score = resolve_score(&conn, &identifier)?;
 
-            if dry_run {
-                println!("Dry run - would update score ID {}:", score.id);
-            }
+            let mut patch = Patch::update("score", score.id, &score.title);
 
             // Update title
             if let Some(new_title) = &title {
                 if dry_run {
-                    println!("  Title: {} -> {}", score.title, new_title);
+                    patch.change("title", score.title.as_str(), new_title.as_str());
                 } else {
-                    let sort_title = new_title.to_lowercase();
+                    let sort_title = sort_title(new_title);
                     conn.execute(
                         "UPDATE ZITEM SET ZTITLE = ?, ZSORTTITLE = ? WHERE Z_PK = ?",
                         rusqlite::params![new_title, sort_title, score.id],
@@ -156,10 +466,10 @@ pub fn handle(cmd: ScoresCommand) -> Result<()> {
             if let Some(key_str) = &key {
                 let key_obj = MusicalKey::from_string(key_str)?;
                 if dry_run {
-                    println!(
-                        "  Key: {} -> {}",
-                        score.key.map(|k| k.display()).unwrap_or_default(),
-                        key_obj.display()
+                    patch.change(
+                        "key",
+                        score.key.as_ref().map(|k| k.display()).unwrap_or_default(),
+                        key_obj.display(),
                     );
                 } else {
                     conn.execute(
@@ -171,11 +481,15 @@ pub fn handle(cmd: ScoresCommand) -> Result<()> {
 
             // Update rating
             if let Some(r) = rating {
-                if r < 1 || r > 6 {
+                if !(1..=6).contains(&r) {
                     return Err(crate::error::ForScoreError::InvalidRating(r));
                 }
                 if dry_run {
-                    println!("  Rating: {} -> {}", score.rating.unwrap_or(0), r);
+                    patch.change(
+                        "rating",
+                        score.rating.map(|r| r.to_string()).unwrap_or_default(),
+                        r.to_string(),
+                    );
                 } else {
                     conn.execute(
                         "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
@@ -186,11 +500,15 @@ pub fn handle(cmd: ScoresCommand) -> Result<()> {
 
             // Update difficulty
             if let Some(d) = difficulty {
-                if d < 1 || d > 5 {
+                if !(1..=5).contains(&d) {
                     return Err(crate::error::ForScoreError::InvalidDifficulty(d));
                 }
                 if dry_run {
-                    println!("  Difficulty: {} -> {}", score.difficulty.unwrap_or(0), d);
+                    patch.change(
+                        "difficulty",
+                        score.difficulty.map(|d| d.to_string()).unwrap_or_default(),
+                        d.to_string(),
+                    );
                 } else {
                     conn.execute(
                         "UPDATE ZITEM SET ZDIFFICULTY = ? WHERE Z_PK = ?",
@@ -199,77 +517,1506 @@ pub fn handle(cmd: ScoresCommand) -> Result<()> {
                 }
             }
 
-            // Update composer
-            if let Some(composer_name) = &composer {
-                if dry_run {
-                    println!(
-                        "  Composer: {} -> {}",
-                        score.composers.first().cloned().unwrap_or_default(),
-                        composer_name
-                    );
-                } else {
-                    let composer_id = get_or_create_composer(&conn, composer_name)?;
-
-                    // Remove existing composer links
-                    conn.execute("DELETE FROM Z_4COMPOSERS WHERE Z_4ITEMS1 = ?", [score.id])?;
+            // Update composers, genres, tags and labels, keeping links that
+            // aren't being removed
+            let split = |s: &str| -> Vec<String> {
+                s.split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            };
+            let lists = [
+                (
+                    "composer",
+                    COMPOSER_LINKS,
+                    &score.composers,
+                    edit_list(
+                        &score.composers,
+                        composer.map(|c| vec![c]).or(clear_composer.then(Vec::new)),
+                        &add_composer,
+                        &remove_composer,
+                    ),
+                ),
+                (
+                    "genre",
+                    GENRE_LINKS,
+                    &score.genres,
+                    edit_list(
+                        &score.genres,
+                        genre.map(|g| vec![g]).or(clear_genre.then(Vec::new)),
+                        &add_genre,
+                        &remove_genre,
+                    ),
+                ),
+                (
+                    "tags",
+                    KEYWORD_LINKS,
+                    &score.keywords,
+                    edit_list(
+                        &score.keywords,
+                        tags.as_deref().map(split).or(clear_tags.then(Vec::new)),
+                        &add_tag,
+                        &remove_tag,
+                    ),
+                ),
+                (
+                    "labels",
+                    LABEL_LINKS,
+                    &score.labels,
+                    edit_list(
+                        &score.labels,
+                        clear_labels.then(Vec::new),
+                        &add_label,
+                        &remove_label,
+                    ),
+                ),
+            ];
 
-                    // Add new link
-                    conn.execute(
-                        "INSERT INTO Z_4COMPOSERS (Z_4ITEMS1, Z_10COMPOSERS) VALUES (?, ?)",
-                        [score.id, composer_id],
-                    )?;
+            let mut itm_update = ItmUpdate::new();
+            for (field, links, current, names) in &lists {
+                if *current == names {
+                    continue;
+                }
+                if dry_run {
+                    patch.change(field, current.join(", "), names.join(", "));
+                    continue;
+                }
+                set_item_links(&conn, *links, score.id, current, names)?;
+                match *field {
+                    "composer" => itm_update.composer = Some(names.join(", ")),
+                    "genre" => itm_update.genre = Some(names.join(", ")),
+                    "tags" => itm_update.keywords = Some(names.clone()),
+                    _ => itm_update.labels = Some(names.clone()),
                 }
             }
 
-            // Update genre
-            if let Some(genre_name) = &genre {
+            // Clear key, rating and difficulty
+            let cleared = [
+                (
+                    clear_key,
+                    "key",
+                    "ZKEY",
+                    score.key.as_ref().map(|k| k.display()),
+                ),
+                (
+                    clear_rating,
+                    "rating",
+                    "ZRATING",
+                    score.rating.map(|r| r.to_string()),
+                ),
+                (
+                    clear_difficulty,
+                    "difficulty",
+                    "ZDIFFICULTY",
+                    score.difficulty.map(|d| d.to_string()),
+                ),
+            ];
+            for (clear, field, column, current) in cleared {
+                if !clear {
+                    continue;
+                }
                 if dry_run {
-                    println!(
-                        "  Genre: {} -> {}",
-                        score.genres.first().cloned().unwrap_or_default(),
-                        genre_name
-                    );
-                } else {
-                    let genre_id = get_or_create_genre(&conn, genre_name)?;
+                    patch.change(field, current.unwrap_or_default(), "");
+                    continue;
+                }
+                conn.execute(
+                    &format!("UPDATE ZITEM SET {} = NULL WHERE Z_PK = ?", column),
+                    [score.id],
+                )?;
+                itm_update.remove.push(field);
+            }
 
-                    // Remove existing genre links
-                    conn.execute("DELETE FROM Z_4GENRES WHERE Z_4ITEMS4 = ?", [score.id])?;
+            if dry_run {
+                let mut preview = DryRun::new(json);
+                preview.add(patch);
+                preview.finish();
+                return Ok(());
+            }
 
-                    // Add new link
-                    conn.execute(
-                        "INSERT INTO Z_4GENRES (Z_4ITEMS4, Z_12GENRES) VALUES (?, ?)",
-                        [score.id, genre_id],
-                    )?;
+            // Mark the score as modified (update timestamp and version)
+            touch_item(&conn, score.id)?;
+
+            // Also update the ITM file for sync
+            itm_update.title = title.clone();
+            if let Some(key_str) = &key {
+                if let Ok(key_obj) = MusicalKey::from_string(key_str) {
+                    itm_update.key = Some(key_obj.code as i64);
                 }
             }
+            itm_update.rating = rating.map(|r| r as i64);
+            itm_update.difficulty = difficulty.map(|d| d as i64);
 
-            if !dry_run {
-                // Mark the score as modified (update timestamp and version)
-                mark_modified(&conn, score.id)?;
-
-                // Also update the ITM file for sync
-                let mut itm_update = ItmUpdate::new();
-                itm_update.title = title.clone();
-                itm_update.composer = composer.clone();
-                itm_update.genre = genre.clone();
-                if let Some(key_str) = &key {
-                    if let Ok(key_obj) = MusicalKey::from_string(key_str) {
-                        itm_update.key = Some(key_obj.code as i64);
-                    }
+            match update_itm(&score.path, &itm_update) {
+                Ok(true) => println!("Updated score and ITM: {}", score.title),
+                Ok(false) => println!("Updated score: {} (no ITM file)", score.title),
+                Err(e) => {
+                    println!("Updated score: {}", score.title);
+                    eprintln!("Warning: Failed to update ITM file: {}", e);
                 }
-                itm_update.rating = rating.map(|r| r as i64);
-                itm_update.difficulty = difficulty.map(|d| d as i64);
-
-                match update_itm(&score.path, &itm_update) {
-                    Ok(true) => println!("Updated score and ITM: {}", score.title),
-                    Ok(false) => println!("Updated score: {} (no ITM file)", score.title),
-                    Err(e) => {
-                        println!("Updated score: {}", score.title);
-                        eprintln!("Warning: Failed to update ITM file: {}", e);
-                    }
+            }
+        }
+
+        ScoresCommand::Flag {
+            identifier,
+            favorite,
+            unfavorite: _,
+        } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let item = resolve_paged_item(&conn, &identifier)?;
+            let (score_id, page) = item_page(&conn, item.id, item.start_page)?;
+
+            set_page_flag(&conn, score_id, page, favorite)?;
+            touch_item(&conn, score_id)?;
+
+            if favorite {
+                println!("Marked as favorite: {}", item.title);
+            } else {
+                println!("Removed from favorites: {}", item.title);
+            }
+        }
+
+        ScoresCommand::Add {
+            pdf,
+            title,
+            composer,
+            genre,
+            key,
+            rating,
+            dry_run,
+            json,
+        } => {
+            let fields = NewScore {
+                title,
+                composer,
+                genre,
+                key,
+                rating,
+            };
+            add(&pdf, fields, dry_run, json)?
+        }
+
+        ScoresCommand::BulkEdit {
+            query,
+            filter,
+            set_composer,
+            set_genre,
+            set_key,
+            set_rating,
+            set_difficulty,
+            yes,
+            dry_run,
+            json,
+        } => {
+            let changes = BulkChanges {
+                composer: set_composer,
+                genre: set_genre,
+                key: set_key
+                    .as_deref()
+                    .map(MusicalKey::from_string)
+                    .transpose()?,
+                rating: set_rating,
+                difficulty: set_difficulty,
+            };
+            bulk_edit(query, &filter, &changes, yes, dry_run, json)?
+        }
+
+        ScoresCommand::Delete {
+            identifier,
+            with_files,
+            dry_run,
+            json,
+        } => delete(&identifier, with_files, dry_run, json)?,
+
+        ScoresCommand::Archive {
+            identifier,
+            filter,
+            archive_library,
+            dry_run,
+            json,
+        } => archive(identifier, &filter, &archive_library, dry_run, json)?,
+
+        ScoresCommand::Unarchive {
+            identifier,
+            filter,
+            archive_library,
+            dry_run,
+            json,
+        } => unarchive(identifier, &filter, &archive_library, dry_run, json)?,
+
+        ScoresCommand::Notes { command } => handle_notes(command)?,
+        ScoresCommand::Review {
+            query,
+            filter,
+            tag_keys,
+            open,
+        } => review::review(query, &filter, &tag_keys, open)?,
+
+        ScoresCommand::Group { command } => groups::handle(command)?,
+    }
+
+    Ok(())
+}
+
+/// Scores sharing a key, for `scores by-key`
+#[derive(Serialize)]
+struct KeyGroup {
+    key: String,
+    signature: String,
+    count: usize,
+    scores: Vec<Score>,
+}
+
+/// `scores recent`: what was added, played or modified in the last `days`
+fn recent(field: Field, days: u32, limit: usize, scores_only: bool, json: bool) -> Result<()> {
+    let (sort, heading) = match field {
+        Field::Played => ("played", "Played"),
+        Field::Modified => ("modified", "Modified"),
+        _ => ("added", "Added"),
+    };
+    let conn = open_readonly()?;
+    let since = query::date_comparison(field, Op::Ge, &format!("{} days ago", days))?;
+    let mut scores = list_scores(&conn, sort, true, limit, scores_only, false, Some(&since))?;
+    load_metadata_batch(&conn, &mut scores)?;
+
+    if json {
+        load_page_counts(&conn, &mut scores)?;
+        output_scores(&scores, json, &[]);
+        return Ok(());
+    }
+    if scores.is_empty() {
+        println!(
+            "Nothing {} in the last {} days",
+            heading.to_lowercase(),
+            days
+        );
+        return Ok(());
+    }
+
+    println!("{} in the last {} days ({}):", heading, days, scores.len());
+    let today = Local::now().date_naive();
+    let whens: Vec<String> = scores
+        .iter()
+        .map(|s| {
+            let date = match field {
+                Field::Played => s.last_played,
+                Field::Modified => s.modified,
+                _ => s.added,
+            };
+            date.map(|d| relative_day(d, today)).unwrap_or_default()
+        })
+        .collect();
+    let width = whens.iter().map(|w| w.len()).max().unwrap_or(0);
+    for (score, when) in scores.iter().zip(&whens) {
+        let composer = if score.composers.is_empty() {
+            String::new()
+        } else {
+            format!(" — {}", score.composers.join(", "))
+        };
+        println!(
+            "  {:<width$}  {}{} (ID {})",
+            when, score.title, composer, score.id
+        );
+    }
+    Ok(())
+}
+
+fn by_key(args: &FilterArgs, minor: bool, major: bool, json: bool) -> Result<()> {
+    let conn = open_readonly()?;
+    let filter = build_filter(&conn, None, args)?;
+    let mut scores = search_scores(&conn, &filter, usize::MAX)?;
+    load_metadata_batch(&conn, &mut scores)?;
+
+    let (keyed, unkeyed): (Vec<Score>, Vec<Score>) =
+        scores.into_iter().partition(|s| s.key.is_some());
+    let keyed = keyed
+        .into_iter()
+        .filter(|s| {
+            let is_minor = s.key.as_ref().is_some_and(|k| k.is_minor());
+            !(minor && !is_minor || major && is_minor)
+        })
+        .collect();
+
+    let groups: Vec<KeyGroup> = circle_of_fifths_groups(keyed)
+        .into_iter()
+        .map(|(key, scores)| KeyGroup {
+            key: key.display(),
+            signature: key.signature(),
+            count: scores.len(),
+            scores,
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+
+    for group in &groups {
+        println!("{} ({}): {}", group.key, group.signature, group.count);
+        for score in &group.scores {
+            match score.composers.first() {
+                Some(composer) => println!("  {} - {}", score.title, composer),
+                None => println!("  {}", score.title),
+            }
+        }
+    }
+    let total: usize = groups.iter().map(|g| g.count).sum();
+    println!();
+    println!("{} scores in {} keys", total, groups.len());
+    if !unkeyed.is_empty() {
+        println!("{} without a key", unkeyed.len());
+    }
+
+    Ok(())
+}
+
+/// Group scores by key, clockwise around the circle of fifths from C (C, G,
+/// D ... F), each major key followed by its relative minor
+fn circle_of_fifths_groups(scores: Vec<Score>) -> Vec<(MusicalKey, Vec<Score>)> {
+    let mut groups: BTreeMap<(i32, bool), (MusicalKey, Vec<Score>)> = BTreeMap::new();
+    for score in scores {
+        let Some(key) = score.key.clone() else {
+            continue;
+        };
+        groups
+            .entry((key.fifths().rem_euclid(12), key.is_minor()))
+            .or_insert_with(|| (key, Vec::new()))
+            .1
+            .push(score);
+    }
+    groups.into_values().collect()
+}
+
+/// Field values given to `scores bulk-edit`
+struct BulkChanges {
+    composer: Option<String>,
+    genre: Option<String>,
+    key: Option<MusicalKey>,
+    rating: Option<i32>,
+    difficulty: Option<i32>,
+}
+
+impl BulkChanges {
+    /// Changes to one item, leaving out fields that already have the value
+    fn patch(&self, kind: &'static str, item: &Score) -> Patch {
+        let mut patch = Patch::update(kind, item.id, &item.title);
+        let current = |values: &[String]| values.join(", ");
+        if let Some(composer) = &self.composer {
+            patch.change("composer", current(&item.composers), composer.as_str());
+        }
+        if let Some(genre) = &self.genre {
+            patch.change("genre", current(&item.genres), genre.as_str());
+        }
+        if let Some(key) = &self.key {
+            patch.change(
+                "key",
+                item.key.as_ref().map(|k| k.display()).unwrap_or_default(),
+                key.display(),
+            );
+        }
+        if let Some(rating) = self.rating {
+            patch.change(
+                "rating",
+                item.rating.map(|r| r.to_string()).unwrap_or_default(),
+                rating.to_string(),
+            );
+        }
+        if let Some(difficulty) = self.difficulty {
+            patch.change(
+                "difficulty",
+                item.difficulty.map(|d| d.to_string()).unwrap_or_default(),
+                difficulty.to_string(),
+            );
+        }
+        patch
+    }
+
+    /// Write the changes to the database
+    fn apply(&self, conn: &Connection, item_id: i64) -> Result<()> {
+        if let Some(name) = &self.composer {
+            let composer_id = get_or_create_composer(conn, name)?;
+            conn.execute("DELETE FROM Z_4COMPOSERS WHERE Z_4ITEMS1 = ?", [item_id])?;
+            conn.execute(
+                "INSERT INTO Z_4COMPOSERS (Z_4ITEMS1, Z_10COMPOSERS) VALUES (?, ?)",
+                [item_id, composer_id],
+            )?;
+        }
+        if let Some(name) = &self.genre {
+            let genre_id = get_or_create_genre(conn, name)?;
+            conn.execute("DELETE FROM Z_4GENRES WHERE Z_4ITEMS4 = ?", [item_id])?;
+            conn.execute(
+                "INSERT INTO Z_4GENRES (Z_4ITEMS4, Z_12GENRES) VALUES (?, ?)",
+                [item_id, genre_id],
+            )?;
+        }
+        if let Some(key) = &self.key {
+            conn.execute(
+                "UPDATE ZITEM SET ZKEY = ? WHERE Z_PK = ?",
+                [key.code as i64, item_id],
+            )?;
+        }
+        if let Some(rating) = self.rating {
+            let rating_id = get_or_create_rating(conn, rating)?;
+            conn.execute(
+                "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
+                [rating_id, item_id],
+            )?;
+        }
+        if let Some(difficulty) = self.difficulty {
+            let difficulty_id = get_or_create_difficulty(conn, difficulty)?;
+            conn.execute(
+                "UPDATE ZITEM SET ZDIFFICULTY = ? WHERE Z_PK = ?",
+                [difficulty_id, item_id],
+            )?;
+        }
+        touch_item(conn, item_id)
+    }
+}
+
+/// Apply the same changes to every item matching a search: preview them, ask
+/// for confirmation (unless `yes`), then update the database and ITM files
+fn bulk_edit(
+    query: Option<String>,
+    args: &FilterArgs,
+    changes: &BulkChanges,
+    yes: bool,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    if query.is_none() && args.is_empty() {
+        return Err(ForScoreError::Other(
+            "Give a search query or at least one filter".into(),
+        ));
+    }
+    if let Some(r) = changes.rating {
+        if !(1..=6).contains(&r) {
+            return Err(ForScoreError::InvalidRating(r));
+        }
+    }
+    if let Some(d) = changes.difficulty {
+        if !(1..=5).contains(&d) {
+            return Err(ForScoreError::InvalidDifficulty(d));
+        }
+    }
+
+    let conn = if dry_run {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+
+    let filter = build_filter(&conn, query, args)?;
+    let mut items = search_scores(&conn, &filter, usize::MAX)?;
+    load_metadata_batch(&conn, &mut items)?;
+
+    // (item, its bookmark row if it is one)
+    let mut planned = Vec::new();
+    let mut preview = DryRun::new(json);
+    for item in items {
+        let bookmark = get_bookmark_by_id(&conn, item.id).ok();
+        let kind = if bookmark.is_some() {
+            "bookmark"
+        } else {
+            "score"
+        };
+        let patch = changes.patch(kind, &item);
+        if patch.changes.is_empty() {
+            continue;
+        }
+        preview.add(patch);
+        planned.push((item, bookmark));
+    }
+
+    if dry_run {
+        preview.finish();
+        return Ok(());
+    }
+    if planned.is_empty() {
+        println!("No matching items need changes.");
+        return Ok(());
+    }
+
+    println!();
+    if !yes
+        && !confirm(&format!(
+            "Apply these changes to {} item(s)?",
+            planned.len()
+        ))?
+    {
+        println!("Nothing changed.");
+        return Ok(());
+    }
+    warn_if_running();
+
+    let progress = Progress::new(planned.len(), "Editing");
+    let mut summary = Summary::default();
+    for (item, bookmark) in &planned {
+        progress.inc();
+        changes.apply(&conn, item.id)?;
+
+        let key = changes.key.as_ref().map(|k| k.code as i64);
+        let itm_result = match bookmark {
+            Some(bookmark) => {
+                let mut update = ItmBookmarkUpdate::new();
+                update.composer = changes.composer.clone();
+                update.genre = changes.genre.clone();
+                update.key = key;
+                update.rating = changes.rating.map(|r| r as i64);
+                update.difficulty = changes.difficulty.map(|d| d as i64);
+                update_bookmark_in_itm(&bookmark.path, bookmark.uuid.as_deref(), &update)
+            }
+            None => {
+                let mut update = ItmUpdate::new();
+                update.composer = changes.composer.clone();
+                update.genre = changes.genre.clone();
+                update.key = key;
+                update.rating = changes.rating.map(|r| r as i64);
+                update.difficulty = changes.difficulty.map(|d| d as i64);
+                update_itm(&item.path, &update)
+            }
+        };
+        if let Err(e) = itm_result {
+            summary.error(&item.title, format!("failed to update ITM: {}", e));
+        }
+        summary.updated += 1;
+    }
+    progress.finish();
+    summary.print("Updated");
+
+    Ok(())
+}
+
+/// Ask a yes/no question on the terminal; anything but "y"/"yes" is no
+pub fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(ForScoreError::Other(
+            "Not a terminal; pass --yes to apply without confirmation".into(),
+        ));
+    }
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Metadata given to `scores add`
+struct NewScore {
+    title: Option<String>,
+    composer: Option<String>,
+    genre: Option<String>,
+    key: Option<String>,
+    rating: Option<i32>,
+}
+
+/// Copy a PDF into the Documents folder and add a score for it, with a
+/// baseline ITM file so it syncs
+fn add(pdf: &Path, fields: NewScore, dry_run: bool, json: bool) -> Result<()> {
+    let is_pdf = pdf
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+    if !pdf.is_file() || !is_pdf {
+        return Err(ForScoreError::Other(format!(
+            "Not a PDF file: {}",
+            pdf.display()
+        )));
+    }
+    let file_name = pdf
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| ForScoreError::Other(format!("Invalid file name: {}", pdf.display())))?
+        .to_string();
+    let title = fields.title.clone().unwrap_or_else(|| {
+        pdf.file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| file_name.clone())
+    });
+
+    let key = fields
+        .key
+        .as_deref()
+        .map(MusicalKey::from_string)
+        .transpose()?;
+    if let Some(r) = fields.rating {
+        if !(1..=6).contains(&r) {
+            return Err(ForScoreError::InvalidRating(r));
+        }
+    }
+
+    if !dry_run {
+        warn_if_running();
+    }
+    let conn = if dry_run {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+
+    let destination = documents_path()?.join(&file_name);
+    if get_score_by_path(&conn, &file_name)?.is_some() {
+        return Err(ForScoreError::Other(format!(
+            "A score for {} is already in the library",
+            file_name
+        )));
+    }
+    if destination.exists() {
+        return Err(ForScoreError::Other(format!(
+            "{} already exists in forScore's Documents folder",
+            file_name
+        )));
+    }
+
+    if dry_run {
+        let mut preview = DryRun::new(json);
+        let mut patch = Patch::create("score", &title);
+        patch.change("path", "", file_name.as_str());
+        patch.change("composer", "", fields.composer.clone().unwrap_or_default());
+        patch.change("genre", "", fields.genre.clone().unwrap_or_default());
+        patch.change(
+            "key",
+            "",
+            key.as_ref().map(|k| k.display()).unwrap_or_default(),
+        );
+        patch.change(
+            "rating",
+            "",
+            fields.rating.map(|r| r.to_string()).unwrap_or_default(),
+        );
+        preview.add(patch);
+        preview.finish();
+        return Ok(());
+    }
+
+    fs::copy(pdf, &destination)?;
+    let score = create_score(&conn, &file_name, &title)?;
+
+    if let Some(name) = &fields.composer {
+        let composer_id = get_or_create_composer(&conn, name)?;
+        conn.execute(
+            "INSERT INTO Z_4COMPOSERS (Z_4ITEMS1, Z_10COMPOSERS) VALUES (?, ?)",
+            [score.id, composer_id],
+        )?;
+    }
+    if let Some(name) = &fields.genre {
+        let genre_id = get_or_create_genre(&conn, name)?;
+        conn.execute(
+            "INSERT INTO Z_4GENRES (Z_4ITEMS4, Z_12GENRES) VALUES (?, ?)",
+            [score.id, genre_id],
+        )?;
+    }
+    if let Some(key) = &key {
+        conn.execute(
+            "UPDATE ZITEM SET ZKEY = ? WHERE Z_PK = ?",
+            [key.code as i64, score.id],
+        )?;
+    }
+    if let Some(r) = fields.rating {
+        let rating_id = get_or_create_rating(&conn, r)?;
+        conn.execute(
+            "UPDATE ZITEM SET ZRATING = ? WHERE Z_PK = ?",
+            [rating_id, score.id],
+        )?;
+    }
+    touch_item(&conn, score.id)?;
+
+    let mut itm = ItmUpdate::new();
+    itm.title = Some(title.clone());
+    itm.composer = fields.composer;
+    itm.genre = fields.genre;
+    itm.key = key.map(|k| k.code as i64);
+    itm.rating = fields.rating.map(|r| r as i64);
+
+    match create_itm(&file_name, &itm) {
+        Ok(true) => println!("Added score and ITM: {} (ID: {})", title, score.id),
+        Ok(false) => println!(
+            "Added score: {} (ID: {}; kept existing ITM file)",
+            title, score.id
+        ),
+        Err(e) => {
+            println!("Added score: {} (ID: {})", title, score.id);
+            eprintln!("Warning: Failed to create ITM file: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Delete a score from the database, its setlists' sync files and, with
+/// `with_files`, the PDF and ITM
+fn delete(identifier: &str, with_files: bool, dry_run: bool, json: bool) -> Result<()> {
+    if !dry_run {
+        warn_if_running();
+    }
+
+    let conn = if dry_run {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+
+    let score = resolve_score_strict(&conn, identifier)?;
+    let bookmarks = list_bookmarks(&conn, score.id)?;
+    let entries = setlist_entries_for_score(&conn, score.id)?;
+
+    // Another score pointing at the same PDF keeps the files
+    let files = if with_files && !path_shared(&conn, score.id, &score.path)? {
+        let mut files = vec![itm_path_for_score(&score.path)?];
+        if let Ok(documents) = documents_path() {
+            files.insert(0, documents.join(&score.path));
+        }
+        files.retain(|f| f.exists());
+        files
+    } else {
+        Vec::new()
+    };
+
+    if dry_run {
+        let mut preview = DryRun::new(json);
+        let mut patch = Patch::new("delete", "score", Some(score.id), &score.title);
+        for bookmark in &bookmarks {
+            patch.change("bookmark", bookmark.title.as_str(), "");
+        }
+        for (setlist, _) in &entries {
+            patch.change("setlist", setlist.as_str(), "");
+        }
+        for file in &files {
+            patch.change("file", file.display().to_string(), "");
+        }
+        preview.add(patch);
+        preview.finish();
+        return Ok(());
+    }
+
+    let mut trash = Bundle::new("score", score.id, &score.title);
+    trash.snapshot_item(&conn, score.id)?;
+    for bookmark in &bookmarks {
+        trash.snapshot_item(&conn, bookmark.id)?;
+    }
+    trash.snapshot_pages(&conn, score.id)?;
+    for file in &files {
+        trash.add_file(file);
+    }
+    trash.save()?;
+
+    delete_score(&conn, score.id)?;
+    println!("Deleted score: {}", score.title);
+    if !bookmarks.is_empty() {
+        println!("  {} bookmark(s)", bookmarks.len());
+    }
+
+    for (setlist, uuid) in &entries {
+        match remove_item_from_setlist_file(setlist, uuid) {
+            Ok(true) => println!("  Removed from setlist '{}' + sync file", setlist),
+            Ok(false) => println!("  Removed from setlist '{}'", setlist),
+            Err(e) => {
+                println!("  Removed from setlist '{}'", setlist);
+                eprintln!("Warning: Failed to update setlist sync file: {}", e);
+            }
+        }
+    }
+
+    if with_files && files.is_empty() {
+        println!("  Files kept (another score uses them, or none found)");
+    }
+    for file in &files {
+        match fs::remove_file(file) {
+            Ok(()) => println!("  Deleted {}", file.display()),
+            Err(e) => eprintln!("Warning: Failed to delete {}: {}", file.display(), e),
+        }
+    }
+    println!("  Restore with: forscore trash restore {}", trash.id());
+
+    Ok(())
+}
+
+fn handle_notes(cmd: NotesCommand) -> Result<()> {
+    match cmd {
+        NotesCommand::Show { identifier, json } => {
+            let conn = open_readonly()?;
+            let item = resolve_paged_item(&conn, &identifier)?;
+            let (score_id, page) = item_page(&conn, item.id, item.start_page)?;
+            let notes = page_notes(&conn, score_id)?;
+
+            let note = notes.iter().find(|(p, _)| *p == page).map(|(_, n)| n);
+            let others: Vec<&(i32, String)> = notes.iter().filter(|(p, _)| *p != page).collect();
+
+            if json {
+                let value = serde_json::json!({
+                    "id": item.id,
+                    "title": item.title,
+                    "note": note,
+                    "page_notes": others
+                        .iter()
+                        .map(|(p, n)| serde_json::json!({ "page": p, "note": n }))
+                        .collect::<Vec<_>>(),
+                });
+                println!("{}", serde_json::to_string_pretty(&value)?);
+                return Ok(());
+            }
+
+            match note {
+                Some(note) => println!("{}", note),
+                None => println!("No note for {}", item.title),
+            }
+            if !others.is_empty() {
+                println!("\nOther page notes:");
+                for (p, n) in others {
+                    println!("  Page {}: {}", p, n);
                 }
             }
         }
+
+        NotesCommand::Set { identifier, text } => {
+            set_note(&identifier, |_| text.clone())?;
+        }
+
+        NotesCommand::Append { identifier, text } => {
+            set_note(&identifier, |current| match current {
+                Some(current) => format!("{}\n{}", current, text),
+                None => text.clone(),
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A score or bookmark whose note or flag is being read or written
+struct PagedItem {
+    id: i64,
+    title: String,
+    start_page: Option<i32>,
+}
+
+/// Resolve a score, falling back to a bookmark
+fn resolve_paged_item(conn: &Connection, identifier: &str) -> Result<PagedItem> {
+    Ok(match resolve_score_or_bookmark(conn, identifier)? {
+        ScoreOrBookmark::Score(score) => PagedItem {
+            id: score.id,
+            title: score.title,
+            start_page: score.start_page,
+        },
+        ScoreOrBookmark::Bookmark(bookmark) => PagedItem {
+            id: bookmark.id,
+            title: bookmark.title,
+            start_page: bookmark.start_page,
+        },
+    })
+}
+
+/// Replace an item's note with `new_note(current)`. Notes live on ZPAGE
+/// rows; the ITM file isn't changed, as it has no place for them that
+/// forScore reads back
+fn set_note(identifier: &str, new_note: impl FnOnce(Option<&str>) -> String) -> Result<()> {
+    warn_if_running();
+    let conn = open_readwrite()?;
+    let item = resolve_paged_item(&conn, identifier)?;
+    let (score_id, page) = item_page(&conn, item.id, item.start_page)?;
+
+    let current = page_notes(&conn, score_id)?
+        .into_iter()
+        .find(|(p, _)| *p == page)
+        .map(|(_, n)| n);
+    let note = new_note(current.as_deref());
+    let note = note.trim_end();
+
+    set_page_note(
+        &conn,
+        score_id,
+        page,
+        if note.is_empty() { None } else { Some(note) },
+    )?;
+    touch_item(&conn, score_id)?;
+
+    let action = if note.is_empty() {
+        "Cleared"
+    } else {
+        "Updated"
+    };
+    println!("{} note: {}", action, item.title);
+    Ok(())
+}
+
+/// Turn command-line filter flags into a search filter, resolving names to IDs
+pub fn build_filter(
+    conn: &Connection,
+    query: Option<String>,
+    args: &FilterArgs,
+) -> Result<ScoreFilter> {
+    let key = match &args.key {
+        Some(k) => Some(MusicalKey::from_string(k)?.code),
+        None => None,
+    };
+
+    let library = match &args.library {
+        Some(library_id) => Some(resolve_library(conn, library_id)?.id),
+        None => None,
+    };
+
+    Ok(ScoreFilter {
+        query,
+        title: args.title.clone(),
+        composer: args.composer.clone(),
+        genre: args.genre.clone(),
+        tag: args.tag.clone(),
+        key,
+        no_key: args.no_key,
+        min_rating: args.rating,
+        no_rating: args.no_rating,
+        difficulty: args.difficulty,
+        library,
+        scores_only: args.scores_only,
+        favorites: args.favorites,
+        expr: date_filter(&args.dates)?,
+    })
+}
+
+/// The date flags as a query expression, or None if none were given
+pub fn date_filter(args: &DateArgs) -> Result<Option<Expr>> {
+    let flags = [
+        (&args.added_since, Field::Added, Op::Ge),
+        (&args.added_before, Field::Added, Op::Lt),
+        (&args.modified_since, Field::Modified, Op::Ge),
+        (&args.modified_before, Field::Modified, Op::Lt),
+        (&args.played_since, Field::Played, Op::Ge),
+        (&args.played_before, Field::Played, Op::Lt),
+    ];
+    let mut exprs = Vec::new();
+    for (value, field, op) in flags {
+        if let Some(value) = value {
+            exprs.push(query::date_comparison(field, op, value)?);
+        }
+    }
+    if args.never_played {
+        exprs.push(query::date_comparison(Field::Played, Op::Eq, "none")?);
+    }
+    Ok(Expr::all(exprs))
+}
+
+/// Map each rating on a `from`-point scale to the nearest rating on a `to`-point scale
+fn linear_rating_map(from: i32, to: i32) -> Result<Vec<(i32, i32)>> {
+    if from < 2 || !(2..=6).contains(&to) {
+        return Err(ForScoreError::Other(
+            "Scales must have at least 2 points, and the target scale at most 6".into(),
+        ));
+    }
+
+    Ok((1..=from)
+        .map(|r| {
+            let scaled = 1.0 + (r - 1) as f64 * (to - 1) as f64 / (from - 1) as f64;
+            (r, scaled.round() as i32)
+        })
+        .collect())
+}
+
+/// Parse an explicit mapping such as "1=1,2=2,3=4"
+fn parse_level_map(spec: &str) -> Result<Vec<(i32, i32)>> {
+    spec.split(',')
+        .map(|pair| {
+            let invalid = || ForScoreError::Other(format!("Invalid mapping entry: {}", pair));
+            let (from, to) = pair.split_once('=').ok_or_else(invalid)?;
+            let from: i32 = from.trim().parse().map_err(|_| invalid())?;
+            let to: i32 = to.trim().parse().map_err(|_| invalid())?;
+            Ok((from, to))
+        })
+        .collect()
+}
+
+/// A rating mapping; targets must be forScore ratings (1-6)
+fn parse_rating_map(spec: &str) -> Result<Vec<(i32, i32)>> {
+    let mapping = parse_level_map(spec)?;
+    if let Some((_, to)) = mapping.iter().find(|(_, to)| !(1..=6).contains(to)) {
+        return Err(ForScoreError::InvalidRating(*to));
+    }
+    Ok(mapping)
+}
+
+/// A difficulty mapping; both sides must be difficulty levels (1-5)
+fn parse_difficulty_map(spec: &str) -> Result<Vec<(i32, i32)>> {
+    let mapping = parse_level_map(spec)?;
+    for (from, to) in &mapping {
+        if let Some(level) = [from, to].into_iter().find(|l| !(1..=5).contains(*l)) {
+            return Err(ForScoreError::InvalidDifficulty(*level));
+        }
+        if mapping.iter().filter(|(f, _)| f == from).count() > 1 {
+            return Err(ForScoreError::Other(format!(
+                "Difficulty {} is mapped more than once",
+                from
+            )));
+        }
+    }
+    Ok(mapping)
+}
+
+/// Apply a difficulty mapping to every matching score and bookmark, in the
+/// database and ITM files
+fn recalibrate_difficulty(
+    mapping: &[(i32, i32)],
+    filter: &FilterArgs,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    if !dry_run {
+        warn_if_running();
+    }
+    let conn = if dry_run {
+        open_readonly()?
+    } else {
+        open_readwrite()?
+    };
+
+    let filter = build_filter(&conn, None, filter)?;
+    let scores: Vec<_> = search_scores(&conn, &filter, usize::MAX)?
+        .into_iter()
+        .filter(|s| s.difficulty.is_some())
+        .collect();
+
+    let mut summary = Summary::default();
+    let mut preview = DryRun::new(json);
+    let progress = if dry_run {
+        Progress::hidden()
+    } else {
+        Progress::new(scores.len(), "Recalibrating difficulty")
+    };
+
+    for score in &scores {
+        progress.inc();
+        let old = score.difficulty.unwrap_or(0);
+
+        // Unlisted levels keep their value
+        let Some(&(_, new)) = mapping.iter().find(|(from, _)| *from == old) else {
+            continue;
+        };
+        if new == old {
+            continue;
+        }
+
+        let bookmark = get_bookmark_by_id(&conn, score.id).ok();
+
+        if dry_run {
+            let kind = if bookmark.is_some() {
+                "bookmark"
+            } else {
+                "score"
+            };
+            let mut patch = Patch::update(kind, score.id, &score.title);
+            patch.change("difficulty", old.to_string(), new.to_string());
+            preview.add(patch);
+            summary.updated += 1;
+            continue;
+        }
+
+        let difficulty_id = get_or_create_difficulty(&conn, new)?;
+        conn.execute(
+            "UPDATE ZITEM SET ZDIFFICULTY = ? WHERE Z_PK = ?",
+            [difficulty_id, score.id],
+        )?;
+        touch_item(&conn, score.id)?;
+
+        // Bookmarks live in their parent's ITM bookmarks array
+        let itm_result = match bookmark {
+            Some(bookmark) => {
+                let mut update = ItmBookmarkUpdate::new();
+                update.difficulty = Some(new as i64);
+                update_bookmark_in_itm(&bookmark.path, bookmark.uuid.as_deref(), &update)
+            }
+            None => {
+                let mut update = ItmUpdate::new();
+                update.difficulty = Some(new as i64);
+                update_itm(&score.path, &update)
+            }
+        };
+        if let Err(e) = itm_result {
+            progress.println(format!(
+                "Warning: Failed to update ITM file for {}: {}",
+                score.title, e
+            ));
+        }
+
+        summary.updated += 1;
+    }
+    progress.finish();
+
+    if dry_run {
+        if !preview.is_json() {
+            println!();
+            summary.print("Would recalibrate");
+        }
+        preview.finish();
+    } else {
+        summary.print("Recalibrated");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(title: &str, key: &str) -> Score {
+        Score {
+            id: 1,
+            path: format!("{}.pdf", title),
+            title: title.to_string(),
+            key: Some(MusicalKey::from_string(key).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_circle_of_fifths_groups() {
+        let scores = vec![
+            score("Waltz", "F Major"),
+            score("Etude", "A Minor"),
+            score("Sonata", "D Major"),
+            score("Prelude", "C Major"),
+            score("Nocturne", "C Major"),
+        ];
+
+        let groups: Vec<(String, Vec<String>)> = circle_of_fifths_groups(scores)
+            .into_iter()
+            .map(|(key, scores)| (key.display(), scores.into_iter().map(|s| s.title).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                ("C Major".into(), vec!["Prelude".into(), "Nocturne".into()]),
+                ("A Minor".into(), vec!["Etude".into()]),
+                ("D Major".into(), vec!["Sonata".into()]),
+                ("F Major".into(), vec!["Waltz".into()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_linear_rating_map() {
+        assert_eq!(
+            linear_rating_map(5, 6).unwrap(),
+            vec![(1, 1), (2, 2), (3, 4), (4, 5), (5, 6)]
+        );
+        assert_eq!(
+            linear_rating_map(6, 5).unwrap(),
+            vec![(1, 1), (2, 2), (3, 3), (4, 3), (5, 4), (6, 5)]
+        );
+        assert!(linear_rating_map(5, 10).is_err());
+    }
+
+    #[test]
+    fn test_parse_rating_map() {
+        assert_eq!(parse_rating_map("1=1, 3=4").unwrap(), vec![(1, 1), (3, 4)]);
+        assert!(parse_rating_map("1-1").is_err());
+        assert!(parse_rating_map("1=7").is_err());
+    }
+
+    #[test]
+    fn test_parse_difficulty_map() {
+        assert_eq!(
+            parse_difficulty_map("1=2,2=3, 3=4").unwrap(),
+            vec![(1, 2), (2, 3), (3, 4)]
+        );
+        assert!(parse_difficulty_map("5=6").is_err());
+        assert!(parse_difficulty_map("0=1").is_err());
+        assert!(parse_difficulty_map("1=2,1=3").is_err());
+    }
+}
+
+/// Raw dump for `scores show --all-fields`
+#[derive(Serialize)]
+struct AllFields {
+    id: i64,
+    database: serde_json::Map<String, serde_json::Value>,
+    /// The ITM plist, if the score has one
+    itm: Option<serde_json::Value>,
+}
+
+fn show_all_fields(conn: &Connection, score: &Score, json: bool) -> Result<()> {
+    let fields = raw_item_fields(conn, score.id)?;
+    let itm_path = itm_path_for_score(&score.path).ok().filter(|p| p.exists());
+    let itm = match &itm_path {
+        Some(path) => Some(read_itm(path)?),
+        None => None,
+    };
+
+    if json {
+        let all = AllFields {
+            id: score.id,
+            database: fields.into_iter().collect(),
+            itm: itm.as_ref().map(plist_to_json),
+        };
+        println!("{}", serde_json::to_string_pretty(&all)?);
+        return Ok(());
+    }
+
+    println!("Database (ZITEM {}):", score.id);
+    print_fields(fields);
+
+    println!();
+    match (itm_path, itm.as_ref().map(plist_to_json)) {
+        (Some(path), Some(serde_json::Value::Object(dict))) => {
+            println!("ITM ({}):", path.display());
+            print_fields(dict.into_iter().collect());
+        }
+        _ => println!("ITM: none"),
+    }
+
+    Ok(())
+}
+
+fn print_fields(fields: Vec<(String, serde_json::Value)>) {
+    let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, value) in fields {
+        let value = match value {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        println!("  {:<width$}  {}", name, value, width = width);
+    }
+}
+
+/// Where one field of `scores show --provenance` comes from
+#[derive(Serialize)]
+struct FieldProvenance {
+    field: &'static str,
+    db: Option<String>,
+    itm: Option<String>,
+    /// agree, differs, db-only or itm-only
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+struct Provenance {
+    id: i64,
+    db_modified: Option<String>,
+    itm_modified: Option<String>,
+    /// Which side was modified more recently: db or itm
+    newer: Option<&'static str>,
+    /// The CLI always displays the database's values
+    shown_from: &'static str,
+    fields: Vec<FieldProvenance>,
+}
+
+#[derive(Serialize)]
+struct SetlistMembership {
+    setlist_id: i64,
+    setlist: String,
+    position: i64,
+    items: i32,
+    /// Set when the entry is one of the score's bookmarks
+    bookmark: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LibraryMembership {
+    id: i64,
+    title: String,
+}
+
+#[derive(Serialize)]
+struct Memberships {
+    id: i64,
+    title: String,
+    setlists: Vec<SetlistMembership>,
+    libraries: Vec<LibraryMembership>,
+}
+
+/// The setlists (directly or through a bookmark) and libraries a score is in
+fn show_memberships(conn: &Connection, score: &Score, json: bool) -> Result<()> {
+    let mut setlists = Vec::new();
+    let items = std::iter::once((score.id, None)).chain(
+        list_bookmarks(conn, score.id)?
+            .into_iter()
+            .map(|bm| (bm.id, Some(bm.title))),
+    );
+    for (item_id, bookmark) in items {
+        for entry in list_item_setlist_entries(conn, item_id)? {
+            setlists.push(SetlistMembership {
+                setlist_id: entry.setlist.id,
+                setlist: entry.setlist.title,
+                position: entry.position,
+                items: entry.setlist.score_count,
+                bookmark: bookmark.clone(),
+            });
+        }
+    }
+    setlists.sort_by(|a, b| a.setlist.cmp(&b.setlist).then(a.position.cmp(&b.position)));
+
+    let memberships = Memberships {
+        id: score.id,
+        title: score.title.clone(),
+        setlists,
+        libraries: list_item_libraries(conn, score.id)?
+            .into_iter()
+            .map(|l| LibraryMembership {
+                id: l.id,
+                title: l.title,
+            })
+            .collect(),
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&memberships)?);
+        return Ok(());
+    }
+
+    println!("{} (ID {})\n", memberships.title, memberships.id);
+    println!("Setlists ({}):", memberships.setlists.len());
+    for m in &memberships.setlists {
+        let via = m
+            .bookmark
+            .as_ref()
+            .map(|b| format!(", bookmark '{}'", b))
+            .unwrap_or_default();
+        println!(
+            "  {} (ID {}): item {} of {}{}",
+            m.setlist, m.setlist_id, m.position, m.items, via
+        );
+    }
+    println!("\nLibraries ({}):", memberships.libraries.len());
+    for l in &memberships.libraries {
+        println!("  {} (ID {})", l.title, l.id);
+    }
+    Ok(())
+}
+
+fn show_provenance(conn: &Connection, score: &Score, json: bool) -> Result<()> {
+    let itm_path = itm_path_for_score(&score.path).ok().filter(|p| p.exists());
+    let itm = match &itm_path {
+        Some(path) => match read_itm(path)? {
+            plist::Value::Dictionary(dict) => Some(dict),
+            _ => None,
+        },
+        None => None,
+    };
+
+    let itm_string = |key: &str| -> Option<String> {
+        match itm.as_ref()?.get(key)? {
+            plist::Value::String(s) => Some(s.clone()),
+            plist::Value::Integer(i) => i.as_signed().map(|n| n.to_string()),
+            other => Some(format!("{:?}", other)),
+        }
+    };
+    let itm_int = |key: &str| -> Option<i64> {
+        match itm.as_ref()?.get(key)? {
+            plist::Value::Integer(i) => i.as_signed(),
+            _ => None,
+        }
+    };
+    let joined = |values: &[String]| Some(values.join(", ")).filter(|v| !v.is_empty());
+    let note = page_notes(conn, score.id)?
+        .into_iter()
+        .find(|(page, _)| *page == 1)
+        .map(|(_, note)| note);
+
+    let rows: Vec<(&'static str, Option<String>, Option<String>)> = vec![
+        ("title", Some(score.title.clone()), itm_string("title")),
+        ("composer", joined(&score.composers), itm_string("composer")),
+        ("genre", joined(&score.genres), itm_string("genre")),
+        (
+            "key",
+            score.key.as_ref().map(|k| k.display()),
+            itm_int("key")
+                .and_then(|code| MusicalKey::from_code(code as i32))
+                .map(|k| k.display()),
+        ),
+        (
+            "rating",
+            score.rating.map(|r| r.to_string()),
+            itm_int("rating").map(|r| r.to_string()),
+        ),
+        (
+            "difficulty",
+            score.difficulty.map(|d| d.to_string()),
+            itm_int("difficulty").map(|d| d.to_string()),
+        ),
+        ("notes", note, itm_string("notes")),
+    ];
+    let fields: Vec<FieldProvenance> = rows
+        .into_iter()
+        .filter(|(_, db, itm)| db.is_some() || itm.is_some())
+        .map(|(field, db, itm)| FieldProvenance {
+            field,
+            status: match (&db, &itm) {
+                (Some(a), Some(b)) if a == b => "agree",
+                (Some(_), Some(_)) => "differs",
+                (Some(_), None) => "db-only",
+                _ => "itm-only",
+            },
+            db,
+            itm,
+        })
+        .collect();
+
+    let db_modified = item_modified(conn, score.id)?.and_then(core_data_to_datetime);
+    let itm_modified: Option<DateTime<Utc>> = itm_path
+        .as_ref()
+        .and_then(|p| std::fs::metadata(p).ok())
+        .and_then(|m| m.modified().ok())
+        .map(DateTime::from);
+    let newer = match (db_modified, itm_modified) {
+        (Some(db), Some(itm)) if itm > db => Some("itm"),
+        (Some(_), _) => Some("db"),
+        (None, Some(_)) => Some("itm"),
+        (None, None) => None,
+    };
+
+    let provenance = Provenance {
+        id: score.id,
+        db_modified: db_modified.map(|d| d.to_rfc3339()),
+        itm_modified: itm_modified.map(|d| d.to_rfc3339()),
+        newer,
+        shown_from: "db",
+        fields,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&provenance)?);
+        return Ok(());
+    }
+
+    let local = |d: Option<DateTime<Utc>>| {
+        d.map(|d| {
+            DateTime::<Local>::from(d)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| "-".to_string())
+    };
+    println!("{} (ID {})", score.title, score.id);
+    println!(
+        "DB modified:  {}{}",
+        local(db_modified),
+        if newer == Some("db") { "  (newer)" } else { "" }
+    );
+    match &itm_path {
+        Some(path) => println!(
+            "ITM modified: {}{}  {}",
+            local(itm_modified),
+            if newer == Some("itm") {
+                "  (newer)"
+            } else {
+                ""
+            },
+            path.display()
+        ),
+        None => println!("ITM modified: -  (no ITM file)"),
+    }
+    println!("Values shown by the CLI come from the database.");
+    println!();
+
+    let show = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+    println!("{:<11} {:<9} {:<28} ITM", "Field", "Status", "Database");
+    for f in &provenance.fields {
+        println!(
+            "{:<11} {:<9} {:<28} {}",
+            f.field,
+            f.status,
+            truncate(&show(&f.db), 28),
+            truncate(&show(&f.itm), 28)
+        );
     }
 
     Ok(())
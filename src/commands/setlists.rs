@@ -1,56 +1,279 @@
-use crate::cli::SetlistsCommand;
+use crate::cache::load_page_counts;
+use crate::cli::{SetlistFormat, SetlistSortField, SetlistsCommand};
+use crate::commands::reorder;
+use crate::commands::scores::build_filter;
 use crate::db::{entity, open_readonly, open_readwrite, warn_if_running};
-use crate::error::Result;
-use crate::models::score::{list_scores_in_setlist, resolve_bookmark, resolve_score};
+use crate::dry_run::{DryRun, Patch};
+use crate::error::{ForScoreError, Result};
+use crate::models::group::resolve_group;
+use crate::models::key::describe_interval;
+use crate::models::score::{
+    list_scores_in_setlist, load_metadata_batch, resolve_score_or_bookmark,
+    resolve_score_or_bookmark_strict, score_duration, score_page_count, score_page_notes,
+    search_scores, ScoreFilter, ScoreOrBookmark,
+};
 use crate::models::setlist::{
-    add_item_to_setlist, add_score_to_setlist, create_setlist, delete_setlist, list_setlists,
-    remove_score_from_setlist, rename_setlist, reorder_score_in_setlist, resolve_setlist,
+    add_item_to_setlist, add_score_to_setlist, create_setlist, delete_setlist, list_setlist_order,
+    list_setlists, mark_setlist_played, remove_score_from_setlist, rename_setlist,
+    reorder_score_in_setlist, resolve_setlist, resolve_setlist_strict, set_setlist_index_fields,
+    set_setlist_item_order, set_setlist_menu_index,
+};
+use crate::models::Score;
+use crate::output::{
+    format_duration, is_structured, output, output_scores, output_setlist_compact,
+    output_setlist_detailed, table_columns, CompactEntry, DetailedEntry,
 };
-use crate::output::output;
+use crate::platform::open_url;
+use crate::progress::{Progress, Summary};
+use crate::query::saved_search;
 use crate::setlist_sync::{
-    add_item_to_setlist_file, create_setlist_file, delete_setlist_file, remove_item_from_setlist_file,
-    rename_setlist_file, reorder_setlist_file, SetlistItem,
+    add_item_to_setlist_file, create_setlist_file, delete_setlist_file, mark_setlist_file_played,
+    remove_item_from_setlist_file, rename_setlist_file, reorder_setlist_file,
+    set_item_pages_in_setlist_file, setlist_file_path, SetlistItem,
 };
+use crate::store::{all_composer_info, open_store};
+use crate::template::{
+    render_program, render_template, ProgramComposer, ProgramContext, ProgramItem, ProgramSetlist,
+};
+use crate::trash::Bundle;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 
 pub fn handle(cmd: SetlistsCommand) -> Result<()> {
     match cmd {
-        SetlistsCommand::Ls { json } => {
+        SetlistsCommand::Ls { verbose, json } => {
             let conn = open_readonly()?;
-            let setlists = list_setlists(&conn)?;
-            output(&setlists, json);
+            if verbose {
+                output(&list_setlist_order(&conn)?, json);
+            } else {
+                output(&list_setlists(&conn)?, json);
+            }
+        }
+
+        SetlistsCommand::SetOrder {
+            identifier,
+            menu_index,
+            index,
+            sort,
+        } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let sl = resolve_setlist(&conn, &identifier)?;
+            if let Some(position) = menu_index {
+                set_setlist_menu_index(&conn, sl.id, position)?;
+            }
+            if index.is_some() || sort.is_some() {
+                set_setlist_index_fields(&conn, sl.id, index, sort)?;
+            }
+            let order = list_setlist_order(&conn)?;
+            let optional = |v: Option<i64>| v.map_or("unset".to_string(), |v| v.to_string());
+            if let Some(updated) = order.iter().find(|s| s.id == sl.id) {
+                println!(
+                    "Setlist '{}': menu index {}, index {}, sort {}",
+                    sl.title,
+                    optional(updated.menu_index),
+                    optional(updated.index),
+                    optional(updated.sort)
+                );
+            }
         }
 
-        SetlistsCommand::Show { identifier, json } => {
+        SetlistsCommand::Show {
+            identifier,
+            layout,
+            columns,
+            json,
+        } => {
             let conn = open_readonly()?;
             let setlist = resolve_setlist(&conn, &identifier)?;
             let mut scores = list_scores_in_setlist(&conn, setlist.id)?;
 
             // Load metadata (composers, genres, etc.) for each score
-            for score in &mut scores {
-                score.load_metadata(&conn)?;
+            load_metadata_batch(&conn, &mut scores)?;
+            load_page_counts(&conn, &mut scores)?;
+
+            if !is_structured(json) {
+                let total_pages: i32 = scores.iter().filter_map(|s| s.pages).sum();
+                println!(
+                    "Setlist: {} ({} scores, {} pages)\n",
+                    setlist.title, setlist.score_count, total_pages
+                );
             }
 
-            println!(
-                "Setlist: {} ({} scores)\n",
-                setlist.title, setlist.score_count
-            );
-            output(&scores, json);
+            let cumulative = running_page_totals(&scores);
+            match layout {
+                None => output_scores(&scores, json, &table_columns(&columns, false, false)?),
+                Some(SetlistFormat::Compact) => {
+                    let entries: Vec<CompactEntry> = scores
+                        .iter()
+                        .enumerate()
+                        .map(|(i, s)| CompactEntry {
+                            position: i + 1,
+                            id: s.id,
+                            title: s.title.clone(),
+                            key: s.key.as_ref().map(|k| k.display()),
+                            pages: s.pages,
+                            cumulative_pages: cumulative[i],
+                        })
+                        .collect();
+                    output_setlist_compact(&entries, json);
+                }
+                Some(SetlistFormat::Detailed) => {
+                    let mut entries = Vec::new();
+                    for (i, s) in scores.iter().enumerate() {
+                        entries.push(DetailedEntry {
+                            position: i + 1,
+                            id: s.id,
+                            title: s.title.clone(),
+                            composers: s.composers.clone(),
+                            key: s.key.as_ref().map(|k| k.display()),
+                            pages: s.pages,
+                            cumulative_pages: cumulative[i],
+                            duration: score_duration(&conn, s.id)?,
+                            notes: score_page_notes(&conn, s.id)?,
+                        });
+                    }
+                    output_setlist_detailed(&entries, json);
+                }
+            }
         }
 
-        SetlistsCommand::Create { name } => {
+        SetlistsCommand::Export {
+            identifier,
+            layout,
+            template,
+            output,
+        } => {
+            let conn = open_readonly()?;
+            let setlist = resolve_setlist(&conn, &identifier)?;
+            let mut scores = list_scores_in_setlist(&conn, setlist.id)?;
+            load_metadata_batch(&conn, &mut scores)?;
+            load_page_counts(&conn, &mut scores)?;
+            let composer_info = all_composer_info(&open_store()?)?;
+
+            let cumulative = running_page_totals(&scores);
+            let mut items = Vec::new();
+            for (i, s) in scores.iter().enumerate() {
+                let duration = score_duration(&conn, s.id)?;
+                items.push(ProgramItem {
+                    position: i + 1,
+                    id: s.id,
+                    title: s.title.clone(),
+                    path: s.path.clone(),
+                    is_bookmark: s.start_page.is_some(),
+                    composers: s
+                        .composers
+                        .iter()
+                        .map(|name| {
+                            let info = composer_info.get(&name.to_lowercase());
+                            ProgramComposer {
+                                name: name.clone(),
+                                years: info.and_then(|i| i.years()),
+                                nationality: info.and_then(|i| i.nationality.clone()),
+                            }
+                        })
+                        .collect(),
+                    genres: s.genres.clone(),
+                    keywords: s.keywords.clone(),
+                    labels: s.labels.clone(),
+                    key: s.key.as_ref().map(|k| k.display()),
+                    rating: s.rating,
+                    difficulty: s.difficulty,
+                    bpm: s.bpm,
+                    pages: s.pages,
+                    cumulative_pages: cumulative[i],
+                    duration: duration.map(format_duration),
+                    duration_seconds: duration,
+                    notes: score_page_notes(&conn, s.id)?,
+                });
+            }
+
+            let total_duration: i32 = items.iter().filter_map(|i| i.duration_seconds).sum();
+            let context = ProgramContext {
+                setlist: ProgramSetlist {
+                    id: setlist.id,
+                    title: setlist.title.clone(),
+                    item_count: items.len(),
+                    total_pages: items.iter().filter_map(|i| i.pages).sum(),
+                    total_duration: if total_duration > 0 {
+                        format_duration(total_duration)
+                    } else {
+                        String::new()
+                    },
+                },
+                items,
+            };
+
+            let rendered = match (layout, template) {
+                (Some(layout), _) => render_program(&context, layout)?,
+                (None, Some(template)) => render_template(Path::new(&template), &context)?,
+                (None, None) => {
+                    return Err(ForScoreError::Other("Give --layout or --template".into()))
+                }
+            };
+            match output {
+                Some(path) => {
+                    fs::write(&path, rendered)?;
+                    println!("Exported '{}' to {}", setlist.title, path);
+                }
+                None => print!("{}", rendered),
+            }
+        }
+
+        SetlistsCommand::Create { name, from_saved } => {
+            let expr = from_saved.as_deref().map(saved_search).transpose()?;
             warn_if_running();
             let conn = open_readwrite()?;
             let setlist = create_setlist(&conn, &name)?;
 
+            // Fill it from the saved search, in search order
+            let mut added = 0;
+            if let Some(expr) = expr {
+                let filter = ScoreFilter {
+                    expr: Some(expr),
+                    ..Default::default()
+                };
+                for item in search_scores(&conn, &filter, usize::MAX)? {
+                    let entity = if item.start_page.is_some() {
+                        entity::bookmark()
+                    } else {
+                        entity::score()
+                    };
+                    add_item_to_setlist(&conn, setlist.id, item.id, entity)?;
+                    added += 1;
+                }
+            }
+
             // Create sync file
             match create_setlist_file(&name) {
-                Ok(true) => println!("Created setlist '{}' (ID: {}) + sync file", setlist.title, setlist.id),
-                Ok(false) => println!("Created setlist '{}' (ID: {}) (sync file exists)", setlist.title, setlist.id),
+                Ok(true) => println!(
+                    "Created setlist '{}' (ID: {}) + sync file",
+                    setlist.title, setlist.id
+                ),
+                Ok(false) => println!(
+                    "Created setlist '{}' (ID: {}) (sync file exists)",
+                    setlist.title, setlist.id
+                ),
                 Err(e) => {
-                    println!("Created setlist '{}' (ID: {}) (database only)", setlist.title, setlist.id);
+                    println!(
+                        "Created setlist '{}' (ID: {}) (database only)",
+                        setlist.title, setlist.id
+                    );
                     eprintln!("Warning: Failed to create sync file: {}", e);
                 }
             }
+
+            if let Some(search) = from_saved {
+                let items = setlist_file_items(&conn, setlist.id)?;
+                if let Err(e) = reorder_setlist_file(&setlist.title, &items) {
+                    eprintln!("Warning: Failed to update sync file: {}", e);
+                }
+                println!("Added {} items from saved search '{}'", added, search);
+            }
         }
 
         SetlistsCommand::Rename {
@@ -65,8 +288,14 @@ pub fn handle(cmd: SetlistsCommand) -> Result<()> {
 
             // Rename sync file
             match rename_setlist_file(&old_name, &new_name) {
-                Ok(true) => println!("Renamed '{}' to '{}' + updated sync file", old_name, new_name),
-                Ok(false) => println!("Renamed '{}' to '{}' (no sync file found)", old_name, new_name),
+                Ok(true) => println!(
+                    "Renamed '{}' to '{}' + updated sync file",
+                    old_name, new_name
+                ),
+                Ok(false) => println!(
+                    "Renamed '{}' to '{}' (no sync file found)",
+                    old_name, new_name
+                ),
                 Err(e) => {
                     println!("Renamed '{}' to '{}' (database only)", old_name, new_name);
                     eprintln!("Warning: Failed to update sync file: {}", e);
@@ -77,8 +306,13 @@ pub fn handle(cmd: SetlistsCommand) -> Result<()> {
         SetlistsCommand::Delete { identifier } => {
             warn_if_running();
             let conn = open_readwrite()?;
-            let setlist = resolve_setlist(&conn, &identifier)?;
+            let setlist = resolve_setlist_strict(&conn, &identifier)?;
             let name = setlist.title.clone();
+            let mut trash = Bundle::new("setlist", setlist.id, &name);
+            trash.snapshot(&conn, "ZSETLIST", "Z_PK", setlist.id)?;
+            trash.snapshot(&conn, "ZCYLON", "ZSETLIST", setlist.id)?;
+            trash.add_file(&setlist_file_path(&name)?);
+            trash.save()?;
             delete_setlist(&conn, setlist.id)?;
 
             // Delete sync file
@@ -90,93 +324,322 @@ pub fn handle(cmd: SetlistsCommand) -> Result<()> {
                     eprintln!("Warning: Failed to delete sync file: {}", e);
                 }
             }
+            println!("Restore with: forscore trash restore {}", trash.id());
+        }
+
+        SetlistsCommand::Play { identifier, open } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let setlist = resolve_setlist(&conn, &identifier)?;
+            mark_setlist_played(&conn, setlist.id)?;
+
+            match mark_setlist_file_played(&setlist.title) {
+                Ok(true) => println!("Marked '{}' as played + sync file", setlist.title),
+                Ok(false) => println!("Marked '{}' as played (no sync file found)", setlist.title),
+                Err(e) => {
+                    println!("Marked '{}' as played (database only)", setlist.title);
+                    eprintln!("Warning: Failed to update sync file: {}", e);
+                }
+            }
+
+            if open {
+                let first = list_scores_in_setlist(&conn, setlist.id)?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| {
+                        ForScoreError::Other(format!("Setlist '{}' is empty", setlist.title))
+                    })?;
+
+                // Use forScore URL scheme
+                let url = format!("forscore://open?path={}", urlencoding::encode(&first.path));
+                open_url(&url)?;
+                println!("Opening {} in forScore...", first.title);
+            }
+        }
+
+        SetlistsCommand::Open { identifier, item } => {
+            let conn = open_readonly()?;
+            let setlist = resolve_setlist(&conn, &identifier)?;
+            let target = match item {
+                None => None,
+                Some(n) => {
+                    let items = list_scores_in_setlist(&conn, setlist.id)?;
+                    let count = items.len();
+                    let found = n.checked_sub(1).and_then(|i| items.into_iter().nth(i));
+                    Some(found.ok_or_else(|| {
+                        ForScoreError::Other(format!(
+                            "Setlist '{}' has {} item(s); there's no item {}",
+                            setlist.title, count, n
+                        ))
+                    })?)
+                }
+            };
+
+            open_url(&setlist_url(&setlist.title, target.as_ref()))?;
+            match &target {
+                Some(score) => println!(
+                    "Opening '{}' in setlist '{}' in forScore...",
+                    score.title, setlist.title
+                ),
+                None => println!("Opening setlist '{}' in forScore...", setlist.title),
+            }
         }
 
-        SetlistsCommand::AddScore { setlist, score } => {
+        SetlistsCommand::AddScore {
+            setlist,
+            score,
+            allow_duplicate,
+        } => {
             warn_if_running();
             let conn = open_readwrite()?;
             let sl = resolve_setlist(&conn, &setlist)?;
 
-            // Try as score first, then as bookmark
-            if let Ok(sc) = resolve_score(&conn, &score) {
-                add_score_to_setlist(&conn, sl.id, sc.id)?;
-
-                // Get the UUID that was used (either reused or newly generated)
-                let identifier: String = conn
-                    .query_row(
-                        "SELECT ZUUID FROM ZCYLON WHERE ZSETLIST = ? AND ZITEM = ?",
-                        [sl.id, sc.id],
-                        |row| row.get(0),
-                    )
-                    .unwrap_or_default();
-
-                let item = SetlistItem {
-                    file_path: sc.path.clone(),
-                    title: sc.title.clone(),
-                    identifier,
-                    is_bookmark: false,
-                    first_page: None,
-                    last_page: None,
-                };
-                match add_item_to_setlist_file(&sl.title, &item) {
-                    Ok(true) => println!("Added '{}' to setlist '{}' + sync file", sc.title, sl.title),
-                    Ok(false) => println!("Added '{}' to setlist '{}' (already in sync file)", sc.title, sl.title),
-                    Err(e) => {
-                        println!("Added '{}' to setlist '{}' (database only)", sc.title, sl.title);
-                        eprintln!("Warning: Failed to update sync file: {}", e);
+            if !allow_duplicate {
+                if let Some((title, reason)) = find_duplicate(&conn, sl.id, &score)? {
+                    println!(
+                        "Skipped: setlist '{}' already has '{}' ({})",
+                        sl.title, title, reason
+                    );
+                    println!("Use --allow-duplicate to add it anyway");
+                    return Ok(());
+                }
+            }
+
+            let item = add_to_setlist(&conn, sl.id, &score)?;
+            let kind = if item.is_bookmark { "bookmark " } else { "" };
+
+            match add_item_to_setlist_file(&sl.title, &item) {
+                Ok(true) => println!(
+                    "Added {}'{}' to setlist '{}' + sync file",
+                    kind, item.title, sl.title
+                ),
+                Ok(false) => println!(
+                    "Added {}'{}' to setlist '{}' (already in sync file)",
+                    kind, item.title, sl.title
+                ),
+                Err(e) => {
+                    println!(
+                        "Added {}'{}' to setlist '{}' (database only)",
+                        kind, item.title, sl.title
+                    );
+                    eprintln!("Warning: Failed to update sync file: {}", e);
+                }
+            }
+        }
+
+        SetlistsCommand::AddGroup {
+            setlist,
+            group,
+            allow_duplicate,
+        } => {
+            warn_if_running();
+            let conn = open_readwrite()?;
+            let sl = resolve_setlist(&conn, &setlist)?;
+            let members = resolve_group(&conn, &group)?;
+
+            let mut added = 0;
+            for member in &members {
+                let identifier = member.id.to_string();
+                if !allow_duplicate {
+                    if let Some((title, reason)) = find_duplicate(&conn, sl.id, &identifier)? {
+                        println!(
+                            "  Skipped '{}': already has '{}' ({})",
+                            member.title, title, reason
+                        );
+                        continue;
                     }
                 }
-            } else if let Ok(bm) = resolve_bookmark(&conn, &score) {
-                add_item_to_setlist(&conn, sl.id, bm.id, entity::BOOKMARK)?;
-
-                // Get the UUID that was used
-                let identifier: String = conn
-                    .query_row(
-                        "SELECT ZUUID FROM ZCYLON WHERE ZSETLIST = ? AND ZITEM = ?",
-                        [sl.id, bm.id],
-                        |row| row.get(0),
-                    )
-                    .unwrap_or_default();
-
-                let item = SetlistItem {
-                    file_path: bm.path.clone(),
-                    title: bm.title.clone(),
-                    identifier,
-                    is_bookmark: true,
-                    first_page: bm.start_page.map(|p| p as i64),
-                    last_page: bm.end_page.map(|p| p as i64),
-                };
+                let item = add_to_setlist(&conn, sl.id, &identifier)?;
+                added += 1;
                 match add_item_to_setlist_file(&sl.title, &item) {
-                    Ok(true) => println!("Added bookmark '{}' to setlist '{}' + sync file", bm.title, sl.title),
-                    Ok(false) => println!("Added bookmark '{}' to setlist '{}' (already in sync file)", bm.title, sl.title),
+                    Ok(_) => println!("  {}", item.title),
                     Err(e) => {
-                        println!("Added bookmark '{}' to setlist '{}' (database only)", bm.title, sl.title);
+                        println!("  {} (database only)", item.title);
                         eprintln!("Warning: Failed to update sync file: {}", e);
                     }
                 }
+            }
+            println!(
+                "Added {} of {} scores in work group '{}' to setlist '{}'",
+                added,
+                members.len(),
+                group,
+                sl.title
+            );
+        }
+
+        SetlistsCommand::ImportDir {
+            folder,
+            dry_run,
+            json,
+        } => {
+            if !dry_run {
+                warn_if_running();
+            }
+
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            import_dir(&conn, &folder, dry_run, json)?;
+        }
+
+        SetlistsCommand::Analyze { setlist, json } => {
+            let conn = open_readonly()?;
+            let sl = resolve_setlist(&conn, &setlist)?;
+            let items = list_scores_in_setlist(&conn, sl.id)?;
+            analyze_key_flow(&sl.title, &items, json)?;
+        }
+
+        SetlistsCommand::SetPages {
+            setlist,
+            score,
+            from,
+            to,
+            clear,
+        } => {
+            let conn = open_readonly()?;
+            let sl = resolve_setlist(&conn, &setlist)?;
+            let sc = match resolve_score_or_bookmark(&conn, &score)? {
+                ScoreOrBookmark::Score(sc) => sc,
+                ScoreOrBookmark::Bookmark(bm) => {
+                    return Err(ForScoreError::Other(format!(
+                        "'{}' is a bookmark, which has its own page range (change it with bookmarks edit)",
+                        bm.title
+                    )))
+                }
+            };
+            // A score that's in the setlist more than once gets the range
+            // on every entry
+            let identifiers = cylon_uuids(&conn, sl.id, sc.id)?;
+            if identifiers.is_empty() {
+                return Err(ForScoreError::Other(format!(
+                    "'{}' isn't in setlist '{}'",
+                    sc.title, sl.title
+                )));
+            }
+
+            let pages = match (from, to) {
+                (Some(first), Some(last)) if !clear => {
+                    check_page_range(first, last, score_page_count(&conn, &sc)?)?;
+                    Some((first, last))
+                }
+                _ => None,
+            };
+            let mut found = false;
+            for identifier in &identifiers {
+                found |= set_item_pages_in_setlist_file(&sl.title, identifier, pages)?;
+            }
+            if !found {
+                return Err(ForScoreError::Other(format!(
+                    "'{}' has no entry for '{}' in its sync file; nothing changed",
+                    sl.title, sc.title
+                )));
+            }
+            match pages {
+                Some((first, last)) => println!(
+                    "'{}' in setlist '{}' now plays pages {}-{}",
+                    sc.title, sl.title, first, last
+                ),
+                None => println!(
+                    "'{}' in setlist '{}' plays the whole score again",
+                    sc.title, sl.title
+                ),
+            }
+        }
+
+        SetlistsCommand::Import {
+            name,
+            from,
+            dry_run,
+            json,
+        } => {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(ForScoreError::Other("Setlist name can't be empty".into()));
+            }
+            if !dry_run {
+                warn_if_running();
+            }
+            let conn = if dry_run {
+                open_readonly()?
             } else {
-                return Err(crate::error::ForScoreError::Other(format!(
-                    "Score or bookmark not found: {}",
-                    score
+                open_readwrite()?
+            };
+            if list_setlists(&conn)?.iter().any(|s| s.title == name) {
+                return Err(ForScoreError::Other(format!(
+                    "Setlist '{}' already exists",
+                    name
                 )));
             }
+
+            let mut summary = Summary::default();
+            let mut preview = DryRun::new(json);
+            let missing = import_title_file(
+                &conn,
+                name,
+                Path::new(&from),
+                dry_run,
+                &mut preview,
+                &mut summary,
+                &Progress::hidden(),
+            )?;
+            if !preview.is_json() {
+                for (scope, error) in &summary.errors {
+                    eprintln!("Warning: {}: {}", scope, error);
+                }
+                if !missing.is_empty() {
+                    println!("\nNo score matches {} line(s) of {}:", missing.len(), from);
+                    for (line_no, line) in &missing {
+                        println!("  line {}: {}", line_no, line);
+                    }
+                }
+            }
+            if dry_run {
+                preview.finish();
+            }
+        }
+
+        SetlistsCommand::Generate {
+            name,
+            from_search,
+            filter,
+            max_per_composer,
+            target_duration,
+            spread_keys,
+            dry_run,
+            json,
+        } => {
+            let options = ProgramOptions {
+                max_per_composer,
+                target_duration: target_duration.as_deref().map(parse_duration).transpose()?,
+                spread_keys,
+            };
+
+            if !dry_run {
+                warn_if_running();
+            }
+
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+
+            let filter = build_filter(&conn, from_search, &filter)?;
+            generate(&conn, &name, &filter, &options, dry_run, json)?;
         }
 
         SetlistsCommand::RemoveScore { setlist, score } => {
             warn_if_running();
             let conn = open_readwrite()?;
-            let sl = resolve_setlist(&conn, &setlist)?;
+            let sl = resolve_setlist_strict(&conn, &setlist)?;
 
-            // Try as score first, then as bookmark
-            let (item_id, item_title) = if let Ok(sc) = resolve_score(&conn, &score) {
-                (sc.id, sc.title)
-            } else if let Ok(bm) = resolve_bookmark(&conn, &score) {
-                (bm.id, bm.title)
-            } else {
-                return Err(crate::error::ForScoreError::Other(format!(
-                    "Score or bookmark not found: {}",
-                    score
-                )));
+            let (item_id, item_title) = match resolve_score_or_bookmark_strict(&conn, &score)? {
+                ScoreOrBookmark::Score(sc) => (sc.id, sc.title),
+                ScoreOrBookmark::Bookmark(bm) => (bm.id, bm.title),
             };
 
             // Get the UUID from ZCYLON before deleting (this is what's in the sync file)
@@ -192,10 +655,19 @@ pub fn handle(cmd: SetlistsCommand) -> Result<()> {
 
             // Update sync file
             match remove_item_from_setlist_file(&sl.title, &identifier) {
-                Ok(true) => println!("Removed '{}' from setlist '{}' + sync file", item_title, sl.title),
-                Ok(false) => println!("Removed '{}' from setlist '{}' (not in sync file)", item_title, sl.title),
+                Ok(true) => println!(
+                    "Removed '{}' from setlist '{}' + sync file",
+                    item_title, sl.title
+                ),
+                Ok(false) => println!(
+                    "Removed '{}' from setlist '{}' (not in sync file)",
+                    item_title, sl.title
+                ),
                 Err(e) => {
-                    println!("Removed '{}' from setlist '{}' (database only)", item_title, sl.title);
+                    println!(
+                        "Removed '{}' from setlist '{}' (database only)",
+                        item_title, sl.title
+                    );
                     eprintln!("Warning: Failed to update sync file: {}", e);
                 }
             }
@@ -205,57 +677,29 @@ pub fn handle(cmd: SetlistsCommand) -> Result<()> {
             setlist,
             score,
             position,
+            interactive,
         } => {
+            if interactive {
+                return reorder::reorder_interactive(&setlist);
+            }
+            let (Some(score), Some(position)) = (score, position) else {
+                return Err(ForScoreError::Other(
+                    "Give a score and --position, or use --interactive".into(),
+                ));
+            };
             warn_if_running();
             let conn = open_readwrite()?;
             let sl = resolve_setlist(&conn, &setlist)?;
 
-            // Try as score first, then as bookmark
-            let (item_id, item_title) = if let Ok(sc) = resolve_score(&conn, &score) {
-                (sc.id, sc.title)
-            } else if let Ok(bm) = resolve_bookmark(&conn, &score) {
-                (bm.id, bm.title)
-            } else {
-                return Err(crate::error::ForScoreError::Other(format!(
-                    "Score or bookmark not found: {}",
-                    score
-                )));
+            let (item_id, item_title) = match resolve_score_or_bookmark(&conn, &score)? {
+                ScoreOrBookmark::Score(sc) => (sc.id, sc.title),
+                ScoreOrBookmark::Bookmark(bm) => (bm.id, bm.title),
             };
 
             reorder_score_in_setlist(&conn, sl.id, item_id, position)?;
 
             // Rebuild sync file with new order from database
-            // Query items with their UUIDs and entity types from ZCYLON
-            let mut stmt = conn.prepare(
-                "SELECT c.ZITEM, c.ZUUID, c.Z4_ITEM, i.ZPATH, i.ZTITLE, i.ZSTARTPAGE, i.ZENDPAGE
-                 FROM ZCYLON c
-                 JOIN ZITEM i ON c.ZITEM = i.Z_PK
-                 WHERE c.ZSETLIST = ?
-                 ORDER BY c.Z_PK"
-            )?;
-            let mut items: Vec<SetlistItem> = Vec::new();
-            let rows = stmt.query_map([sl.id], |row| {
-                Ok((
-                    row.get::<_, String>(1)?,           // ZUUID
-                    row.get::<_, i32>(2)?,              // Z4_ITEM (entity type)
-                    row.get::<_, String>(3)?,           // ZPATH
-                    row.get::<_, String>(4)?,           // ZTITLE
-                    row.get::<_, Option<i32>>(5)?,      // ZSTARTPAGE
-                    row.get::<_, Option<i32>>(6)?,      // ZENDPAGE
-                ))
-            })?;
-            for row in rows {
-                let (identifier, entity_type, path, title, start_page, end_page) = row?;
-                let is_bookmark = entity_type == entity::BOOKMARK;
-                items.push(SetlistItem {
-                    file_path: path,
-                    title,
-                    identifier,
-                    is_bookmark,
-                    first_page: if is_bookmark { start_page.map(|p| p as i64) } else { None },
-                    last_page: if is_bookmark { end_page.map(|p| p as i64) } else { None },
-                });
-            }
+            let items = setlist_file_items(&conn, sl.id)?;
 
             match reorder_setlist_file(&sl.title, &items) {
                 Ok(true) => println!(
@@ -275,7 +719,1012 @@ pub fn handle(cmd: SetlistsCommand) -> Result<()> {
                 }
             }
         }
+
+        SetlistsCommand::Sort {
+            identifier,
+            by,
+            reverse,
+            dry_run,
+            json,
+        } => {
+            if !dry_run {
+                warn_if_running();
+            }
+            let conn = if dry_run {
+                open_readonly()?
+            } else {
+                open_readwrite()?
+            };
+            let sl = resolve_setlist(&conn, &identifier)?;
+            let mut items = list_scores_in_setlist(&conn, sl.id)?;
+            load_metadata_batch(&conn, &mut items)?;
+
+            let before: Vec<i64> = items.iter().map(|s| s.id).collect();
+            items.sort_by(|a, b| compare_setlist_items(a, b, by, reverse));
+            let moved: Vec<(usize, usize, &Score)> = items
+                .iter()
+                .enumerate()
+                .filter(|(i, s)| before[*i] != s.id)
+                .map(|(i, s)| {
+                    let old = before.iter().position(|id| *id == s.id).unwrap_or(i);
+                    (old + 1, i + 1, s)
+                })
+                .collect();
+
+            if dry_run {
+                let mut preview = DryRun::new(json);
+                for (old, new, item) in &moved {
+                    let mut patch = Patch::new("move", "setlist-entry", Some(item.id), &item.title);
+                    patch.change("position", old.to_string(), new.to_string());
+                    preview.add(patch);
+                }
+                preview.finish();
+                return Ok(());
+            }
+            if moved.is_empty() {
+                println!("'{}' is already in that order", sl.title);
+                return Ok(());
+            }
+
+            let order: Vec<i64> = items.iter().map(|s| s.id).collect();
+            set_setlist_item_order(&conn, sl.id, &order)?;
+            let file_items = setlist_file_items(&conn, sl.id)?;
+            match reorder_setlist_file(&sl.title, &file_items) {
+                Ok(true) => println!(
+                    "Sorted '{}' ({} items moved) + updated sync file",
+                    sl.title,
+                    moved.len()
+                ),
+                Ok(false) => println!(
+                    "Sorted '{}' ({} items moved) (no sync file)",
+                    sl.title,
+                    moved.len()
+                ),
+                Err(e) => {
+                    println!(
+                        "Sorted '{}' ({} items moved) (database only)",
+                        sl.title,
+                        moved.len()
+                    );
+                    eprintln!("Warning: Failed to update sync file: {}", e);
+                }
+            }
+        }
     }
 
     Ok(())
 }
+
+/// Pages from the start of a setlist through each item, for page turners
+/// keeping their place in the stack. Unknown once an item's count is unknown
+/// The forscore:// URL that opens a setlist, at `item` if given (a
+/// bookmark opens at its first page)
+fn setlist_url(title: &str, item: Option<&Score>) -> String {
+    let mut url = format!("forscore://open?setlist={}", urlencoding::encode(title));
+    if let Some(item) = item {
+        url.push_str(&format!("&path={}", urlencoding::encode(&item.path)));
+        if let Some(page) = item.start_page {
+            url.push_str(&format!("&page={}", page));
+        }
+    }
+    url
+}
+
+fn running_page_totals(items: &[Score]) -> Vec<Option<i32>> {
+    items
+        .iter()
+        .scan(Some(0), |total, item| {
+            *total = total.zip(item.pages).map(|(total, pages)| total + pages);
+            Some(*total)
+        })
+        .collect()
+}
+
+/// Order two setlist items by `by`, with items missing the field last either
+/// way, then by title
+fn compare_setlist_items(a: &Score, b: &Score, by: SetlistSortField, reverse: bool) -> Ordering {
+    fn missing_last<T: Ord>(a: Option<T>, b: Option<T>, reverse: bool) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) if reverse => b.cmp(&a),
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        }
+    }
+    let title = |s: &Score| {
+        s.sort_title
+            .clone()
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| s.title.to_lowercase())
+    };
+    let composer = |s: &Score| s.composers.first().map(|c| c.to_lowercase());
+    let primary = match by {
+        SetlistSortField::Title => missing_last(Some(title(a)), Some(title(b)), reverse),
+        SetlistSortField::Composer => missing_last(composer(a), composer(b), reverse),
+        SetlistSortField::Key => missing_last(
+            a.key.as_ref().map(|k| k.code),
+            b.key.as_ref().map(|k| k.code),
+            reverse,
+        ),
+        SetlistSortField::Difficulty => missing_last(a.difficulty, b.difficulty, reverse),
+    };
+    primary.then_with(|| title(a).cmp(&title(b)))
+}
+
+/// A setlist's items as sync file entries, in database order (for rebuilding
+/// the .set file after ZCYLON rows were renumbered)
+pub fn setlist_file_items(conn: &Connection, setlist_id: i64) -> Result<Vec<SetlistItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.ZITEM, c.ZUUID, c.Z4_ITEM, i.ZPATH, i.ZTITLE, i.ZSTARTPAGE, i.ZENDPAGE
+         FROM ZCYLON c
+         JOIN ZITEM i ON c.ZITEM = i.Z_PK
+         WHERE c.ZSETLIST = ?
+         ORDER BY c.Z_PK",
+    )?;
+    let mut items: Vec<SetlistItem> = Vec::new();
+    let rows = stmt.query_map([setlist_id], |row| {
+        Ok((
+            row.get::<_, String>(1)?,      // ZUUID
+            row.get::<_, i32>(2)?,         // Z4_ITEM (entity type)
+            row.get::<_, String>(3)?,      // ZPATH
+            row.get::<_, String>(4)?,      // ZTITLE
+            row.get::<_, Option<i32>>(5)?, // ZSTARTPAGE
+            row.get::<_, Option<i32>>(6)?, // ZENDPAGE
+        ))
+    })?;
+    for row in rows {
+        let (identifier, entity_type, path, title, start_page, end_page) = row?;
+        let is_bookmark = entity_type == entity::bookmark();
+        items.push(SetlistItem {
+            file_path: path,
+            title,
+            identifier,
+            is_bookmark,
+            first_page: if is_bookmark {
+                start_page.map(|p| p as i64)
+            } else {
+                None
+            },
+            last_page: if is_bookmark {
+                end_page.map(|p| p as i64)
+            } else {
+                None
+            },
+        });
+    }
+    Ok(items)
+}
+
+/// Resolve a score or bookmark and add it to a setlist in the database,
+/// returning the entry to mirror into the .set sync file
+fn add_to_setlist(conn: &Connection, setlist_id: i64, identifier: &str) -> Result<SetlistItem> {
+    add_resolved_to_setlist(
+        conn,
+        setlist_id,
+        resolve_score_or_bookmark(conn, identifier)?,
+    )
+}
+
+/// `add_to_setlist` for an item that's already been looked up
+fn add_resolved_to_setlist(
+    conn: &Connection,
+    setlist_id: i64,
+    item: ScoreOrBookmark,
+) -> Result<SetlistItem> {
+    let item = match item {
+        ScoreOrBookmark::Score(sc) => {
+            let identifier = add_score_to_setlist(conn, setlist_id, sc.id)?;
+            SetlistItem {
+                file_path: sc.path,
+                title: sc.title,
+                identifier,
+                is_bookmark: false,
+                first_page: None,
+                last_page: None,
+            }
+        }
+        ScoreOrBookmark::Bookmark(bm) => {
+            let identifier = add_item_to_setlist(conn, setlist_id, bm.id, entity::bookmark())?;
+            SetlistItem {
+                file_path: bm.path,
+                title: bm.title,
+                identifier,
+                is_bookmark: true,
+                first_page: bm.start_page.map(|p| p as i64),
+                last_page: bm.end_page.map(|p| p as i64),
+            }
+        }
+    };
+
+    Ok(item)
+}
+
+/// An entry already in the setlist for the same piece as `identifier` under
+/// a different item, as (its title, why it counts as the same piece)
+fn find_duplicate(
+    conn: &Connection,
+    setlist_id: i64,
+    identifier: &str,
+) -> Result<Option<(String, &'static str)>> {
+    let (id, path, pages) = match resolve_score_or_bookmark(conn, identifier) {
+        Ok(ScoreOrBookmark::Score(sc)) => (sc.id, sc.path, None),
+        Ok(ScoreOrBookmark::Bookmark(bm)) => (bm.id, bm.path, bm.start_page.zip(bm.end_page)),
+        // add_to_setlist reports the missing item
+        Err(_) => return Ok(None),
+    };
+
+    for existing in list_scores_in_setlist(conn, setlist_id)? {
+        if existing.id == id {
+            continue;
+        }
+        if let Some(reason) = duplicate_reason(&path, pages, &existing) {
+            return Ok(Some((existing.title, reason)));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether a setlist entry is the same piece as an item with this PDF and
+/// bookmark page range (`None` for a full score)
+fn duplicate_reason(
+    path: &str,
+    pages: Option<(i32, i32)>,
+    existing: &Score,
+) -> Option<&'static str> {
+    if existing.path != path {
+        return None;
+    }
+    match (pages, existing.start_page.zip(existing.end_page)) {
+        (None, None) => Some("another score entry for the same PDF"),
+        (None, Some(_)) => Some("a bookmark of this score"),
+        (Some(_), None) => Some("the full score"),
+        (Some((start, end)), Some((other_start, other_end)))
+            if start <= other_end && other_start <= end =>
+        {
+            Some("an overlapping bookmark")
+        }
+        _ => None,
+    }
+}
+
+/// The UUIDs of an item's entries in a setlist, in setlist order
+fn cylon_uuids(conn: &Connection, setlist_id: i64, item_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT ZUUID FROM ZCYLON WHERE ZSETLIST = ? AND ZITEM = ? AND ZUUID IS NOT NULL
+         ORDER BY Z_PK",
+    )?;
+    let uuids = stmt
+        .query_map([setlist_id, item_id], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(uuids)
+}
+
+/// Signatures this many steps apart on the circle of fifths make an awkward
+/// change
+const AWKWARD_FIFTHS: i32 = 5;
+
+#[derive(Serialize)]
+struct KeyChange {
+    /// e.g. "up a minor 3rd"
+    interval: String,
+    semitones: i32,
+    /// Steps between the key signatures on the circle of fifths
+    fifths: i32,
+    /// Why the change is awkward, if it is
+    awkward: Option<String>,
+}
+
+#[derive(Serialize)]
+struct KeyFlowItem {
+    position: usize,
+    id: i64,
+    title: String,
+    key: Option<String>,
+    /// From the previous item; None when either has no key
+    change: Option<KeyChange>,
+}
+
+#[derive(Serialize)]
+struct KeyCount {
+    key: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct KeyFlow {
+    setlist: String,
+    items: Vec<KeyFlowItem>,
+    /// Most used keys first
+    distribution: Vec<KeyCount>,
+    without_key: usize,
+    awkward: usize,
+}
+
+/// The key change from each item to the next (one fewer than items)
+fn key_changes(items: &[Score]) -> Vec<Option<KeyChange>> {
+    items
+        .windows(2)
+        .map(|pair| {
+            let (from, to) = (pair[0].key.as_ref()?, pair[1].key.as_ref()?);
+            let semitones = from.semitones_to(to);
+            let fifths = from.fifths_between(to);
+            let awkward = if semitones == 6 {
+                Some("tritone".to_string())
+            } else if fifths >= AWKWARD_FIFTHS {
+                Some(format!("{} steps apart on the circle of fifths", fifths))
+            } else {
+                None
+            };
+            Some(KeyChange {
+                interval: describe_interval(semitones),
+                semitones,
+                fifths,
+                awkward,
+            })
+        })
+        .collect()
+}
+
+fn analyze_key_flow(title: &str, items: &[Score], json: bool) -> Result<()> {
+    let mut counts: Vec<KeyCount> = Vec::new();
+    for key in items.iter().filter_map(|s| s.key.as_ref()) {
+        match counts.iter_mut().find(|c| c.key == key.display()) {
+            Some(count) => count.count += 1,
+            None => counts.push(KeyCount {
+                key: key.display(),
+                count: 1,
+            }),
+        }
+    }
+    // Stable, so equally common keys stay in setlist order
+    counts.sort_by_key(|c| std::cmp::Reverse(c.count));
+
+    let changes = std::iter::once(None).chain(key_changes(items));
+    let flow_items: Vec<KeyFlowItem> = items
+        .iter()
+        .zip(changes)
+        .enumerate()
+        .map(|(i, (item, change))| KeyFlowItem {
+            position: i + 1,
+            id: item.id,
+            title: item.title.clone(),
+            key: item.key.as_ref().map(|k| k.display()),
+            change,
+        })
+        .collect();
+    let flow = KeyFlow {
+        setlist: title.to_string(),
+        awkward: flow_items
+            .iter()
+            .filter(|i| i.change.as_ref().is_some_and(|c| c.awkward.is_some()))
+            .count(),
+        without_key: items.iter().filter(|s| s.key.is_none()).count(),
+        distribution: counts,
+        items: flow_items,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&flow)?);
+        return Ok(());
+    }
+
+    println!("Key flow of '{}'\n", flow.setlist);
+    let width = flow
+        .items
+        .iter()
+        .map(|i| i.title.chars().count())
+        .max()
+        .unwrap_or(0);
+    for item in &flow.items {
+        let key = item.key.as_deref().unwrap_or("?");
+        let change = match &item.change {
+            Some(c) => match &c.awkward {
+                Some(reason) => format!("{}  ⚠ {}", c.interval, reason),
+                None => c.interval.clone(),
+            },
+            None => String::new(),
+        };
+        let line = format!(
+            "{:>3}. {:<width$}  {:<9}  {}",
+            item.position,
+            item.title,
+            key,
+            change,
+            width = width
+        );
+        println!("{}", line.trim_end());
+    }
+
+    println!("\nKeys:");
+    for count in &flow.distribution {
+        println!("  {:<9} {}", count.key, count.count);
+    }
+    if flow.without_key > 0 {
+        println!("  {:<9} {}", "no key", flow.without_key);
+    }
+    println!("\n{} awkward key change(s)", flow.awkward);
+    Ok(())
+}
+
+/// A setlist entry's page range must be in order and inside the score
+fn check_page_range(first: i64, last: i64, pages: Option<i32>) -> Result<()> {
+    if first < 1 || last < first {
+        return Err(ForScoreError::Other(format!(
+            "Invalid page range {}-{}: pages start at 1 and --to can't come before --from",
+            first, last
+        )));
+    }
+    if let Some(pages) = pages.filter(|p| last > i64::from(*p)) {
+        return Err(ForScoreError::Other(format!(
+            "Invalid page range {}-{}: the score has {} pages",
+            first, last, pages
+        )));
+    }
+    Ok(())
+}
+
+/// Constraints for `setlists generate`
+struct ProgramOptions {
+    max_per_composer: Option<usize>,
+    /// Seconds
+    target_duration: Option<i32>,
+    spread_keys: bool,
+}
+
+/// What `pick_program` needs to know about a candidate piece
+struct Candidate {
+    composer: Option<String>,
+    genre: Option<String>,
+    key: Option<i32>,
+    /// Seconds
+    duration: Option<i32>,
+}
+
+/// Choose candidates (by index, in program order): one per genre in turn,
+/// skipping composers at their limit, pieces that would overrun the target
+/// duration and, with `spread_keys`, a piece in the same key as the last one
+/// when its genre offers another
+fn pick_program(candidates: &[Candidate], options: &ProgramOptions) -> Vec<usize> {
+    let mut genres: Vec<Option<&str>> = Vec::new();
+    for c in candidates {
+        let genre = c.genre.as_deref();
+        if !genres.contains(&genre) {
+            genres.push(genre);
+        }
+    }
+
+    let mut used = vec![false; candidates.len()];
+    let mut per_composer: HashMap<String, usize> = HashMap::new();
+    let mut program = Vec::new();
+    let mut total = 0;
+    let mut last_key = None;
+
+    loop {
+        let mut added = false;
+        for genre in &genres {
+            let allowed = |i: usize| {
+                let c = &candidates[i];
+                let composer_ok = match (&c.composer, options.max_per_composer) {
+                    (Some(name), Some(max)) => {
+                        per_composer.get(&name.to_lowercase()).copied().unwrap_or(0) < max
+                    }
+                    _ => true,
+                };
+                let duration_ok = match options.target_duration {
+                    Some(target) => c.duration.is_some_and(|d| total + d <= target),
+                    None => true,
+                };
+                !used[i] && c.genre.as_deref() == *genre && composer_ok && duration_ok
+            };
+
+            let mut choices = (0..candidates.len()).filter(|&i| allowed(i));
+            let first = choices.next();
+            let pick = if options.spread_keys && last_key.is_some() {
+                first
+                    .into_iter()
+                    .chain(choices)
+                    .find(|&i| candidates[i].key != last_key)
+                    .or(first)
+            } else {
+                first
+            };
+
+            if let Some(i) = pick {
+                let c = &candidates[i];
+                used[i] = true;
+                if let Some(name) = &c.composer {
+                    *per_composer.entry(name.to_lowercase()).or_default() += 1;
+                }
+                total += c.duration.unwrap_or(0);
+                last_key = c.key;
+                program.push(i);
+                added = true;
+            }
+        }
+        if !added {
+            return program;
+        }
+    }
+}
+
+/// "45m", "1h30m", "90s" or plain minutes ("45"), in seconds
+fn parse_duration(text: &str) -> Result<i32> {
+    let invalid = || {
+        ForScoreError::Other(format!(
+            "Invalid duration: {} (use e.g. \"45m\" or \"1h30m\")",
+            text
+        ))
+    };
+    let text = text.trim();
+    if let Ok(minutes) = text.parse::<i32>() {
+        return Ok(minutes * 60);
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for ch in text.chars() {
+        if ch.is_ascii_digit() {
+            number.push(ch);
+            continue;
+        }
+        let value: i32 = number.parse().map_err(|_| invalid())?;
+        number.clear();
+        total += match ch {
+            'h' => value * 3600,
+            'm' => value * 60,
+            's' => value,
+            _ => return Err(invalid()),
+        };
+    }
+    if !number.is_empty() || total <= 0 {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Build a draft setlist from the scores matching `filter`
+fn generate(
+    conn: &Connection,
+    name: &str,
+    filter: &ScoreFilter,
+    options: &ProgramOptions,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    if list_setlists(conn)?.iter().any(|s| s.title == name) {
+        return Err(ForScoreError::Other(format!(
+            "Setlist already exists: {}",
+            name
+        )));
+    }
+
+    let mut scores = search_scores(conn, filter, i64::MAX as usize)?;
+    load_metadata_batch(conn, &mut scores)?;
+    let mut candidates = Vec::new();
+    for score in &scores {
+        candidates.push(Candidate {
+            composer: score.composers.first().cloned(),
+            genre: score.genres.first().cloned(),
+            key: score.key.as_ref().map(|k| k.code),
+            duration: score_duration(conn, score.id)?,
+        });
+    }
+
+    let program = pick_program(&candidates, options);
+    if program.is_empty() {
+        return Err(ForScoreError::Other(
+            "No matching scores fit the constraints".into(),
+        ));
+    }
+    let total: i32 = program.iter().filter_map(|&i| candidates[i].duration).sum();
+
+    if dry_run {
+        let mut preview = DryRun::new(json);
+        let mut patch = Patch::create("setlist", name);
+        for (n, &i) in program.iter().enumerate() {
+            patch.change(
+                &format!("item {}", n + 1),
+                "",
+                program_line(&scores[i], &candidates[i]),
+            );
+        }
+        preview.add(patch);
+        if !preview.is_json() {
+            print_program_total(program.len(), scores.len(), total, options);
+        }
+        preview.finish();
+        return Ok(());
+    }
+
+    let setlist = create_setlist(conn, name)?;
+    let sync_file = match create_setlist_file(name) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("Warning: Failed to create sync file: {}", e);
+            false
+        }
+    };
+
+    println!("Created setlist '{}' (ID: {})", name, setlist.id);
+    for (n, &i) in program.iter().enumerate() {
+        let item = add_to_setlist(conn, setlist.id, &scores[i].id.to_string())?;
+        if sync_file {
+            if let Err(e) = add_item_to_setlist_file(name, &item) {
+                eprintln!("Warning: Failed to update sync file: {}", e);
+            }
+        }
+        println!(
+            "  {:>2}. {}",
+            n + 1,
+            program_line(&scores[i], &candidates[i])
+        );
+    }
+    print_program_total(program.len(), scores.len(), total, options);
+
+    Ok(())
+}
+
+/// "Title (Composer; Genre; Key; 4:30)"
+fn program_line(score: &Score, candidate: &Candidate) -> String {
+    let details: Vec<String> = [
+        candidate.composer.clone(),
+        candidate.genre.clone(),
+        score.key.as_ref().map(|k| k.display()),
+        candidate.duration.map(format_duration),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if details.is_empty() {
+        score.title.clone()
+    } else {
+        format!("{} ({})", score.title, details.join("; "))
+    }
+}
+
+fn print_program_total(picked: usize, matched: usize, total: i32, options: &ProgramOptions) {
+    print!("{} of {} matching pieces", picked, matched);
+    match options.target_duration {
+        Some(target) => println!(
+            ", {} of {} target",
+            format_duration(total),
+            format_duration(target)
+        ),
+        None if total > 0 => println!(", {} (known durations)", format_duration(total)),
+        None => println!(),
+    }
+}
+
+/// Create one setlist per .txt file in a folder (one score title per line)
+fn import_dir(conn: &Connection, folder: &str, dry_run: bool, json: bool) -> Result<()> {
+    let mut files: Vec<_> = fs::read_dir(folder)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("txt"))
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        println!("No .txt files found in {}", folder);
+        return Ok(());
+    }
+
+    // Dry runs print a preview per setlist, so only show a bar for real imports
+    let progress = if dry_run {
+        Progress::hidden()
+    } else {
+        Progress::new(files.len(), "Importing setlists")
+    };
+    let mut summary = Summary::default();
+    let mut preview = DryRun::new(json);
+
+    for path in &files {
+        progress.inc();
+
+        let name = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(n) if !n.trim().is_empty() => n.trim().to_string(),
+            _ => continue,
+        };
+        if list_setlists(conn)?.iter().any(|s| s.title == name) {
+            summary.skip(&name, "setlist already exists");
+            continue;
+        }
+
+        let missing = import_title_file(
+            conn,
+            &name,
+            path,
+            dry_run,
+            &mut preview,
+            &mut summary,
+            &progress,
+        )?;
+        for (line_no, line) in missing {
+            summary.skip(
+                format!("{} line {}", name, line_no),
+                format!("unresolved title '{}'", line),
+            );
+        }
+    }
+    progress.finish();
+
+    if dry_run {
+        if !preview.is_json() {
+            println!();
+            summary.print("Would create");
+        }
+        preview.finish();
+    } else {
+        println!();
+        summary.print("Created");
+    }
+
+    Ok(())
+}
+
+/// Create the setlist `name` from a title-per-line file (or add it to
+/// `preview` for dry runs), returning the lines that match nothing
+fn import_title_file(
+    conn: &Connection,
+    name: &str,
+    path: &Path,
+    dry_run: bool,
+    preview: &mut DryRun,
+    summary: &mut Summary,
+    progress: &Progress,
+) -> Result<Vec<(usize, String)>> {
+    let mut resolved = Vec::new();
+    let mut missing = Vec::new();
+    for (line_no, line) in read_title_lines(path)? {
+        match resolve_score_or_bookmark(conn, &line) {
+            Ok(item) => resolved.push(item),
+            Err(_) => missing.push((line_no, line)),
+        }
+    }
+
+    if dry_run {
+        let mut patch = Patch::create("setlist", name);
+        for (i, item) in resolved.iter().enumerate() {
+            let title = match item {
+                ScoreOrBookmark::Score(score) => &score.title,
+                ScoreOrBookmark::Bookmark(bookmark) => &bookmark.title,
+            };
+            patch.change(&format!("item {}", i + 1), "", title.as_str());
+        }
+        preview.add(patch);
+    } else {
+        let setlist = create_setlist(conn, name)?;
+        let sync_file = match create_setlist_file(name) {
+            Ok(_) => true,
+            Err(e) => {
+                summary.error(name, format!("failed to create sync file: {}", e));
+                false
+            }
+        };
+
+        let count = resolved.len();
+        for item in resolved {
+            let item = add_resolved_to_setlist(conn, setlist.id, item)?;
+            if sync_file {
+                if let Err(e) = add_item_to_setlist_file(name, &item) {
+                    summary.error(&item.title, format!("failed to update sync file: {}", e));
+                }
+            }
+        }
+
+        progress.println(format!(
+            "Created setlist '{}' (ID: {}) with {} items{}",
+            name,
+            setlist.id,
+            count,
+            if sync_file {
+                " + sync file"
+            } else {
+                " (database only)"
+            }
+        ));
+    }
+
+    summary.updated += 1;
+    Ok(missing)
+}
+
+/// Read a title-per-line file, skipping blank lines and `#` comments
+fn read_title_lines(path: &Path) -> Result<Vec<(usize, String)>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim().to_string()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::key::MusicalKey;
+
+    fn entry(path: &str, pages: Option<(i32, i32)>) -> Score {
+        Score {
+            id: 1,
+            path: path.to_string(),
+            title: path.to_string(),
+            start_page: pages.map(|p| p.0),
+            end_page: pages.map(|p| p.1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_setlist_url() {
+        assert_eq!(
+            setlist_url("Sunday Gig", None),
+            "forscore://open?setlist=Sunday%20Gig"
+        );
+        assert_eq!(
+            setlist_url("Gig", Some(&entry("Prelude in C.pdf", None))),
+            "forscore://open?setlist=Gig&path=Prelude%20in%20C.pdf"
+        );
+        assert_eq!(
+            setlist_url("Gig", Some(&entry("Method Book.pdf", Some((5, 9))))),
+            "forscore://open?setlist=Gig&path=Method%20Book.pdf&page=5"
+        );
+    }
+
+    fn candidate(composer: &str, genre: &str, key: i32, duration: Option<i32>) -> Candidate {
+        Candidate {
+            composer: Some(composer.to_string()),
+            genre: Some(genre.to_string()),
+            key: Some(key),
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_key_changes() {
+        let keyed = |code: Option<i32>| Score {
+            key: code.and_then(MusicalKey::from_code),
+            ..entry("x.pdf", None)
+        };
+        // C Major -> A Minor -> Eb Major -> (none) -> A Major
+        let items = [
+            keyed(Some(100)),
+            keyed(Some(601)),
+            keyed(Some(210)),
+            keyed(None),
+            keyed(Some(600)),
+        ];
+        let changes = key_changes(&items);
+        assert_eq!(changes.len(), 4);
+
+        let relative = changes[0].as_ref().unwrap();
+        assert_eq!(relative.interval, "down a minor 3rd");
+        assert_eq!(relative.fifths, 0);
+        assert!(relative.awkward.is_none());
+
+        let tritone = changes[1].as_ref().unwrap();
+        assert_eq!(tritone.semitones, 6);
+        assert_eq!(tritone.awkward.as_deref(), Some("tritone"));
+
+        assert!(changes[2].is_none() && changes[3].is_none());
+    }
+
+    #[test]
+    fn test_check_page_range() {
+        assert!(check_page_range(3, 7, Some(10)).is_ok());
+        assert!(check_page_range(3, 3, None).is_ok());
+        assert!(check_page_range(7, 3, Some(10)).is_err());
+        assert!(check_page_range(0, 3, None).is_err());
+        assert!(check_page_range(3, 11, Some(10)).is_err());
+    }
+
+    #[test]
+    fn test_pick_program() {
+        let candidates = vec![
+            candidate("Bach", "Baroque", 100, Some(300)),
+            candidate("Bach", "Baroque", 100, Some(300)),
+            candidate("Bach", "Baroque", 500, Some(300)),
+            candidate("Chopin", "Romantic", 100, Some(600)),
+            candidate("Chopin", "Romantic", 601, None),
+        ];
+        let mut options = ProgramOptions {
+            max_per_composer: None,
+            target_duration: None,
+            spread_keys: false,
+        };
+        assert_eq!(pick_program(&candidates, &options), vec![0, 3, 1, 4, 2]);
+
+        options.max_per_composer = Some(2);
+        options.spread_keys = true;
+        assert_eq!(pick_program(&candidates, &options), vec![0, 4, 1, 3]);
+
+        options.target_duration = Some(1200);
+        assert_eq!(pick_program(&candidates, &options), vec![0, 3, 2]);
+    }
+
+    #[test]
+    fn test_compare_setlist_items() {
+        let item = |title: &str, composer: Option<&str>, difficulty: Option<i32>| {
+            let mut score = entry(title, None);
+            score.composers = composer.into_iter().map(str::to_string).collect();
+            score.difficulty = difficulty;
+            score
+        };
+        let items = vec![
+            item("Nocturne", Some("chopin"), Some(4)),
+            item("Invention", None, Some(2)),
+            item("Gavotte", Some("Bach"), None),
+            item("Air", Some("Bach"), Some(3)),
+        ];
+        let sorted = |by, reverse| {
+            let mut items = items.clone();
+            items.sort_by(|a, b| compare_setlist_items(a, b, by, reverse));
+            items.into_iter().map(|s| s.title).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sorted(SetlistSortField::Title, false),
+            ["Air", "Gavotte", "Invention", "Nocturne"]
+        );
+        assert_eq!(
+            sorted(SetlistSortField::Composer, false),
+            ["Air", "Gavotte", "Nocturne", "Invention"]
+        );
+        assert_eq!(
+            sorted(SetlistSortField::Difficulty, true),
+            ["Nocturne", "Air", "Invention", "Gavotte"]
+        );
+    }
+
+    #[test]
+    fn test_running_page_totals() {
+        let items: Vec<Score> = [Some(3), Some(4), None, Some(2)]
+            .into_iter()
+            .map(|pages| Score {
+                pages,
+                ..entry("Book.pdf", None)
+            })
+            .collect();
+        assert_eq!(
+            running_page_totals(&items),
+            vec![Some(3), Some(7), None, None]
+        );
+        assert!(running_page_totals(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45m").unwrap(), 2700);
+        assert_eq!(parse_duration("1h30m").unwrap(), 5400);
+        assert_eq!(parse_duration("90s").unwrap(), 90);
+        assert_eq!(parse_duration("45").unwrap(), 2700);
+        assert!(parse_duration("45x").is_err());
+        assert!(parse_duration("1h30").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_reason() {
+        let score = entry("Book.pdf", None);
+        let lesson = entry("Book.pdf", Some((5, 9)));
+
+        assert_eq!(
+            duplicate_reason("Book.pdf", None, &score),
+            Some("another score entry for the same PDF")
+        );
+        assert_eq!(
+            duplicate_reason("Book.pdf", None, &lesson),
+            Some("a bookmark of this score")
+        );
+        assert_eq!(
+            duplicate_reason("Book.pdf", Some((1, 4)), &score),
+            Some("the full score")
+        );
+        assert_eq!(
+            duplicate_reason("Book.pdf", Some((8, 12)), &lesson),
+            Some("an overlapping bookmark")
+        );
+        assert_eq!(duplicate_reason("Book.pdf", Some((1, 4)), &lesson), None);
+        assert_eq!(duplicate_reason("Other.pdf", None, &score), None);
+    }
+}
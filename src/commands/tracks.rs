@@ -0,0 +1,187 @@
+use crate::cli::TracksCommand;
+use crate::db::{documents_path, open_readonly};
+use crate::error::Result;
+use crate::itm::sync_folder_path;
+use crate::models::score::{search_scores, ScoreFilter};
+use crate::models::track::{is_audio_file, list_audio_links, AudioLink};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize)]
+struct ScoreRef {
+    id: i64,
+    title: String,
+}
+
+#[derive(Serialize)]
+struct MissingAudio {
+    score_id: i64,
+    score_title: String,
+    /// "track" or "recording"
+    kind: &'static str,
+    file: String,
+}
+
+#[derive(Serialize)]
+struct TracksReport {
+    without_audio: Vec<ScoreRef>,
+    missing_files: Vec<MissingAudio>,
+    /// Audio files in the Sync folder that no track or recording links to
+    orphaned_files: Vec<String>,
+}
+
+pub fn handle(cmd: TracksCommand) -> Result<()> {
+    match cmd {
+        TracksCommand::Report { json } => report(json),
+    }
+}
+
+/// Just the file name, since links may be stored with a folder
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(path)
+}
+
+/// Audio files among `files` that no link names
+fn orphaned_audio(files: &[String], links: &[AudioLink]) -> Vec<String> {
+    let linked: HashSet<&str> = links
+        .iter()
+        .filter_map(|l| l.file.as_deref())
+        .map(file_name)
+        .collect();
+    let mut orphaned: Vec<String> = files
+        .iter()
+        .filter(|f| is_audio_file(f) && !linked.contains(f.as_str()))
+        .cloned()
+        .collect();
+    orphaned.sort();
+    orphaned
+}
+
+fn file_names(dir: &Path) -> Vec<String> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| e.file_name().to_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn report(json: bool) -> Result<()> {
+    let conn = open_readonly()?;
+    let filter = ScoreFilter {
+        scores_only: true,
+        ..Default::default()
+    };
+    let scores = search_scores(&conn, &filter, usize::MAX)?;
+    let links = list_audio_links(&conn)?;
+
+    let sync_folder = sync_folder_path().ok();
+    let folders: Vec<PathBuf> = [sync_folder.clone(), documents_path().ok()]
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let with_audio: HashSet<i64> = links.iter().map(|l| l.score_id).collect();
+    let without_audio = scores
+        .iter()
+        .filter(|s| !with_audio.contains(&s.id))
+        .map(|s| ScoreRef {
+            id: s.id,
+            title: s.title.clone(),
+        })
+        .collect();
+
+    let title_of = |id: i64| {
+        scores
+            .iter()
+            .find(|s| s.id == id)
+            .map(|s| s.title.clone())
+            .unwrap_or_default()
+    };
+    let missing_files = links
+        .iter()
+        .filter_map(|link| {
+            let file = link.file.as_deref()?;
+            let found = folders
+                .iter()
+                .any(|dir| dir.join(file).exists() || dir.join(file_name(file)).exists());
+            (!found).then(|| MissingAudio {
+                score_id: link.score_id,
+                score_title: title_of(link.score_id),
+                kind: link.kind,
+                file: file.to_string(),
+            })
+        })
+        .collect();
+
+    let orphaned_files = match &sync_folder {
+        Some(dir) => orphaned_audio(&file_names(dir), &links),
+        None => Vec::new(),
+    };
+
+    let report = TracksReport {
+        without_audio,
+        missing_files,
+        orphaned_files,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("Scores without audio ({}):", report.without_audio.len());
+    for score in &report.without_audio {
+        println!("  {} (ID {})", score.title, score.id);
+    }
+    println!(
+        "\nLinked audio missing from disk ({}):",
+        report.missing_files.len()
+    );
+    for missing in &report.missing_files {
+        println!(
+            "  {} (ID {}): {} '{}'",
+            missing.score_title, missing.score_id, missing.kind, missing.file
+        );
+    }
+    println!(
+        "\nAudio files in the Sync folder nothing links to ({}):",
+        report.orphaned_files.len()
+    );
+    for file in &report.orphaned_files {
+        println!("  {}", file);
+    }
+    if folders.is_empty() {
+        eprintln!("\nNeither the Sync folder nor Documents was found, so no files were checked");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orphaned_audio() {
+        let link = |file: Option<&str>| AudioLink {
+            score_id: 1,
+            kind: "track",
+            title: None,
+            file: file.map(str::to_string),
+        };
+        let links = vec![link(Some("audio/Take 1.m4a")), link(None)];
+        let files: Vec<String> = ["Take 1.m4a", "Score.itm", "Old take.wav", "Backing.mp3"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            orphaned_audio(&files, &links),
+            vec!["Backing.mp3".to_string(), "Old take.wav".to_string()]
+        );
+    }
+}
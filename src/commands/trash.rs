@@ -0,0 +1,207 @@
+use crate::cli::TrashCommand;
+use crate::commands::setlists::setlist_file_items;
+use crate::db::{open_readonly, open_readwrite, warn_if_running};
+use crate::error::{ForScoreError, Result};
+use crate::itm::{add_bookmarks_to_itm, ItmNewBookmark};
+use crate::setlist_sync::add_item_to_setlist_file;
+use crate::touch::{touch_item, touch_setlist};
+use crate::trash::{list_bundles, load_bundle, remove_bundle, restore_rows, Bundle, TrashRow};
+use chrono::{DateTime, Local, Utc};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::fs;
+
+#[derive(Serialize)]
+struct TrashEntry {
+    id: String,
+    kind: String,
+    item_id: i64,
+    title: String,
+    deleted_at: String,
+    expires_at: String,
+    expired: bool,
+}
+
+pub fn handle(cmd: TrashCommand) -> Result<()> {
+    match cmd {
+        TrashCommand::Ls { json } => ls(json),
+        TrashCommand::Restore { id } => restore(&id),
+    }
+}
+
+fn local_date(date: DateTime<Utc>) -> String {
+    DateTime::<Local>::from(date)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn ls(json: bool) -> Result<()> {
+    let now = Utc::now();
+    let bundles = list_bundles()?;
+    let entries: Vec<TrashEntry> = bundles
+        .iter()
+        .map(|b| TrashEntry {
+            id: b.id(),
+            kind: b.kind.clone(),
+            item_id: b.item_id,
+            title: b.title.clone(),
+            deleted_at: b.deleted_at.to_rfc3339(),
+            expires_at: b.expires_at().to_rfc3339(),
+            expired: b.is_expired(now),
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("Trash is empty");
+        return Ok(());
+    }
+    for bundle in &bundles {
+        let until = if bundle.is_expired(now) {
+            "expired".to_string()
+        } else {
+            format!("until {}", local_date(bundle.expires_at()))
+        };
+        println!(
+            "{}  {} '{}' (ID {}), deleted {}, restorable {}",
+            bundle.id(),
+            bundle.kind,
+            bundle.title,
+            bundle.item_id,
+            local_date(bundle.deleted_at),
+            until
+        );
+    }
+    Ok(())
+}
+
+fn restore(id: &str) -> Result<()> {
+    let bundle = load_bundle(id)?;
+    if bundle.is_expired(Utc::now()) {
+        return Err(ForScoreError::Other(format!(
+            "'{}' expired on {} and can no longer be restored",
+            bundle.title,
+            local_date(bundle.expires_at())
+        )));
+    }
+    if bundle.kind == "setlist" && setlist_named(&open_readonly()?, &bundle.title)? {
+        return Err(ForScoreError::Other(format!(
+            "There's a setlist named '{}' again; rename it before restoring this one",
+            bundle.title
+        )));
+    }
+
+    warn_if_running();
+    let conn = open_readwrite()?;
+    let dropped = restore_rows(&conn, &bundle.rows)?;
+    let restored: Vec<&TrashRow> = bundle
+        .rows
+        .iter()
+        .filter(|row| !dropped.contains(row))
+        .collect();
+
+    for row in &restored {
+        match (row.table.as_str(), row.integer("Z_PK")) {
+            ("ZITEM", Some(id)) => touch_item(&conn, id)?,
+            ("ZSETLIST", Some(id)) => touch_setlist(&conn, id)?,
+            ("ZCYLON", _) => {
+                if let Some(setlist_id) = row.integer("ZSETLIST") {
+                    touch_setlist(&conn, setlist_id)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    println!("Restored {} '{}'", bundle.kind, bundle.title);
+
+    for file in &bundle.files {
+        if file.exists() {
+            eprintln!(
+                "Warning: {} exists again, so the trashed copy wasn't restored",
+                file.display()
+            );
+            continue;
+        }
+        match fs::copy(bundle.stored_file(file)?, file) {
+            Ok(_) => println!("  Restored {}", file.display()),
+            Err(e) => eprintln!("Warning: Failed to restore {}: {}", file.display(), e),
+        }
+    }
+
+    match bundle.kind.as_str() {
+        "bookmark" => restore_itm_bookmark(&bundle),
+        "score" => restore_setlist_entries(&conn, &restored)?,
+        _ => {}
+    }
+
+    if !dropped.is_empty() {
+        println!(
+            "  {} link(s) left out: what they linked to was deleted since",
+            dropped.len()
+        );
+    }
+    remove_bundle(&bundle)?;
+    Ok(())
+}
+
+fn setlist_named(conn: &Connection, title: &str) -> Result<bool> {
+    Ok(conn
+        .query_row("SELECT 1 FROM ZSETLIST WHERE ZTITLE = ?", [title], |_| {
+            Ok(())
+        })
+        .optional()?
+        .is_some())
+}
+
+/// Put a restored bookmark back into its score's ITM file
+fn restore_itm_bookmark(bundle: &Bundle) {
+    let Some(row) = bundle.rows.iter().find(|r| r.table == "ZITEM") else {
+        return;
+    };
+    let (Some(path), Some(uuid)) = (row.text("ZPATH"), row.text("ZUUID")) else {
+        return;
+    };
+    let first_page = row.integer("ZSTARTPAGE").unwrap_or(1);
+    let bookmark = ItmNewBookmark {
+        title: bundle.title.clone(),
+        identifier: uuid.to_string(),
+        first_page,
+        last_page: row.integer("ZENDPAGE").unwrap_or(first_page),
+    };
+    match add_bookmarks_to_itm(path, &[bookmark]) {
+        Ok(true) => println!("  + ITM"),
+        Ok(false) => {}
+        Err(e) => eprintln!("Warning: Failed to update ITM file: {}", e),
+    }
+}
+
+/// Put a restored score's setlist entries back into the .set sync files
+fn restore_setlist_entries(conn: &Connection, restored: &[&TrashRow]) -> Result<()> {
+    for row in restored.iter().filter(|r| r.table == "ZCYLON") {
+        let (Some(setlist_id), Some(uuid)) = (row.integer("ZSETLIST"), row.text("ZUUID")) else {
+            continue;
+        };
+        let setlist: String = conn.query_row(
+            "SELECT ZTITLE FROM ZSETLIST WHERE Z_PK = ?",
+            [setlist_id],
+            |r| r.get(0),
+        )?;
+        let Some(item) = setlist_file_items(conn, setlist_id)?
+            .into_iter()
+            .find(|i| i.identifier == uuid)
+        else {
+            continue;
+        };
+        match add_item_to_setlist_file(&setlist, &item) {
+            Ok(_) => println!("  Back in setlist '{}' + sync file", setlist),
+            Err(e) => {
+                println!("  Back in setlist '{}'", setlist);
+                eprintln!("Warning: Failed to update setlist sync file: {}", e);
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,100 +1,278 @@
-use crate::db::{database_path, open_readonly};
-use crate::error::Result;
-use chrono::{DateTime, Local};
+use crate::db::{database_path, documents_path, entity, open_readonly};
+use crate::error::{ForScoreError, Result};
+use crate::itm::{itm_path_for_score, sync_folder_path};
+use crate::models::score::{search_scores, ScoreFilter};
+use crate::output::{output, SyncStateFile};
+use crate::platform::{
+    container_candidates, db_override, icloud_drive_candidate, is_forscore_running,
+    preferences_path, require_macos, run_applescript, sync_dir_override,
+};
+use crate::schema::{known_schema, known_version, Schema};
+use crate::sync_state::{file_kind, read_sync_preferences, read_sync_state};
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
 use std::fs;
 use std::path::PathBuf;
-use std::process::Command;
 
-/// Show library statistics
-pub fn info() -> Result<()> {
+/// Library statistics, as printed by `info`
+#[derive(Serialize)]
+struct LibraryStats {
+    database: PathBuf,
+    schema_matches: bool,
+    schema_version: &'static str,
+    scores: i64,
+    bookmarks: i64,
+    pages: i64,
+    setlists: i64,
+    libraries: i64,
+    composers: i64,
+    genres: i64,
+    tracks: i64,
+    completeness: Completeness,
+}
+
+/// How many scores have each kind of metadata, and what percentage that is
+#[derive(Serialize)]
+struct Completeness {
+    rating: Coverage,
+    difficulty: Coverage,
+    key: Coverage,
+    composer: Coverage,
+}
+
+#[derive(Serialize)]
+struct Coverage {
+    count: i64,
+    percent: f64,
+}
+
+impl Coverage {
+    /// An empty library is complete: nothing is missing
+    fn new(count: i64, total: i64) -> Self {
+        let percent = if total == 0 {
+            100.0
+        } else {
+            100.0 * count as f64 / total as f64
+        };
+        Self { count, percent }
+    }
+}
+
+/// Minimum completeness percentages for `info`, per kind of metadata
+#[derive(Debug, Default)]
+pub struct Thresholds {
+    pub rating: Option<f64>,
+    pub key: Option<f64>,
+    pub composer: Option<f64>,
+}
+
+impl Thresholds {
+    /// `--min-complete` for each kind, unless a kind has its own minimum
+    pub fn new(
+        all: Option<f64>,
+        rating: Option<f64>,
+        key: Option<f64>,
+        composer: Option<f64>,
+    ) -> Self {
+        Self {
+            rating: rating.or(all),
+            key: key.or(all),
+            composer: composer.or(all),
+        }
+    }
+
+    /// The kinds below their minimum, e.g. "key 82.5% < 90%"
+    fn failures(&self, completeness: &Completeness) -> Vec<String> {
+        [
+            ("rating", self.rating, &completeness.rating),
+            ("key", self.key, &completeness.key),
+            ("composer", self.composer, &completeness.composer),
+        ]
+        .into_iter()
+        .filter_map(|(name, min, coverage)| {
+            let min = min?;
+            (coverage.percent < min)
+                .then(|| format!("{} {:.1}% < {}%", name, coverage.percent, min))
+        })
+        .collect()
+    }
+}
+
+fn count(conn: &Connection, sql: &str, params: impl rusqlite::Params) -> Result<i64> {
+    Ok(conn.query_row(sql, params, |row| row.get(0))?)
+}
+
+fn library_stats(conn: &Connection) -> Result<LibraryStats> {
+    let score = entity::score();
+    let scores = count(conn, "SELECT COUNT(*) FROM ZITEM WHERE Z_ENT = ?", [score])?;
+    let with = |condition: &str| -> Result<Coverage> {
+        let sql = format!(
+            "SELECT COUNT(*) FROM ZITEM i WHERE i.Z_ENT = ? AND {}",
+            condition
+        );
+        Ok(Coverage::new(count(conn, &sql, [score])?, scores))
+    };
+
+    Ok(LibraryStats {
+        database: database_path()?,
+        schema_matches: Schema::read(conn)?.diff(&known_schema()).is_empty(),
+        schema_version: known_version(),
+        scores,
+        bookmarks: count(
+            conn,
+            "SELECT COUNT(*) FROM ZITEM WHERE Z_ENT = ?",
+            [entity::bookmark()],
+        )?,
+        pages: count(conn, "SELECT COUNT(*) FROM ZPAGE", [])?,
+        setlists: count(conn, "SELECT COUNT(*) FROM ZSETLIST", [])?,
+        libraries: count(conn, "SELECT COUNT(*) FROM ZLIBRARY", [])?,
+        composers: count(
+            conn,
+            "SELECT COUNT(*) FROM ZMETA WHERE Z_ENT = ?",
+            [entity::composer()],
+        )?,
+        genres: count(
+            conn,
+            "SELECT COUNT(*) FROM ZMETA WHERE Z_ENT = ?",
+            [entity::genre()],
+        )?,
+        tracks: count(conn, "SELECT COUNT(*) FROM ZTRACK", [])?,
+        completeness: Completeness {
+            rating: with("i.ZRATING IS NOT NULL AND i.ZRATING > 0")?,
+            difficulty: with("i.ZDIFFICULTY IS NOT NULL AND i.ZDIFFICULTY > 0")?,
+            key: with("i.ZKEY IS NOT NULL AND i.ZKEY > 0")?,
+            composer: with("EXISTS (SELECT 1 FROM Z_4COMPOSERS c WHERE c.Z_4ITEMS1 = i.Z_PK)")?,
+        },
+    })
+}
+
+/// Show library statistics; fails if metadata completeness is below any of
+/// the thresholds (after printing them)
+pub fn info(json: bool, thresholds: &Thresholds) -> Result<()> {
     let conn = open_readonly()?;
+    let stats = library_stats(&conn)?;
 
-    let score_count: i64 =
-        conn.query_row("SELECT COUNT(*) FROM ZITEM WHERE Z_ENT = 6", [], |row| {
-            row.get(0)
-        })?;
-
-    let bookmark_count: i64 =
-        conn.query_row("SELECT COUNT(*) FROM ZITEM WHERE Z_ENT = 5", [], |row| {
-            row.get(0)
-        })?;
-
-    let setlist_count: i64 =
-        conn.query_row("SELECT COUNT(*) FROM ZSETLIST", [], |row| row.get(0))?;
-
-    let library_count: i64 =
-        conn.query_row("SELECT COUNT(*) FROM ZLIBRARY", [], |row| row.get(0))?;
-
-    let composer_count: i64 =
-        conn.query_row("SELECT COUNT(*) FROM ZMETA WHERE Z_ENT = 10", [], |row| {
-            row.get(0)
-        })?;
-
-    let genre_count: i64 =
-        conn.query_row("SELECT COUNT(*) FROM ZMETA WHERE Z_ENT = 12", [], |row| {
-            row.get(0)
-        })?;
-
-    let page_count: i64 = conn.query_row("SELECT COUNT(*) FROM ZPAGE", [], |row| row.get(0))?;
-
-    let track_count: i64 = conn.query_row("SELECT COUNT(*) FROM ZTRACK", [], |row| row.get(0))?;
-
-    // Scores with ratings
-    let rated_count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM ZITEM WHERE Z_ENT = 6 AND ZRATING IS NOT NULL AND ZRATING > 0",
-        [],
-        |row| row.get(0),
-    )?;
-
-    // Scores with difficulty
-    let difficulty_count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM ZITEM WHERE Z_ENT = 6 AND ZDIFFICULTY IS NOT NULL AND ZDIFFICULTY > 0",
-        [],
-        |row| row.get(0),
-    )?;
-
-    // Scores with key
-    let key_count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM ZITEM WHERE Z_ENT = 6 AND ZKEY IS NOT NULL AND ZKEY > 0",
-        [],
-        |row| row.get(0),
-    )?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print_stats(&stats);
+    }
 
-    let db_path = database_path()?;
+    let failures = thresholds.failures(&stats.completeness);
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(ForScoreError::Other(format!(
+            "Metadata completeness below the minimum: {}",
+            failures.join(", ")
+        )))
+    }
+}
 
+fn print_stats(stats: &LibraryStats) {
     println!("forScore Library Statistics");
     println!("===========================");
     println!();
-    println!("Database: {}", db_path.display());
+    println!("Database: {}", stats.database.display());
+    println!(
+        "Schema:   {} forScore {}",
+        if stats.schema_matches {
+            "matches"
+        } else {
+            "differs from"
+        },
+        stats.schema_version
+    );
     println!();
     println!("Content:");
-    println!("  Scores:     {:>6}", score_count);
-    println!("  Bookmarks:  {:>6}", bookmark_count);
-    println!("  Pages:      {:>6}", page_count);
-    println!("  Setlists:   {:>6}", setlist_count);
-    println!("  Libraries:  {:>6}", library_count);
+    println!("  Scores:     {:>6}", stats.scores);
+    println!("  Bookmarks:  {:>6}", stats.bookmarks);
+    println!("  Pages:      {:>6}", stats.pages);
+    println!("  Setlists:   {:>6}", stats.setlists);
+    println!("  Libraries:  {:>6}", stats.libraries);
     println!();
     println!("Metadata:");
-    println!("  Composers:  {:>6}", composer_count);
-    println!("  Genres:     {:>6}", genre_count);
-    println!("  Tracks:     {:>6}", track_count);
+    println!("  Composers:  {:>6}", stats.composers);
+    println!("  Genres:     {:>6}", stats.genres);
+    println!("  Tracks:     {:>6}", stats.tracks);
     println!();
     println!("Scores with metadata:");
-    println!(
-        "  With rating:     {:>6} ({:.1}%)",
-        rated_count,
-        100.0 * rated_count as f64 / score_count as f64
-    );
-    println!(
-        "  With difficulty: {:>6} ({:.1}%)",
-        difficulty_count,
-        100.0 * difficulty_count as f64 / score_count as f64
-    );
-    println!(
-        "  With key:        {:>6} ({:.1}%)",
-        key_count,
-        100.0 * key_count as f64 / score_count as f64
-    );
+    let completeness = &stats.completeness;
+    for (name, coverage) in [
+        ("rating", &completeness.rating),
+        ("difficulty", &completeness.difficulty),
+        ("key", &completeness.key),
+        ("composer", &completeness.composer),
+    ] {
+        println!(
+            "  {:<16} {:>6} ({:.1}%)",
+            format!("With {}:", name),
+            coverage.count,
+            coverage.percent
+        );
+    }
+}
+
+/// Show where the database, Sync folder and Documents folder were found,
+/// listing every place that was checked
+pub fn paths() -> Result<()> {
+    let mark = |found: bool| if found { "found" } else { "-" };
+
+    println!("Database:");
+    match db_override() {
+        Some(db) => println!("  {} (from --db)", db.display()),
+        None => {
+            let candidates = container_candidates()?;
+            for c in &candidates {
+                println!(
+                    "  {:<6} {:<21} {}",
+                    mark(c.found),
+                    c.label,
+                    c.path.display()
+                );
+            }
+            match candidates.iter().find(|c| c.found) {
+                Some(c) => println!("  Using the {} (the first with a library.4sl)", c.label),
+                None => println!("  No library.4sl found; is forScore installed? (or use --db)"),
+            }
+        }
+    }
+
+    println!();
+    println!("Sync folder:");
+    match sync_dir_override() {
+        Some(dir) => println!("  {} (from --sync-dir)", dir.display()),
+        None => {
+            let next_to_db = preferences_path()?.join("Sync");
+            println!(
+                "  {:<6} {:<21} {}",
+                mark(next_to_db.exists()),
+                "Next to the database",
+                next_to_db.display()
+            );
+            if db_override().is_none() {
+                let icloud = icloud_drive_candidate()?;
+                println!(
+                    "  {:<6} {:<21} {}",
+                    mark(icloud.found),
+                    icloud.label,
+                    icloud.path.display()
+                );
+            }
+            match sync_folder_path() {
+                Ok(path) => println!("  Using {}", path.display()),
+                Err(_) => println!("  No Sync folder found (ITM files won't be updated)"),
+            }
+        }
+    }
+
+    println!();
+    println!("Documents (PDFs):");
+    match documents_path() {
+        Ok(path) => println!("  {}", path.display()),
+        Err(_) => println!("  Not found (expected next to the database's Library folder)"),
+    }
 
     Ok(())
 }
@@ -133,83 +311,51 @@ pub fn backup(output: Option<String>) -> Result<()> {
 
 /// Show iCloud sync status
 pub fn sync_status() -> Result<()> {
-    let plist_path = dirs::home_dir()
-        .unwrap()
-        .join("Library/Containers/com.mgsdevelopment.forscore/Data/Library/Preferences/com.mgsdevelopment.forscore.plist");
+    let plist_path = preferences_path()?.join("com.mgsdevelopment.forscore.plist");
 
     if !plist_path.exists() {
         println!("forScore preferences not found");
         return Ok(());
     }
 
-    // Use plutil to read plist values
-    let output = Command::new("plutil")
-        .args(["-p", plist_path.to_str().unwrap()])
-        .output()?;
-
-    let plist_str = String::from_utf8_lossy(&output.stdout);
-
-    // Parse sync values from plist output
-    let mut sync_enabled = false;
-    let mut last_sync_date: Option<String> = None;
-    let mut last_sync_error: i32 = 0;
-
-    for line in plist_str.lines() {
-        if line.contains("&SYNC;syncEnabled") {
-            sync_enabled = line.contains("true");
-        } else if line.contains("&SYNC;lastSyncDate") {
-            // Extract date: "  \"&SYNC;lastSyncDate\" => 2025-12-24 15:02:11 +0000"
-            if let Some(pos) = line.find("=>") {
-                last_sync_date = Some(line[pos + 3..].trim().to_string());
-            }
-        } else if line.contains("&SYNC;lastSyncErrorCode") {
-            if let Some(pos) = line.find("=>") {
-                if let Ok(code) = line[pos + 3..].trim().parse::<i32>() {
-                    last_sync_error = code;
-                }
-            }
-        }
-    }
+    let prefs = read_sync_preferences(&plist_path)?;
 
     println!("forScore iCloud Sync Status");
     println!("===========================");
     println!();
-    println!("Sync Enabled: {}", if sync_enabled { "Yes" } else { "No" });
-
-    if let Some(date_str) = last_sync_date {
-        // Parse the date string and convert to local time
-        // Format: "2025-12-24 15:02:11 +0000"
-        if let Ok(utc_time) = DateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S %z") {
-            let local_time: DateTime<Local> = utc_time.into();
-            let now = Local::now();
-            let duration = now.signed_duration_since(local_time);
-
-            let ago = if duration.num_days() > 0 {
-                format!("{} days ago", duration.num_days())
-            } else if duration.num_hours() > 0 {
-                format!("{} hours ago", duration.num_hours())
-            } else if duration.num_minutes() > 0 {
-                format!("{} minutes ago", duration.num_minutes())
-            } else {
-                "just now".to_string()
-            };
+    println!(
+        "Sync Enabled: {}",
+        if prefs.sync_enabled { "Yes" } else { "No" }
+    );
 
-            println!(
-                "Last Sync:    {} ({})",
-                local_time.format("%Y-%m-%d %H:%M:%S"),
-                ago
-            );
+    if let Some(sync_time) = prefs.last_sync_time() {
+        let local_time: DateTime<Local> = sync_time.into();
+        let now = Local::now();
+        let duration = now.signed_duration_since(local_time);
+
+        let ago = if duration.num_days() > 0 {
+            format!("{} days ago", duration.num_days())
+        } else if duration.num_hours() > 0 {
+            format!("{} hours ago", duration.num_hours())
+        } else if duration.num_minutes() > 0 {
+            format!("{} minutes ago", duration.num_minutes())
         } else {
-            println!("Last Sync:    {}", date_str);
-        }
+            "just now".to_string()
+        };
+
+        println!(
+            "Last Sync:    {} ({})",
+            local_time.format("%Y-%m-%d %H:%M:%S"),
+            ago
+        );
     } else {
         println!("Last Sync:    Never");
     }
 
-    if last_sync_error == 0 {
+    if prefs.last_sync_error_code == 0 {
         println!("Status:       OK");
     } else {
-        println!("Status:       Error (code {})", last_sync_error);
+        println!("Status:       Error (code {})", prefs.last_sync_error_code);
     }
 
     println!();
@@ -221,46 +367,23 @@ pub fn sync_status() -> Result<()> {
 
 /// Show sync log (recently synced files)
 pub fn sync_log(limit: usize) -> Result<()> {
-    let state_path = dirs::home_dir()
-        .unwrap()
-        .join("Library/Containers/com.mgsdevelopment.forscore/Data/Library/Preferences/Sync/.syncFolderState");
+    let state_path = sync_folder_path()?.join(".syncFolderState");
 
     if !state_path.exists() {
         println!("No sync state file found");
         return Ok(());
     }
 
-    // Use plutil to convert plist to JSON for easier parsing
-    let output = Command::new("plutil")
-        .args(["-convert", "json", "-o", "-", state_path.to_str().unwrap()])
-        .output()?;
-
-    let json_str = String::from_utf8_lossy(&output.stdout);
-
-    // Parse JSON array
-    let entries: Vec<serde_json::Value> = serde_json::from_str(&json_str).unwrap_or_default();
+    let entries = read_sync_state(&state_path)?;
 
     if entries.is_empty() {
         println!("No sync entries found");
         return Ok(());
     }
 
-    // Convert to sortable structs
     let mut sync_entries: Vec<(f64, String, i64)> = entries
         .iter()
-        .filter_map(|e| {
-            let modified = e.get("modified")?.as_f64()?;
-            let path = e.get("path")?.as_str()?;
-            let size = e.get("fileSize")?.as_i64().unwrap_or(0);
-
-            // Clean up path - remove {%SYNC_DIR%}/ prefix and URL decode
-            let clean_path = path.strip_prefix("{%SYNC_DIR%}/").unwrap_or(path);
-            let decoded = urlencoding::decode(clean_path)
-                .unwrap_or_else(|_| clean_path.into())
-                .to_string();
-
-            Some((modified, decoded, size))
-        })
+        .map(|e| (e.modified, e.display_path(), e.file_size))
         .collect();
 
     // Sort by modification time descending (most recent first)
@@ -312,12 +435,144 @@ pub fn sync_log(limit: usize) -> Result<()> {
     Ok(())
 }
 
+/// `sync export-state`: every file in .syncFolderState, by path, in the
+/// `--format` given (a table by default)
+pub fn sync_export_state(json: bool) -> Result<()> {
+    let state_path = sync_folder_path()?.join(".syncFolderState");
+    if !state_path.exists() {
+        return Err(ForScoreError::Other(format!(
+            "No sync state file at {}",
+            state_path.display()
+        )));
+    }
+
+    let mut files: Vec<SyncStateFile> = read_sync_state(&state_path)?
+        .iter()
+        .map(|entry| {
+            let path = entry.display_path();
+            SyncStateFile {
+                kind: file_kind(&path),
+                path,
+                size: entry.file_size,
+                modified: entry
+                    .modified_time()
+                    .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            }
+        })
+        .collect();
+    // By path, so exports taken at different times diff cleanly
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    output(&files, json);
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct StaleScore {
+    id: i64,
+    title: String,
+    itm_path: String,
+    db_modified: Option<String>,
+    itm_modified: String,
+    /// How far the ITM is ahead; None when the row has no modified date
+    ahead_seconds: Option<i64>,
+}
+
+/// Seconds `itm` is ahead of `db`, if that's more than `threshold`. A row
+/// without a modified date counts as behind any ITM
+fn itm_lead(db: Option<DateTime<Utc>>, itm: DateTime<Utc>, threshold: i64) -> Option<Option<i64>> {
+    match db {
+        None => Some(None),
+        Some(db) => {
+            let ahead = itm.signed_duration_since(db).num_seconds();
+            (ahead > threshold).then_some(Some(ahead))
+        }
+    }
+}
+
+/// "45s", "12m", "3h 5m", "2d 4h"
+fn describe_lead(seconds: i64) -> String {
+    let (d, h, m) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    if d > 0 {
+        format!("{}d {}h", d, h)
+    } else if h > 0 {
+        format!("{}h {}m", h, m)
+    } else if m > 0 {
+        format!("{}m", m)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// List scores whose ITM file is newer than their database row
+pub fn sync_stale_db(threshold: i64, json: bool) -> Result<()> {
+    let conn = open_readonly()?;
+    let filter = ScoreFilter {
+        scores_only: true,
+        ..Default::default()
+    };
+    let scores = search_scores(&conn, &filter, usize::MAX)?;
+
+    let mut stale = Vec::new();
+    for score in scores {
+        let Ok(itm_path) = itm_path_for_score(&score.path) else {
+            continue;
+        };
+        let Some(itm_modified) = fs::metadata(&itm_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(DateTime::<Utc>::from)
+        else {
+            continue;
+        };
+        let Some(ahead_seconds) = itm_lead(score.modified, itm_modified, threshold) else {
+            continue;
+        };
+        stale.push(StaleScore {
+            id: score.id,
+            title: score.title,
+            itm_path: itm_path.display().to_string(),
+            db_modified: score.modified.map(|d| d.to_rfc3339()),
+            itm_modified: itm_modified.to_rfc3339(),
+            ahead_seconds,
+        });
+    }
+    // Unknown lead first, then the furthest behind
+    stale.sort_by_key(|s| std::cmp::Reverse(s.ahead_seconds.unwrap_or(i64::MAX)));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stale)?);
+        return Ok(());
+    }
+    if stale.is_empty() {
+        println!(
+            "No score's ITM is more than {}s newer than the database",
+            threshold
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} score(s) with ITM changes the database doesn't have yet:",
+        stale.len()
+    );
+    for s in &stale {
+        let ahead = match s.ahead_seconds {
+            Some(secs) => format!("ITM {} newer", describe_lead(secs)),
+            None => "no database modified date".to_string(),
+        };
+        println!("  {} (ID {}): {}", s.title, s.id, ahead);
+    }
+    println!();
+    println!("Let forScore sync and import these before editing them here");
+
+    Ok(())
+}
+
 /// Trigger a sync via UI automation
 pub fn sync_trigger() -> Result<()> {
     // First check if forScore is running
-    let check = Command::new("pgrep").args(["-x", "forScore"]).output()?;
-
-    if !check.status.success() {
+    require_macos("Triggering a sync")?;
+    if !is_forscore_running() {
         eprintln!("forScore is not running. Please start forScore first.");
         return Ok(());
     }
@@ -358,7 +613,7 @@ return "ok"
 
     println!("Triggering forScore sync...");
 
-    let output = Command::new("osascript").arg("-e").arg(script).output()?;
+    let output = run_applescript(script)?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -383,3 +638,40 @@ return "ok"
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_itm_lead() {
+        let db = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let later = |secs: i64| DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+        assert_eq!(itm_lead(Some(db), later(30), 60), None);
+        assert_eq!(itm_lead(Some(db), later(-600), 60), None);
+        assert_eq!(itm_lead(Some(db), later(3600), 60), Some(Some(3600)));
+        assert_eq!(itm_lead(None, later(0), 60), Some(None));
+        assert_eq!(describe_lead(45), "45s");
+        assert_eq!(describe_lead(3900), "1h 5m");
+        assert_eq!(describe_lead(2 * 86400 + 4 * 3600), "2d 4h");
+    }
+
+    #[test]
+    fn test_threshold_failures() {
+        let completeness = Completeness {
+            rating: Coverage::new(9, 10),
+            difficulty: Coverage::new(0, 10),
+            key: Coverage::new(8, 10),
+            composer: Coverage::new(10, 10),
+        };
+        let thresholds = Thresholds::new(Some(90.0), None, Some(75.0), None);
+        assert!(thresholds.failures(&completeness).is_empty());
+
+        let thresholds = Thresholds::new(Some(95.0), None, None, Some(100.0));
+        assert_eq!(
+            thresholds.failures(&completeness),
+            vec!["rating 90.0% < 95%", "key 80.0% < 95%"]
+        );
+        assert_eq!(Coverage::new(0, 0).percent, 100.0);
+    }
+}
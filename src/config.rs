@@ -0,0 +1,200 @@
+//! User configuration
+//!
+//! Read from `config.toml` in the platform config directory
+//! (`~/Library/Application Support/forscore-cli` on macOS,
+//! `~/.config/forscore-cli` on Linux). Every section is optional.
+
+use crate::error::{ForScoreError, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use toml_edit::{DocumentMut, Item, Table};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub hooks: HooksConfig,
+    /// Saved searches: name to query expression (see `query`), run with
+    /// `scores search --saved <name>`
+    pub searches: BTreeMap<String, String>,
+    pub output: OutputConfig,
+    /// Short names for scores, bookmarks and setlists: alias to the
+    /// identifier it stands for (see `alias`)
+    pub aliases: BTreeMap<String, String>,
+    pub sort_title: SortTitleConfig,
+}
+
+/// Table output defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Columns for score listings, e.g. ["id", "title", "genre", "added"]
+    /// (see `--columns`)
+    pub columns: Option<Vec<String>>,
+}
+
+/// How sort titles are derived from titles (see `sort_title`); all off
+/// means the lowercased title
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SortTitleConfig {
+    /// Drop a leading article ("The", "Le", "Die"...)
+    pub strip_articles: bool,
+    /// The articles to drop, replacing the built-in list
+    pub articles: Option<Vec<String>>,
+    /// Leave out punctuation
+    pub ignore_punctuation: bool,
+    /// Move an opus number ("Op. 9 No. 2") to the end
+    pub opus_last: bool,
+}
+
+/// Shell commands run after the CLI changes the library (see `hooks`)
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// After scores or bookmarks are edited
+    pub post_edit: Option<String>,
+    /// After setlists are created, changed or deleted
+    pub post_setlist_change: Option<String>,
+}
+
+/// Get the path to the config file
+pub fn config_path() -> Result<PathBuf> {
+    let dir = dirs::config_dir()
+        .ok_or_else(|| ForScoreError::Other("Cannot find config directory".into()))?;
+    Ok(dir.join("forscore-cli").join("config.toml"))
+}
+
+/// Load the config file (defaults if there isn't one)
+pub fn load_config() -> Result<Config> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+
+    let text = std::fs::read_to_string(&path)?;
+    parse_config(&text)
+        .map_err(|e| ForScoreError::Other(format!("Invalid config {}: {}", path.display(), e)))
+}
+
+fn parse_config(text: &str) -> std::result::Result<Config, toml::de::Error> {
+    toml::from_str(text)
+}
+
+/// The identifier an alias stands for. Config errors are reported by the
+/// commands that need the rest of the config, so here they mean no aliases
+pub fn alias_target(name: &str) -> Option<&'static str> {
+    static ALIASES: OnceLock<BTreeMap<String, String>> = OnceLock::new();
+    ALIASES
+        .get_or_init(|| load_config().map(|c| c.aliases).unwrap_or_default())
+        .get(name)
+        .map(String::as_str)
+}
+
+/// Set (or with `target` None, remove) an alias in the config file, keeping
+/// the rest of the file as it is. Returns whether anything changed
+pub fn write_alias(name: &str, target: Option<&str>) -> Result<bool> {
+    let path = config_path()?;
+    let text = if path.exists() {
+        std::fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+    let Some(updated) = edit_alias(&text, name, target)
+        .map_err(|e| ForScoreError::Other(format!("Invalid config {}: {}", path.display(), e)))?
+    else {
+        return Ok(false);
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, updated)?;
+    Ok(true)
+}
+
+/// The config text with an alias set or removed, or None if it wouldn't change
+fn edit_alias(
+    text: &str,
+    name: &str,
+    target: Option<&str>,
+) -> std::result::Result<Option<String>, toml_edit::TomlError> {
+    let mut doc: DocumentMut = text.parse()?;
+    let aliases = doc
+        .entry("aliases")
+        .or_insert_with(|| Item::Table(Table::new()));
+    let Some(aliases) = aliases.as_table_mut() else {
+        return Ok(None);
+    };
+    let changed = match target {
+        Some(target) => {
+            let unchanged = aliases.get(name).and_then(Item::as_str) == Some(target);
+            aliases.insert(name, toml_edit::value(target));
+            !unchanged
+        }
+        None => aliases.remove(name).is_some(),
+    };
+    if aliases.is_empty() {
+        doc.remove("aliases");
+    }
+    Ok(changed.then(|| doc.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config("[hooks]\npost_edit = \"notify-send edited\"\n").unwrap();
+        assert_eq!(
+            config.hooks.post_edit.as_deref(),
+            Some("notify-send edited")
+        );
+        assert!(config.hooks.post_setlist_change.is_none());
+
+        assert!(parse_config("").unwrap().hooks.post_edit.is_none());
+        assert!(parse_config("[hooks]\npost_delete = \"x\"\n").is_err());
+
+        let config =
+            parse_config("[searches]\nwarmups = \"difficulty<=2 AND rating>=5\"\n").unwrap();
+        assert_eq!(
+            config.searches.get("warmups").map(String::as_str),
+            Some("difficulty<=2 AND rating>=5")
+        );
+
+        let config = parse_config("[aliases]\nbb5 = \"Brahms 5 - Horn 1\"\n").unwrap();
+        assert_eq!(
+            config.aliases.get("bb5").map(String::as_str),
+            Some("Brahms 5 - Horn 1")
+        );
+
+        let config = parse_config("[output]\ncolumns = [\"id\", \"title\", \"added\"]\n").unwrap();
+        assert_eq!(
+            config.output.columns,
+            Some(vec!["id".into(), "title".into(), "added".into()])
+        );
+
+        let config =
+            parse_config("[sort_title]\nstrip_articles = true\nopus_last = true\n").unwrap();
+        assert!(config.sort_title.strip_articles && config.sort_title.opus_last);
+        assert!(!config.sort_title.ignore_punctuation);
+        assert!(parse_config("[sort_title]\nstrip_opus = true\n").is_err());
+    }
+
+    #[test]
+    fn test_edit_alias() {
+        let text = "# My settings\n[output]\ncolumns = [\"id\"]\n";
+        let added = edit_alias(text, "bb5", Some("Brahms 5")).unwrap().unwrap();
+        assert_eq!(
+            added,
+            "# My settings\n[output]\ncolumns = [\"id\"]\n\n[aliases]\nbb5 = \"Brahms 5\"\n"
+        );
+        assert_eq!(edit_alias(&added, "bb5", Some("Brahms 5")).unwrap(), None);
+        assert_eq!(
+            edit_alias(&added, "bb5", None).unwrap().as_deref(),
+            Some("# My settings\n[output]\ncolumns = [\"id\"]\n")
+        );
+        assert_eq!(edit_alias(text, "bb5", None).unwrap(), None);
+    }
+}
@@ -0,0 +1,105 @@
+//! CSV dialects for `export csv` and `import csv`
+//!
+//! Excel in many European locales expects semicolon-separated files, and
+//! only reads UTF-8 correctly when the file starts with a byte order mark
+//! (otherwise it assumes the Windows code page).
+
+use crate::cli::CsvFormatArgs;
+use crate::error::{ForScoreError, Result};
+use encoding_rs::{Encoding, UTF_8};
+
+/// The UTF-8 byte order mark
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+pub struct CsvFormat {
+    pub delimiter: u8,
+    pub encoding: &'static Encoding,
+}
+
+impl CsvFormat {
+    pub fn from_args(args: &CsvFormatArgs) -> Result<CsvFormat> {
+        if !args.delimiter.is_ascii() {
+            return Err(ForScoreError::Other(format!(
+                "Delimiter must be a single ASCII character, not '{}'",
+                args.delimiter
+            )));
+        }
+        let encoding = Encoding::for_label(args.encoding.as_bytes())
+            .ok_or_else(|| ForScoreError::Other(format!("Unknown encoding: {}", args.encoding)))?;
+        Ok(CsvFormat {
+            delimiter: args.delimiter as u8,
+            encoding,
+        })
+    }
+
+    /// Encode CSV text for writing, failing if a character has no
+    /// representation in the encoding
+    pub fn encode(&self, text: &str, bom: bool) -> Result<Vec<u8>> {
+        if bom && self.encoding != UTF_8 {
+            return Err(ForScoreError::Other(format!(
+                "--bom only applies to UTF-8, not {}",
+                self.encoding.name()
+            )));
+        }
+
+        if self.encoding.output_encoding() != self.encoding {
+            return Err(ForScoreError::Other(format!(
+                "Can't write {} files",
+                self.encoding.name()
+            )));
+        }
+
+        let (bytes, _, unmappable) = self.encoding.encode(text);
+        if unmappable {
+            return Err(ForScoreError::Other(format!(
+                "Some text can't be written in {}; use --encoding utf-8 (with --bom for Excel)",
+                self.encoding.name()
+            )));
+        }
+
+        let mut out = if bom { BOM.to_vec() } else { Vec::new() };
+        out.extend_from_slice(&bytes);
+        Ok(out)
+    }
+
+    /// Decode a CSV file's bytes; a byte order mark overrides the encoding
+    /// and is dropped
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let (text, _, _) = self.encoding.decode(bytes);
+        text.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(delimiter: char, encoding: &str) -> CsvFormat {
+        CsvFormat::from_args(&CsvFormatArgs {
+            delimiter,
+            encoding: encoding.to_string(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let utf8 = format(';', "utf-8");
+        let bytes = utf8.encode("id;title\n1;Dvořák\n", true).unwrap();
+        assert!(bytes.starts_with(BOM));
+        assert_eq!(utf8.decode(&bytes), "id;title\n1;Dvořák\n");
+
+        let latin = format(';', "windows-1252");
+        let bytes = latin.encode("1;Fauré\n", false).unwrap();
+        assert_eq!(bytes, b"1;Faur\xE9\n");
+        assert_eq!(latin.decode(&bytes), "1;Fauré\n");
+        assert!(latin.encode("1;Dvořák\n", false).is_err());
+        assert!(latin.encode("1;Fauré\n", true).is_err());
+
+        assert!(CsvFormat::from_args(&CsvFormatArgs {
+            delimiter: '→',
+            encoding: "utf-8".into()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Column mappings for `import csv --mapping`
+//!
+//! Other librarian tools name their columns differently ("Komponist",
+//! "Stars"). A mapping file renames them to the columns `import csv`
+//! understands and can rewrite their values on the way in:
+//!
+//! ```toml
+//! ignore = ["Shelf"]
+//!
+//! [columns]
+//! Komponist = "composer"
+//! Stars = "rating"
+//!
+//! [transform]
+//! rating = "stars"        # "★★★★" -> "4"
+//!
+//! [values.key]
+//! "c-Moll" = "C Minor"
+//! ```
+//!
+//! Transforms and values are keyed by the renamed column; transforms run
+//! first, then exact value replacements.
+
+use crate::error::{ForScoreError, Result};
+use csv::StringRecord;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnMapping {
+    /// Source header to the column it's imported as
+    pub columns: BTreeMap<String, String>,
+    /// Source headers to leave out
+    pub ignore: Vec<String>,
+    /// Column to the transform applied to each of its cells
+    pub transform: BTreeMap<String, Transform>,
+    /// Column to exact cell replacements
+    pub values: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transform {
+    Trim,
+    Lowercase,
+    Uppercase,
+    /// Count star characters ("★★★" or "***" becomes "3")
+    Stars,
+}
+
+impl Transform {
+    fn apply(self, value: &str) -> String {
+        match self {
+            Transform::Trim => value.trim().to_string(),
+            Transform::Lowercase => value.to_lowercase(),
+            Transform::Uppercase => value.to_uppercase(),
+            Transform::Stars => {
+                let stars = value.chars().filter(|c| matches!(c, '★' | '*')).count();
+                if stars > 0 {
+                    stars.to_string()
+                } else {
+                    value.to_string()
+                }
+            }
+        }
+    }
+}
+
+impl ColumnMapping {
+    pub fn load(path: &Path) -> Result<ColumnMapping> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|e| ForScoreError::Other(format!("Invalid mapping {}: {}", path.display(), e)))
+    }
+
+    /// Rename, drop and rewrite columns; records keep their positions, so
+    /// problems are still reported by line
+    pub fn apply(
+        &self,
+        headers: &StringRecord,
+        records: Vec<StringRecord>,
+    ) -> (StringRecord, Vec<StringRecord>) {
+        let kept: Vec<(usize, &str)> = headers
+            .iter()
+            .enumerate()
+            .filter(|(_, header)| !self.ignore.iter().any(|i| i == header))
+            .map(|(i, header)| {
+                let column = self.columns.get(header).map_or(header, String::as_str);
+                (i, column)
+            })
+            .collect();
+
+        let new_headers: StringRecord = kept.iter().map(|(_, column)| *column).collect();
+        let new_records = records
+            .into_iter()
+            .map(|record| {
+                let mut mapped: StringRecord = kept
+                    .iter()
+                    .map(|(i, column)| self.value(column, record.get(*i).unwrap_or("")))
+                    .collect();
+                mapped.set_position(record.position().cloned());
+                mapped
+            })
+            .collect();
+
+        (new_headers, new_records)
+    }
+
+    fn value(&self, column: &str, cell: &str) -> String {
+        let cell = match self.transform.get(column) {
+            Some(transform) => transform.apply(cell),
+            None => cell.to_string(),
+        };
+        self.values
+            .get(column)
+            .and_then(|values| values.get(&cell))
+            .cloned()
+            .unwrap_or(cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mapping: ColumnMapping = toml::from_str(
+            r#"
+            ignore = ["Shelf"]
+
+            [columns]
+            Nr = "id"
+            Komponist = "composer"
+            Stars = "rating"
+
+            [transform]
+            rating = "stars"
+            composer = "trim"
+
+            [values.composer]
+            "J. S. Bach" = "Johann Sebastian Bach"
+            "#,
+        )
+        .unwrap();
+
+        let headers = StringRecord::from(vec!["Nr", "Shelf", "Komponist", "Stars", "title"]);
+        let records = vec![StringRecord::from(vec![
+            "7",
+            "B2",
+            " J. S. Bach ",
+            "★★★★",
+            "Prelude",
+        ])];
+        let (headers, records) = mapping.apply(&headers, records);
+
+        assert_eq!(
+            headers,
+            StringRecord::from(vec!["id", "composer", "rating", "title"])
+        );
+        assert_eq!(
+            records[0],
+            StringRecord::from(vec!["7", "Johann Sebastian Bach", "4", "Prelude"])
+        );
+        assert!(toml::from_str::<ColumnMapping>("[transform]\nrating = \"round\"").is_err());
+    }
+}
@@ -1,40 +1,93 @@
 use crate::error::{ForScoreError, Result};
+use crate::platform::{database_not_found, db_override, is_forscore_running, preferences_path};
+use crate::profile::{self, Phase};
+use crate::schema;
+use chrono::{DateTime, Utc};
 use rusqlite::{Connection, OpenFlags};
-use std::path::PathBuf;
-use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Core Data epoch: seconds between Unix epoch (1970-01-01) and Core Data epoch (2001-01-01)
 const CORE_DATA_EPOCH_OFFSET: i64 = 978307200;
 
-const FORSCORE_CONTAINER: &str =
-    "Library/Containers/com.mgsdevelopment.forscore/Data/Library/Preferences/library.4sl";
+/// How long to wait on a locked database by default (see `set_lock_wait`)
+const DEFAULT_LOCK_WAIT: Duration = Duration::from_secs(5);
 
-/// Get the path to the forScore database
+/// Longest pause between retries on a locked database
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// `--wait`, if given
+static LOCK_WAIT: OnceLock<Duration> = OnceLock::new();
+
+/// The advisory write lock, held from the first `open_readwrite` until exit
+static WRITE_LOCK: OnceLock<File> = OnceLock::new();
+
+/// Get the path to the forScore database (`--db` if given)
 pub fn database_path() -> Result<PathBuf> {
-    let home = dirs::home_dir()
-        .ok_or_else(|| ForScoreError::Other("Cannot find home directory".into()))?;
-    let path = home.join(FORSCORE_CONTAINER);
+    let path = match db_override() {
+        Some(db) => db.to_path_buf(),
+        None => preferences_path()?.join("library.4sl"),
+    };
 
     if path.exists() {
         Ok(path)
     } else {
-        Err(ForScoreError::DatabaseNotFound)
+        Err(database_not_found())
     }
 }
 
-/// Check if forScore is currently running
-pub fn is_forscore_running() -> bool {
-    Command::new("pgrep")
-        .args(["-x", "forScore"])
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+/// Get the path to the folder holding forScore's PDF files
+/// (Data/Documents, alongside the database's Data/Library/Preferences)
+pub fn documents_path() -> Result<PathBuf> {
+    let preferences = preferences_path()?;
+    let path = preferences
+        .parent()
+        .and_then(Path::parent)
+        .map(|data| data.join("Documents"))
+        .filter(|path| path.exists());
+
+    path.ok_or_else(|| ForScoreError::Other("Documents folder not found".into()))
+}
+
+/// Record `--wait`; call once at startup
+pub fn set_lock_wait(wait: Duration) {
+    let _ = LOCK_WAIT.set(wait);
+}
+
+fn lock_wait() -> Duration {
+    LOCK_WAIT.get().copied().unwrap_or(DEFAULT_LOCK_WAIT)
 }
 
-/// Print a warning if forScore is running
+/// How long to sleep before retry number `attempt` (from 0) on a locked
+/// database: doubling from 10ms up to a second, or None once the retries
+/// would take longer than `wait` in total
+fn backoff_delay(attempt: u32, wait: Duration) -> Option<Duration> {
+    let delay = |n: u32| {
+        Duration::from_millis(10)
+            .saturating_mul(2u32.saturating_pow(n))
+            .min(MAX_BACKOFF)
+    };
+    let waited: Duration = (0..attempt).map(delay).sum();
+    let next = delay(attempt);
+    (waited + next <= wait).then_some(next)
+}
+
+/// SQLite busy handler: back off and retry while forScore holds the lock
+fn busy_handler(attempt: i32) -> bool {
+    match backoff_delay(attempt.max(0) as u32, lock_wait()) {
+        Some(delay) => {
+            std::thread::sleep(delay);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Print a warning if forScore is running (not for a copied library given with `--db`)
 pub fn warn_if_running() {
-    if is_forscore_running() {
+    if db_override().is_none() && is_forscore_running() {
         eprintln!(
             "WARNING: forScore is currently running. Changes may conflict or be overwritten."
         );
@@ -45,29 +98,145 @@ pub fn warn_if_running() {
 /// Open the database in read-only mode
 pub fn open_readonly() -> Result<Connection> {
     let path = database_path()?;
-    let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    connect(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+}
+
+/// Open the database read-only inside a read transaction, so everything read
+/// through the connection comes from one snapshot of the library
+///
+/// forScore keeps its database in write-ahead-log mode, so it can go on saving
+/// while the snapshot is open; its changes just aren't seen. The transaction
+/// ends when the connection is dropped.
+pub fn open_snapshot() -> Result<Connection> {
+    let conn = open_readonly()?;
+    conn.execute_batch("BEGIN DEFERRED")?;
+    // A deferred transaction only takes its snapshot at the first read
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))?;
     Ok(conn)
 }
 
 /// Open the database in read-write mode
+///
+/// Takes the advisory write lock first, so a second forscore-cli writing to
+/// the same library fails up front instead of interleaving its changes.
 pub fn open_readwrite() -> Result<Connection> {
     let path = database_path()?;
-    let conn = Connection::open_with_flags(
+    if WRITE_LOCK.get().is_none() {
+        let _ = WRITE_LOCK.set(wait_for_write_lock(&path)?);
+    }
+
+    connect(
         &path,
         OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
-    )?;
+    )
+}
+
+/// Open a connection and check its schema; with `--profile`, statements run
+/// on it afterwards are timed
+fn connect(path: &Path, flags: OpenFlags) -> Result<Connection> {
+    let mut conn = profile::time(Phase::OpenDatabase, || -> Result<_> {
+        let conn = Connection::open_with_flags(path, flags)?;
+        conn.busy_handler(Some(busy_handler))?;
+        schema::load(&conn)?;
+        Ok(conn)
+    })?;
+    if profile::enabled() {
+        conn.profile(Some(profile::sql));
+    }
     Ok(conn)
 }
 
-/// Entity type constants from Z_PRIMARYKEY
+/// Take the advisory write lock, retrying with the same backoff as a locked
+/// database while another forscore-cli holds it
+fn wait_for_write_lock(db_path: &Path) -> Result<File> {
+    let mut attempt = 0;
+    loop {
+        match acquire_write_lock(db_path) {
+            Err(ForScoreError::Busy) => match backoff_delay(attempt, lock_wait()) {
+                Some(delay) => std::thread::sleep(delay),
+                None => return Err(ForScoreError::Busy),
+            },
+            result => return result,
+        }
+        attempt += 1;
+    }
+}
+
+/// Lock `<database>.lock`, failing if another process holds it
+fn acquire_write_lock(db_path: &Path) -> Result<File> {
+    let mut lock_path = db_path.as_os_str().to_owned();
+    lock_path.push(".lock");
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => Err(ForScoreError::Busy),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Entity type IDs from Z_PRIMARYKEY
+///
+/// Read from the open database (see `schema`), falling back to forScore 15's
+/// numbering before one is opened.
 pub mod entity {
-    pub const BOOKMARK: i32 = 5;
-    pub const SCORE: i32 = 6;
-    pub const META: i32 = 9;
-    pub const COMPOSER: i32 = 10;
-    pub const GENRE: i32 = 12;
-    pub const KEYWORD: i32 = 13;
-    pub const SETLIST: i32 = 19;
+    use crate::schema::entity_id;
+
+    pub fn item() -> i32 {
+        entity_id("Item", 4)
+    }
+
+    pub fn bookmark() -> i32 {
+        entity_id("Bookmark", 5)
+    }
+
+    pub fn score() -> i32 {
+        entity_id("Score", 6)
+    }
+
+    pub fn library() -> i32 {
+        entity_id("Library", 7)
+    }
+
+    pub fn meta() -> i32 {
+        entity_id("Meta", 9)
+    }
+
+    pub fn composer() -> i32 {
+        entity_id("Composer", 10)
+    }
+
+    pub fn difficulty() -> i32 {
+        entity_id("Difficulty", 11)
+    }
+
+    pub fn genre() -> i32 {
+        entity_id("Genre", 12)
+    }
+
+    pub fn keyword() -> i32 {
+        entity_id("Keyword", 13)
+    }
+
+    pub fn label() -> i32 {
+        entity_id("Label", 14)
+    }
+
+    pub fn rating() -> i32 {
+        entity_id("Rating", 15)
+    }
+
+    pub fn page() -> i32 {
+        entity_id("Page", 16)
+    }
+
+    pub fn setlist() -> i32 {
+        entity_id("Setlist", 19)
+    }
 }
 
 /// Get current timestamp in Core Data format (seconds since 2001-01-01)
@@ -79,12 +248,47 @@ pub fn core_data_timestamp() -> f64 {
     unix_time - CORE_DATA_EPOCH_OFFSET as f64
 }
 
-/// Update ZMODIFIED timestamp and increment Z_OPT for an item
-pub fn mark_modified(conn: &Connection, item_id: i64) -> Result<()> {
-    let timestamp = core_data_timestamp();
-    conn.execute(
-        "UPDATE ZITEM SET ZMODIFIED = ?, Z_OPT = Z_OPT + 1 WHERE Z_PK = ?",
-        rusqlite::params![timestamp, item_id],
-    )?;
-    Ok(())
+/// Convert a Core Data timestamp to a UTC datetime
+pub fn core_data_to_datetime(timestamp: f64) -> Option<DateTime<Utc>> {
+    let unix = timestamp + CORE_DATA_EPOCH_OFFSET as f64;
+    DateTime::from_timestamp(unix.floor() as i64, (unix.fract() * 1e9) as u32)
+}
+
+/// Convert a UTC datetime to a Core Data timestamp
+pub fn datetime_to_core_data(datetime: DateTime<Utc>) -> f64 {
+    datetime.timestamp() as f64 - CORE_DATA_EPOCH_OFFSET as f64
+        + datetime.timestamp_subsec_nanos() as f64 / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let wait = Duration::from_millis(100);
+        assert_eq!(backoff_delay(0, wait), Some(Duration::from_millis(10)));
+        assert_eq!(backoff_delay(2, wait), Some(Duration::from_millis(40)));
+        // 10 + 20 + 40 + 80 > 100
+        assert_eq!(backoff_delay(3, wait), None);
+        assert_eq!(backoff_delay(0, Duration::ZERO), None);
+        assert_eq!(
+            backoff_delay(10, Duration::from_secs(60)),
+            Some(MAX_BACKOFF)
+        );
+    }
+
+    #[test]
+    fn test_write_lock_is_exclusive() {
+        let db = std::env::temp_dir().join(format!("forscore-lock-{}.4sl", std::process::id()));
+
+        let held = acquire_write_lock(&db).unwrap();
+        assert!(matches!(acquire_write_lock(&db), Err(ForScoreError::Busy)));
+        drop(held);
+        assert!(acquire_write_lock(&db).is_ok());
+
+        let mut lock_path = db.into_os_string();
+        lock_path.push(".lock");
+        std::fs::remove_file(lock_path).unwrap();
+    }
 }
@@ -0,0 +1,142 @@
+//! Dry-run previews
+//!
+//! Every `--dry-run` reports planned changes the same way: a header line per
+//! item followed by `field: old -> new` lines, or (with `--json`) an array of
+//! patch objects that review tooling can consume.
+
+use serde::Serialize;
+
+/// A change to one field; `None` means the field is (or becomes) unset
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// The planned changes to one item
+#[derive(Debug, Clone, Serialize)]
+pub struct Patch {
+    /// "update", "create", "delete" or "merge"
+    pub action: &'static str,
+    /// "score", "bookmark", "setlist", ...
+    pub kind: &'static str,
+    pub id: Option<i64>,
+    pub title: String,
+    pub changes: Vec<FieldChange>,
+}
+
+impl Patch {
+    pub fn new(action: &'static str, kind: &'static str, id: Option<i64>, title: &str) -> Self {
+        Self {
+            action,
+            kind,
+            id,
+            title: title.to_string(),
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn update(kind: &'static str, id: i64, title: &str) -> Self {
+        Self::new("update", kind, Some(id), title)
+    }
+
+    pub fn create(kind: &'static str, title: &str) -> Self {
+        Self::new("create", kind, None, title)
+    }
+
+    /// Record a field change; empty strings are treated as unset and
+    /// changes that leave the value as it was are dropped
+    pub fn change(&mut self, field: &str, old: impl Into<String>, new: impl Into<String>) {
+        let non_empty = |s: String| if s.is_empty() { None } else { Some(s) };
+        let (old, new) = (non_empty(old.into()), non_empty(new.into()));
+        if old == new {
+            return;
+        }
+        self.changes.push(FieldChange {
+            field: field.to_string(),
+            old,
+            new,
+        });
+    }
+
+    fn header(&self) -> String {
+        match self.id {
+            Some(id) => format!("{} {} {} \"{}\"", self.action, self.kind, id, self.title),
+            None => format!("{} {} \"{}\"", self.action, self.kind, self.title),
+        }
+    }
+}
+
+/// Collects patches and prints them as text or JSON
+pub struct DryRun {
+    json: bool,
+    patches: Vec<Patch>,
+}
+
+impl DryRun {
+    pub fn new(json: bool) -> Self {
+        Self {
+            json,
+            patches: Vec::new(),
+        }
+    }
+
+    /// Whether output is JSON, in which case callers should print nothing else
+    pub fn is_json(&self) -> bool {
+        self.json
+    }
+
+    /// Record a patch; updates that change nothing are ignored
+    pub fn add(&mut self, patch: Patch) {
+        if patch.action == "update" && patch.changes.is_empty() {
+            return;
+        }
+        if !self.json {
+            println!("{}", render_patch(&patch));
+        }
+        self.patches.push(patch);
+    }
+
+    /// Print the JSON array, or the closing line of a text preview
+    pub fn finish(self) {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&self.patches).unwrap());
+        } else {
+            println!(
+                "Dry run: {} item(s) would change, nothing written.",
+                self.patches.len()
+            );
+        }
+    }
+}
+
+fn render_patch(patch: &Patch) -> String {
+    let mut out = patch.header();
+    for change in &patch.changes {
+        out.push_str(&format!(
+            "\n  {}: {} -> {}",
+            change.field,
+            change.old.as_deref().unwrap_or("(none)"),
+            change.new.as_deref().unwrap_or("(none)")
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_patch() {
+        let mut patch = Patch::update("score", 12, "Prelude");
+        patch.change("rating", "5", "6");
+        patch.change("key", "", "C Major");
+        patch.change("title", "Prelude", "Prelude");
+        assert_eq!(
+            render_patch(&patch),
+            "update score 12 \"Prelude\"\n  rating: 5 -> 6\n  key: (none) -> C Major"
+        );
+    }
+}
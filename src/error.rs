@@ -3,7 +3,10 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum ForScoreError {
     #[error("Database error: {0}")]
-    Database(#[from] rusqlite::Error),
+    Database(rusqlite::Error),
+
+    #[error("The forScore database is locked by another app (usually forScore saving); try again, or use --wait <secs> to wait longer")]
+    DatabaseLocked,
 
     #[error("Score not found: {0}")]
     ScoreNotFound(String),
@@ -29,6 +32,11 @@ pub enum ForScoreError {
     #[error("Invalid difficulty: {0}. Must be 1-5")]
     InvalidDifficulty(i32),
 
+    #[error(
+        "Another forscore-cli operation is running on this library; try again when it finishes"
+    )]
+    Busy,
+
     #[error("forScore database not found at expected location")]
     DatabaseNotFound,
 
@@ -46,3 +54,14 @@ pub enum ForScoreError {
 }
 
 pub type Result<T> = std::result::Result<T, ForScoreError>;
+
+impl From<rusqlite::Error> for ForScoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => {
+                ForScoreError::DatabaseLocked
+            }
+            _ => ForScoreError::Database(e),
+        }
+    }
+}
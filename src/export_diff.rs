@@ -0,0 +1,314 @@
+//! Differences between two exports, for `export diff`
+//!
+//! Both files are read into flat records (one per score, bookmark or
+//! setlist, each a map of field to text) and matched by UUID, or by path
+//! and title where there's none. CSV rows become records as they are;
+//! `export json` documents are flattened, with a score's bookmarks as
+//! records of their own and a setlist's items as one "items" field.
+
+use crate::error::{ForScoreError, Result};
+use csv::StringRecord;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub kind: &'static str,
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Record {
+    fn field(&self, name: &str) -> &str {
+        self.fields.get(name).map_or("", String::as_str)
+    }
+
+    /// UUID, or path and title
+    fn key(&self) -> String {
+        match self.field("uuid") {
+            "" => format!("{}\n{}", self.field("path"), self.field("title")),
+            uuid => uuid.to_string(),
+        }
+    }
+
+    /// How the record is named in the summary
+    fn label(&self) -> String {
+        match (self.field("title"), self.field("path")) {
+            (title, "") => title.to_string(),
+            ("", path) => path.to_string(),
+            (title, path) if self.kind == "score" => format!("{} ({})", title, path),
+            (title, path) => format!("{} in {}", title, path),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordDiff {
+    pub kind: &'static str,
+    pub label: String,
+    pub change: Change,
+    pub fields: Vec<FieldChange>,
+}
+
+/// One record per CSV row, keyed by header; `kind` is "bookmark" when the
+/// headers are `export bookmarks-csv`'s
+pub fn csv_records(headers: &StringRecord, rows: &[StringRecord]) -> Vec<Record> {
+    let kind = if headers.iter().any(|h| h == "start_page") {
+        "bookmark"
+    } else {
+        "score"
+    };
+    rows.iter()
+        .map(|row| Record {
+            kind,
+            fields: headers
+                .iter()
+                .zip(row.iter())
+                .map(|(h, v)| (h.to_string(), v.to_string()))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Flatten an `export json` document into records
+pub fn json_records(text: &str) -> Result<Vec<Record>> {
+    let document: Value = serde_json::from_str(text)
+        .map_err(|e| ForScoreError::Other(format!("Not a JSON export: {}", e)))?;
+    let list = |name: &str| {
+        document
+            .get(name)
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let mut records = Vec::new();
+    for score in list("scores") {
+        let Value::Object(fields) = score else {
+            continue;
+        };
+        let mut record = Record {
+            kind: "score",
+            fields: BTreeMap::new(),
+        };
+        for (name, value) in fields {
+            if name != "bookmarks" {
+                record.fields.insert(name, text_value(&value));
+                continue;
+            }
+            for bookmark in value.as_array().into_iter().flatten() {
+                if let Value::Object(fields) = bookmark {
+                    records.push(Record {
+                        kind: "bookmark",
+                        fields: fields
+                            .iter()
+                            .map(|(name, value)| (name.clone(), text_value(value)))
+                            .collect(),
+                    });
+                }
+            }
+        }
+        records.push(record);
+    }
+
+    for setlist in list("setlists") {
+        let items: Vec<String> = setlist
+            .get("items")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(|item| text_value(item.get("title").unwrap_or(&Value::Null)))
+            .collect();
+        let mut fields = BTreeMap::new();
+        for name in ["title", "uuid"] {
+            fields.insert(
+                name.to_string(),
+                text_value(setlist.get(name).unwrap_or(&Value::Null)),
+            );
+        }
+        fields.insert("items".to_string(), items.join("; "));
+        records.push(Record {
+            kind: "setlist",
+            fields,
+        });
+    }
+    Ok(records)
+}
+
+/// A JSON value as the text a CSV export would hold: lists joined with
+/// "; ", and objects (keys) as their text fields, e.g. "A Minor"
+fn text_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(text_value).collect::<Vec<_>>().join("; "),
+        Value::Object(fields) => {
+            let text: Vec<&str> = ["note", "mode"]
+                .iter()
+                .filter_map(|name| fields.get(*name).and_then(Value::as_str))
+                .collect();
+            if text.is_empty() {
+                value.to_string()
+            } else {
+                text.join(" ")
+            }
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Added, removed and changed records, in the new file's order (removed
+/// ones last, in the old file's order)
+pub fn diff_records(old: &[Record], new: &[Record]) -> Vec<RecordDiff> {
+    let old_by_key: BTreeMap<(&str, String), &Record> =
+        old.iter().map(|r| ((r.kind, r.key()), r)).collect();
+    let new_by_key: BTreeMap<(&str, String), &Record> =
+        new.iter().map(|r| ((r.kind, r.key()), r)).collect();
+
+    let mut diffs = Vec::new();
+    for record in new {
+        let Some(before) = old_by_key.get(&(record.kind, record.key())) else {
+            diffs.push(RecordDiff {
+                kind: record.kind,
+                label: record.label(),
+                change: Change::Added,
+                fields: Vec::new(),
+            });
+            continue;
+        };
+
+        let mut names: Vec<&String> = before.fields.keys().chain(record.fields.keys()).collect();
+        names.sort();
+        names.dedup();
+        let fields: Vec<FieldChange> = names
+            .into_iter()
+            .filter(|name| before.field(name) != record.field(name))
+            .map(|name| FieldChange {
+                field: name.clone(),
+                old: before.field(name).to_string(),
+                new: record.field(name).to_string(),
+            })
+            .collect();
+        if !fields.is_empty() {
+            diffs.push(RecordDiff {
+                kind: record.kind,
+                label: record.label(),
+                change: Change::Changed,
+                fields,
+            });
+        }
+    }
+
+    for record in old {
+        if !new_by_key.contains_key(&(record.kind, record.key())) {
+            diffs.push(RecordDiff {
+                kind: record.kind,
+                label: record.label(),
+                change: Change::Removed,
+                fields: Vec::new(),
+            });
+        }
+    }
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(uuid: &str, path: &str, title: &str, rating: &str) -> Record {
+        Record {
+            kind: "score",
+            fields: [
+                ("uuid", uuid),
+                ("path", path),
+                ("title", title),
+                ("rating", rating),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_records() {
+        let old = vec![
+            score("A", "a.pdf", "Prelude", "4"),
+            score("B", "b.pdf", "Fugue", ""),
+            score("", "c.pdf", "Etude", "3"),
+        ];
+        let new = vec![
+            score("A", "renamed.pdf", "Prelude", "5"),
+            score("", "c.pdf", "Etude", "3"),
+            score("D", "d.pdf", "Sonata", ""),
+        ];
+        let diffs = diff_records(&old, &new);
+
+        assert_eq!(diffs.len(), 3);
+        assert_eq!(diffs[0].change, Change::Changed);
+        assert_eq!(diffs[0].label, "Prelude (renamed.pdf)");
+        assert_eq!(
+            diffs[0].fields,
+            vec![
+                FieldChange {
+                    field: "path".to_string(),
+                    old: "a.pdf".to_string(),
+                    new: "renamed.pdf".to_string(),
+                },
+                FieldChange {
+                    field: "rating".to_string(),
+                    old: "4".to_string(),
+                    new: "5".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            (diffs[1].change == Change::Added, diffs[1].label.as_str()),
+            (true, "Sonata (d.pdf)")
+        );
+        assert_eq!(
+            (diffs[2].change == Change::Removed, diffs[2].label.as_str()),
+            (true, "Fugue (b.pdf)")
+        );
+    }
+
+    #[test]
+    fn test_json_records() {
+        let records = json_records(
+            r#"{"version": 1,
+                "scores": [{"uuid": "A", "path": "a.pdf", "title": "Prelude",
+                            "key": {"code": 601, "note": "A", "mode": "Minor"},
+                            "composers": ["Bach", "Busoni"], "rating": null,
+                            "bookmarks": [{"uuid": "BM", "path": "a.pdf", "title": "Intro",
+                                           "start_page": 1, "end_page": 2}]}],
+                "setlists": [{"title": "Gig", "uuid": "S",
+                              "items": [{"title": "Prelude"}, {"title": "Intro"}]}]}"#,
+        )
+        .unwrap();
+
+        let kinds: Vec<&str> = records.iter().map(|r| r.kind).collect();
+        assert_eq!(kinds, ["bookmark", "score", "setlist"]);
+        assert_eq!(records[0].field("start_page"), "1");
+        assert_eq!(records[1].field("key"), "A Minor");
+        assert_eq!(records[1].field("composers"), "Bach; Busoni");
+        assert_eq!(records[1].field("rating"), "");
+        assert!(!records[1].fields.contains_key("bookmarks"));
+        assert_eq!(records[2].field("items"), "Prelude; Intro");
+    }
+}
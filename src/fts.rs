@@ -0,0 +1,161 @@
+//! Full-text search over titles and metadata (`scores search --fts`)
+//!
+//! The FTS5 index is built from forScore's database on first use and kept in
+//! the cache database, rebuilt whenever the library file changes. If the
+//! cache can't be opened the index is built in memory for the one search.
+
+use crate::cache::open_cache;
+use crate::db::{database_path, entity};
+use crate::error::Result;
+use rusqlite::{Connection, OptionalExtension};
+use std::time::UNIX_EPOCH;
+
+/// Item IDs matching a full-text query, best match first
+pub fn fts_search(conn: &Connection, query: &str) -> Result<Vec<i64>> {
+    let Some(query) = fts_query(query) else {
+        return Ok(Vec::new());
+    };
+    let index = open_index(conn)?;
+
+    // Title matches count most, then composer and genre
+    let mut stmt = index.prepare(
+        "SELECT item_id FROM search_index WHERE search_index MATCH ?
+         ORDER BY bm25(search_index, 10.0, 5.0, 3.0, 1.0, 1.0)",
+    )?;
+    let ids = stmt
+        .query_map([query], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(ids)
+}
+
+/// Turn what the user typed into an FTS5 query: every word must match the
+/// start of a word, in any order and ignoring accents ("bach prel" finds
+/// "Prelude in C - J.S. Bach")
+fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|word| word.trim_end_matches('*').replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// The search index, (re)built if it wasn't made from the library as it is now
+fn open_index(conn: &Connection) -> Result<Connection> {
+    let index = match open_cache() {
+        Ok(cache) => cache,
+        Err(_) => Connection::open_in_memory()?,
+    };
+    index.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS search_index USING fts5(
+            title, composer, genre, keywords, labels,
+            item_id UNINDEXED,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+        CREATE TABLE IF NOT EXISTS search_index_source (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            source TEXT NOT NULL
+        )",
+    )?;
+
+    let source = library_version()?;
+    let indexed: Option<String> = index
+        .query_row(
+            "SELECT source FROM search_index_source WHERE id = 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if indexed.as_deref() != Some(source.as_str()) {
+        rebuild(conn, &index)?;
+        index.execute(
+            "INSERT OR REPLACE INTO search_index_source (id, source) VALUES (1, ?)",
+            [&source],
+        )?;
+    }
+
+    Ok(index)
+}
+
+/// Identifies the library and its state: path plus the modification times of
+/// the database and its write-ahead log
+fn library_version() -> Result<String> {
+    let path = database_path()?;
+    let mtime = |p: &std::path::Path| {
+        std::fs::metadata(p)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)
+    };
+    Ok(format!(
+        "{}:{}:{}",
+        path.display(),
+        mtime(&path),
+        mtime(&path.with_extension("4sl-wal"))
+    ))
+}
+
+/// Replace the index contents with every score and bookmark in the library
+fn rebuild(conn: &Connection, index: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT i.Z_PK, i.ZTITLE,
+            (SELECT GROUP_CONCAT(m.ZVALUE, ' ') FROM ZMETA m
+             JOIN Z_4COMPOSERS c ON m.Z_PK = c.Z_10COMPOSERS WHERE c.Z_4ITEMS1 = i.Z_PK),
+            (SELECT GROUP_CONCAT(m.ZVALUE2, ' ') FROM ZMETA m
+             JOIN Z_4GENRES g ON m.Z_PK = g.Z_12GENRES WHERE g.Z_4ITEMS4 = i.Z_PK),
+            (SELECT GROUP_CONCAT(m.ZVALUE, ' ') FROM ZMETA m
+             JOIN Z_4KEYWORDS k ON m.Z_PK = k.Z_13KEYWORDS WHERE k.Z_4ITEMS5 = i.Z_PK),
+            (SELECT GROUP_CONCAT(m.ZVALUE, ' ') FROM ZMETA m
+             JOIN Z_4LABELS l ON m.Z_PK = l.Z_14LABELS WHERE l.Z_4ITEMS2 = i.Z_PK)
+         FROM ZITEM i
+         WHERE i.Z_ENT IN (?, ?)",
+    )?;
+    let rows = stmt
+        .query_map([entity::score(), entity::bookmark()], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+            ))
+        })?
+        .filter_map(|r| r.ok());
+
+    let tx = index.unchecked_transaction()?;
+    tx.execute("DELETE FROM search_index", [])?;
+    for (id, title, composer, genre, keywords, labels) in rows {
+        tx.execute(
+            "INSERT INTO search_index (title, composer, genre, keywords, labels, item_id)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            rusqlite::params![title, composer, genre, keywords, labels, id],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(
+            fts_query("bach  prel*").as_deref(),
+            Some("\"bach\"* \"prel\"*")
+        );
+        assert_eq!(fts_query("say \"hi\"").as_deref(), Some("\"say\"* \"hi\"*"));
+        assert_eq!(fts_query("  "), None);
+    }
+}
@@ -0,0 +1,191 @@
+//! Fuzzy name matching, the last resort when resolving identifiers
+//!
+//! Names are compared by their character trigrams (Dice coefficient over
+//! lowercased words, ignoring punctuation), which tolerates typos, missing
+//! letters and a word or two of difference. A clear winner above
+//! `ACCEPT` is used; otherwise the closest names are offered as suggestions.
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Mutex;
+
+/// Similarity a best match needs to be used without asking
+const ACCEPT: f64 = 0.5;
+/// How far ahead of the runner-up a best match has to be
+const MARGIN: f64 = 0.1;
+/// Similarity a name needs to be suggested
+const SUGGEST: f64 = 0.25;
+const MAX_SUGGESTIONS: usize = 3;
+
+pub enum FuzzyMatch<'a, T> {
+    /// One candidate is clearly what was meant
+    Match(&'a T),
+    /// The closest candidates, best first (possibly none)
+    Suggestions(Vec<&'a T>),
+}
+
+/// Find the candidate whose name is closest to `query`
+pub fn best_match<'a, T>(
+    query: &str,
+    candidates: &'a [T],
+    name: impl Fn(&T) -> &str,
+) -> FuzzyMatch<'a, T> {
+    let query = trigrams(query);
+    let mut scored: Vec<(f64, &T)> = candidates
+        .iter()
+        .map(|c| (similarity(&query, &trigrams(name(c))), c))
+        .filter(|(score, _)| *score >= SUGGEST)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    match scored.as_slice() {
+        [(best, c), rest @ ..]
+            if *best >= ACCEPT && rest.first().is_none_or(|(next, _)| best - next >= MARGIN) =>
+        {
+            FuzzyMatch::Match(c)
+        }
+        _ => FuzzyMatch::Suggestions(
+            scored
+                .into_iter()
+                .take(MAX_SUGGESTIONS)
+                .map(|(_, c)| c)
+                .collect(),
+        ),
+    }
+}
+
+/// Tell the user which name a fuzzy match picked, since they didn't type it
+pub fn note_closest(query: &str, name: &str) {
+    // Once per query: an identifier may be resolved more than once
+    static NOTED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+    if let Ok(mut noted) = NOTED.lock() {
+        if !noted.insert(query.to_string()) {
+            return;
+        }
+    }
+    eprintln!(
+        "Note: nothing matches '{}'; using closest match '{}'",
+        query, name
+    );
+}
+
+/// "x" plus a "did you mean" list for a not-found error
+pub fn with_suggestions(query: &str, suggestions: &[&str]) -> String {
+    if suggestions.is_empty() {
+        return query.to_string();
+    }
+    let names: Vec<String> = suggestions.iter().map(|s| format!("'{}'", s)).collect();
+    format!("{} (did you mean {}?)", query, names.join(", "))
+}
+
+/// Levenshtein distance between two strings, by character, if it is at
+/// most `max`. Only the diagonal band that can stay within `max` is computed,
+/// so comparing every pair of titles in a library stays cheap
+pub fn edit_distance_within(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    // Anything over `max` is clamped to this
+    let over = max + 1;
+    let mut row: Vec<usize> = (0..=b.len()).map(|j| j.min(over)).collect();
+    for i in 1..=a.len() {
+        let low = i.saturating_sub(max).max(1);
+        let high = (i + max).min(b.len());
+        let mut diagonal = row[low - 1];
+        row[low - 1] = if low == 1 { i.min(over) } else { over };
+        let mut row_min = row[low - 1];
+        for j in low..=high {
+            let substitution = diagonal + usize::from(a[i - 1] != b[j - 1]);
+            diagonal = row[j];
+            row[j] = substitution.min(row[j] + 1).min(row[j - 1] + 1).min(over);
+            row_min = row_min.min(row[j]);
+        }
+        if row_min > max {
+            return None;
+        }
+    }
+    Some(row[b.len()]).filter(|d| *d <= max)
+}
+
+/// Trigrams of each word, padded so word starts and ends count
+fn trigrams(s: &str) -> HashSet<[char; 3]> {
+    let mut grams = HashSet::new();
+    let cleaned: String = s
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_lowercase().next().unwrap_or(c)
+            } else {
+                ' '
+            }
+        })
+        .collect();
+    for word in cleaned.split_whitespace() {
+        let chars: Vec<char> = format!("  {} ", word).chars().collect();
+        for w in chars.windows(3) {
+            grams.insert([w[0], w[1], w[2]]);
+        }
+    }
+    grams
+}
+
+fn similarity(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    2.0 * a.intersection(b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TITLES: &[&str] = &[
+        "Prelude in C",
+        "Prelude in D",
+        "Nocturne Op. 9 No. 2",
+        "Fugue in G Minor",
+    ];
+
+    fn best(query: &str) -> Result<&'static str, Vec<&'static str>> {
+        match best_match(query, TITLES, |t| t) {
+            FuzzyMatch::Match(t) => Ok(t),
+            FuzzyMatch::Suggestions(s) => Err(s.into_iter().copied().collect()),
+        }
+    }
+
+    #[test]
+    fn test_best_match() {
+        assert_eq!(best("Nocturn op 9 no 2"), Ok("Nocturne Op. 9 No. 2"));
+        assert_eq!(best("fuge in g minr"), Ok("Fugue in G Minor"));
+        // Two equally close names are suggestions, not a match
+        assert_eq!(best("prelud in"), Err(vec!["Prelude in C", "Prelude in D"]));
+        assert_eq!(best("xyzzy"), Err(vec![]));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance_within("", "", 0), Some(0));
+        assert_eq!(edit_distance_within("abc", "", 3), Some(3));
+        assert_eq!(edit_distance_within("abc", "", 2), None);
+        assert_eq!(edit_distance_within("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance_within("kitten", "sitting", 2), None);
+        assert_eq!(edit_distance_within("flaw", "lawn", 2), Some(2));
+        assert_eq!(
+            edit_distance_within("nocturne op. 9", "nocturne op 9", 1),
+            Some(1)
+        );
+        assert_eq!(edit_distance_within("café", "cafe", 1), Some(1));
+        assert_eq!(edit_distance_within("abcdef", "badcfe", 2), None);
+    }
+
+    #[test]
+    fn test_with_suggestions() {
+        assert_eq!(with_suggestions("x", &[]), "x");
+        assert_eq!(
+            with_suggestions("prelud", &["Prelude in C", "Prelude in D"]),
+            "prelud (did you mean 'Prelude in C', 'Prelude in D'?)"
+        );
+    }
+}
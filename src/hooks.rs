@@ -0,0 +1,131 @@
+//! User hooks run after the CLI changes the library
+//!
+//! Changes are recorded as they are written (by `touch`) and each configured hook runs once, after
+//! the command has finished successfully. The hook is run by the shell with a
+//! JSON payload on stdin:
+//!
+//! ```json
+//! {"event": "post_edit", "args": ["scores", "edit", "12", "--rating", "5"],
+//!  "database": "/path/to/library.4sl", "ids": [12]}
+//! ```
+//!
+//! A failing hook only produces a warning; the change has already been made.
+
+use crate::config::load_config;
+use crate::db::database_path;
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct Changes {
+    items: BTreeSet<i64>,
+    setlists: BTreeSet<i64>,
+}
+
+static CHANGES: Mutex<Changes> = Mutex::new(Changes {
+    items: BTreeSet::new(),
+    setlists: BTreeSet::new(),
+});
+
+/// Payload written to a hook's stdin
+#[derive(Debug, Serialize)]
+struct HookPayload<'a> {
+    event: &'a str,
+    /// The forscore command line that made the change
+    args: Vec<String>,
+    database: String,
+    /// Changed item (score/bookmark) or setlist IDs
+    ids: Vec<i64>,
+}
+
+/// Note that a score or bookmark was edited
+pub fn record_edit(item_id: i64) {
+    if let Ok(mut changes) = CHANGES.lock() {
+        changes.items.insert(item_id);
+    }
+}
+
+/// Note that a setlist was created, changed or deleted
+pub fn record_setlist_change(setlist_id: i64) {
+    if let Ok(mut changes) = CHANGES.lock() {
+        changes.setlists.insert(setlist_id);
+    }
+}
+
+/// Forget everything recorded so far, for changes that weren't made to the
+/// library (e.g. building a `fixture`)
+pub fn discard_changes() {
+    if let Ok(mut changes) = CHANGES.lock() {
+        *changes = Changes::default();
+    }
+}
+
+/// Run the configured hooks for everything recorded so far
+pub fn run_hooks() -> Result<()> {
+    let changes = match CHANGES.lock() {
+        Ok(mut changes) => std::mem::take(&mut *changes),
+        Err(_) => return Ok(()),
+    };
+    if changes.items.is_empty() && changes.setlists.is_empty() {
+        return Ok(());
+    }
+
+    let hooks = load_config()?.hooks;
+    let hooks = [
+        ("post_edit", hooks.post_edit, changes.items),
+        (
+            "post_setlist_change",
+            hooks.post_setlist_change,
+            changes.setlists,
+        ),
+    ];
+
+    for (event, command, ids) in hooks {
+        let Some(command) = command else { continue };
+        if ids.is_empty() {
+            continue;
+        }
+
+        let payload = HookPayload {
+            event,
+            args: std::env::args().skip(1).collect(),
+            database: database_path()?.display().to_string(),
+            ids: ids.into_iter().collect(),
+        };
+        if let Err(e) = run_hook(&command, &serde_json::to_string(&payload)?) {
+            eprintln!("Warning: {} hook failed: {}", event, e);
+        }
+    }
+
+    Ok(())
+}
+
+fn run_hook(command: &str, payload: &str) -> std::io::Result<()> {
+    let mut child = if cfg!(windows) {
+        Command::new("cmd")
+            .args(["/C", command])
+            .stdin(Stdio::piped())
+            .spawn()?
+    } else {
+        Command::new("sh")
+            .args(["-c", command])
+            .stdin(Stdio::piped())
+            .spawn()?
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores its input may exit before reading it
+        let _ = stdin.write_all(payload.as_bytes());
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(status.to_string()))
+    }
+}
@@ -5,23 +5,47 @@
 //! for changes to sync to other devices.
 
 use crate::error::{ForScoreError, Result};
+use crate::platform::{db_override, icloud_drive_candidate, preferences_path, sync_dir_override};
+use crate::profile::{self, Phase};
+use crate::progress::Progress;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
-use plist::Value;
+use plist::{Dictionary, Value};
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-/// Get the path to the forScore sync folder
+/// The sync folder once found; it's looked up for every ITM file a bulk
+/// command touches
+static SYNC_FOLDER: OnceLock<PathBuf> = OnceLock::new();
+
+/// Get the path to the forScore sync folder (`--sync-dir` if given, otherwise
+/// the Sync folder next to the database, or forScore's iCloud Drive folder
+/// if that's where the sync files are)
 pub fn sync_folder_path() -> Result<PathBuf> {
-    let home = dirs::home_dir()
-        .ok_or_else(|| ForScoreError::Other("Cannot find home directory".into()))?;
-    let path =
-        home.join("Library/Containers/com.mgsdevelopment.forscore/Data/Library/Preferences/Sync");
+    if let Some(path) = SYNC_FOLDER.get() {
+        return Ok(path.clone());
+    }
+
+    let path = match sync_dir_override() {
+        Some(dir) => dir.to_path_buf(),
+        None => {
+            let next_to_db = preferences_path()?.join("Sync");
+            if next_to_db.exists() || db_override().is_some() {
+                next_to_db
+            } else {
+                match icloud_drive_candidate()? {
+                    icloud if icloud.found => icloud.path,
+                    _ => next_to_db,
+                }
+            }
+        }
+    };
 
     if path.exists() {
-        Ok(path)
+        Ok(SYNC_FOLDER.get_or_init(|| path).clone())
     } else {
         Err(ForScoreError::Other("Sync folder not found".into()))
     }
@@ -34,8 +58,30 @@ pub fn itm_path_for_score(pdf_path: &str) -> Result<PathBuf> {
     Ok(sync_folder.join(itm_filename))
 }
 
+/// Rename the ITM file for a score whose PDF path changed
+pub fn rename_itm(old_pdf_path: &str, new_pdf_path: &str) -> Result<bool> {
+    let old_path = itm_path_for_score(old_pdf_path)?;
+    let new_path = itm_path_for_score(new_pdf_path)?;
+
+    if !old_path.exists() {
+        return Ok(false);
+    }
+
+    // On a case-insensitive filesystem a case-only rename targets the same file
+    let case_only = old_pdf_path.to_lowercase() == new_pdf_path.to_lowercase();
+    if new_path.exists() && !case_only {
+        return Err(ForScoreError::Other(format!(
+            "ITM file already exists: {}",
+            new_path.display()
+        )));
+    }
+
+    std::fs::rename(&old_path, &new_path)?;
+    Ok(true)
+}
+
 /// Read and decompress an ITM file, returning the plist Value
-pub fn read_itm(path: &PathBuf) -> Result<Value> {
+pub fn read_itm(path: &Path) -> Result<Value> {
     if !path.exists() {
         return Err(ForScoreError::Other(format!(
             "ITM file not found: {}",
@@ -43,10 +89,7 @@ pub fn read_itm(path: &PathBuf) -> Result<Value> {
         )));
     }
 
-    let file = File::open(path)?;
-    let mut decoder = GzDecoder::new(file);
-    let mut decompressed = Vec::new();
-    decoder.read_to_end(&mut decompressed)?;
+    let decompressed = read_gzipped(path)?;
 
     let value: Value = plist::from_bytes(&decompressed)
         .map_err(|e| ForScoreError::Other(format!("Failed to parse ITM plist: {}", e)))?;
@@ -54,30 +97,95 @@ pub fn read_itm(path: &PathBuf) -> Result<Value> {
     Ok(value)
 }
 
+/// Convert a plist value to JSON: dates become RFC 3339 strings and data
+/// becomes a hex string
+pub fn plist_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Array(items) => items.iter().map(plist_to_json).collect(),
+        Value::Dictionary(dict) => dict
+            .iter()
+            .map(|(k, v)| (k.clone(), plist_to_json(v)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        Value::Boolean(b) => (*b).into(),
+        Value::Data(bytes) => hex(bytes).into(),
+        Value::Date(date) => date.to_xml_format().into(),
+        Value::Real(r) => (*r).into(),
+        Value::Integer(i) => match i.as_signed() {
+            Some(n) => n.into(),
+            None => i.as_unsigned().unwrap_or_default().into(),
+        },
+        Value::String(s) => s.clone().into(),
+        Value::Uid(uid) => uid.get().into(),
+        _ => serde_json::Value::Null,
+    }
+}
+
+/// Lowercase hex encoding of raw bytes
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 /// Write a plist Value to a gzipped ITM file
-pub fn write_itm(path: &PathBuf, value: &Value) -> Result<()> {
+pub fn write_itm(path: &Path, value: &Value) -> Result<()> {
+    write_plist_if_changed(path, value)?;
+    Ok(())
+}
+
+fn read_gzipped(path: &Path) -> Result<Vec<u8>> {
+    let _span = profile::span(Phase::ItmRead);
+    let file = File::open(path)?;
+    let mut decoder = GzDecoder::new(file);
+    let mut decompressed = Vec::new();
+    decoder.read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Write a plist Value as a gzipped binary plist, unless the file already holds
+/// the same plist. Skipping identical writes keeps mtimes stable so iCloud
+/// doesn't re-upload unchanged sidecars after bulk operations.
+/// Returns whether the file was written.
+pub fn write_plist_if_changed(path: &Path, value: &Value) -> Result<bool> {
     // Serialize to binary plist
     let mut plist_data = Vec::new();
     plist::to_writer_binary(&mut plist_data, value)
-        .map_err(|e| ForScoreError::Other(format!("Failed to serialize ITM plist: {}", e)))?;
+        .map_err(|e| ForScoreError::Other(format!("Failed to serialize plist: {}", e)))?;
+
+    if let Ok(existing) = read_gzipped(path) {
+        // forScore's serializer may lay out the same plist differently, so
+        // fall back to comparing the parsed values
+        let unchanged = existing == plist_data
+            || plist::from_bytes::<Value>(&existing).is_ok_and(|v| &v == value);
+        if unchanged {
+            return Ok(false);
+        }
+    }
 
     // Gzip compress
+    let _span = profile::span(Phase::ItmWrite);
     let file = File::create(path)?;
     let mut encoder = GzEncoder::new(file, Compression::default());
     encoder.write_all(&plist_data)?;
     encoder.finish()?;
 
-    Ok(())
+    Ok(true)
 }
 
 /// Update fields in an ITM file for a score
 pub struct ItmUpdate {
     pub title: Option<String>,
+    /// Composers, comma-separated; an empty string removes them
     pub composer: Option<String>,
+    /// Genres, comma-separated; an empty string removes them
     pub genre: Option<String>,
     pub key: Option<i64>,
     pub rating: Option<i64>,
     pub difficulty: Option<i64>,
+    /// Tags (the keywords array)
+    pub keywords: Option<Vec<String>>,
+    pub labels: Option<Vec<String>>,
+    /// Keys to remove, for fields being cleared
+    pub remove: Vec<&'static str>,
 }
 
 impl ItmUpdate {
@@ -89,6 +197,9 @@ impl ItmUpdate {
             key: None,
             rating: None,
             difficulty: None,
+            keywords: None,
+            labels: None,
+            remove: Vec::new(),
         }
     }
 
@@ -99,6 +210,52 @@ impl ItmUpdate {
             && self.key.is_none()
             && self.rating.is_none()
             && self.difficulty.is_none()
+            && self.keywords.is_none()
+            && self.labels.is_none()
+            && self.remove.is_empty()
+    }
+
+    /// Apply the changes to an ITM dictionary; keys not being changed
+    /// (including ones the CLI doesn't know, like Pro settings) are kept
+    pub fn apply(&self, dict: &mut Dictionary) {
+        if let Some(title) = &self.title {
+            dict.insert("title".to_string(), Value::String(title.clone()));
+        }
+
+        for (key, value) in [("composer", &self.composer), ("genre", &self.genre)] {
+            match value.as_deref() {
+                Some("") => {
+                    dict.remove(key);
+                }
+                Some(value) => {
+                    dict.insert(key.to_string(), Value::String(value.to_string()));
+                }
+                None => {}
+            }
+        }
+
+        if let Some(key) = self.key {
+            dict.insert("key".to_string(), Value::Integer(key.into()));
+        }
+
+        if let Some(rating) = self.rating {
+            dict.insert("rating".to_string(), Value::Integer(rating.into()));
+        }
+
+        if let Some(difficulty) = self.difficulty {
+            dict.insert("difficulty".to_string(), Value::Integer(difficulty.into()));
+        }
+
+        for (key, names) in [("keywords", &self.keywords), ("labels", &self.labels)] {
+            if let Some(names) = names {
+                let names = names.iter().cloned().map(Value::String).collect();
+                dict.insert(key.to_string(), Value::Array(names));
+            }
+        }
+
+        for key in &self.remove {
+            dict.remove(key);
+        }
     }
 }
 
@@ -123,33 +280,25 @@ pub fn update_itm(pdf_path: &str, update: &ItmUpdate) -> Result<bool> {
         Value::Dictionary(d) => d,
         _ => return Err(ForScoreError::Other("ITM file is not a dictionary".into())),
     };
+    update.apply(&mut dict);
 
-    // Apply updates
-    if let Some(title) = &update.title {
-        dict.insert("title".to_string(), Value::String(title.clone()));
-    }
-
-    if let Some(composer) = &update.composer {
-        dict.insert("composer".to_string(), Value::String(composer.clone()));
-    }
-
-    if let Some(genre) = &update.genre {
-        dict.insert("genre".to_string(), Value::String(genre.clone()));
-    }
-
-    if let Some(key) = update.key {
-        dict.insert("key".to_string(), Value::Integer(key.into()));
-    }
+    // Write back
+    write_itm(&itm_path, &Value::Dictionary(dict))?;
 
-    if let Some(rating) = update.rating {
-        dict.insert("rating".to_string(), Value::Integer(rating.into()));
-    }
+    Ok(true)
+}
 
-    if let Some(difficulty) = update.difficulty {
-        dict.insert("difficulty".to_string(), Value::Integer(difficulty.into()));
+/// Write a new ITM file for a score added by the CLI, with the fields set in
+/// `fields` and no bookmarks; an existing file is left alone
+pub fn create_itm(pdf_path: &str, fields: &ItmUpdate) -> Result<bool> {
+    let itm_path = itm_path_for_score(pdf_path)?;
+    if itm_path.exists() {
+        return Ok(false);
     }
 
-    // Write back
+    let mut dict = Dictionary::new();
+    fields.apply(&mut dict);
+    dict.insert("bookmarks".to_string(), Value::Array(vec![]));
     write_itm(&itm_path, &Value::Dictionary(dict))?;
 
     Ok(true)
@@ -163,6 +312,10 @@ pub struct ItmBookmarkUpdate {
     pub key: Option<i64>,
     pub rating: Option<i64>,
     pub difficulty: Option<i64>,
+    pub first_page: Option<i64>,
+    pub last_page: Option<i64>,
+    /// Keys to remove from the bookmark, for fields being cleared
+    pub remove: Vec<&'static str>,
 }
 
 impl ItmBookmarkUpdate {
@@ -174,6 +327,9 @@ impl ItmBookmarkUpdate {
             key: None,
             rating: None,
             difficulty: None,
+            first_page: None,
+            last_page: None,
+            remove: Vec::new(),
         }
     }
 
@@ -184,7 +340,130 @@ impl ItmBookmarkUpdate {
             && self.key.is_none()
             && self.rating.is_none()
             && self.difficulty.is_none()
+            && self.first_page.is_none()
+            && self.last_page.is_none()
+            && self.remove.is_empty()
+    }
+}
+
+/// A bookmark entry in an ITM file's bookmarks array
+#[derive(Debug, PartialEq)]
+pub struct ItmBookmark {
+    pub identifier: Option<String>,
+    pub title: String,
+    pub first_page: Option<i64>,
+    pub last_page: Option<i64>,
+}
+
+/// The bookmarks array of an ITM file
+pub fn itm_bookmarks(value: &Value) -> Vec<ItmBookmark> {
+    let Some(Value::Array(bookmarks)) = value.as_dictionary().and_then(|d| d.get("bookmarks"))
+    else {
+        return Vec::new();
+    };
+
+    bookmarks
+        .iter()
+        .filter_map(Value::as_dictionary)
+        .map(|bm| ItmBookmark {
+            identifier: bm
+                .get("Identifier")
+                .and_then(Value::as_string)
+                .map(str::to_string),
+            title: bm
+                .get("Title")
+                .and_then(Value::as_string)
+                .unwrap_or_default()
+                .to_string(),
+            first_page: bm.get("First Page").and_then(Value::as_signed_integer),
+            last_page: bm.get("Last Page").and_then(Value::as_signed_integer),
+        })
+        .collect()
+}
+
+/// A new bookmark entry to append to an ITM file
+pub struct ItmNewBookmark {
+    pub title: String,
+    pub identifier: String,
+    pub first_page: i64,
+    pub last_page: i64,
+}
+
+/// Append bookmarks to an ITM file, skipping identifiers already present
+pub fn add_bookmarks_to_itm(pdf_path: &str, new_bookmarks: &[ItmNewBookmark]) -> Result<bool> {
+    if new_bookmarks.is_empty() {
+        return Ok(false);
+    }
+
+    let itm_path = itm_path_for_score(pdf_path)?;
+
+    if !itm_path.exists() {
+        return Ok(false);
+    }
+
+    let value = read_itm(&itm_path)?;
+
+    // Convert to dictionary for modification
+    let mut dict = match value {
+        Value::Dictionary(d) => d,
+        _ => return Err(ForScoreError::Other("ITM file is not a dictionary".into())),
+    };
+
+    // Get or create bookmarks array
+    if !matches!(dict.get("bookmarks"), Some(Value::Array(_))) {
+        dict.insert("bookmarks".to_string(), Value::Array(vec![]));
+    }
+    let bookmarks = match dict.get_mut("bookmarks") {
+        Some(Value::Array(arr)) => arr,
+        _ => {
+            return Err(ForScoreError::Other(
+                "Failed to create bookmarks array".into(),
+            ))
+        }
+    };
+
+    let mut added = false;
+    for new_bookmark in new_bookmarks {
+        let exists = bookmarks.iter().any(|bookmark| match bookmark {
+            Value::Dictionary(bm_dict) => matches!(
+                bm_dict.get("Identifier"),
+                Some(Value::String(id)) if id == &new_bookmark.identifier
+            ),
+            _ => false,
+        });
+        if exists {
+            continue;
+        }
+
+        let mut bm_dict = plist::Dictionary::new();
+        bm_dict.insert(
+            "Title".to_string(),
+            Value::String(new_bookmark.title.clone()),
+        );
+        bm_dict.insert(
+            "Identifier".to_string(),
+            Value::String(new_bookmark.identifier.clone()),
+        );
+        bm_dict.insert(
+            "First Page".to_string(),
+            Value::Integer(new_bookmark.first_page.into()),
+        );
+        bm_dict.insert(
+            "Last Page".to_string(),
+            Value::Integer(new_bookmark.last_page.into()),
+        );
+        bookmarks.push(Value::Dictionary(bm_dict));
+        added = true;
+    }
+
+    if !added {
+        return Ok(false);
     }
+
+    // Write back
+    write_itm(&itm_path, &Value::Dictionary(dict))?;
+
+    Ok(true)
 }
 
 /// Delete a bookmark from an ITM file by UUID
@@ -237,6 +516,52 @@ pub fn delete_bookmark_from_itm(pdf_path: &str, bookmark_uuid: Option<&str>) ->
     Ok(true)
 }
 
+/// Give an ITM bookmark that has no identifier one, so the database row
+/// created for it matches it from then on. The entry is found by title and
+/// page range; returns false if there's no such entry.
+pub fn set_bookmark_identifier_in_itm(
+    pdf_path: &str,
+    bookmark: &ItmBookmark,
+    identifier: &str,
+) -> Result<bool> {
+    let itm_path = itm_path_for_score(pdf_path)?;
+    if !itm_path.exists() {
+        return Ok(false);
+    }
+
+    let mut dict = match read_itm(&itm_path)? {
+        Value::Dictionary(d) => d,
+        _ => return Err(ForScoreError::Other("ITM file is not a dictionary".into())),
+    };
+    let Some(Value::Array(bookmarks)) = dict.get_mut("bookmarks") else {
+        return Ok(false);
+    };
+
+    let entry = bookmarks
+        .iter_mut()
+        .filter_map(Value::as_dictionary_mut)
+        .find(|bm| {
+            bm.get("Identifier").is_none()
+                && bm
+                    .get("Title")
+                    .and_then(Value::as_string)
+                    .unwrap_or_default()
+                    == bookmark.title
+                && bm.get("First Page").and_then(Value::as_signed_integer) == bookmark.first_page
+                && bm.get("Last Page").and_then(Value::as_signed_integer) == bookmark.last_page
+        });
+    let Some(entry) = entry else {
+        return Ok(false);
+    };
+    entry.insert(
+        "Identifier".to_string(),
+        Value::String(identifier.to_string()),
+    );
+
+    write_itm(&itm_path, &Value::Dictionary(dict))?;
+    Ok(true)
+}
+
 /// Rename a composer across all ITM files (both score-level and bookmark-level)
 /// Returns (files_modified, score_fixes, bookmark_fixes)
 pub fn rename_composer_in_all_itm(old_name: &str, new_name: &str) -> Result<(usize, usize, usize)> {
@@ -247,14 +572,17 @@ pub fn rename_composer_in_all_itm(old_name: &str, new_name: &str) -> Result<(usi
     let mut bookmark_fixes = 0;
 
     // Iterate over all ITM files
-    let entries = std::fs::read_dir(&sync_folder)
-        .map_err(|e| ForScoreError::Other(format!("Cannot read sync folder: {}", e)))?;
+    let paths: Vec<PathBuf> = std::fs::read_dir(&sync_folder)
+        .map_err(|e| ForScoreError::Other(format!("Cannot read sync folder: {}", e)))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("itm"))
+        .collect();
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) != Some("itm") {
-            continue;
-        }
+    let progress = Progress::new(paths.len(), "Updating ITM files");
+
+    for path in paths {
+        progress.inc();
 
         let value = match read_itm(&path) {
             Ok(v) => v,
@@ -300,10 +628,94 @@ pub fn rename_composer_in_all_itm(old_name: &str, new_name: &str) -> Result<(usi
             files_modified += 1;
         }
     }
+    progress.finish();
 
     Ok((files_modified, score_fixes, bookmark_fixes))
 }
 
+/// Rename keywords across all ITM files using `rename`, which returns the new
+/// name for keywords that should change. Returns (files_modified, keyword_fixes).
+pub fn rename_keywords_in_all_itm(
+    rename: impl Fn(&str) -> Option<String>,
+) -> Result<(usize, usize)> {
+    replace_keywords_in_all_itm(|name| rename(name).map(Some))
+}
+
+/// Remove a keyword (ignoring case) from all ITM files.
+/// Returns (files_modified, keyword_fixes).
+pub fn remove_keyword_from_all_itm(name: &str) -> Result<(usize, usize)> {
+    replace_keywords_in_all_itm(|keyword| keyword.eq_ignore_ascii_case(name).then_some(None))
+}
+
+/// Change keywords across all ITM files: `replace` returns Some(new name) to
+/// rename a keyword, Some(None) to drop it. A rename onto a keyword the file
+/// already has leaves just the one
+fn replace_keywords_in_all_itm(
+    replace: impl Fn(&str) -> Option<Option<String>>,
+) -> Result<(usize, usize)> {
+    let sync_folder = sync_folder_path()?;
+
+    let mut files_modified = 0;
+    let mut keyword_fixes = 0;
+
+    let paths: Vec<PathBuf> = std::fs::read_dir(&sync_folder)
+        .map_err(|e| ForScoreError::Other(format!("Cannot read sync folder: {}", e)))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("itm"))
+        .collect();
+
+    let progress = Progress::new(paths.len(), "Updating ITM files");
+
+    for path in paths {
+        progress.inc();
+
+        let mut dict = match read_itm(&path) {
+            Ok(Value::Dictionary(d)) => d,
+            _ => continue, // Skip unreadable files
+        };
+
+        let mut modified = false;
+
+        if let Some(Value::Array(keywords)) = dict.get_mut("keywords") {
+            let mut kept: Vec<Value> = Vec::with_capacity(keywords.len());
+            for keyword in keywords.drain(..) {
+                let keyword = match &keyword {
+                    Value::String(name) => match replace(name) {
+                        Some(new_name) => {
+                            keyword_fixes += 1;
+                            modified = true;
+                            new_name.map(Value::String)
+                        }
+                        None => Some(keyword),
+                    },
+                    _ => Some(keyword),
+                };
+                let Some(keyword) = keyword else {
+                    continue;
+                };
+                let duplicate = keyword.as_string().is_some_and(|name| {
+                    kept.iter()
+                        .filter_map(Value::as_string)
+                        .any(|k| k.eq_ignore_ascii_case(name))
+                });
+                if !duplicate {
+                    kept.push(keyword);
+                }
+            }
+            *keywords = kept;
+        }
+
+        if modified {
+            write_itm(&path, &Value::Dictionary(dict))?;
+            files_modified += 1;
+        }
+    }
+    progress.finish();
+
+    Ok((files_modified, keyword_fixes))
+}
+
 /// Update a bookmark within an ITM file
 pub fn update_bookmark_in_itm(
     pdf_path: &str,
@@ -377,6 +789,18 @@ pub fn update_bookmark_in_itm(
                     bm_dict.insert("Difficulty".to_string(), Value::Integer(difficulty.into()));
                 }
 
+                if let Some(first_page) = update.first_page {
+                    bm_dict.insert("First Page".to_string(), Value::Integer(first_page.into()));
+                }
+
+                if let Some(last_page) = update.last_page {
+                    bm_dict.insert("Last Page".to_string(), Value::Integer(last_page.into()));
+                }
+
+                for key in &update.remove {
+                    bm_dict.remove(key);
+                }
+
                 break;
             }
         }
@@ -391,3 +815,67 @@ pub fn update_bookmark_in_itm(
 
     Ok(true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_plist_if_changed() {
+        let path = std::env::temp_dir().join(format!("forscore-test-{}.itm", std::process::id()));
+        let mut dict = plist::Dictionary::new();
+        dict.insert("title".to_string(), Value::String("Prelude".to_string()));
+        let value = Value::Dictionary(dict.clone());
+
+        assert!(write_plist_if_changed(&path, &value).unwrap());
+        assert!(!write_plist_if_changed(&path, &value).unwrap());
+
+        dict.insert("rating".to_string(), Value::Integer(5.into()));
+        assert!(write_plist_if_changed(&path, &Value::Dictionary(dict)).unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_itm_bookmarks() {
+        let mut bookmark = Dictionary::new();
+        bookmark.insert("Title".to_string(), Value::String("Lesson 1".to_string()));
+        bookmark.insert("Identifier".to_string(), Value::String("BM-1".to_string()));
+        bookmark.insert("First Page".to_string(), Value::Integer(1.into()));
+        bookmark.insert("Last Page".to_string(), Value::Integer(4.into()));
+        let mut dict = Dictionary::new();
+        dict.insert(
+            "bookmarks".to_string(),
+            Value::Array(vec![Value::Dictionary(bookmark)]),
+        );
+
+        assert_eq!(
+            itm_bookmarks(&Value::Dictionary(dict)),
+            vec![ItmBookmark {
+                identifier: Some("BM-1".to_string()),
+                title: "Lesson 1".to_string(),
+                first_page: Some(1),
+                last_page: Some(4),
+            }]
+        );
+        assert!(itm_bookmarks(&Value::Dictionary(Dictionary::new())).is_empty());
+    }
+
+    #[test]
+    fn test_update_keeps_unknown_keys() {
+        let mut dict = Dictionary::new();
+        dict.insert("title".to_string(), Value::String("Prelude".to_string()));
+        dict.insert("cueIdentifier".to_string(), Value::Integer(42.into()));
+
+        let mut update = ItmUpdate::new();
+        update.rating = Some(5);
+        update.apply(&mut dict);
+
+        assert_eq!(dict.get("cueIdentifier"), Some(&Value::Integer(42.into())));
+        assert_eq!(dict.get("rating"), Some(&Value::Integer(5.into())));
+        assert_eq!(
+            plist_to_json(&Value::Dictionary(dict)),
+            serde_json::json!({"title": "Prelude", "cueIdentifier": 42, "rating": 5})
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! The library as one JSON document, for `export json` and `import json`
+//!
+//! Scores are sorted by path and carry their bookmarks; setlists are sorted
+//! by title and refer to their items by UUID and path, so the document
+//! reads the same from one export to the next and can be applied to a
+//! library on another device.
+
+use crate::cache::load_page_counts;
+use crate::error::{ForScoreError, Result};
+use crate::models::score::{
+    list_bookmarks, list_scores_in_setlist, list_scores_with_metadata, Bookmark,
+};
+use crate::models::setlist::list_setlists;
+use crate::models::Score;
+use chrono::Timelike;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+/// Bumped when the document changes shape
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LibraryJson {
+    pub version: u32,
+    pub scores: Vec<ScoreJson>,
+    pub setlists: Vec<SetlistJson>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScoreJson {
+    #[serde(flatten)]
+    pub score: Score,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetlistJson {
+    pub title: String,
+    pub uuid: Option<String>,
+    pub items: Vec<SetlistItemJson>,
+}
+
+/// A setlist entry: a score, or a bookmark (with its page range) in the
+/// score at `path`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetlistItemJson {
+    pub uuid: Option<String>,
+    pub path: String,
+    pub title: String,
+    pub start_page: Option<i32>,
+    pub end_page: Option<i32>,
+}
+
+/// Read the whole library
+pub fn read_library(conn: &Connection) -> Result<LibraryJson> {
+    let mut scores = list_scores_with_metadata(conn)?;
+    load_page_counts(conn, &mut scores)?;
+    scores.sort_by(|a, b| a.path.cmp(&b.path).then(a.id.cmp(&b.id)));
+
+    let mut score_docs = Vec::with_capacity(scores.len());
+    for score in scores {
+        let mut bookmarks = list_bookmarks(conn, score.id)?;
+        for bookmark in &mut bookmarks {
+            bookmark.load_metadata(conn)?;
+        }
+        score_docs.push(ScoreJson { score, bookmarks });
+    }
+
+    let mut setlists = list_setlists(conn)?;
+    setlists.sort_by(|a, b| a.title.cmp(&b.title).then(a.id.cmp(&b.id)));
+    let mut setlist_docs = Vec::with_capacity(setlists.len());
+    for setlist in setlists {
+        let items = list_scores_in_setlist(conn, setlist.id)?
+            .into_iter()
+            .map(|item| SetlistItemJson {
+                uuid: item.uuid,
+                path: item.path,
+                title: item.title,
+                start_page: item.start_page,
+                end_page: item.end_page,
+            })
+            .collect();
+        setlist_docs.push(SetlistJson {
+            title: setlist.title,
+            uuid: setlist.uuid,
+            items,
+        });
+    }
+
+    Ok(LibraryJson {
+        version: VERSION,
+        scores: score_docs,
+        setlists: setlist_docs,
+    })
+}
+
+impl LibraryJson {
+    /// Reorder for `--stable`: scores, bookmarks and setlists by UUID, list
+    /// fields sorted and dates cut to whole seconds (setlist items keep
+    /// their order, which is part of the setlist)
+    pub fn stabilize(&mut self) {
+        self.scores
+            .sort_by_key(|s| stable_key(&s.score.uuid, &s.score.path));
+        for doc in &mut self.scores {
+            stabilize_score(&mut doc.score);
+            doc.bookmarks.sort_by_key(|b| stable_key(&b.uuid, &b.title));
+            for bookmark in &mut doc.bookmarks {
+                bookmark.composers.sort();
+                bookmark.genres.sort();
+            }
+        }
+        self.setlists.sort_by_key(|s| stable_key(&s.uuid, &s.title));
+    }
+}
+
+/// Sort key for `--stable` output: by UUID, then by `fallback` (a path or
+/// title) for items without one, which come last
+pub fn stable_key(uuid: &Option<String>, fallback: &str) -> (bool, String, String) {
+    (
+        uuid.is_none(),
+        uuid.clone().unwrap_or_default(),
+        fallback.to_string(),
+    )
+}
+
+/// Sort a score's list fields and cut its dates to whole seconds (Core
+/// Data stores them as floats), so an unchanged score always writes the
+/// same way
+pub fn stabilize_score(score: &mut Score) {
+    for list in [
+        &mut score.composers,
+        &mut score.genres,
+        &mut score.keywords,
+        &mut score.labels,
+        &mut score.libraries,
+    ] {
+        list.sort();
+    }
+    for date in [
+        &mut score.added,
+        &mut score.modified,
+        &mut score.last_played,
+    ] {
+        *date = date.and_then(|d| d.with_nanosecond(0));
+    }
+}
+
+/// Parse a document, refusing versions this build doesn't know
+pub fn parse_library(text: &str) -> Result<LibraryJson> {
+    let library: LibraryJson = serde_json::from_str(text)
+        .map_err(|e| ForScoreError::Other(format!("Not a library export: {}", e)))?;
+    if library.version > VERSION {
+        return Err(ForScoreError::Other(format!(
+            "This export is version {}; this forscore-cli reads up to version {}",
+            library.version, VERSION
+        )));
+    }
+    Ok(library)
+}
@@ -1,17 +1,41 @@
+mod cache;
 mod cli;
 mod commands;
+mod config;
+mod csv_format;
+mod csv_mapping;
 mod db;
+mod dry_run;
 mod error;
+mod export_diff;
+mod fts;
+mod fuzzy;
+mod hooks;
 mod itm;
+mod library_json;
 mod models;
 mod output;
+mod platform;
+mod profile;
+mod progress;
+mod query;
+mod schema;
 mod setlist_sync;
+mod snapshot;
+mod sort_title;
+mod store;
+mod sync_state;
+mod template;
+mod touch;
+mod trash;
 
 use clap::Parser;
-use cli::{Cli, Commands, SyncCommand};
+use cli::{Cli, Commands, FixtureCommand, SyncCommand};
 
 fn main() {
-    if let Err(e) = run() {
+    let result = run();
+    profile::report();
+    if let Err(e) = result {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
@@ -19,6 +43,20 @@ fn main() {
 
 fn run() -> error::Result<()> {
     let cli = Cli::parse();
+    if cli.profile {
+        profile::enable();
+    }
+    platform::set_path_overrides(platform::PathOverrides {
+        db: cli.db,
+        sync_dir: cli.sync_dir,
+    });
+    if let Some(wait) = cli.wait {
+        db::set_lock_wait(std::time::Duration::from_secs(wait));
+    }
+    output::set_format(cli.format);
+    if let Some(template) = &cli.template {
+        output::set_template(output::Template::parse(template)?);
+    }
 
     match cli.command {
         Commands::Scores { command } => commands::scores::handle(command)?,
@@ -33,13 +71,39 @@ fn run() -> error::Result<()> {
 
         Commands::Tags { command } => commands::metadata::handle_tags(command)?,
 
+        Commands::Meta { command } => commands::metadata::handle_meta(command)?,
+
         Commands::Export { command } => commands::export::handle(command)?,
 
         Commands::Import { command } => commands::import::handle(command)?,
 
         Commands::Bookmarks { command } => commands::bookmarks::handle(command)?,
 
-        Commands::Info => commands::utils::info()?,
+        Commands::Tracks { command } => commands::tracks::handle(command)?,
+        Commands::Trash { command } => commands::trash::handle(command)?,
+
+        Commands::Find { query, limit, json } => commands::find::find(query, limit, json)?,
+
+        Commands::Info {
+            paths,
+            json,
+            min_complete,
+            min_rating,
+            min_key,
+            min_composer,
+        } => {
+            if paths {
+                commands::utils::paths()?
+            } else {
+                let thresholds = commands::utils::Thresholds::new(
+                    min_complete,
+                    min_rating,
+                    min_key,
+                    min_composer,
+                );
+                commands::utils::info(json, &thresholds)?
+            }
+        }
 
         Commands::Backup { output } => commands::utils::backup(output)?,
 
@@ -47,10 +111,35 @@ fn run() -> error::Result<()> {
             None => commands::utils::sync_status()?,
             Some(SyncCommand::Log { limit }) => commands::utils::sync_log(limit)?,
             Some(SyncCommand::Trigger) => commands::utils::sync_trigger()?,
+            Some(SyncCommand::ExportState { json }) => commands::utils::sync_export_state(json)?,
+            Some(SyncCommand::StaleDb { threshold, json }) => {
+                commands::utils::sync_stale_db(threshold, json)?
+            }
         },
 
         Commands::Fixes { command } => commands::fixes::handle(command)?,
+
+        Commands::Itm { command } => commands::itm::handle(command)?,
+
+        Commands::Fixture { command } => match command {
+            FixtureCommand::Create {
+                output,
+                scores,
+                anonymize,
+                force,
+            } => commands::fixture::create(&output, scores, anonymize, force)?,
+        },
+
+        Commands::Migrate {
+            from,
+            dry_run,
+            json,
+        } => commands::migrate::migrate(&from, dry_run, json)?,
+
+        Commands::Alias { command } => commands::alias::handle(command)?,
+
+        Commands::Raw { command } => commands::raw::handle(command)?,
     }
 
-    Ok(())
+    hooks::run_hooks()
 }
@@ -0,0 +1,111 @@
+//! Work groups: scores that make up one work (orchestra parts, movements in
+//! separate PDFs), kept together by a shared tag nested under "Work", e.g.
+//! "Work/Symphony No. 5". forScore shows the tag like any other, and
+//! `scores search --tag Work` finds every grouped score.
+
+use crate::error::{ForScoreError, Result};
+use crate::fuzzy::{best_match, with_suggestions, FuzzyMatch};
+use crate::models::meta::{list_keywords, tag_score, untag_score, HIERARCHY_SEPARATOR};
+use crate::models::score::{load_metadata_batch, search_scores, ScoreFilter};
+use crate::models::Score;
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// Parent tag of every group tag
+pub const GROUP_TAG: &str = "Work";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkGroup {
+    pub name: String,
+    pub score_count: i32,
+}
+
+/// The tag that marks a score as part of a group
+pub fn group_tag(name: &str) -> String {
+    format!("{}{}{}", GROUP_TAG, HIERARCHY_SEPARATOR, name)
+}
+
+/// The group a tag marks, if it is a group tag
+fn group_name(tag: &str) -> Option<&str> {
+    tag.strip_prefix(GROUP_TAG)?
+        .strip_prefix(HIERARCHY_SEPARATOR)
+        .filter(|name| !name.is_empty())
+}
+
+/// All groups with at least one score, by name
+pub fn list_groups(conn: &Connection) -> Result<Vec<WorkGroup>> {
+    Ok(list_keywords(conn, false)?
+        .into_iter()
+        .filter(|k| k.score_count > 0)
+        .filter_map(|k| {
+            group_name(&k.name).map(|name| WorkGroup {
+                name: name.to_string(),
+                score_count: k.score_count,
+            })
+        })
+        .collect())
+}
+
+/// A group's scores in title order, with metadata loaded
+pub fn group_members(conn: &Connection, name: &str) -> Result<Vec<Score>> {
+    let filter = ScoreFilter {
+        tag: Some(group_tag(name)),
+        scores_only: true,
+        ..Default::default()
+    };
+    let mut scores = search_scores(conn, &filter, usize::MAX)?;
+    // The tag filter also matches groups nested beneath this one
+    load_metadata_batch(conn, &mut scores)?;
+    let tag = group_tag(name);
+    scores.retain(|s| s.keywords.iter().any(|k| k.eq_ignore_ascii_case(&tag)));
+    Ok(scores)
+}
+
+/// Like `group_members`, but an empty group is an error naming close matches
+pub fn resolve_group(conn: &Connection, name: &str) -> Result<Vec<Score>> {
+    let members = group_members(conn, name)?;
+    if !members.is_empty() {
+        return Ok(members);
+    }
+    let groups = list_groups(conn)?;
+    let names: Vec<&str> = groups.iter().map(|g| g.name.as_str()).collect();
+    let close: Vec<&str> = match best_match(name, &names, |n| n) {
+        FuzzyMatch::Match(n) => vec![*n],
+        FuzzyMatch::Suggestions(s) => s.into_iter().copied().collect(),
+    };
+    Err(ForScoreError::Other(format!(
+        "No work group {}",
+        with_suggestions(&format!("'{}'", name), &close)
+    )))
+}
+
+/// Tag a score as part of a group. Returns its tags afterwards, or None if
+/// it was already in the group
+pub fn add_to_group(conn: &Connection, score: &Score, name: &str) -> Result<Option<Vec<String>>> {
+    tag_score(conn, score, &group_tag(name))
+}
+
+/// Untag a score from a group. Returns its tags afterwards, or None if it
+/// wasn't in the group
+pub fn remove_from_group(
+    conn: &Connection,
+    score: &Score,
+    name: &str,
+) -> Result<Option<Vec<String>>> {
+    untag_score(conn, score, &group_tag(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_name() {
+        assert_eq!(group_tag("Symphony No. 5"), "Work/Symphony No. 5");
+        assert_eq!(group_name("Work/Symphony No. 5"), Some("Symphony No. 5"));
+        assert_eq!(group_name("Work/Suite/Prelude"), Some("Suite/Prelude"));
+        assert_eq!(group_name("Work"), None);
+        assert_eq!(group_name("Work/"), None);
+        assert_eq!(group_name("Workshop"), None);
+    }
+}
@@ -10,7 +10,7 @@ pub struct MusicalKey {
 }
 
 impl MusicalKey {
-    /// Parse a key code (e.g., 110 = C Major, 311 = E Minor)
+    /// Parse a key code (e.g., 100 = C Major, 301 = E Minor, 411 = F# Minor)
     /// Format: first digit = note (1-7 = C-B), second = sharp (0/1), third = mode (0=major, 1=minor)
     pub fn from_code(code: i32) -> Option<Self> {
         if code <= 0 {
@@ -91,6 +91,80 @@ impl MusicalKey {
     pub fn display(&self) -> String {
         format!("{} {}", self.note, self.mode)
     }
+
+    pub fn is_minor(&self) -> bool {
+        self.code % 10 == 1
+    }
+
+    /// Pitch class of the tonic (C = 0 ... B = 11)
+    pub fn pitch_class(&self) -> i32 {
+        let natural = match self.code / 100 {
+            1 => 0,
+            2 => 2,
+            3 => 4,
+            4 => 5,
+            5 => 7,
+            6 => 9,
+            _ => 11,
+        };
+        (natural + (self.code / 10) % 10) % 12
+    }
+
+    /// Key signature as sharps (positive) or flats (negative), from 5 flats
+    /// to 6 sharps; a minor key has its relative major's signature
+    pub fn fifths(&self) -> i32 {
+        let major = if self.is_minor() {
+            (self.pitch_class() + 3) % 12
+        } else {
+            self.pitch_class()
+        };
+        let sharps = (major * 7) % 12;
+        if sharps > 6 {
+            sharps - 12
+        } else {
+            sharps
+        }
+    }
+
+    /// Key signature for display, e.g. "2#", "3b" or "0"
+    pub fn signature(&self) -> String {
+        match self.fifths() {
+            0 => "0".to_string(),
+            n if n > 0 => format!("{}#", n),
+            n => format!("{}b", -n),
+        }
+    }
+
+    /// Semitones up from this key's tonic to `other`'s (0-11)
+    pub fn semitones_to(&self, other: &MusicalKey) -> i32 {
+        (other.pitch_class() - self.pitch_class()).rem_euclid(12)
+    }
+
+    /// Steps between the two key signatures around the circle of fifths
+    /// (0-6); a major key and its relative minor are 0 apart
+    pub fn fifths_between(&self, other: &MusicalKey) -> i32 {
+        let steps = (self.fifths() - other.fifths()).rem_euclid(12);
+        steps.min(12 - steps)
+    }
+}
+
+/// How the tonic moves over `semitones` (0-11), taking the shorter way:
+/// "up a minor 3rd", "down a major 2nd", "a tritone"
+pub fn describe_interval(semitones: i32) -> String {
+    const NAMES: [&str; 6] = [
+        "unison",
+        "minor 2nd",
+        "major 2nd",
+        "minor 3rd",
+        "major 3rd",
+        "perfect 4th",
+    ];
+    match semitones.rem_euclid(12) {
+        0 => "same tonic".to_string(),
+        6 => "a tritone".to_string(),
+        s if s < 6 => format!("up a {}", NAMES[s as usize]),
+        s => format!("down a {}", NAMES[(12 - s) as usize]),
+    }
 }
 
 impl std::fmt::Display for MusicalKey {
@@ -105,17 +179,46 @@ mod tests {
 
     #[test]
     fn test_from_code() {
-        assert_eq!(MusicalKey::from_code(110).unwrap().display(), "C Major");
-        assert_eq!(MusicalKey::from_code(111).unwrap().display(), "C Minor");
-        assert_eq!(MusicalKey::from_code(310).unwrap().display(), "E Major");
-        assert_eq!(MusicalKey::from_code(311).unwrap().display(), "E Minor");
-        assert_eq!(MusicalKey::from_code(410).unwrap().display(), "F Major");
-        assert_eq!(MusicalKey::from_code(510).unwrap().display(), "G Major");
+        assert_eq!(MusicalKey::from_code(100).unwrap().display(), "C Major");
+        assert_eq!(MusicalKey::from_code(101).unwrap().display(), "C Minor");
+        assert_eq!(MusicalKey::from_code(300).unwrap().display(), "E Major");
+        assert_eq!(MusicalKey::from_code(301).unwrap().display(), "E Minor");
+        assert_eq!(MusicalKey::from_code(400).unwrap().display(), "F Major");
+        assert_eq!(MusicalKey::from_code(500).unwrap().display(), "G Major");
+        assert_eq!(MusicalKey::from_code(411).unwrap().display(), "F# Minor");
+    }
+
+    #[test]
+    fn test_fifths() {
+        let fifths = |s: &str| MusicalKey::from_string(s).unwrap().fifths();
+        assert_eq!(fifths("C Major"), 0);
+        assert_eq!(fifths("A Minor"), 0);
+        assert_eq!(fifths("D Major"), 2);
+        assert_eq!(fifths("F# Major"), 6);
+        assert_eq!(fifths("Bb Major"), -2);
+        assert_eq!(fifths("C Minor"), -3);
+        assert_eq!(fifths("C# Minor"), 4);
+        assert_eq!(MusicalKey::from_code(610).unwrap().signature(), "2b");
+    }
+
+    #[test]
+    fn test_intervals() {
+        let key = |s: &str| MusicalKey::from_string(s).unwrap();
+        assert_eq!(key("C Major").semitones_to(&key("Eb Major")), 3);
+        assert_eq!(key("A Minor").semitones_to(&key("C Major")), 3);
+        assert_eq!(key("G Major").semitones_to(&key("C Major")), 5);
+        assert_eq!(key("C Major").fifths_between(&key("A Minor")), 0);
+        assert_eq!(key("C Major").fifths_between(&key("F# Major")), 6);
+        assert_eq!(key("B Major").fifths_between(&key("Db Major")), 2);
+        assert_eq!(describe_interval(3), "up a minor 3rd");
+        assert_eq!(describe_interval(10), "down a major 2nd");
+        assert_eq!(describe_interval(6), "a tritone");
+        assert_eq!(describe_interval(0), "same tonic");
     }
 
     #[test]
     fn test_from_string() {
-        assert_eq!(MusicalKey::from_string("C Major").unwrap().code, 110);
+        assert_eq!(MusicalKey::from_string("C Major").unwrap().code, 100);
         assert_eq!(MusicalKey::from_string("F# Minor").unwrap().code, 411);
         assert_eq!(MusicalKey::from_string("Bb Major").unwrap().code, 610);
     }
@@ -1,4 +1,6 @@
+use crate::db::entity;
 use crate::error::{ForScoreError, Result};
+use crate::fuzzy::{best_match, note_closest, with_suggestions, FuzzyMatch};
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 
@@ -112,14 +114,83 @@ pub fn get_library_by_name(conn: &Connection, name: &str) -> Result<Library> {
     }
 }
 
-/// Resolve library by ID or name
+/// The library whose name is closest to `name`, allowing for typos
+fn closest_library(conn: &Connection, name: &str) -> Result<Library> {
+    let all = list_libraries(conn)?;
+    match best_match(name, &all, |l| &l.title) {
+        FuzzyMatch::Match(library) => {
+            note_closest(name, &library.title);
+            Ok(library.clone())
+        }
+        FuzzyMatch::Suggestions(closest) => {
+            let names: Vec<&str> = closest.iter().map(|l| l.title.as_str()).collect();
+            Err(ForScoreError::LibraryNotFound(with_suggestions(
+                name, &names,
+            )))
+        }
+    }
+}
+
+/// Resolve library by ID or name, falling back to the closest name
 pub fn resolve_library(conn: &Connection, identifier: &str) -> Result<Library> {
     if let Ok(id) = identifier.parse::<i64>() {
         if let Ok(library) = get_library_by_id(conn, id) {
             return Ok(library);
         }
     }
-    get_library_by_name(conn, identifier)
+    match get_library_by_name(conn, identifier) {
+        Err(ForScoreError::LibraryNotFound(_)) => closest_library(conn, identifier),
+        result => result,
+    }
+}
+
+/// Create a new library
+pub fn create_library(conn: &Connection, name: &str) -> Result<Library> {
+    let max_pk: i64 = conn.query_row("SELECT COALESCE(MAX(Z_PK), 0) FROM ZLIBRARY", [], |row| {
+        row.get(0)
+    })?;
+    let max_index: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(ZINDEX), -1) FROM ZLIBRARY",
+        [],
+        |row| row.get(0),
+    )?;
+
+    conn.execute(
+        "INSERT INTO ZLIBRARY (Z_PK, Z_ENT, Z_OPT, ZTITLE, ZINDEX) VALUES (?, ?, 1, ?, ?)",
+        rusqlite::params![max_pk + 1, entity::library(), name, max_index + 1],
+    )?;
+
+    conn.execute(
+        "UPDATE Z_PRIMARYKEY SET Z_MAX = ? WHERE Z_ENT = ?",
+        [max_pk + 1, entity::library() as i64],
+    )?;
+
+    get_library_by_id(conn, max_pk + 1)
+}
+
+/// Libraries an item belongs to
+pub fn list_item_libraries(conn: &Connection, item_id: i64) -> Result<Vec<Library>> {
+    let mut stmt = conn.prepare(
+        "SELECT l.Z_PK, l.ZTITLE,
+                (SELECT COUNT(*) FROM Z_4LIBRARIES z WHERE z.Z_7LIBRARIES = l.Z_PK) as score_count
+         FROM ZLIBRARY l
+         JOIN Z_4LIBRARIES m ON m.Z_7LIBRARIES = l.Z_PK
+         WHERE m.Z_4ITEMS3 = ?
+         ORDER BY l.ZTITLE",
+    )?;
+
+    let libraries: Vec<Library> = stmt
+        .query_map([item_id], |row| {
+            Ok(Library {
+                id: row.get("Z_PK")?,
+                title: row.get("ZTITLE")?,
+                score_count: row.get("score_count")?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(libraries)
 }
 
 /// Add a score to a library
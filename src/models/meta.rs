@@ -1,13 +1,22 @@
 use crate::db::entity;
 use crate::error::{ForScoreError, Result};
+use crate::models::Score;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 
+/// Separator for hierarchical tag and genre names ("Jazz/Bebop")
+pub const HIERARCHY_SEPARATOR: char = '/';
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Composer {
     pub id: i64,
     pub name: String,
     pub score_count: i32,
+    /// Lifespan from the sidecar store, e.g. "1685-1750"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub years: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nationality: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,11 +51,13 @@ pub fn list_composers(conn: &Connection, unused_only: bool) -> Result<Vec<Compos
     let mut stmt = conn.prepare(sql)?;
 
     let composers: Vec<Composer> = stmt
-        .query_map([entity::COMPOSER], |row| {
+        .query_map([entity::composer()], |row| {
             Ok(Composer {
                 id: row.get("Z_PK")?,
                 name: row.get::<_, Option<String>>("ZVALUE")?.unwrap_or_default(),
                 score_count: row.get("score_count")?,
+                years: None,
+                nationality: None,
             })
         })?
         .filter_map(|r| r.ok())
@@ -64,11 +75,13 @@ pub fn get_composer_by_name(conn: &Connection, name: &str) -> Result<Composer> {
          FROM ZMETA m WHERE m.Z_ENT = ? AND m.ZVALUE = ?",
     )?;
 
-    stmt.query_row(rusqlite::params![entity::COMPOSER, name], |row| {
+    stmt.query_row(rusqlite::params![entity::composer(), name], |row| {
         Ok(Composer {
             id: row.get("Z_PK")?,
             name: row.get::<_, Option<String>>("ZVALUE")?.unwrap_or_default(),
             score_count: row.get("score_count")?,
+            years: None,
+            nationality: None,
         })
     })
     .map_err(|_| ForScoreError::ComposerNotFound(name.to_string()))
@@ -78,7 +91,7 @@ pub fn get_composer_by_name(conn: &Connection, name: &str) -> Result<Composer> {
 pub fn rename_composer(conn: &Connection, old_name: &str, new_name: &str) -> Result<()> {
     let affected = conn.execute(
         "UPDATE ZMETA SET ZVALUE = ? WHERE Z_ENT = ? AND ZVALUE = ?",
-        rusqlite::params![new_name, entity::COMPOSER, old_name],
+        rusqlite::params![new_name, entity::composer(), old_name],
     )?;
 
     if affected == 0 {
@@ -92,6 +105,13 @@ pub fn merge_composers(conn: &Connection, source_name: &str, target_name: &str)
     let source = get_composer_by_name(conn, source_name)?;
     let target = get_composer_by_name(conn, target_name)?;
 
+    // Items linked to both keep a single link
+    conn.execute(
+        "DELETE FROM Z_4COMPOSERS WHERE Z_10COMPOSERS = ?1 AND Z_4ITEMS1 IN
+            (SELECT Z_4ITEMS1 FROM Z_4COMPOSERS WHERE Z_10COMPOSERS = ?2)",
+        [source.id, target.id],
+    )?;
+
     // Update all references
     conn.execute(
         "UPDATE Z_4COMPOSERS SET Z_10COMPOSERS = ? WHERE Z_10COMPOSERS = ?",
@@ -104,6 +124,37 @@ pub fn merge_composers(conn: &Connection, source_name: &str, target_name: &str)
     Ok(())
 }
 
+/// Scores and bookmarks linked to a composer, as (id, Z_ENT, title)
+pub fn composer_items(conn: &Connection, composer_id: i64) -> Result<Vec<(i64, i32, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.Z_PK, i.Z_ENT, i.ZTITLE
+         FROM Z_4COMPOSERS c JOIN ZITEM i ON c.Z_4ITEMS1 = i.Z_PK
+         WHERE c.Z_10COMPOSERS = ?
+         ORDER BY i.ZTITLE",
+    )?;
+    let items = stmt
+        .query_map([composer_id], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(items)
+}
+
+/// Delete a composer, unlinking it from any items first
+pub fn delete_composer(conn: &Connection, composer_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM Z_4COMPOSERS WHERE Z_10COMPOSERS = ?",
+        [composer_id],
+    )?;
+    conn.execute("DELETE FROM ZMETA WHERE Z_PK = ?", [composer_id])?;
+    Ok(())
+}
+
 /// List all genres
 pub fn list_genres(conn: &Connection, unused_only: bool) -> Result<Vec<Genre>> {
     let sql = "SELECT m.Z_PK, m.ZVALUE2,
@@ -114,7 +165,7 @@ pub fn list_genres(conn: &Connection, unused_only: bool) -> Result<Vec<Genre>> {
     let mut stmt = conn.prepare(sql)?;
 
     let genres: Vec<Genre> = stmt
-        .query_map([entity::GENRE], |row| {
+        .query_map([entity::genre()], |row| {
             Ok(Genre {
                 id: row.get("Z_PK")?,
                 name: row.get::<_, Option<String>>("ZVALUE2")?.unwrap_or_default(),
@@ -138,7 +189,7 @@ pub fn list_keywords(conn: &Connection, unused_only: bool) -> Result<Vec<Keyword
     let mut stmt = conn.prepare(sql)?;
 
     let keywords: Vec<Keyword> = stmt
-        .query_map([entity::KEYWORD], |row| {
+        .query_map([entity::keyword()], |row| {
             Ok(Keyword {
                 id: row.get("Z_PK")?,
                 name: row.get::<_, Option<String>>("ZVALUE")?.unwrap_or_default(),
@@ -152,6 +203,146 @@ pub fn list_keywords(conn: &Connection, unused_only: bool) -> Result<Vec<Keyword
     Ok(keywords)
 }
 
+/// Rename a keyword and every keyword nested beneath it ("Jazz" also renames "Jazz/Bebop").
+/// Returns the renamed (old, new) names.
+pub fn rename_keyword_subtree(
+    conn: &Connection,
+    old_name: &str,
+    new_name: &str,
+) -> Result<Vec<(String, String)>> {
+    let keywords = list_keywords(conn, false)?;
+    let renames: Vec<(String, String)> = keywords
+        .iter()
+        .filter_map(|k| rename_in_subtree(&k.name, old_name, new_name).map(|n| (k.name.clone(), n)))
+        .collect();
+
+    if renames.is_empty() {
+        return Err(ForScoreError::Other(format!("Tag not found: {}", old_name)));
+    }
+
+    // A renamed tag must not land on one that stays, or on another renamed tag
+    for (i, (old, new)) in renames.iter().enumerate() {
+        let existing = keywords
+            .iter()
+            .map(|k| &k.name)
+            .filter(|name| !renames.iter().any(|(o, _)| o == *name))
+            .chain(renames[..i].iter().map(|(_, n)| n))
+            .find(|name| name.eq_ignore_ascii_case(new));
+        if let Some(existing) = existing {
+            return Err(ForScoreError::Other(format!(
+                "Cannot rename '{}' to '{}': tag '{}' already exists (use `tags merge`)",
+                old, new, existing
+            )));
+        }
+    }
+
+    for (old, new) in &renames {
+        conn.execute(
+            "UPDATE ZMETA SET ZVALUE = ? WHERE Z_ENT = ? AND ZVALUE = ?",
+            rusqlite::params![new, entity::keyword(), old],
+        )?;
+    }
+
+    Ok(renames)
+}
+
+/// If `name` is `root` or nested beneath it (ignoring case), return it with `root` replaced by `new_root`
+pub fn rename_in_subtree(name: &str, root: &str, new_root: &str) -> Option<String> {
+    let rest = name
+        .get(..root.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(root))
+        .map(|_| &name[root.len()..])?;
+    if rest.is_empty() || rest.starts_with(HIERARCHY_SEPARATOR) {
+        Some(format!("{}{}", new_root, rest))
+    } else {
+        None
+    }
+}
+
+/// Get a tag by name, ignoring case
+pub fn get_keyword_by_name(conn: &Connection, name: &str) -> Result<Keyword> {
+    list_keywords(conn, false)?
+        .into_iter()
+        .find(|k| k.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| ForScoreError::Other(format!("Tag not found: {}", name)))
+}
+
+/// Items tagged with a keyword, as (id, Z_ENT, title)
+pub fn keyword_items(conn: &Connection, keyword_id: i64) -> Result<Vec<(i64, i32, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.Z_PK, i.Z_ENT, i.ZTITLE
+         FROM Z_4KEYWORDS k JOIN ZITEM i ON k.Z_4ITEMS5 = i.Z_PK
+         WHERE k.Z_13KEYWORDS = ?
+         ORDER BY i.ZTITLE",
+    )?;
+    let items = stmt
+        .query_map([keyword_id], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(items)
+}
+
+/// Merge tags: move all items from source to target, then delete source
+pub fn merge_keywords(conn: &Connection, source_id: i64, target_id: i64) -> Result<()> {
+    // Items tagged with both keep a single link
+    conn.execute(
+        "DELETE FROM Z_4KEYWORDS WHERE Z_13KEYWORDS = ?1 AND Z_4ITEMS5 IN
+            (SELECT Z_4ITEMS5 FROM Z_4KEYWORDS WHERE Z_13KEYWORDS = ?2)",
+        [source_id, target_id],
+    )?;
+    conn.execute(
+        "UPDATE Z_4KEYWORDS SET Z_13KEYWORDS = ? WHERE Z_13KEYWORDS = ?",
+        [target_id, source_id],
+    )?;
+    conn.execute("DELETE FROM ZMETA WHERE Z_PK = ?", [source_id])?;
+    Ok(())
+}
+
+/// Delete a tag, unlinking it from any items first
+pub fn delete_keyword(conn: &Connection, keyword_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM Z_4KEYWORDS WHERE Z_13KEYWORDS = ?",
+        [keyword_id],
+    )?;
+    conn.execute("DELETE FROM ZMETA WHERE Z_PK = ?", [keyword_id])?;
+    Ok(())
+}
+
+/// Tag a score. Returns its tags afterwards, or None if it already had the
+/// tag
+pub fn tag_score(conn: &Connection, score: &Score, tag: &str) -> Result<Option<Vec<String>>> {
+    if score.keywords.iter().any(|k| k.eq_ignore_ascii_case(tag)) {
+        return Ok(None);
+    }
+    link_item(conn, KEYWORD_LINKS, score.id, tag)?;
+    let mut keywords = score.keywords.clone();
+    keywords.push(tag.to_string());
+    Ok(Some(keywords))
+}
+
+/// Untag a score. Returns its tags afterwards, or None if it didn't have the
+/// tag
+pub fn untag_score(conn: &Connection, score: &Score, tag: &str) -> Result<Option<Vec<String>>> {
+    if !score.keywords.iter().any(|k| k.eq_ignore_ascii_case(tag)) {
+        return Ok(None);
+    }
+    unlink_item(conn, KEYWORD_LINKS, score.id, tag)?;
+    Ok(Some(
+        score
+            .keywords
+            .iter()
+            .filter(|k| !k.eq_ignore_ascii_case(tag))
+            .cloned()
+            .collect(),
+    ))
+}
+
 /// Get or create a composer, returning its ID
 pub fn get_or_create_composer(conn: &Connection, name: &str) -> Result<i64> {
     // Try to find existing
@@ -166,13 +357,13 @@ pub fn get_or_create_composer(conn: &Connection, name: &str) -> Result<i64> {
 
     conn.execute(
         "INSERT INTO ZMETA (Z_PK, Z_ENT, Z_OPT, ZVALUE) VALUES (?, ?, 1, ?)",
-        rusqlite::params![max_pk + 1, entity::COMPOSER, name],
+        rusqlite::params![max_pk + 1, entity::composer(), name],
     )?;
 
     // Update Z_PRIMARYKEY
     conn.execute(
         "UPDATE Z_PRIMARYKEY SET Z_MAX = ? WHERE Z_ENT = ?",
-        [max_pk + 1, entity::META as i64],
+        [max_pk + 1, entity::meta() as i64],
     )?;
 
     Ok(max_pk + 1)
@@ -183,7 +374,7 @@ pub fn get_or_create_genre(conn: &Connection, name: &str) -> Result<i64> {
     // Try to find existing
     let mut stmt = conn.prepare("SELECT Z_PK FROM ZMETA WHERE Z_ENT = ? AND ZVALUE2 = ?")?;
 
-    if let Ok(id) = stmt.query_row(rusqlite::params![entity::GENRE, name], |row| {
+    if let Ok(id) = stmt.query_row(rusqlite::params![entity::genre(), name], |row| {
         row.get::<_, i64>(0)
     }) {
         return Ok(id);
@@ -196,8 +387,344 @@ pub fn get_or_create_genre(conn: &Connection, name: &str) -> Result<i64> {
 
     conn.execute(
         "INSERT INTO ZMETA (Z_PK, Z_ENT, Z_OPT, ZVALUE2) VALUES (?, ?, 1, ?)",
-        rusqlite::params![max_pk + 1, entity::GENRE, name],
+        rusqlite::params![max_pk + 1, entity::genre(), name],
+    )?;
+
+    Ok(max_pk + 1)
+}
+
+/// A controlled vocabulary stored as ZMETA rows of one entity
+#[derive(Debug, Clone, Copy)]
+pub struct Vocabulary {
+    pub entity: fn() -> i32,
+    /// Column holding the name
+    pub column: &'static str,
+}
+
+pub const GENRES: Vocabulary = Vocabulary {
+    entity: entity::genre,
+    column: "ZVALUE2",
+};
+pub const KEYWORDS: Vocabulary = Vocabulary {
+    entity: entity::keyword,
+    column: "ZVALUE",
+};
+pub const LABELS: Vocabulary = Vocabulary {
+    entity: entity::label,
+    column: "ZVALUE",
+};
+pub const COMPOSERS: Vocabulary = Vocabulary {
+    entity: entity::composer,
+    column: "ZVALUE",
+};
+
+/// The join table linking items to the entries of a vocabulary
+#[derive(Debug, Clone, Copy)]
+pub struct ItemLinks {
+    pub vocabulary: Vocabulary,
+    pub table: &'static str,
+    pub item_column: &'static str,
+    pub meta_column: &'static str,
+}
+
+pub const COMPOSER_LINKS: ItemLinks = ItemLinks {
+    vocabulary: COMPOSERS,
+    table: "Z_4COMPOSERS",
+    item_column: "Z_4ITEMS1",
+    meta_column: "Z_10COMPOSERS",
+};
+pub const GENRE_LINKS: ItemLinks = ItemLinks {
+    vocabulary: GENRES,
+    table: "Z_4GENRES",
+    item_column: "Z_4ITEMS4",
+    meta_column: "Z_12GENRES",
+};
+pub const KEYWORD_LINKS: ItemLinks = ItemLinks {
+    vocabulary: KEYWORDS,
+    table: "Z_4KEYWORDS",
+    item_column: "Z_4ITEMS5",
+    meta_column: "Z_13KEYWORDS",
+};
+pub const LABEL_LINKS: ItemLinks = ItemLinks {
+    vocabulary: LABELS,
+    table: "Z_4LABELS",
+    item_column: "Z_4ITEMS2",
+    meta_column: "Z_14LABELS",
+};
+
+/// Link an item to a name, creating the name if needed; other links are kept
+pub fn link_item(conn: &Connection, links: ItemLinks, item_id: i64, name: &str) -> Result<()> {
+    let meta_id = get_or_create_vocabulary_entry(conn, links.vocabulary, name)?;
+    conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO {} ({}, {}) VALUES (?, ?)",
+            links.table, links.item_column, links.meta_column
+        ),
+        [item_id, meta_id],
     )?;
+    Ok(())
+}
+
+/// Remove an item's link to a name (ignoring case); the name itself is kept
+pub fn unlink_item(conn: &Connection, links: ItemLinks, item_id: i64, name: &str) -> Result<()> {
+    conn.execute(
+        &format!(
+            "DELETE FROM {table} WHERE {item} = ? AND {meta} IN
+                (SELECT Z_PK FROM ZMETA WHERE Z_ENT = ? AND LOWER({col}) = LOWER(?))",
+            table = links.table,
+            item = links.item_column,
+            meta = links.meta_column,
+            col = links.vocabulary.column
+        ),
+        rusqlite::params![item_id, (links.vocabulary.entity)(), name],
+    )?;
+    Ok(())
+}
+
+/// Make an item's links match `names`, touching only the ones that change
+pub fn set_item_links(
+    conn: &Connection,
+    links: ItemLinks,
+    item_id: i64,
+    current: &[String],
+    names: &[String],
+) -> Result<()> {
+    let has = |list: &[String], name: &String| list.iter().any(|n| n.eq_ignore_ascii_case(name));
+    for name in current.iter().filter(|n| !has(names, n)) {
+        unlink_item(conn, links, item_id, name)?;
+    }
+    for name in names.iter().filter(|n| !has(current, n)) {
+        link_item(conn, links, item_id, name)?;
+    }
+    Ok(())
+}
+
+/// A metadata list after `scores edit`: replaced by `set` if given, then with
+/// `add` appended and `remove` taken out (names compared ignoring case)
+pub fn edit_list(
+    current: &[String],
+    set: Option<Vec<String>>,
+    add: &[String],
+    remove: &[String],
+) -> Vec<String> {
+    let mut list = set.unwrap_or_else(|| current.to_vec());
+    for name in add {
+        if !list.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            list.push(name.clone());
+        }
+    }
+    list.retain(|n| !remove.iter().any(|r| r.eq_ignore_ascii_case(n)));
+    list
+}
+
+/// All names in a vocabulary, sorted
+pub fn list_vocabulary(conn: &Connection, vocabulary: Vocabulary) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {col} FROM ZMETA WHERE Z_ENT = ? AND {col} IS NOT NULL AND {col} != '' ORDER BY {col}",
+        col = vocabulary.column
+    ))?;
+    let names = stmt
+        .query_map([(vocabulary.entity)()], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(names)
+}
+
+/// Get or create a name in a vocabulary, returning its ID
+pub fn get_or_create_vocabulary_entry(
+    conn: &Connection,
+    vocabulary: Vocabulary,
+    name: &str,
+) -> Result<i64> {
+    let existing = conn.query_row(
+        &format!(
+            "SELECT Z_PK FROM ZMETA WHERE Z_ENT = ? AND {} = ?",
+            vocabulary.column
+        ),
+        rusqlite::params![(vocabulary.entity)(), name],
+        |row| row.get(0),
+    );
+    match existing {
+        Ok(id) => Ok(id),
+        Err(_) => create_vocabulary_entry(conn, vocabulary, name),
+    }
+}
+
+/// Add a name to a vocabulary
+pub fn create_vocabulary_entry(
+    conn: &Connection,
+    vocabulary: Vocabulary,
+    name: &str,
+) -> Result<i64> {
+    let id = next_meta_pk(conn)?;
+    conn.execute(
+        &format!(
+            "INSERT INTO ZMETA (Z_PK, Z_ENT, Z_OPT, {}) VALUES (?, ?, 1, ?)",
+            vocabulary.column
+        ),
+        rusqlite::params![id, (vocabulary.entity)(), name],
+    )?;
+    Ok(id)
+}
 
+/// A difficulty level and its display label, if forScore has one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyLevel {
+    pub level: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Difficulty levels with their ZMETA IDs, in level order
+pub fn list_difficulty_levels(conn: &Connection) -> Result<Vec<(i64, DifficultyLevel)>> {
+    let mut stmt = conn.prepare(
+        "SELECT Z_PK, ZVALUE1, ZVALUE FROM ZMETA
+         WHERE Z_ENT = ? AND ZVALUE1 IS NOT NULL ORDER BY ZVALUE1",
+    )?;
+    let levels = stmt
+        .query_map([entity::difficulty()], |row| {
+            Ok((
+                row.get(0)?,
+                DifficultyLevel {
+                    level: row.get(1)?,
+                    label: row.get::<_, Option<String>>(2)?.filter(|l| !l.is_empty()),
+                },
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(levels)
+}
+
+/// Add a difficulty level
+pub fn create_difficulty_level(conn: &Connection, level: &DifficultyLevel) -> Result<i64> {
+    let id = next_meta_pk(conn)?;
+    conn.execute(
+        "INSERT INTO ZMETA (Z_PK, Z_ENT, Z_OPT, ZVALUE1, ZVALUE) VALUES (?, ?, 1, ?, ?)",
+        rusqlite::params![id, entity::difficulty(), level.level, level.label],
+    )?;
+    Ok(id)
+}
+
+/// Get or create the ZMETA row for a difficulty level (ZITEM.ZDIFFICULTY
+/// points at it), returning its ID
+pub fn get_or_create_difficulty(conn: &Connection, level: i32) -> Result<i64> {
+    match list_difficulty_levels(conn)?
+        .into_iter()
+        .find(|(_, l)| l.level == level)
+    {
+        Some((id, _)) => Ok(id),
+        None => create_difficulty_level(conn, &DifficultyLevel { level, label: None }),
+    }
+}
+
+/// Get or create the ZMETA row for a rating value (ZITEM.ZRATING points at
+/// it), returning its ID
+pub fn get_or_create_rating(conn: &Connection, rating: i32) -> Result<i64> {
+    let existing = conn.query_row(
+        "SELECT Z_PK FROM ZMETA WHERE Z_ENT = ? AND ZVALUE5 = ?",
+        [entity::rating(), rating],
+        |row| row.get(0),
+    );
+    if let Ok(id) = existing {
+        return Ok(id);
+    }
+
+    let id = next_meta_pk(conn)?;
+    conn.execute(
+        "INSERT INTO ZMETA (Z_PK, Z_ENT, Z_OPT, ZVALUE5) VALUES (?, ?, 1, ?)",
+        rusqlite::params![id, entity::rating(), rating],
+    )?;
+    Ok(id)
+}
+
+/// Set the label of an existing difficulty level
+pub fn set_difficulty_label(conn: &Connection, id: i64, label: Option<&str>) -> Result<()> {
+    conn.execute(
+        "UPDATE ZMETA SET ZVALUE = ?, Z_OPT = Z_OPT + 1 WHERE Z_PK = ?",
+        rusqlite::params![label, id],
+    )?;
+    Ok(())
+}
+
+/// Reserve the next ZMETA primary key
+fn next_meta_pk(conn: &Connection) -> Result<i64> {
+    let max_pk: i64 = conn.query_row("SELECT COALESCE(MAX(Z_PK), 0) FROM ZMETA", [], |row| {
+        row.get(0)
+    })?;
+    conn.execute(
+        "UPDATE Z_PRIMARYKEY SET Z_MAX = ? WHERE Z_ENT = ?",
+        [max_pk + 1, entity::meta() as i64],
+    )?;
     Ok(max_pk + 1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_list() {
+        let current = vec!["Bach".to_string(), "Busoni".to_string()];
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            edit_list(&current, None, &names(&["Liszt", "bach"]), &[]),
+            names(&["Bach", "Busoni", "Liszt"])
+        );
+        assert_eq!(
+            edit_list(&current, None, &[], &names(&["busoni"])),
+            names(&["Bach"])
+        );
+        assert_eq!(
+            edit_list(&current, Some(names(&["Chopin"])), &names(&["Liszt"]), &[]),
+            names(&["Chopin", "Liszt"])
+        );
+    }
+
+    #[test]
+    fn test_rename_in_subtree() {
+        assert_eq!(
+            rename_in_subtree("Jazz", "Jazz", "Swing").as_deref(),
+            Some("Swing")
+        );
+        assert_eq!(
+            rename_in_subtree("Jazz/Bebop", "Jazz", "Swing").as_deref(),
+            Some("Swing/Bebop")
+        );
+        assert_eq!(rename_in_subtree("Jazz Fusion", "Jazz", "Swing"), None);
+        assert_eq!(rename_in_subtree("Classical", "Jazz", "Swing"), None);
+        assert_eq!(
+            rename_in_subtree("Jazz/Bebop", "jazz", "Swing").as_deref(),
+            Some("Swing/Bebop")
+        );
+    }
+
+    #[test]
+    fn test_rename_keyword_subtree() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::schema::create_known_schema(&conn).unwrap();
+        for name in ["Jazz", "Jazz/Bebop", "Swing/Bebop"] {
+            conn.execute(
+                "INSERT INTO ZMETA (Z_ENT, ZVALUE) VALUES (?, ?)",
+                rusqlite::params![entity::keyword(), name],
+            )
+            .unwrap();
+        }
+
+        let err = rename_keyword_subtree(&conn, "jazz", "Swing").unwrap_err();
+        assert!(err.to_string().contains("tags merge"));
+
+        let renames = rename_keyword_subtree(&conn, "jazz", "Blues").unwrap();
+        assert_eq!(
+            renames,
+            vec![
+                ("Jazz".to_string(), "Blues".to_string()),
+                ("Jazz/Bebop".to_string(), "Blues/Bebop".to_string()),
+            ]
+        );
+
+        // Changing only the case is not a collision
+        assert!(rename_keyword_subtree(&conn, "blues", "BLUES").is_ok());
+    }
+}
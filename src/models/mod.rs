@@ -1,8 +1,10 @@
+pub mod group;
 pub mod key;
 pub mod library;
 pub mod meta;
 pub mod score;
 pub mod setlist;
+pub mod track;
 
 pub use library::Library;
 pub use meta::{Composer, Genre, Keyword};
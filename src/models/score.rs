@@ -1,10 +1,20 @@
-use crate::db::entity;
+use crate::config::alias_target;
+use crate::db::{core_data_timestamp, core_data_to_datetime, entity};
 use crate::error::{ForScoreError, Result};
+use crate::fuzzy::{best_match, note_closest, with_suggestions, FuzzyMatch};
+use crate::itm::{hex, plist_to_json};
 use crate::models::key::MusicalKey;
+use crate::query::Expr;
+use crate::schema::LINK_TABLES;
+use crate::sort_title::sort_title;
+use crate::touch::touch_setlist;
+use chrono::{DateTime, Utc};
+use rusqlite::types::{Value, ValueRef};
 use rusqlite::{Connection, Row};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Score {
     pub id: i64,
     pub path: String,
@@ -21,6 +31,12 @@ pub struct Score {
     pub genres: Vec<String>,
     pub keywords: Vec<String>,
     pub labels: Vec<String>,
+    pub libraries: Vec<String>,
+    /// Page count (see `cache::load_page_counts`); not loaded by default
+    pub pages: Option<i32>,
+    pub added: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    pub last_played: Option<DateTime<Utc>>,
 }
 
 impl Score {
@@ -42,6 +58,11 @@ impl Score {
             genres: Vec::new(),
             keywords: Vec::new(),
             labels: Vec::new(),
+            libraries: Vec::new(),
+            pages: None,
+            added: date(row, "ZADDED")?,
+            modified: date(row, "ZMODIFIED")?,
+            last_played: date(row, "ZLASTPLAYED")?,
         })
     }
 
@@ -90,10 +111,111 @@ impl Score {
             .filter_map(|r| r.ok())
             .collect();
 
+        // Load libraries
+        let mut stmt = conn.prepare(
+            "SELECT l.ZTITLE FROM ZLIBRARY l
+             JOIN Z_4LIBRARIES z ON l.Z_PK = z.Z_7LIBRARIES
+             WHERE z.Z_4ITEMS3 = ?",
+        )?;
+        self.libraries = stmt
+            .query_map([self.id], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+
         Ok(())
     }
 }
 
+/// A Core Data timestamp column as a datetime
+fn date(row: &Row, column: &str) -> rusqlite::Result<Option<DateTime<Utc>>> {
+    Ok(row
+        .get::<_, Option<f64>>(column)?
+        .and_then(core_data_to_datetime))
+}
+
+/// Load metadata for many scores at once, using one query per relationship
+/// instead of one per score
+pub fn load_metadata_batch(conn: &Connection, scores: &mut [Score]) -> Result<()> {
+    if scores.is_empty() {
+        return Ok(());
+    }
+
+    let ids: Vec<i64> = scores.iter().map(|s| s.id).collect();
+
+    let mut composers = load_links(
+        conn,
+        "SELECT c.Z_4ITEMS1, m.ZVALUE FROM ZMETA m
+         JOIN Z_4COMPOSERS c ON m.Z_PK = c.Z_10COMPOSERS
+         WHERE c.Z_4ITEMS1 IN",
+        &ids,
+    )?;
+    let mut genres = load_links(
+        conn,
+        "SELECT g.Z_4ITEMS4, m.ZVALUE2 FROM ZMETA m
+         JOIN Z_4GENRES g ON m.Z_PK = g.Z_12GENRES
+         WHERE g.Z_4ITEMS4 IN",
+        &ids,
+    )?;
+    let mut keywords = load_links(
+        conn,
+        "SELECT k.Z_4ITEMS5, m.ZVALUE FROM ZMETA m
+         JOIN Z_4KEYWORDS k ON m.Z_PK = k.Z_13KEYWORDS
+         WHERE k.Z_4ITEMS5 IN",
+        &ids,
+    )?;
+    let mut labels = load_links(
+        conn,
+        "SELECT l.Z_4ITEMS2, m.ZVALUE FROM ZMETA m
+         JOIN Z_4LABELS l ON m.Z_PK = l.Z_14LABELS
+         WHERE l.Z_4ITEMS2 IN",
+        &ids,
+    )?;
+    let mut libraries = load_links(
+        conn,
+        "SELECT z.Z_4ITEMS3, l.ZTITLE FROM ZLIBRARY l
+         JOIN Z_4LIBRARIES z ON l.Z_PK = z.Z_7LIBRARIES
+         WHERE z.Z_4ITEMS3 IN",
+        &ids,
+    )?;
+
+    for score in scores.iter_mut() {
+        score.composers = composers.remove(&score.id).unwrap_or_default();
+        score.genres = genres.remove(&score.id).unwrap_or_default();
+        score.keywords = keywords.remove(&score.id).unwrap_or_default();
+        score.labels = labels.remove(&score.id).unwrap_or_default();
+        score.libraries = libraries.remove(&score.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+/// Run an (item ID, value) query over a list of item IDs, grouping values by item.
+/// `sql_prefix` must end with `IN`; the ID list is appended in chunks.
+fn load_links(
+    conn: &Connection,
+    sql_prefix: &str,
+    ids: &[i64],
+) -> Result<HashMap<i64, Vec<String>>> {
+    let mut links: HashMap<i64, Vec<String>> = HashMap::new();
+
+    for chunk in ids.chunks(500) {
+        let id_list: Vec<String> = chunk.iter().map(|id| id.to_string()).collect();
+        let sql = format!("{} ({})", sql_prefix, id_list.join(", "));
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+        })?;
+
+        for (id, value) in rows.flatten() {
+            if let Some(value) = value {
+                links.entry(id).or_default().push(value);
+            }
+        }
+    }
+
+    Ok(links)
+}
+
 /// List all scores with sorting and limit
 pub fn list_scores(
     conn: &Connection,
@@ -101,7 +223,39 @@ pub fn list_scores(
     desc: bool,
     limit: usize,
     scores_only: bool,
+    favorites: bool,
+    expr: Option<&Expr>,
 ) -> Result<Vec<Score>> {
+    let mut scores = Vec::new();
+    list_scores_each(
+        conn,
+        sort,
+        desc,
+        limit,
+        scores_only,
+        favorites,
+        expr,
+        |score| {
+            scores.push(score);
+            Ok(())
+        },
+    )?;
+    Ok(scores)
+}
+
+/// `list_scores`, handing each score to `f` as its row is read instead of
+/// collecting them
+#[allow(clippy::too_many_arguments)]
+pub fn list_scores_each(
+    conn: &Connection,
+    sort: &str,
+    desc: bool,
+    limit: usize,
+    scores_only: bool,
+    favorites: bool,
+    expr: Option<&Expr>,
+    f: impl FnMut(Score) -> Result<()>,
+) -> Result<()> {
     let order_col = match sort {
         "title" => "i.ZSORTTITLE",
         "added" => "i.ZADDED",
@@ -115,14 +269,27 @@ pub fn list_scores(
 
     let direction = if desc { "DESC" } else { "ASC" };
 
-    let entity_filter = if scores_only {
+    let mut params = vec![Value::Integer(entity::score() as i64)];
+    let mut entity_filter = if scores_only {
         "i.Z_ENT = ?".to_string()
     } else {
+        params.push(Value::Integer(entity::bookmark() as i64));
         "i.Z_ENT IN (?, ?)".to_string()
     };
+    if favorites {
+        entity_filter.push_str(" AND ");
+        entity_filter.push_str(&favorite_condition());
+    }
+    if let Some(expr) = expr {
+        let (condition, values) = expr.to_sql();
+        entity_filter.push_str(" AND ");
+        entity_filter.push_str(&condition);
+        params.extend(values);
+    }
+    params.push(Value::Integer(limit as i64));
 
     let sql = format!(
-        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE
+        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
          FROM ZITEM i
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
          LEFT JOIN ZMETA d ON i.ZDIFFICULTY = d.Z_PK
@@ -131,39 +298,23 @@ pub fn list_scores(
     );
 
     let mut stmt = conn.prepare(&sql)?;
-
-    let scores: Vec<Score> = if scores_only {
-        stmt.query_map(
-            rusqlite::params![entity::SCORE, limit as i64],
-            Score::from_row,
-        )?
-        .filter_map(|r| r.ok())
-        .collect()
-    } else {
-        stmt.query_map(
-            rusqlite::params![entity::SCORE, entity::BOOKMARK, limit as i64],
-            Score::from_row,
-        )?
+    stmt.query_map(rusqlite::params_from_iter(params), Score::from_row)?
         .filter_map(|r| r.ok())
-        .collect()
-    };
-
-    Ok(scores)
+        .try_for_each(f)?;
+    Ok(())
 }
 
 /// List scores with full metadata
 pub fn list_scores_with_metadata(conn: &Connection) -> Result<Vec<Score>> {
-    let mut scores = list_scores(conn, "title", false, 10000, true)?;
-    for score in &mut scores {
-        score.load_metadata(conn)?;
-    }
+    let mut scores = list_scores(conn, "title", false, 10000, true, false, None)?;
+    load_metadata_batch(conn, &mut scores)?;
     Ok(scores)
 }
 
 /// List scores in a setlist (includes both scores and bookmarks)
 pub fn list_scores_in_setlist(conn: &Connection, setlist_id: i64) -> Result<Vec<Score>> {
     let mut stmt = conn.prepare(
-        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE
+        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
          FROM ZITEM i
          JOIN ZCYLON c ON i.Z_PK = c.ZITEM
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
@@ -174,7 +325,11 @@ pub fn list_scores_in_setlist(conn: &Connection, setlist_id: i64) -> Result<Vec<
 
     let scores: Vec<Score> = stmt
         .query_map(
-            [setlist_id, entity::BOOKMARK as i64, entity::SCORE as i64],
+            [
+                setlist_id,
+                entity::bookmark() as i64,
+                entity::score() as i64,
+            ],
             Score::from_row,
         )?
         .filter_map(|r| r.ok())
@@ -186,7 +341,7 @@ pub fn list_scores_in_setlist(conn: &Connection, setlist_id: i64) -> Result<Vec<
 /// List scores in a library
 pub fn list_scores_in_library(conn: &Connection, library_id: i64) -> Result<Vec<Score>> {
     let mut stmt = conn.prepare(
-        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE
+        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
          FROM ZITEM i
          JOIN Z_4LIBRARIES l ON i.Z_PK = l.Z_4ITEMS3
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
@@ -196,7 +351,7 @@ pub fn list_scores_in_library(conn: &Connection, library_id: i64) -> Result<Vec<
     )?;
 
     let scores: Vec<Score> = stmt
-        .query_map([library_id, entity::SCORE as i64], Score::from_row)?
+        .query_map([library_id, entity::score() as i64], Score::from_row)?
         .filter_map(|r| r.ok())
         .collect();
 
@@ -206,7 +361,7 @@ pub fn list_scores_in_library(conn: &Connection, library_id: i64) -> Result<Vec<
 /// Get a score by ID
 pub fn get_score_by_id(conn: &Connection, id: i64) -> Result<Score> {
     let mut stmt = conn.prepare(
-        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE
+        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
          FROM ZITEM i
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
          LEFT JOIN ZMETA d ON i.ZDIFFICULTY = d.Z_PK
@@ -214,7 +369,7 @@ pub fn get_score_by_id(conn: &Connection, id: i64) -> Result<Score> {
     )?;
 
     let mut score = stmt
-        .query_row([id, entity::SCORE as i64], Score::from_row)
+        .query_row([id, entity::score() as i64], Score::from_row)
         .map_err(|_| ForScoreError::ScoreNotFound(id.to_string()))?;
 
     score.load_metadata(conn)?;
@@ -224,14 +379,34 @@ pub fn get_score_by_id(conn: &Connection, id: i64) -> Result<Score> {
 /// Get a score by path
 pub fn get_score_by_path(conn: &Connection, path: &str) -> Result<Option<Score>> {
     let mut stmt = conn.prepare(
-        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE
+        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
          FROM ZITEM i
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
          LEFT JOIN ZMETA d ON i.ZDIFFICULTY = d.Z_PK
          WHERE i.ZPATH = ? AND i.Z_ENT = ?",
     )?;
 
-    match stmt.query_row([path, &entity::SCORE.to_string()], Score::from_row) {
+    match stmt.query_row([path, &entity::score().to_string()], Score::from_row) {
+        Ok(mut score) => {
+            score.load_metadata(conn)?;
+            Ok(Some(score))
+        }
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Get a score by UUID, which stays the same across devices (unlike Z_PK)
+pub fn get_score_by_uuid(conn: &Connection, uuid: &str) -> Result<Option<Score>> {
+    let mut stmt = conn.prepare(
+        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
+         FROM ZITEM i
+         LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
+         LEFT JOIN ZMETA d ON i.ZDIFFICULTY = d.Z_PK
+         WHERE i.ZUUID = ? COLLATE NOCASE AND i.Z_ENT = ?",
+    )?;
+
+    match stmt.query_row([uuid, &entity::score().to_string()], Score::from_row) {
         Ok(mut score) => {
             score.load_metadata(conn)?;
             Ok(Some(score))
@@ -245,35 +420,35 @@ pub fn get_score_by_path(conn: &Connection, path: &str) -> Result<Option<Score>>
 pub fn get_score_by_title(conn: &Connection, title: &str) -> Result<Score> {
     // Try exact match first
     let mut stmt = conn.prepare(
-        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE
+        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
          FROM ZITEM i
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
          LEFT JOIN ZMETA d ON i.ZDIFFICULTY = d.Z_PK
          WHERE i.ZTITLE = ? AND i.Z_ENT = ?",
     )?;
 
-    if let Ok(mut score) = stmt.query_row([title, &entity::SCORE.to_string()], Score::from_row) {
+    if let Ok(mut score) = stmt.query_row([title, &entity::score().to_string()], Score::from_row) {
         score.load_metadata(conn)?;
         return Ok(score);
     }
 
     // Try case-insensitive match
     let mut stmt = conn.prepare(
-        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE
+        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
          FROM ZITEM i
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
          LEFT JOIN ZMETA d ON i.ZDIFFICULTY = d.Z_PK
          WHERE LOWER(i.ZTITLE) = LOWER(?) AND i.Z_ENT = ?",
     )?;
 
-    if let Ok(mut score) = stmt.query_row([title, &entity::SCORE.to_string()], Score::from_row) {
+    if let Ok(mut score) = stmt.query_row([title, &entity::score().to_string()], Score::from_row) {
         score.load_metadata(conn)?;
         return Ok(score);
     }
 
     // Try contains match
     let mut stmt = conn.prepare(
-        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE
+        "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
          FROM ZITEM i
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
          LEFT JOIN ZMETA d ON i.ZDIFFICULTY = d.Z_PK
@@ -282,7 +457,7 @@ pub fn get_score_by_title(conn: &Connection, title: &str) -> Result<Score> {
 
     let pattern = format!("%{}%", title);
     let scores: Vec<Score> = stmt
-        .query_map([&pattern, &entity::SCORE.to_string()], Score::from_row)?
+        .query_map([&pattern, &entity::score().to_string()], Score::from_row)?
         .filter_map(|r| r.ok())
         .collect();
 
@@ -297,8 +472,104 @@ pub fn get_score_by_title(conn: &Connection, title: &str) -> Result<Score> {
     }
 }
 
-/// Resolve a score identifier (ID, path, or title)
+/// The score whose title is closest to `title`, allowing for typos
+fn closest_score(conn: &Connection, title: &str, take_match: bool) -> Result<Score> {
+    let mut stmt =
+        conn.prepare("SELECT Z_PK, ZTITLE FROM ZITEM WHERE Z_ENT = ? AND ZTITLE IS NOT NULL")?;
+    let titles: Vec<(i64, String)> = stmt
+        .query_map([entity::score()], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    match best_match(title, &titles, |(_, t)| t) {
+        FuzzyMatch::Match((id, name)) if take_match => {
+            note_closest(title, name);
+            get_score_by_id(conn, *id)
+        }
+        FuzzyMatch::Match((_, name)) => Err(ForScoreError::ScoreNotFound(with_suggestions(
+            title,
+            &[name.as_str()],
+        ))),
+        FuzzyMatch::Suggestions(closest) => {
+            let names: Vec<&str> = closest.iter().map(|(_, t)| t.as_str()).collect();
+            Err(ForScoreError::ScoreNotFound(with_suggestions(
+                title, &names,
+            )))
+        }
+    }
+}
+
+/// Resolve a score identifier (ID, path, title or alias), falling back to
+/// the closest title
 pub fn resolve_score(conn: &Connection, identifier: &str) -> Result<Score> {
+    resolve_score_with(conn, identifier, true)
+}
+
+/// Resolve a score identifier (ID, path, title or alias) for a command that
+/// deletes or unlinks something: the closest titles are suggested, not used
+pub fn resolve_score_strict(conn: &Connection, identifier: &str) -> Result<Score> {
+    resolve_score_with(conn, identifier, false)
+}
+
+fn resolve_score_with(conn: &Connection, identifier: &str, fuzzy: bool) -> Result<Score> {
+    match resolve_score_exact(conn, identifier) {
+        Err(ForScoreError::ScoreNotFound(_)) => match alias_target(identifier) {
+            Some(target) => resolve_score_exact(conn, target),
+            None => closest_score(conn, identifier, fuzzy),
+        },
+        result => result,
+    }
+}
+
+/// A score, or a bookmark where no score matches
+pub enum ScoreOrBookmark {
+    Score(Score),
+    Bookmark(Bookmark),
+}
+
+/// Resolve a score or bookmark identifier. A fuzzy match on score titles is
+/// only tried after both have been looked up exactly
+pub fn resolve_score_or_bookmark(conn: &Connection, identifier: &str) -> Result<ScoreOrBookmark> {
+    resolve_score_or_bookmark_with(conn, identifier, true)
+}
+
+/// `resolve_score_or_bookmark` for a command that deletes or unlinks
+/// something: the closest titles are suggested, not used
+pub fn resolve_score_or_bookmark_strict(
+    conn: &Connection,
+    identifier: &str,
+) -> Result<ScoreOrBookmark> {
+    resolve_score_or_bookmark_with(conn, identifier, false)
+}
+
+fn resolve_score_or_bookmark_with(
+    conn: &Connection,
+    identifier: &str,
+    fuzzy: bool,
+) -> Result<ScoreOrBookmark> {
+    let exact = resolve_score_exact(conn, identifier);
+    if let Ok(score) = exact {
+        return Ok(ScoreOrBookmark::Score(score));
+    }
+    if let Ok(bookmark) = resolve_bookmark(conn, identifier) {
+        return Ok(ScoreOrBookmark::Bookmark(bookmark));
+    }
+    if let Some(target) = alias_target(identifier) {
+        if let Ok(score) = resolve_score_exact(conn, target) {
+            return Ok(ScoreOrBookmark::Score(score));
+        }
+        return resolve_bookmark(conn, target).map(ScoreOrBookmark::Bookmark);
+    }
+    match exact {
+        Err(ForScoreError::ScoreNotFound(_)) => {
+            closest_score(conn, identifier, fuzzy).map(ScoreOrBookmark::Score)
+        }
+        Err(e) => Err(e),
+        Ok(_) => unreachable!(),
+    }
+}
+
+/// Resolve a score identifier (ID, path, or title) without fuzzy matching
+pub fn resolve_score_exact(conn: &Connection, identifier: &str) -> Result<Score> {
     // Try as numeric ID first
     if let Ok(id) = identifier.parse::<i64>() {
         if let Ok(score) = get_score_by_id(conn, id) {
@@ -315,46 +586,72 @@ pub fn resolve_score(conn: &Connection, identifier: &str) -> Result<Score> {
     get_score_by_title(conn, identifier)
 }
 
+/// Filters for score search
+#[derive(Debug, Clone, Default)]
+pub struct ScoreFilter {
+    /// Matches title or composer
+    pub query: Option<String>,
+    pub title: Option<String>,
+    pub composer: Option<String>,
+    pub genre: Option<String>,
+    /// Tag name; also matches tags nested beneath it ("Jazz" matches "Jazz/Bebop")
+    pub tag: Option<String>,
+    pub key: Option<i32>,
+    pub no_key: bool,
+    pub min_rating: Option<i32>,
+    pub no_rating: bool,
+    pub difficulty: Option<i32>,
+    /// Library ID; bookmarks match through their parent score
+    pub library: Option<i64>,
+    pub scores_only: bool,
+    pub favorites: bool,
+    /// Expression from the query language (`scores query`)
+    pub expr: Option<Expr>,
+}
+
 /// Search scores with filters
-pub fn search_scores(
+pub fn search_scores(conn: &Connection, filter: &ScoreFilter, limit: usize) -> Result<Vec<Score>> {
+    let mut scores = Vec::new();
+    search_scores_each(conn, filter, limit, |score| {
+        scores.push(score);
+        Ok(())
+    })?;
+    Ok(scores)
+}
+
+/// `search_scores`, handing each score to `f` as its row is read instead of
+/// collecting them
+pub fn search_scores_each(
     conn: &Connection,
-    query: Option<&str>,
-    title: Option<&str>,
-    composer: Option<&str>,
-    genre: Option<&str>,
-    key: Option<i32>,
-    no_key: bool,
-    min_rating: Option<i32>,
-    no_rating: bool,
-    difficulty: Option<i32>,
+    filter: &ScoreFilter,
     limit: usize,
-    scores_only: bool,
-) -> Result<Vec<Score>> {
+    f: impl FnMut(Score) -> Result<()>,
+) -> Result<()> {
     let mut sql = String::from(
-        "SELECT DISTINCT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE
+        "SELECT DISTINCT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZSORTTITLE, i.ZUUID, r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY, i.ZBPM, i.ZSTARTPAGE, i.ZENDPAGE, i.ZADDED, i.ZMODIFIED, i.ZLASTPLAYED
          FROM ZITEM i
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
          LEFT JOIN ZMETA d ON i.ZDIFFICULTY = d.Z_PK",
     );
     let mut joins = Vec::new();
-    let mut conditions = if scores_only {
-        vec![format!("i.Z_ENT = {}", entity::SCORE)]
+    let mut conditions = if filter.scores_only {
+        vec![format!("i.Z_ENT = {}", entity::score())]
     } else {
         vec![format!(
             "i.Z_ENT IN ({}, {})",
-            entity::SCORE,
-            entity::BOOKMARK
+            entity::score(),
+            entity::bookmark()
         )]
     };
     let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
     // General query searches both title and composer
-    let needs_composer_join = query.is_some() || composer.is_some();
+    let needs_composer_join = filter.query.is_some() || filter.composer.is_some();
     if needs_composer_join {
         joins.push("LEFT JOIN Z_4COMPOSERS c ON i.Z_PK = c.Z_4ITEMS1 LEFT JOIN ZMETA mc ON c.Z_10COMPOSERS = mc.Z_PK");
     }
 
-    if let Some(q) = query {
+    if let Some(q) = &filter.query {
         conditions.push("(i.ZTITLE LIKE ? OR mc.ZVALUE LIKE ?)".to_string());
         // Split on whitespace and join with % to match "Op 28" -> "Op. 28"
         let words: Vec<&str> = q.split_whitespace().collect();
@@ -363,43 +660,77 @@ pub fn search_scores(
         params.push(Box::new(pattern));
     }
 
-    if let Some(c) = composer {
+    if let Some(c) = &filter.composer {
         conditions.push("mc.ZVALUE LIKE ?".to_string());
         params.push(Box::new(format!("%{}%", c)));
     }
 
-    if genre.is_some() {
+    if let Some(g) = &filter.genre {
         joins.push(
             "JOIN Z_4GENRES g ON i.Z_PK = g.Z_4ITEMS4 JOIN ZMETA mg ON g.Z_12GENRES = mg.Z_PK",
         );
         conditions.push("mg.ZVALUE2 LIKE ?".to_string());
-        params.push(Box::new(format!("%{}%", genre.unwrap())));
+        params.push(Box::new(format!("%{}%", g)));
+    }
+
+    if let Some(t) = &filter.tag {
+        conditions.push(
+            "EXISTS (SELECT 1 FROM Z_4KEYWORDS k JOIN ZMETA mk ON k.Z_13KEYWORDS = mk.Z_PK
+                     WHERE k.Z_4ITEMS5 = i.Z_PK
+                     AND (LOWER(mk.ZVALUE) = LOWER(?) OR LOWER(mk.ZVALUE) LIKE LOWER(?) || '/%'))"
+                .to_string(),
+        );
+        params.push(Box::new(t.clone()));
+        params.push(Box::new(t.clone()));
     }
 
-    if let Some(t) = title {
+    if let Some(t) = &filter.title {
         conditions.push("i.ZTITLE LIKE ?".to_string());
         params.push(Box::new(format!("%{}%", t)));
     }
 
-    if let Some(k) = key {
+    if let Some(k) = filter.key {
         conditions.push("i.ZKEY = ?".to_string());
         params.push(Box::new(k));
-    } else if no_key {
+    } else if filter.no_key {
         conditions.push("(i.ZKEY IS NULL OR i.ZKEY = 0)".to_string());
     }
 
-    if let Some(rating) = min_rating {
+    if let Some(rating) = filter.min_rating {
         conditions.push("r.ZVALUE5 >= ?".to_string());
         params.push(Box::new(rating));
-    } else if no_rating {
+    } else if filter.no_rating {
         conditions.push("i.ZRATING IS NULL".to_string());
     }
 
-    if let Some(diff) = difficulty {
+    if let Some(diff) = filter.difficulty {
         conditions.push("d.ZVALUE1 = ?".to_string());
         params.push(Box::new(diff));
     }
 
+    if let Some(library_id) = filter.library {
+        conditions.push(
+            "EXISTS (SELECT 1 FROM Z_4LIBRARIES l
+                     WHERE l.Z_7LIBRARIES = ? AND l.Z_4ITEMS3 IN (i.Z_PK, i.ZSCORE))"
+                .to_string(),
+        );
+        params.push(Box::new(library_id));
+    }
+
+    if filter.favorites {
+        conditions.push(favorite_condition());
+    }
+
+    if let Some(expr) = &filter.expr {
+        let (condition, values) = expr.to_sql();
+        conditions.push(condition);
+        params.extend(
+            values
+                .into_iter()
+                .map(|v| Box::new(v) as Box<dyn rusqlite::ToSql>),
+        );
+    }
+
     for join in &joins {
         sql.push(' ');
         sql.push_str(join);
@@ -413,28 +744,47 @@ pub fn search_scores(
     let mut stmt = conn.prepare(&sql)?;
     let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
-    let scores: Vec<Score> = stmt
-        .query_map(param_refs.as_slice(), Score::from_row)?
+    stmt.query_map(param_refs.as_slice(), Score::from_row)?
         .filter_map(|r| r.ok())
-        .collect();
-
-    Ok(scores)
+        .try_for_each(f)?;
+    Ok(())
 }
 
 /// List bookmarks in a score
 pub fn list_bookmarks(conn: &Connection, score_id: i64) -> Result<Vec<Bookmark>> {
-    let mut stmt = conn.prepare(
+    query_bookmarks(
+        conn,
+        "i.ZSCORE = ? AND i.Z_ENT = ? ORDER BY i.ZSTARTPAGE",
+        [score_id, entity::bookmark() as i64],
+    )
+}
+
+/// Every bookmark in the library, grouped by score path
+pub fn list_all_bookmarks(conn: &Connection) -> Result<Vec<Bookmark>> {
+    query_bookmarks(
+        conn,
+        "i.Z_ENT = ? ORDER BY i.ZPATH, i.ZSTARTPAGE",
+        [entity::bookmark() as i64],
+    )
+}
+
+fn query_bookmarks(
+    conn: &Connection,
+    condition: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<Bookmark>> {
+    let mut stmt = conn.prepare(&format!(
         "SELECT i.Z_PK, i.ZPATH, i.ZTITLE, i.ZUUID, i.ZSTARTPAGE, i.ZENDPAGE,
                 r.ZVALUE5 as rating_value, d.ZVALUE1 as difficulty_value, i.ZKEY
          FROM ZITEM i
          LEFT JOIN ZMETA r ON i.ZRATING = r.Z_PK
          LEFT JOIN ZMETA d ON i.ZDIFFICULTY = d.Z_PK
-         WHERE i.ZSCORE = ? AND i.Z_ENT = ?
-         ORDER BY i.ZSTARTPAGE",
-    )?;
+         WHERE {}",
+        condition
+    ))?;
 
     let bookmarks: Vec<Bookmark> = stmt
-        .query_map([score_id, entity::BOOKMARK as i64], |row| {
+        .query_map(params, |row| {
             let key_code: Option<i32> = row.get("ZKEY")?;
             Ok(Bookmark {
                 id: row.get("Z_PK")?,
@@ -511,9 +861,9 @@ pub fn get_bookmark_by_id(conn: &Connection, id: i64) -> Result<Bookmark> {
     )?;
 
     let key_code: Option<i32> =
-        stmt.query_row([id, entity::BOOKMARK as i64], |row| row.get("ZKEY"))?;
+        stmt.query_row([id, entity::bookmark() as i64], |row| row.get("ZKEY"))?;
 
-    let mut bookmark = stmt.query_row([id, entity::BOOKMARK as i64], |row| {
+    let mut bookmark = stmt.query_row([id, entity::bookmark() as i64], |row| {
         Ok(Bookmark {
             id: row.get("Z_PK")?,
             path: row.get("ZPATH")?,
@@ -544,10 +894,12 @@ pub fn get_bookmark_by_title(conn: &Connection, title: &str) -> Result<Bookmark>
          WHERE i.ZTITLE = ? AND i.Z_ENT = ?",
     )?;
 
-    let key_code: Option<i32> =
-        stmt.query_row(rusqlite::params![title, entity::BOOKMARK], |row| row.get("ZKEY"))?;
+    let key_code: Option<i32> = stmt
+        .query_row(rusqlite::params![title, entity::bookmark()], |row| {
+            row.get("ZKEY")
+        })?;
 
-    let mut bookmark = stmt.query_row(rusqlite::params![title, entity::BOOKMARK], |row| {
+    let mut bookmark = stmt.query_row(rusqlite::params![title, entity::bookmark()], |row| {
         Ok(Bookmark {
             id: row.get("Z_PK")?,
             path: row.get("ZPATH")?,
@@ -579,3 +931,506 @@ pub fn resolve_bookmark(conn: &Connection, identifier: &str) -> Result<Bookmark>
     // Try as title
     get_bookmark_by_title(conn, identifier)
 }
+
+/// Get the number of pages in a score, if known
+pub fn score_page_count(conn: &Connection, score: &Score) -> Result<Option<i32>> {
+    let pages: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM ZPAGE WHERE ZSCORE = ?",
+        [score.id],
+        |row| row.get(0),
+    )?;
+
+    if pages > 0 {
+        Ok(Some(pages))
+    } else {
+        Ok(score.end_page)
+    }
+}
+
+/// Performance duration in seconds (forScore's minutes/seconds fields), if set
+pub fn score_duration(conn: &Connection, id: i64) -> Result<Option<i32>> {
+    let (minutes, seconds): (Option<i32>, Option<i32>) = conn.query_row(
+        "SELECT ZMINUTES, ZSECONDS FROM ZITEM WHERE Z_PK = ?",
+        [id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let total = minutes.unwrap_or(0) * 60 + seconds.unwrap_or(0);
+    Ok(if total > 0 { Some(total) } else { None })
+}
+
+/// Page notes for a score, in page order
+pub fn score_page_notes(conn: &Connection, score_id: i64) -> Result<Vec<String>> {
+    Ok(page_notes(conn, score_id)?
+        .into_iter()
+        .map(|(_, note)| note)
+        .collect())
+}
+
+/// The page that stands in for an item: forScore has no item-level notes or
+/// flag fields, so a score uses its first page and a bookmark its start page.
+/// Returns (score ID, page number).
+pub fn item_page(conn: &Connection, item_id: i64, start_page: Option<i32>) -> Result<(i64, i32)> {
+    match start_page {
+        Some(start) => {
+            let score_id: i64 = conn.query_row(
+                "SELECT ZSCORE FROM ZITEM WHERE Z_PK = ?",
+                [item_id],
+                |row| row.get(0),
+            )?;
+            Ok((score_id, start))
+        }
+        None => Ok((item_id, 1)),
+    }
+}
+
+/// Notes on a score's pages as (page number, note), in page order
+pub fn page_notes(conn: &Connection, score_id: i64) -> Result<Vec<(i32, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT ZNUMBER, ZNOTE FROM ZPAGE
+         WHERE ZSCORE = ? AND ZNOTE IS NOT NULL AND ZNOTE != ''
+         ORDER BY ZNUMBER",
+    )?;
+
+    let notes = stmt
+        .query_map([score_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(notes)
+}
+
+/// Set (or with `None`, clear) the note on one page of a score
+pub fn set_page_note(
+    conn: &Connection,
+    score_id: i64,
+    page: i32,
+    note: Option<&str>,
+) -> Result<()> {
+    let updated = conn.execute(
+        "UPDATE ZPAGE SET ZNOTE = ?, Z_OPT = Z_OPT + 1 WHERE ZSCORE = ? AND ZNUMBER = ?",
+        rusqlite::params![note, score_id, page],
+    )?;
+    if updated == 0 && note.is_some() {
+        insert_page(conn, score_id, page, note, false)?;
+    }
+    Ok(())
+}
+
+/// SQL condition (on ZITEM alias `i`) for items marked as favorites: a score
+/// whose first page is flagged, or a bookmark whose start page is
+pub fn favorite_condition() -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM ZPAGE fp WHERE fp.ZFLAGGED = 1
+         AND ((i.Z_ENT = {} AND fp.ZSCORE = i.Z_PK AND fp.ZNUMBER = 1)
+           OR (i.Z_ENT = {} AND fp.ZSCORE = i.ZSCORE AND fp.ZNUMBER = i.ZSTARTPAGE)))",
+        entity::score(),
+        entity::bookmark()
+    )
+}
+
+/// IDs of all items marked as favorites
+pub fn favorite_item_ids(conn: &Connection) -> Result<HashSet<i64>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT i.Z_PK FROM ZITEM i WHERE {}",
+        favorite_condition()
+    ))?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(ids)
+}
+
+/// Flag or unflag one page of a score
+pub fn set_page_flag(conn: &Connection, score_id: i64, page: i32, flagged: bool) -> Result<()> {
+    let updated = conn.execute(
+        "UPDATE ZPAGE SET ZFLAGGED = ?, Z_OPT = Z_OPT + 1 WHERE ZSCORE = ? AND ZNUMBER = ?",
+        rusqlite::params![flagged, score_id, page],
+    )?;
+    if updated == 0 && flagged {
+        insert_page(conn, score_id, page, None, true)?;
+    }
+    Ok(())
+}
+
+/// Create the page record forScore hasn't made yet
+fn insert_page(
+    conn: &Connection,
+    score_id: i64,
+    page: i32,
+    note: Option<&str>,
+    flagged: bool,
+) -> Result<()> {
+    let max_pk: i64 = conn.query_row("SELECT COALESCE(MAX(Z_PK), 0) FROM ZPAGE", [], |row| {
+        row.get(0)
+    })?;
+
+    conn.execute(
+        "INSERT INTO ZPAGE (Z_PK, Z_ENT, Z_OPT, ZNUMBER, ZSCORE, ZNOTE, ZFLAGGED)
+         VALUES (?, ?, 1, ?, ?, ?, ?)",
+        rusqlite::params![max_pk + 1, entity::page(), page, score_id, note, flagged],
+    )?;
+
+    conn.execute(
+        "UPDATE Z_PRIMARYKEY SET Z_MAX = ? WHERE Z_ENT = ?",
+        [max_pk + 1, entity::page() as i64],
+    )?;
+
+    Ok(())
+}
+
+/// Add a score for a PDF in the Documents folder, returning the new score
+pub fn create_score(conn: &Connection, path: &str, title: &str) -> Result<Score> {
+    let uuid = uuid::Uuid::new_v4().to_string().to_uppercase();
+    let timestamp = core_data_timestamp();
+
+    let max_pk: i64 = conn.query_row("SELECT COALESCE(MAX(Z_PK), 0) FROM ZITEM", [], |row| {
+        row.get(0)
+    })?;
+
+    conn.execute(
+        "INSERT INTO ZITEM (Z_PK, Z_ENT, Z_OPT, ZPATH, ZTITLE, ZSORTTITLE, ZUUID, ZADDED, ZMODIFIED)
+         VALUES (?, ?, 1, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            max_pk + 1,
+            entity::score(),
+            path,
+            title,
+            sort_title(title),
+            uuid,
+            timestamp,
+            timestamp
+        ],
+    )?;
+
+    conn.execute(
+        "UPDATE Z_PRIMARYKEY SET Z_MAX = ? WHERE Z_ENT = ?",
+        [max_pk + 1, entity::item() as i64],
+    )?;
+
+    get_score_by_id(conn, max_pk + 1)
+}
+
+/// Create a bookmark within a score, returning the new bookmark
+pub fn create_bookmark(
+    conn: &Connection,
+    score: &Score,
+    title: &str,
+    start_page: i32,
+    end_page: i32,
+) -> Result<Bookmark> {
+    let uuid = uuid::Uuid::new_v4().to_string().to_uppercase();
+    create_bookmark_with_uuid(conn, score, title, start_page, end_page, &uuid)
+}
+
+/// Create a bookmark with a given UUID (e.g. one an ITM file already uses)
+pub fn create_bookmark_with_uuid(
+    conn: &Connection,
+    score: &Score,
+    title: &str,
+    start_page: i32,
+    end_page: i32,
+    uuid: &str,
+) -> Result<Bookmark> {
+    let timestamp = core_data_timestamp();
+
+    let max_pk: i64 = conn.query_row("SELECT COALESCE(MAX(Z_PK), 0) FROM ZITEM", [], |row| {
+        row.get(0)
+    })?;
+
+    conn.execute(
+        "INSERT INTO ZITEM (Z_PK, Z_ENT, Z_OPT, ZPATH, ZTITLE, ZSORTTITLE, ZUUID,
+                            ZSTARTPAGE, ZENDPAGE, ZSCORE, ZADDED, ZMODIFIED)
+         VALUES (?, ?, 1, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            max_pk + 1,
+            entity::bookmark(),
+            score.path,
+            title,
+            sort_title(title),
+            uuid,
+            start_page,
+            end_page,
+            score.id,
+            timestamp,
+            timestamp
+        ],
+    )?;
+
+    // Update Z_PRIMARYKEY (bookmarks share the Item key space)
+    conn.execute(
+        "UPDATE Z_PRIMARYKEY SET Z_MAX = ? WHERE Z_ENT = ?",
+        [max_pk + 1, entity::item() as i64],
+    )?;
+
+    get_bookmark_by_id(conn, max_pk + 1)
+}
+
+/// Fold a duplicate score into `keep_id`: setlist entries, tracks, recordings,
+/// bookmarks, metadata links and (if `keep_id` has none) pages move over, then
+/// the duplicate is deleted with any pages left. Run it in a transaction.
+pub fn merge_score_into(conn: &Connection, duplicate_id: i64, keep_id: i64) -> Result<()> {
+    let keep_path: String =
+        conn.query_row("SELECT ZPATH FROM ZITEM WHERE Z_PK = ?", [keep_id], |row| {
+            row.get(0)
+        })?;
+
+    for table in ["ZCYLON", "ZTRACK", "ZRECORDING"] {
+        conn.execute(
+            &format!("UPDATE {} SET ZITEM = ? WHERE ZITEM = ?", table),
+            [keep_id, duplicate_id],
+        )?;
+    }
+
+    conn.execute(
+        "UPDATE ZITEM SET ZSCORE = ?, ZPATH = ? WHERE ZSCORE = ? AND Z_ENT = ?",
+        rusqlite::params![keep_id, keep_path, duplicate_id, entity::bookmark()],
+    )?;
+
+    let keep_pages: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM ZPAGE WHERE ZSCORE = ?",
+        [keep_id],
+        |row| row.get(0),
+    )?;
+    if keep_pages == 0 {
+        conn.execute(
+            "UPDATE ZPAGE SET ZSCORE = ? WHERE ZSCORE = ?",
+            [keep_id, duplicate_id],
+        )?;
+    } else {
+        for (table, columns) in PAGE_LINK_TABLES {
+            conn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE {}",
+                    table,
+                    page_link_condition(columns)
+                ),
+                [duplicate_id],
+            )?;
+        }
+        conn.execute("DELETE FROM ZPAGE WHERE ZSCORE = ?", [duplicate_id])?;
+    }
+
+    // Composers, genres, tags, labels and libraries the kept score lacks
+    for (table, item_column, meta_column) in LINK_TABLES {
+        conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {0} ({1}, {2}) SELECT ?, {2} FROM {0} WHERE {1} = ?",
+                table, item_column, meta_column
+            ),
+            [keep_id, duplicate_id],
+        )?;
+        conn.execute(
+            &format!("DELETE FROM {} WHERE {} = ?", table, item_column),
+            [duplicate_id],
+        )?;
+    }
+
+    conn.execute("DELETE FROM ZITEM WHERE Z_PK = ?", [duplicate_id])?;
+    Ok(())
+}
+
+/// Setlist entries of a score and its bookmarks, as (setlist name, entry UUID)
+pub fn setlist_entries_for_score(
+    conn: &Connection,
+    score_id: i64,
+) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.ZTITLE, c.ZUUID
+         FROM ZCYLON c
+         JOIN ZSETLIST s ON c.ZSETLIST = s.Z_PK
+         JOIN ZITEM i ON c.ZITEM = i.Z_PK
+         WHERE i.Z_PK = ?1 OR (i.ZSCORE = ?1 AND i.Z_ENT = ?2)
+         ORDER BY s.ZTITLE, c.Z_PK",
+    )?;
+    let entries = stmt
+        .query_map(rusqlite::params![score_id, entity::bookmark()], |row| {
+            Ok((row.get(0)?, row.get::<_, Option<String>>(1)?))
+        })?
+        .filter_map(|r| r.ok())
+        .filter_map(|(name, uuid)| Some((name, uuid?)))
+        .collect();
+    Ok(entries)
+}
+
+/// Delete a score with its bookmarks, pages (and their buttons, text
+/// annotations and links), tracks, recordings, setlist entries and metadata
+/// links, in one transaction
+pub fn delete_score(conn: &Connection, score_id: i64) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT Z_PK FROM ZITEM WHERE ZSCORE = ? AND Z_ENT = ?")?;
+    let mut item_ids: Vec<i64> = stmt
+        .query_map(rusqlite::params![score_id, entity::bookmark()], |row| {
+            row.get(0)
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    item_ids.push(score_id);
+
+    let tx = conn.unchecked_transaction()?;
+    for &id in &item_ids {
+        delete_item(&tx, id)?;
+    }
+    for (table, columns) in PAGE_LINK_TABLES {
+        tx.execute(
+            &format!(
+                "DELETE FROM {} WHERE {}",
+                table,
+                page_link_condition(columns)
+            ),
+            [score_id],
+        )?;
+    }
+    tx.execute("DELETE FROM ZPAGE WHERE ZSCORE = ?", [score_id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Rows hanging off a score's pages, as (table, columns holding a ZPAGE ID):
+/// buttons, text annotations and links between pages
+pub const PAGE_LINK_TABLES: &[(&str, &[&str])] = &[
+    ("ZBUTTON", &["ZPAGE"]),
+    ("ZTEXTANNOTATION", &["ZPAGE"]),
+    ("ZLINK", &["ZFROM", "ZTO"]),
+];
+
+/// SQL condition matching rows whose `columns` point at a page of score `?1`
+pub fn page_link_condition(columns: &[&str]) -> String {
+    columns
+        .iter()
+        .map(|c| format!("{} IN (SELECT Z_PK FROM ZPAGE WHERE ZSCORE = ?1)", c))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// Rows belonging to a score or bookmark besides its own, as (table, column
+/// holding the item's ID): setlist entries, tracks, recordings and metadata
+/// links
+pub const ITEM_LINK_TABLES: &[(&str, &str)] = &[
+    ("ZCYLON", "ZITEM"),
+    ("ZTRACK", "ZITEM"),
+    ("ZRECORDING", "ZITEM"),
+    ("Z_4COMPOSERS", "Z_4ITEMS1"),
+    ("Z_4LABELS", "Z_4ITEMS2"),
+    ("Z_4LIBRARIES", "Z_4ITEMS3"),
+    ("Z_4GENRES", "Z_4ITEMS4"),
+    ("Z_4KEYWORDS", "Z_4ITEMS5"),
+];
+
+/// Delete one score or bookmark row with its setlist entries, tracks,
+/// recordings and metadata links (a score's bookmarks and pages are left)
+pub fn delete_item(conn: &Connection, item_id: i64) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT DISTINCT ZSETLIST FROM ZCYLON WHERE ZITEM = ?")?;
+    let setlists: Vec<i64> = stmt
+        .query_map([item_id], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+    for setlist_id in setlists {
+        touch_setlist(conn, setlist_id)?;
+    }
+
+    for (table, column) in ITEM_LINK_TABLES {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE {} = ?", table, column),
+            [item_id],
+        )?;
+    }
+    conn.execute("DELETE FROM ZITEM WHERE Z_PK = ?", [item_id])?;
+    Ok(())
+}
+
+/// Whether another score uses the same PDF (e.g. a duplicate entry)
+pub fn path_shared(conn: &Connection, score_id: i64, path: &str) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM ZITEM WHERE ZPATH = ? AND Z_ENT = ? AND Z_PK != ?)",
+        rusqlite::params![path, entity::score(), score_id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Every ZITEM column of an item, in table order. Blobs holding a plist are
+/// decoded; other blobs are shown as hex.
+pub fn raw_item_fields(
+    conn: &Connection,
+    item_id: i64,
+) -> Result<Vec<(String, serde_json::Value)>> {
+    let mut stmt = conn.prepare("SELECT * FROM ZITEM WHERE Z_PK = ?")?;
+    let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+
+    let fields = stmt
+        .query_row([item_id], |row| {
+            let mut fields = Vec::new();
+            for (i, name) in names.iter().enumerate() {
+                let value = match row.get_ref(i)? {
+                    ValueRef::Null => serde_json::Value::Null,
+                    ValueRef::Integer(n) => n.into(),
+                    ValueRef::Real(r) => r.into(),
+                    ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned().into(),
+                    ValueRef::Blob(b) => match plist::from_bytes::<plist::Value>(b) {
+                        Ok(value) => plist_to_json(&value),
+                        Err(_) => hex(b).into(),
+                    },
+                };
+                fields.push((name.clone(), value));
+            }
+            Ok(fields)
+        })
+        .map_err(|_| ForScoreError::ScoreNotFound(item_id.to_string()))?;
+
+    Ok(fields)
+}
+
+/// An item's ZMODIFIED (Core Data timestamp)
+pub fn item_modified(conn: &Connection, item_id: i64) -> Result<Option<f64>> {
+    conn.query_row(
+        "SELECT ZMODIFIED FROM ZITEM WHERE Z_PK = ?",
+        [item_id],
+        |row| row.get(0),
+    )
+    .map_err(|_| ForScoreError::ScoreNotFound(item_id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::create_known_schema;
+
+    #[test]
+    fn test_merge_score_into() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_known_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO ZITEM (Z_PK, Z_ENT, ZPATH, ZTITLE) VALUES (1, 6, 'a.pdf', 'Air'), (2, 6, 'A.pdf', 'Air');
+             INSERT INTO Z_4COMPOSERS VALUES (1, 10), (2, 10), (2, 11);
+             INSERT INTO Z_4LIBRARIES VALUES (2, 7);
+             INSERT INTO ZPAGE (Z_PK, ZSCORE) VALUES (1, 1), (2, 2);
+             INSERT INTO ZBUTTON (Z_PK, ZPAGE) VALUES (1, 2);
+             INSERT INTO ZLINK (Z_PK, ZFROM, ZTO) VALUES (1, 2, 1);",
+        )
+        .unwrap();
+
+        let tx = conn.unchecked_transaction().unwrap();
+        merge_score_into(&tx, 2, 1).unwrap();
+        tx.commit().unwrap();
+
+        let ids = |sql: &str| -> Vec<i64> {
+            conn.prepare(sql)
+                .unwrap()
+                .query_map([], |row| row.get(0))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+        };
+        assert_eq!(ids("SELECT Z_PK FROM ZITEM"), [1]);
+        assert_eq!(
+            ids("SELECT Z_10COMPOSERS FROM Z_4COMPOSERS WHERE Z_4ITEMS1 = 1 ORDER BY 1"),
+            [10, 11]
+        );
+        assert_eq!(
+            ids("SELECT Z_7LIBRARIES FROM Z_4LIBRARIES WHERE Z_4ITEMS3 = 1"),
+            [7]
+        );
+        assert_eq!(ids("SELECT Z_PK FROM ZPAGE"), [1]);
+        assert!(ids("SELECT Z_PK FROM ZBUTTON UNION ALL SELECT Z_PK FROM ZLINK").is_empty());
+    }
+}
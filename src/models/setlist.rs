@@ -1,7 +1,11 @@
-use crate::db::entity;
+use crate::config::alias_target;
+use crate::db::{core_data_timestamp, entity};
 use crate::error::{ForScoreError, Result};
-use rusqlite::Connection;
+use crate::fuzzy::{best_match, note_closest, with_suggestions, FuzzyMatch};
+use crate::touch::touch_setlist;
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Setlist {
@@ -11,6 +15,39 @@ pub struct Setlist {
     pub score_count: i32,
 }
 
+/// A score or bookmark that appears in several setlists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedItem {
+    pub id: i64,
+    pub title: String,
+    pub is_bookmark: bool,
+    pub setlists: Vec<String>,
+}
+
+/// A setlist's ordering fields: its place in forScore's setlist menu
+/// (ZMENUINDEX, counted within its folder), plus the raw ZINDEX and ZSORT values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetlistOrder {
+    pub id: i64,
+    pub title: String,
+    pub menu_index: Option<i64>,
+    pub index: Option<i64>,
+    pub sort: Option<i64>,
+    /// ZFOLDER, the menu folder the setlist is in
+    pub folder: Option<i64>,
+    pub score_count: i32,
+}
+
+/// Where an item appears in a setlist
+#[derive(Debug, Clone)]
+pub struct SetlistEntry {
+    pub setlist: Setlist,
+    /// ZCYLON UUID, the entry's identifier in the .set sync file
+    pub uuid: Option<String>,
+    /// 1-based position in the setlist
+    pub position: i64,
+}
+
 /// List all setlists
 pub fn list_setlists(conn: &Connection) -> Result<Vec<Setlist>> {
     let mut stmt = conn.prepare(
@@ -35,6 +72,83 @@ pub fn list_setlists(conn: &Connection) -> Result<Vec<Setlist>> {
     Ok(setlists)
 }
 
+/// All setlists with their ordering fields, in menu order (folder by folder)
+pub fn list_setlist_order(conn: &Connection) -> Result<Vec<SetlistOrder>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.Z_PK, s.ZTITLE, s.ZMENUINDEX, s.ZINDEX, s.ZSORT, s.ZFOLDER,
+                (SELECT COUNT(*) FROM ZCYLON c WHERE c.ZSETLIST = s.Z_PK) as score_count
+         FROM ZSETLIST s
+         ORDER BY s.ZFOLDER NULLS FIRST, s.ZMENUINDEX NULLS LAST, s.ZTITLE",
+    )?;
+
+    let setlists: Vec<SetlistOrder> = stmt
+        .query_map([], |row| {
+            Ok(SetlistOrder {
+                id: row.get("Z_PK")?,
+                title: row.get("ZTITLE")?,
+                menu_index: row.get("ZMENUINDEX")?,
+                index: row.get("ZINDEX")?,
+                sort: row.get("ZSORT")?,
+                folder: row.get("ZFOLDER")?,
+                score_count: row.get("score_count")?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(setlists)
+}
+
+/// Move a setlist to a 0-based position in its folder of the setlist menu,
+/// renumbering the folder's other setlists so menu indexes stay 0, 1, 2, ...
+/// Only rows whose index changes are written.
+pub fn set_setlist_menu_index(conn: &Connection, setlist_id: i64, position: usize) -> Result<()> {
+    let setlists = list_setlist_order(conn)?;
+    let folder = setlists
+        .iter()
+        .find(|s| s.id == setlist_id)
+        .ok_or_else(|| ForScoreError::SetlistNotFound(setlist_id.to_string()))?
+        .folder;
+    let mut order: Vec<(i64, Option<i64>)> = setlists
+        .into_iter()
+        .filter(|s| s.folder == folder)
+        .map(|s| (s.id, s.menu_index))
+        .collect();
+    let current = order
+        .iter()
+        .position(|(id, _)| *id == setlist_id)
+        .ok_or_else(|| ForScoreError::SetlistNotFound(setlist_id.to_string()))?;
+    let moved = order.remove(current);
+    order.insert(position.min(order.len()), moved);
+
+    for (i, (id, menu_index)) in order.into_iter().enumerate() {
+        if menu_index != Some(i as i64) {
+            conn.execute(
+                "UPDATE ZSETLIST SET ZMENUINDEX = ? WHERE Z_PK = ?",
+                [i as i64, id],
+            )?;
+            touch_setlist(conn, id)?;
+        }
+    }
+    Ok(())
+}
+
+/// Set a setlist's raw ZINDEX and/or ZSORT values
+pub fn set_setlist_index_fields(
+    conn: &Connection,
+    setlist_id: i64,
+    index: Option<i64>,
+    sort: Option<i64>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE ZSETLIST SET ZINDEX = COALESCE(?, ZINDEX), ZSORT = COALESCE(?, ZSORT)
+         WHERE Z_PK = ?",
+        rusqlite::params![index, sort, setlist_id],
+    )?;
+    touch_setlist(conn, setlist_id)?;
+    Ok(())
+}
+
 /// Get setlist by ID
 pub fn get_setlist_by_id(conn: &Connection, id: i64) -> Result<Setlist> {
     let mut stmt = conn.prepare(
@@ -119,8 +233,50 @@ pub fn get_setlist_by_name(conn: &Connection, name: &str) -> Result<Setlist> {
     }
 }
 
-/// Resolve setlist by ID or name
+/// The setlist whose name is closest to `name`, allowing for typos
+fn closest_setlist(conn: &Connection, name: &str, take_match: bool) -> Result<Setlist> {
+    let all = list_setlists(conn)?;
+    match best_match(name, &all, |s| &s.title) {
+        FuzzyMatch::Match(setlist) if take_match => {
+            note_closest(name, &setlist.title);
+            Ok(setlist.clone())
+        }
+        FuzzyMatch::Match(setlist) => Err(ForScoreError::SetlistNotFound(with_suggestions(
+            name,
+            &[setlist.title.as_str()],
+        ))),
+        FuzzyMatch::Suggestions(closest) => {
+            let names: Vec<&str> = closest.iter().map(|s| s.title.as_str()).collect();
+            Err(ForScoreError::SetlistNotFound(with_suggestions(
+                name, &names,
+            )))
+        }
+    }
+}
+
+/// Resolve setlist by ID, name or alias, falling back to the closest name
 pub fn resolve_setlist(conn: &Connection, identifier: &str) -> Result<Setlist> {
+    resolve_setlist_with(conn, identifier, true)
+}
+
+/// Resolve a setlist by ID, name or alias for a command that deletes or
+/// unlinks something: the closest names are suggested, not used
+pub fn resolve_setlist_strict(conn: &Connection, identifier: &str) -> Result<Setlist> {
+    resolve_setlist_with(conn, identifier, false)
+}
+
+fn resolve_setlist_with(conn: &Connection, identifier: &str, fuzzy: bool) -> Result<Setlist> {
+    match resolve_setlist_exact(conn, identifier) {
+        Err(ForScoreError::SetlistNotFound(_)) => match alias_target(identifier) {
+            Some(target) => resolve_setlist_exact(conn, target),
+            None => closest_setlist(conn, identifier, fuzzy),
+        },
+        result => result,
+    }
+}
+
+/// Resolve a setlist by ID or name, without fuzzy matching
+pub fn resolve_setlist_exact(conn: &Connection, identifier: &str) -> Result<Setlist> {
     if let Ok(id) = identifier.parse::<i64>() {
         if let Ok(setlist) = get_setlist_by_id(conn, id) {
             return Ok(setlist);
@@ -139,7 +295,7 @@ pub fn create_setlist(conn: &Connection, name: &str) -> Result<Setlist> {
     })?;
 
     // Get entity info
-    let z_ent = entity::SETLIST;
+    let z_ent = entity::setlist();
 
     conn.execute(
         "INSERT INTO ZSETLIST (Z_PK, Z_ENT, Z_OPT, ZTITLE, ZUUID, ZINDEX, ZMENUINDEX, ZSORT)
@@ -152,6 +308,7 @@ pub fn create_setlist(conn: &Connection, name: &str) -> Result<Setlist> {
         "UPDATE Z_PRIMARYKEY SET Z_MAX = ? WHERE Z_ENT = ?",
         [max_pk + 1, z_ent as i64],
     )?;
+    touch_setlist(conn, max_pk + 1)?;
 
     get_setlist_by_id(conn, max_pk + 1)
 }
@@ -166,6 +323,21 @@ pub fn rename_setlist(conn: &Connection, setlist_id: i64, new_name: &str) -> Res
     if affected == 0 {
         return Err(ForScoreError::SetlistNotFound(setlist_id.to_string()));
     }
+    touch_setlist(conn, setlist_id)?;
+    Ok(())
+}
+
+/// Set a setlist's last played time to now
+pub fn mark_setlist_played(conn: &Connection, setlist_id: i64) -> Result<()> {
+    let affected = conn.execute(
+        "UPDATE ZSETLIST SET ZLASTPLAYED = ? WHERE Z_PK = ?",
+        rusqlite::params![core_data_timestamp(), setlist_id],
+    )?;
+
+    if affected == 0 {
+        return Err(ForScoreError::SetlistNotFound(setlist_id.to_string()));
+    }
+    touch_setlist(conn, setlist_id)?;
     Ok(())
 }
 
@@ -180,20 +352,23 @@ pub fn delete_setlist(conn: &Connection, setlist_id: i64) -> Result<()> {
     if affected == 0 {
         return Err(ForScoreError::SetlistNotFound(setlist_id.to_string()));
     }
+    touch_setlist(conn, setlist_id)?;
     Ok(())
 }
 
-/// Add a score to a setlist
-pub fn add_score_to_setlist(conn: &Connection, setlist_id: i64, score_id: i64) -> Result<()> {
+/// Add a score to a setlist, returning its entry's UUID (the existing
+/// entry's if the score is already there)
+pub fn add_score_to_setlist(conn: &Connection, setlist_id: i64, score_id: i64) -> Result<String> {
     // Check if already in setlist
-    let exists: bool = conn.query_row(
-        "SELECT EXISTS(SELECT 1 FROM ZCYLON WHERE ZSETLIST = ? AND ZITEM = ?)",
-        [setlist_id, score_id],
-        |row| row.get(0),
-    )?;
-
-    if exists {
-        return Ok(()); // Already in setlist
+    let existing: Option<Option<String>> = conn
+        .query_row(
+            "SELECT ZUUID FROM ZCYLON WHERE ZSETLIST = ? AND ZITEM = ? ORDER BY Z_PK LIMIT 1",
+            [setlist_id, score_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(uuid) = existing {
+        return Ok(uuid.unwrap_or_default()); // Already in setlist
     }
 
     // Get max Z_PK for ordering
@@ -216,28 +391,31 @@ pub fn add_score_to_setlist(conn: &Connection, setlist_id: i64, score_id: i64) -
     conn.execute(
         "INSERT INTO ZCYLON (Z_PK, Z_ENT, Z_OPT, ZSETLIST, ZITEM, Z4_ITEM, ZSHUFFLE, ZUUID)
          VALUES (?, 2, 1, ?, ?, ?, 0, ?)",
-        rusqlite::params![max_pk + 1, setlist_id, score_id, entity::SCORE, uuid],
+        rusqlite::params![max_pk + 1, setlist_id, score_id, entity::score(), uuid],
     )?;
+    touch_setlist(conn, setlist_id)?;
 
-    Ok(())
+    Ok(uuid)
 }
 
-/// Add an item (score or bookmark) to a setlist with specified entity type
+/// Add an item (score or bookmark) to a setlist with specified entity type,
+/// returning its entry's UUID (the existing entry's if it's already there)
 pub fn add_item_to_setlist(
     conn: &Connection,
     setlist_id: i64,
     item_id: i64,
     entity_type: i32,
-) -> Result<()> {
+) -> Result<String> {
     // Check if already in setlist
-    let exists: bool = conn.query_row(
-        "SELECT EXISTS(SELECT 1 FROM ZCYLON WHERE ZSETLIST = ? AND ZITEM = ?)",
-        [setlist_id, item_id],
-        |row| row.get(0),
-    )?;
-
-    if exists {
-        return Ok(()); // Already in setlist
+    let existing: Option<Option<String>> = conn
+        .query_row(
+            "SELECT ZUUID FROM ZCYLON WHERE ZSETLIST = ? AND ZITEM = ? ORDER BY Z_PK LIMIT 1",
+            [setlist_id, item_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(uuid) = existing {
+        return Ok(uuid.unwrap_or_default()); // Already in setlist
     }
 
     // Get max Z_PK for ordering
@@ -261,8 +439,9 @@ pub fn add_item_to_setlist(
          VALUES (?, 2, 1, ?, ?, ?, 0, ?)",
         rusqlite::params![max_pk + 1, setlist_id, item_id, entity_type, uuid],
     )?;
+    touch_setlist(conn, setlist_id)?;
 
-    Ok(())
+    Ok(uuid)
 }
 
 /// Remove a score from a setlist
@@ -271,9 +450,42 @@ pub fn remove_score_from_setlist(conn: &Connection, setlist_id: i64, score_id: i
         "DELETE FROM ZCYLON WHERE ZSETLIST = ? AND ZITEM = ?",
         [setlist_id, score_id],
     )?;
+    touch_setlist(conn, setlist_id)?;
     Ok(())
 }
 
+/// Setlists an item is in, with its position in each
+pub fn list_item_setlist_entries(conn: &Connection, item_id: i64) -> Result<Vec<SetlistEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.Z_PK, s.ZTITLE, s.ZUUID,
+                (SELECT COUNT(*) FROM ZCYLON c2 WHERE c2.ZSETLIST = s.Z_PK) as score_count,
+                c.ZUUID as entry_uuid,
+                (SELECT COUNT(*) FROM ZCYLON c3 WHERE c3.ZSETLIST = s.Z_PK AND c3.Z_PK <= c.Z_PK) as position
+         FROM ZCYLON c
+         JOIN ZSETLIST s ON c.ZSETLIST = s.Z_PK
+         WHERE c.ZITEM = ?
+         ORDER BY s.ZTITLE",
+    )?;
+
+    let entries: Vec<SetlistEntry> = stmt
+        .query_map([item_id], |row| {
+            Ok(SetlistEntry {
+                setlist: Setlist {
+                    id: row.get("Z_PK")?,
+                    title: row.get("ZTITLE")?,
+                    uuid: row.get("ZUUID")?,
+                    score_count: row.get("score_count")?,
+                },
+                uuid: row.get("entry_uuid")?,
+                position: row.get("position")?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(entries)
+}
+
 /// Reorder a score within a setlist
 pub fn reorder_score_in_setlist(
     conn: &Connection,
@@ -281,47 +493,225 @@ pub fn reorder_score_in_setlist(
     score_id: i64,
     new_position: usize,
 ) -> Result<()> {
-    // Get all items in current order with their entity types
-    let mut stmt =
-        conn.prepare("SELECT Z_PK, ZITEM, Z4_ITEM FROM ZCYLON WHERE ZSETLIST = ? ORDER BY Z_PK")?;
-
-    let members: Vec<(i64, i64, i32)> = stmt
-        .query_map([setlist_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+    // Get all items in current order
+    let mut stmt = conn.prepare("SELECT ZITEM FROM ZCYLON WHERE ZSETLIST = ? ORDER BY Z_PK")?;
+    let mut new_order: Vec<i64> = stmt
+        .query_map([setlist_id], |row| row.get(0))?
         .filter_map(|r| r.ok())
         .collect();
 
     // Find current position
-    let current_pos = members.iter().position(|(_, id, _)| *id == score_id);
-    if current_pos.is_none() {
+    let Some(current_pos) = new_order.iter().position(|id| *id == score_id) else {
         return Err(ForScoreError::Other(format!(
             "Score {} not in setlist {}",
             score_id, setlist_id
         )));
+    };
+    let removed = new_order.remove(current_pos);
+    let insert_pos = (new_position - 1).min(new_order.len());
+    new_order.insert(insert_pos, removed);
+
+    set_setlist_item_order(conn, setlist_id, &new_order)
+}
+
+/// One ZCYLON row: a setlist entry
+#[derive(Debug, PartialEq)]
+struct CylonRow {
+    item: i64,
+    entity: i32,
+    uuid: Option<String>,
+}
+
+/// A setlist's rows in `order` (item IDs). An item that's in the setlist
+/// more than once keeps each entry's own row, in their original order.
+/// None if `order` doesn't hold exactly the setlist's items.
+fn rows_in_order(members: Vec<CylonRow>, order: &[i64]) -> Option<Vec<CylonRow>> {
+    if members.len() != order.len() {
+        return None;
+    }
+    let mut by_item: HashMap<i64, VecDeque<CylonRow>> = HashMap::new();
+    for row in members {
+        by_item.entry(row.item).or_default().push_back(row);
     }
+    order
+        .iter()
+        .map(|item| by_item.get_mut(item).and_then(VecDeque::pop_front))
+        .collect()
+}
+
+/// Put a setlist's items in the given order, by item ID. `order` must hold
+/// exactly the items the setlist has, an item that's in it twice included
+/// twice
+pub fn set_setlist_item_order(conn: &Connection, setlist_id: i64, order: &[i64]) -> Result<()> {
+    let mut stmt =
+        conn.prepare("SELECT ZITEM, Z4_ITEM, ZUUID FROM ZCYLON WHERE ZSETLIST = ? ORDER BY Z_PK")?;
+    let members: Vec<CylonRow> = stmt
+        .query_map([setlist_id], |row| {
+            Ok(CylonRow {
+                item: row.get(0)?,
+                entity: row.get(1)?,
+                uuid: row.get(2)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let rows = rows_in_order(members, order).ok_or_else(|| {
+        ForScoreError::Other(format!(
+            "New order for setlist {} doesn't match its items",
+            setlist_id
+        ))
+    })?;
 
     // Reorder by deleting and re-inserting with new Z_PK values
     let max_base: i64 = conn.query_row("SELECT COALESCE(MAX(Z_PK), 0) FROM ZCYLON", [], |row| {
         row.get(0)
     })?;
 
-    // Build new order (item_id, entity_type)
-    let mut new_order: Vec<(i64, i32)> = members.iter().map(|(_, id, ent)| (*id, *ent)).collect();
-    let removed = new_order.remove(current_pos.unwrap());
-    let insert_pos = (new_position - 1).min(new_order.len());
-    new_order.insert(insert_pos, removed);
-
     // Delete all memberships for this setlist
     conn.execute("DELETE FROM ZCYLON WHERE ZSETLIST = ?", [setlist_id])?;
 
-    // Re-insert in new order, preserving entity types
-    for (i, (item_id, entity_type)) in new_order.iter().enumerate() {
-        let uuid = uuid::Uuid::new_v4().to_string().to_uppercase();
+    // Re-insert in new order, preserving entity types and UUIDs (the sync
+    // file's identifiers, which per-entry settings there are keyed by)
+    for (i, row) in rows.into_iter().enumerate() {
+        let uuid = row
+            .uuid
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string().to_uppercase());
         conn.execute(
             "INSERT INTO ZCYLON (Z_PK, Z_ENT, Z_OPT, ZSETLIST, ZITEM, Z4_ITEM, ZSHUFFLE, ZUUID)
              VALUES (?, 2, 1, ?, ?, ?, 0, ?)",
-            rusqlite::params![max_base + 1 + i as i64, setlist_id, item_id, entity_type, uuid],
+            rusqlite::params![
+                max_base + 1 + i as i64,
+                setlist_id,
+                row.item,
+                row.entity,
+                uuid
+            ],
         )?;
     }
+    touch_setlist(conn, setlist_id)?;
 
     Ok(())
 }
+
+/// List items that belong to at least `min_setlists` setlists, most shared first
+pub fn list_shared_items(conn: &Connection, min_setlists: usize) -> Result<Vec<SharedItem>> {
+    // One row per item and setlist, so setlists sharing a title count separately
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT i.Z_PK, s.Z_PK, i.ZTITLE, i.Z_ENT, s.ZTITLE
+         FROM ZCYLON c
+         JOIN ZITEM i ON c.ZITEM = i.Z_PK
+         JOIN ZSETLIST s ON c.ZSETLIST = s.Z_PK
+         ORDER BY i.Z_PK, s.ZTITLE",
+    )?;
+
+    let rows: Vec<(i64, Option<String>, i32, Option<String>)> = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut items: Vec<SharedItem> = Vec::new();
+    for (id, title, ent, setlist) in rows {
+        match items.last_mut() {
+            Some(last) if last.id == id => last.setlists.push(setlist.unwrap_or_default()),
+            _ => items.push(SharedItem {
+                id,
+                title: title.unwrap_or_default(),
+                is_bookmark: ent == entity::bookmark(),
+                setlists: vec![setlist.unwrap_or_default()],
+            }),
+        }
+    }
+
+    items.retain(|item| item.setlists.len() >= min_setlists);
+    items.sort_by(|a, b| {
+        b.setlists
+            .len()
+            .cmp(&a.setlists.len())
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    });
+
+    Ok(items)
+}
+
+/// Titles of the setlists each score is in, itself or through one of its
+/// bookmarks, by score ID
+pub fn setlists_by_score(conn: &Connection) -> Result<HashMap<i64, Vec<String>>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT CASE WHEN i.Z_ENT = ? THEN i.ZSCORE ELSE i.Z_PK END, s.ZTITLE
+         FROM ZCYLON c
+         JOIN ZITEM i ON c.ZITEM = i.Z_PK
+         JOIN ZSETLIST s ON c.ZSETLIST = s.Z_PK
+         ORDER BY s.ZTITLE",
+    )?;
+    let mut setlists: HashMap<i64, Vec<String>> = HashMap::new();
+    let rows = stmt.query_map([entity::bookmark()], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+    for (score_id, title) in rows.flatten() {
+        setlists
+            .entry(score_id)
+            .or_default()
+            .push(title.unwrap_or_default());
+    }
+    Ok(setlists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_setlist_item_order_with_duplicate_entry() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ZSETLIST (Z_PK INTEGER PRIMARY KEY, Z_OPT INTEGER, ZTITLE TEXT);
+             CREATE TABLE ZCYLON (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, Z_OPT INTEGER,
+                 ZSETLIST INTEGER, ZITEM INTEGER, Z4_ITEM INTEGER, ZSHUFFLE INTEGER, ZUUID TEXT);
+             INSERT INTO ZSETLIST VALUES (1, 1, NULL);
+             INSERT INTO ZCYLON VALUES (1, 2, 1, 1, 10, 6, 0, 'A');
+             INSERT INTO ZCYLON VALUES (2, 2, 1, 1, 20, 5, 0, 'B');
+             INSERT INTO ZCYLON VALUES (3, 2, 1, 1, 10, 6, 0, 'C');",
+        )
+        .unwrap();
+
+        set_setlist_item_order(&conn, 1, &[10, 10, 20]).unwrap();
+
+        let rows: Vec<(i64, i32, String)> = conn
+            .prepare("SELECT ZITEM, Z4_ITEM, ZUUID FROM ZCYLON WHERE ZSETLIST = 1 ORDER BY Z_PK")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (10, 6, "A".to_string()),
+                (10, 6, "C".to_string()),
+                (20, 5, "B".to_string()),
+            ]
+        );
+
+        assert!(set_setlist_item_order(&conn, 1, &[10, 20]).is_err());
+        assert!(set_setlist_item_order(&conn, 1, &[10, 20, 20]).is_err());
+    }
+
+    #[test]
+    fn test_list_shared_items_counts_same_named_setlists() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::schema::create_known_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO ZITEM (Z_PK, Z_ENT, ZTITLE) VALUES (10, 6, 'Prelude');
+             INSERT INTO ZSETLIST (Z_PK, ZTITLE) VALUES (1, 'Gig'), (2, 'Gig');
+             INSERT INTO ZCYLON (Z_PK, ZSETLIST, ZITEM) VALUES (1, 1, 10), (2, 2, 10), (3, 2, 10);",
+        )
+        .unwrap();
+
+        let items = list_shared_items(&conn, 2).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].setlists, vec!["Gig", "Gig"]);
+    }
+}
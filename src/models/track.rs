@@ -0,0 +1,90 @@
+//! Audio linked to scores: tracks (ZTRACK), either from the Music library or
+//! imported audio files, and recordings made in forScore (ZRECORDING)
+
+use crate::db::entity;
+use crate::error::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::Path;
+
+/// Extensions of the audio files forScore can link
+const AUDIO_EXTENSIONS: &[&str] = &["aac", "aif", "aiff", "caf", "flac", "m4a", "mp3", "wav"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioLink {
+    /// The score, or for a bookmark's audio the bookmark's score
+    pub score_id: i64,
+    /// "track" or "recording"
+    pub kind: &'static str,
+    pub title: Option<String>,
+    /// File name of an imported track or a recording; None for a track
+    /// from the Music library
+    pub file: Option<String>,
+}
+
+/// Whether a file name has an audio extension
+pub fn is_audio_file(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Every track and recording, with the score it belongs to
+pub fn list_audio_links(conn: &Connection) -> Result<Vec<AudioLink>> {
+    let mut links = Vec::new();
+    // A bookmark's audio counts for its score
+    let score_of = format!(
+        "CASE WHEN i.Z_ENT = {} THEN i.ZSCORE ELSE i.Z_PK END",
+        entity::bookmark()
+    );
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, t.ZTITLE, t.ZPERSISTENTIDENTIFIER
+         FROM ZTRACK t JOIN ZITEM i ON t.ZITEM = i.Z_PK",
+        score_of
+    ))?;
+    let tracks = stmt.query_map([], |row| {
+        let identifier: Option<String> = row.get(2)?;
+        Ok(AudioLink {
+            score_id: row.get(0)?,
+            kind: "track",
+            title: row.get(1)?,
+            // Music library tracks are identified by a persistent ID instead
+            file: identifier.filter(|id| is_audio_file(id)),
+        })
+    })?;
+    links.extend(tracks.filter_map(|r| r.ok()));
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, r.ZFILENAME
+         FROM ZRECORDING r JOIN ZITEM i ON r.ZITEM = i.Z_PK",
+        score_of
+    ))?;
+    let recordings = stmt.query_map([], |row| {
+        let file: Option<String> = row.get(1)?;
+        Ok(AudioLink {
+            score_id: row.get(0)?,
+            kind: "recording",
+            title: file.clone(),
+            file,
+        })
+    })?;
+    links.extend(recordings.filter_map(|r| r.ok()));
+
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_audio_file() {
+        assert!(is_audio_file("Take 1.m4a"));
+        assert!(is_audio_file("backing/Track.MP3"));
+        assert!(!is_audio_file("1234567890123456789"));
+        assert!(!is_audio_file("Score.pdf"));
+        assert!(!is_audio_file("m4a"));
+    }
+}
@@ -1,22 +1,557 @@
-use serde::Serialize;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use tabled::builder::Builder;
 use tabled::{Table, Tabled};
 
+use crate::cli::{ColumnArgs, OutputFormat};
+use crate::config::load_config;
+use crate::error::{ForScoreError, Result};
+use crate::models::meta::HIERARCHY_SEPARATOR;
 use crate::models::score::Bookmark;
+use crate::models::setlist::{SetlistOrder, SharedItem};
 use crate::models::{Composer, Genre, Keyword, Library, Score, Setlist};
+use crate::profile::{self, Phase};
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// `--template`, if given
+static TEMPLATE: OnceLock<Template> = OnceLock::new();
+
+/// Record `--format`; call once at startup
+pub fn set_format(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// The format to print in: a command's own `--json` wins over `--format`
+fn format(json: bool) -> OutputFormat {
+    if json {
+        OutputFormat::Json
+    } else {
+        FORMAT.get().copied().unwrap_or_default()
+    }
+}
+
+/// Record `--template`; call once at startup
+pub fn set_template(template: Template) {
+    let _ = TEMPLATE.set(template);
+}
+
+/// Whether output goes out in a structured format or through `--template`,
+/// so headings and other decoration should be left out
+pub fn is_structured(json: bool) -> bool {
+    format(json) != OutputFormat::Table || TEMPLATE.get().is_some()
+}
+
+/// Whether items go out as NDJSON, which `write_ndjson` can write as they
+/// are read rather than after collecting them all
+pub fn is_ndjson(json: bool) -> bool {
+    format(json) == OutputFormat::Ndjson && TEMPLATE.get().is_none()
+}
+
+/// Write items as NDJSON, one line each as the iterator yields them
+pub fn write_ndjson<T: Serialize>(items: impl IntoIterator<Item = T>) {
+    let _span = profile::span(Phase::Render);
+    report(write_ndjson_lines(&mut io::stdout().lock(), items));
+}
+
+fn write_ndjson_lines<T: Serialize>(
+    mut out: impl Write,
+    items: impl IntoIterator<Item = T>,
+) -> WriteResult {
+    for item in items {
+        serde_json::to_writer(&mut out, &item)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
 
 /// Output format helper
 pub fn output<T: Serialize + ToTable>(items: &[T], json: bool) {
-    if json {
-        println!("{}", serde_json::to_string_pretty(items).unwrap());
+    let _span = profile::span(Phase::Render);
+    if is_structured(json) {
+        write_structured(items, format(json));
     } else {
         println!("{}", T::to_table(items));
     }
 }
 
+/// Print one item in a structured format: JSON and YAML as a single object,
+/// the row formats as a single row
+fn write_structured_one<T: Serialize>(item: &T, format: OutputFormat) {
+    match format {
+        OutputFormat::Json | OutputFormat::Yaml => {
+            report(print_document(item, format));
+        }
+        _ => write_structured(std::slice::from_ref(item), format),
+    }
+}
+
+/// Print items in a structured format, or one `--template` line each (the
+/// table format here means a template was given). NDJSON, CSV, TSV and
+/// template lines are written to stdout a row at a time rather than built
+/// up as one string first
+fn write_structured<T: Serialize>(items: &[T], format: OutputFormat) {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let result = match format {
+        OutputFormat::Table => match TEMPLATE.get() {
+            Some(template) => write_templated(&mut out, items, template),
+            None => Ok(()),
+        },
+        OutputFormat::Ndjson => write_ndjson_lines(&mut out, items),
+        OutputFormat::Csv => write_delimited(&mut out, items, b','),
+        OutputFormat::Tsv => write_delimited(&mut out, items, b'\t'),
+        _ => print_document(items, format),
+    };
+    report(result);
+}
+
+type WriteResult = std::result::Result<(), Box<dyn std::error::Error>>;
+
+/// Print a value as pretty JSON, or as YAML
+fn print_document<T: Serialize + ?Sized>(value: &T, format: OutputFormat) -> WriteResult {
+    let text = match format {
+        OutputFormat::Yaml => serde_norway::to_string(value)?,
+        _ => serde_json::to_string_pretty(value)? + "\n",
+    };
+    io::stdout().lock().write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// One row per item with a header of field names from the first item. Lists
+/// are joined with "; " (as in `export csv`) and nested objects written as JSON
+fn write_delimited<T: Serialize>(out: impl Write, items: &[T], delimiter: u8) -> WriteResult {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(out);
+    for (i, item) in items.iter().enumerate() {
+        let fields = ordered_fields(item)?;
+        if i == 0 {
+            writer.write_record(fields.iter().map(|(name, _)| name))?;
+        }
+        writer.write_record(fields.iter().map(|(_, value)| cell(value)))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// An item's fields in declaration order (a non-object is one "value" field)
+fn ordered_fields<T: Serialize>(item: &T) -> serde_json::Result<Vec<(String, Value)>> {
+    let json = serde_json::to_string(item)?;
+    match serde_json::from_str::<OrderedFields>(&json) {
+        Ok(fields) => Ok(fields.0),
+        Err(_) => Ok(vec![("value".into(), serde_json::from_str(&json)?)]),
+    }
+}
+
+/// A JSON object's fields in the order they appear; serde_json's own maps
+/// sort their keys
+struct OrderedFields(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for OrderedFields {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = OrderedFields;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<OrderedFields, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(OrderedFields(fields))
+            }
+        }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+/// A field as CSV/TSV cell text
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join("; "),
+        Value::Object(_) => value.to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// A `--template` such as "{title} — {composer} ({key})": text with
+/// placeholders naming an item's fields. `{{` and `}}` are literal braces
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, PartialEq)]
+enum TemplatePart {
+    Text(String),
+    Field(String),
+}
+
+impl Template {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = spec.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(ForScoreError::Other(format!(
+                                    "Unclosed '{{' in template: {}",
+                                    spec
+                                )))
+                            }
+                        }
+                    }
+                    let name = name.trim();
+                    if name.is_empty() {
+                        return Err(ForScoreError::Other("Empty '{}' in template".into()));
+                    }
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(TemplatePart::Field(name.to_lowercase()));
+                }
+                '}' => {
+                    return Err(ForScoreError::Other(format!(
+                        "Unmatched '}}' in template (use '}}}}' for a brace): {}",
+                        spec
+                    )))
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Fill in the placeholders from an item's fields. Returns the line and
+    /// any placeholders the item has no field for (they come out empty)
+    fn render(&self, fields: &[(String, Value)]) -> (String, Vec<&str>) {
+        let mut line = String::new();
+        let mut unknown = Vec::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(text) => line.push_str(text),
+                TemplatePart::Field(name) => {
+                    match fields.iter().find(|(field, _)| *field == field_name(name)) {
+                        Some((_, value)) => line.push_str(&template_value(value)),
+                        None => unknown.push(name.as_str()),
+                    }
+                }
+            }
+        }
+        (line, unknown)
+    }
+}
+
+/// The field a placeholder refers to: the singular and table-column names
+/// work too, e.g. {composer} for composers and {tags} for keywords
+fn field_name(name: &str) -> &str {
+    match name {
+        "composer" => "composers",
+        "genre" => "genres",
+        "tag" | "tags" | "keyword" => "keywords",
+        "label" => "labels",
+        "library" => "libraries",
+        "setlist" => "setlists",
+        "played" => "last_played",
+        other => other,
+    }
+}
+
+/// A field as template text: lists joined with ", ", dates as local days
+/// and keys as e.g. "G Minor"
+fn template_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => match s.parse::<DateTime<Utc>>() {
+            Ok(date) => local_date(date),
+            Err(_) => s.clone(),
+        },
+        Value::Array(items) => items
+            .iter()
+            .map(template_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Object(map) => match (map.get("note"), map.get("mode")) {
+            (Some(Value::String(note)), Some(Value::String(mode))) => {
+                format!("{} {}", note, mode)
+            }
+            _ => value.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+/// One template line per item. Placeholders no item has are reported once
+fn write_templated<T: Serialize>(
+    out: &mut impl Write,
+    items: &[T],
+    template: &Template,
+) -> WriteResult {
+    let mut warned = false;
+    for item in items {
+        let fields = ordered_fields(item)?;
+        let (line, unknown) = template.render(&fields);
+        if !unknown.is_empty() && !warned {
+            warned = true;
+            let names: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+            eprintln!(
+                "Warning: no field {} here (fields: {})",
+                unknown
+                    .iter()
+                    .map(|n| format!("'{{{}}}'", n))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                names.join(", ")
+            );
+        }
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// Report a failure to write output; a closed pipe (`| head`) is not one
+fn report(result: WriteResult) {
+    if let Err(e) = result {
+        let broken_pipe = e
+            .downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+            || e.downcast_ref::<csv::Error>().is_some_and(|e| {
+                matches!(e.kind(), csv::ErrorKind::Io(e) if e.kind() == io::ErrorKind::BrokenPipe)
+            });
+        if !broken_pipe {
+            eprintln!("Error writing output: {}", e);
+        }
+    }
+}
+
+/// A table column for score listings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Id,
+    Title,
+    Composer,
+    Genre,
+    Key,
+    Rating,
+    Difficulty,
+    Bpm,
+    Pages,
+    Tags,
+    Labels,
+    Libraries,
+    Path,
+    Added,
+    Modified,
+    Played,
+}
+
+/// Columns shown when neither `--columns` nor the config picks any
+pub const DEFAULT_COLUMNS: &[Column] = &[
+    Column::Id,
+    Column::Title,
+    Column::Composer,
+    Column::Key,
+    Column::Rating,
+    Column::Pages,
+];
+
+const DATE_COLUMNS: &[Column] = &[Column::Added, Column::Modified, Column::Played];
+
+impl Column {
+    const NAMES: &'static [(&'static str, Column)] = &[
+        ("id", Column::Id),
+        ("title", Column::Title),
+        ("composer", Column::Composer),
+        ("genre", Column::Genre),
+        ("key", Column::Key),
+        ("rating", Column::Rating),
+        ("difficulty", Column::Difficulty),
+        ("bpm", Column::Bpm),
+        ("pages", Column::Pages),
+        ("tags", Column::Tags),
+        ("labels", Column::Labels),
+        ("libraries", Column::Libraries),
+        ("path", Column::Path),
+        ("added", Column::Added),
+        ("modified", Column::Modified),
+        ("played", Column::Played),
+    ];
+
+    fn parse(name: &str) -> Result<Self> {
+        let name = name.trim().to_lowercase();
+        let alias = match name.as_str() {
+            "composers" => "composer",
+            "genres" => "genre",
+            "tag" | "keywords" => "tags",
+            "label" => "labels",
+            "library" => "libraries",
+            "last_played" => "played",
+            other => other,
+        };
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| *n == alias)
+            .map(|(_, column)| *column)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::NAMES.iter().map(|(n, _)| *n).collect();
+                ForScoreError::Other(format!(
+                    "Unknown column '{}' (columns: {})",
+                    name,
+                    names.join(", ")
+                ))
+            })
+    }
+
+    fn header(self) -> &'static str {
+        match self {
+            Column::Id => "ID",
+            Column::Title => "Title",
+            Column::Composer => "Composer",
+            Column::Genre => "Genre",
+            Column::Key => "Key",
+            Column::Rating => "Rating",
+            Column::Difficulty => "Difficulty",
+            Column::Bpm => "BPM",
+            Column::Pages => "Pages",
+            Column::Tags => "Tags",
+            Column::Labels => "Labels",
+            Column::Libraries => "Libraries",
+            Column::Path => "Path",
+            Column::Added => "Added",
+            Column::Modified => "Modified",
+            Column::Played => "Played",
+        }
+    }
+
+    fn value(self, s: &Score) -> String {
+        let date = |d: Option<DateTime<Utc>>| d.map(local_date).unwrap_or_default();
+        match self {
+            Column::Id => s.id.to_string(),
+            Column::Title => truncate(&s.title, 40),
+            Column::Composer => truncate(&s.composers.join(", "), 30),
+            Column::Genre => truncate(&s.genres.join(", "), 30),
+            Column::Key => s.key.as_ref().map(|k| k.display()).unwrap_or_default(),
+            Column::Rating => s.rating.map(|r| "★".repeat(r as usize)).unwrap_or_default(),
+            Column::Difficulty => s.difficulty.map(|d| d.to_string()).unwrap_or_default(),
+            Column::Bpm => s
+                .bpm
+                .filter(|b| *b > 0)
+                .map(|b| b.to_string())
+                .unwrap_or_default(),
+            Column::Pages => s.pages.map(|p| p.to_string()).unwrap_or_default(),
+            Column::Tags => truncate(&s.keywords.join(", "), 40),
+            Column::Labels => s.labels.join(", "),
+            Column::Libraries => s.libraries.join(", "),
+            Column::Path => s.path.clone(),
+            Column::Added => date(s.added),
+            Column::Modified => date(s.modified),
+            Column::Played => date(s.last_played),
+        }
+    }
+}
+
+/// Parse a comma-separated column list such as "id,title,genre,added"
+pub fn parse_columns(spec: &str) -> Result<Vec<Column>> {
+    let columns = spec
+        .split(',')
+        .filter(|name| !name.trim().is_empty())
+        .map(Column::parse)
+        .collect::<Result<Vec<_>>>()?;
+    if columns.is_empty() {
+        return Err(ForScoreError::Other("No columns given".into()));
+    }
+    Ok(columns)
+}
+
+/// The columns to show: `--columns`, else `[output] columns` from the config,
+/// else the defaults; plus the date and Libraries columns if asked for
+pub fn table_columns(args: &ColumnArgs, dates: bool, libraries: bool) -> Result<Vec<Column>> {
+    let mut columns = match &args.columns {
+        Some(spec) => parse_columns(spec)?,
+        None => match load_config()?.output.columns {
+            Some(names) => parse_columns(&names.join(","))
+                .map_err(|e| ForScoreError::Other(format!("[output] columns: {}", e)))?,
+            None => DEFAULT_COLUMNS.to_vec(),
+        },
+    };
+    let mut extra = Vec::new();
+    if dates || args.show_dates {
+        extra.extend_from_slice(DATE_COLUMNS);
+    }
+    if libraries {
+        extra.push(Column::Libraries);
+    }
+    for column in extra {
+        if !columns.contains(&column) {
+            columns.push(column);
+        }
+    }
+    Ok(columns)
+}
+
+/// Output scores as a table with the given columns, or in a structured format
+pub fn output_scores(scores: &[Score], json: bool, columns: &[Column]) {
+    let _span = profile::span(Phase::Render);
+    if is_structured(json) {
+        write_structured(scores, format(json));
+    } else {
+        println!("{}", score_table(scores, columns));
+    }
+}
+
+fn score_table(scores: &[Score], columns: &[Column]) -> String {
+    let mut builder = Builder::default();
+    builder.push_record(columns.iter().map(|c| c.header()));
+    for s in scores {
+        builder.push_record(columns.iter().map(|c| c.value(s)));
+    }
+    builder.build().to_string()
+}
+
+/// A date in local time, e.g. "2024-03-31"
+fn local_date(date: DateTime<Utc>) -> String {
+    date.with_timezone(&Local).format("%Y-%m-%d").to_string()
+}
+
 /// Output single score with clean formatting
 pub fn output_score(score: &Score, json: bool) {
-    if json {
-        println!("{}", serde_json::to_string_pretty(score).unwrap());
+    let _span = profile::span(Phase::Render);
+    if is_structured(json) {
+        write_structured_one(score, format(json));
     } else {
         println!("ID:         {}", score.id);
         println!("Title:      {}", score.title);
@@ -50,6 +585,9 @@ pub fn output_score(score: &Score, json: bool) {
                 println!("Pages:      {}", pages);
             }
         }
+        if let Some(count) = score.pages {
+            println!("Page count: {}", count);
+        }
         if !score.composers.is_empty() {
             println!("Composers:  {}", score.composers.join(", "));
         }
@@ -62,6 +600,24 @@ pub fn output_score(score: &Score, json: bool) {
         if !score.labels.is_empty() {
             println!("Labels:     {}", score.labels.join(", "));
         }
+        if !score.libraries.is_empty() {
+            println!("Libraries:  {}", score.libraries.join(", "));
+        }
+        let time = |date: DateTime<Utc>| {
+            date.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        };
+        if let Some(added) = score.added {
+            println!("Added:      {}", time(added));
+        }
+        if let Some(modified) = score.modified {
+            println!("Modified:   {}", time(modified));
+        }
+        println!(
+            "Played:     {}",
+            score.last_played.map(time).as_deref().unwrap_or("never")
+        );
     }
 }
 
@@ -71,54 +627,141 @@ pub trait ToTable {
         Self: Sized;
 }
 
+impl ToTable for Score {
+    fn to_table(items: &[Self]) -> String {
+        score_table(items, DEFAULT_COLUMNS)
+    }
+}
+
 #[derive(Tabled)]
-struct ScoreRow {
+struct SetlistRow {
     #[tabled(rename = "ID")]
     id: i64,
-    #[tabled(rename = "Title")]
+    #[tabled(rename = "Name")]
     title: String,
-    #[tabled(rename = "Composer")]
-    composer: String,
-    #[tabled(rename = "Key")]
-    key: String,
-    #[tabled(rename = "Rating")]
-    rating: String,
+    #[tabled(rename = "Scores")]
+    score_count: i32,
 }
 
-impl ToTable for Score {
+impl ToTable for Setlist {
     fn to_table(items: &[Self]) -> String {
-        let rows: Vec<ScoreRow> = items
+        let rows: Vec<SetlistRow> = items
             .iter()
-            .map(|s| ScoreRow {
+            .map(|s| SetlistRow {
                 id: s.id,
-                title: truncate(&s.title, 40),
-                composer: truncate(&s.composers.first().cloned().unwrap_or_default(), 30),
-                key: s.key.as_ref().map(|k| k.display()).unwrap_or_default(),
-                rating: s.rating.map(|r| "★".repeat(r as usize)).unwrap_or_default(),
+                title: s.title.clone(),
+                score_count: s.score_count,
             })
             .collect();
         Table::new(rows).to_string()
     }
 }
 
+/// An alias and what it stands for, for `alias ls`
+#[derive(Debug, Serialize)]
+pub struct AliasEntry {
+    pub name: String,
+    pub target: String,
+    /// What the target resolves to now, e.g. "score 12", or None if nothing
+    pub resolves_to: Option<String>,
+}
+
 #[derive(Tabled)]
-struct SetlistRow {
+struct AliasRow {
+    #[tabled(rename = "Alias")]
+    name: String,
+    #[tabled(rename = "Stands for")]
+    target: String,
+    #[tabled(rename = "Resolves to")]
+    resolves_to: String,
+}
+
+impl ToTable for AliasEntry {
+    fn to_table(items: &[Self]) -> String {
+        let rows: Vec<AliasRow> = items
+            .iter()
+            .map(|a| AliasRow {
+                name: a.name.clone(),
+                target: truncate(&a.target, 50),
+                resolves_to: a
+                    .resolves_to
+                    .clone()
+                    .unwrap_or_else(|| "nothing (stale)".into()),
+            })
+            .collect();
+        Table::new(rows).to_string()
+    }
+}
+
+/// A file recorded in .syncFolderState, for `sync export-state`
+#[derive(Debug, Serialize)]
+pub struct SyncStateFile {
+    /// Relative to the Sync folder
+    pub path: String,
+    pub size: i64,
+    pub modified: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+}
+
+#[derive(Tabled)]
+struct SyncStateRow {
+    #[tabled(rename = "Path")]
+    path: String,
+    #[tabled(rename = "Type")]
+    kind: &'static str,
+    #[tabled(rename = "Size")]
+    size: i64,
+    #[tabled(rename = "Modified")]
+    modified: String,
+}
+
+impl ToTable for SyncStateFile {
+    fn to_table(items: &[Self]) -> String {
+        let rows: Vec<SyncStateRow> = items
+            .iter()
+            .map(|f| SyncStateRow {
+                path: truncate(&f.path, 60),
+                kind: f.kind,
+                size: f.size,
+                modified: f.modified.clone().unwrap_or_default(),
+            })
+            .collect();
+        Table::new(rows).to_string()
+    }
+}
+
+#[derive(Tabled)]
+struct SetlistOrderRow {
     #[tabled(rename = "ID")]
     id: i64,
     #[tabled(rename = "Name")]
     title: String,
     #[tabled(rename = "Scores")]
     score_count: i32,
+    #[tabled(rename = "Folder")]
+    folder: String,
+    #[tabled(rename = "Menu Index")]
+    menu_index: String,
+    #[tabled(rename = "Index")]
+    index: String,
+    #[tabled(rename = "Sort")]
+    sort: String,
 }
 
-impl ToTable for Setlist {
+impl ToTable for SetlistOrder {
     fn to_table(items: &[Self]) -> String {
-        let rows: Vec<SetlistRow> = items
+        let value = |v: Option<i64>| v.map(|v| v.to_string()).unwrap_or_default();
+        let rows: Vec<SetlistOrderRow> = items
             .iter()
-            .map(|s| SetlistRow {
+            .map(|s| SetlistOrderRow {
                 id: s.id,
                 title: s.title.clone(),
                 score_count: s.score_count,
+                folder: value(s.folder),
+                menu_index: value(s.menu_index),
+                index: value(s.index),
+                sort: value(s.sort),
             })
             .collect();
         Table::new(rows).to_string()
@@ -157,6 +800,10 @@ struct ComposerRow {
     name: String,
     #[tabled(rename = "Scores")]
     score_count: i32,
+    #[tabled(rename = "Years")]
+    years: String,
+    #[tabled(rename = "Nationality")]
+    nationality: String,
 }
 
 impl ToTable for Composer {
@@ -167,6 +814,8 @@ impl ToTable for Composer {
                 id: c.id,
                 name: c.name.clone(),
                 score_count: c.score_count,
+                years: c.years.clone().unwrap_or_default(),
+                nationality: c.nationality.clone().unwrap_or_default(),
             })
             .collect();
         Table::new(rows).to_string()
@@ -250,10 +899,286 @@ impl ToTable for Bookmark {
     }
 }
 
-fn truncate(s: &str, max_len: usize) -> String {
+#[derive(Tabled)]
+struct SharedItemRow {
+    #[tabled(rename = "ID")]
+    id: i64,
+    #[tabled(rename = "Title")]
+    title: String,
+    #[tabled(rename = "Type")]
+    kind: String,
+    #[tabled(rename = "Count")]
+    count: usize,
+    #[tabled(rename = "Setlists")]
+    setlists: String,
+}
+
+impl ToTable for SharedItem {
+    fn to_table(items: &[Self]) -> String {
+        let rows: Vec<SharedItemRow> = items
+            .iter()
+            .map(|i| SharedItemRow {
+                id: i.id,
+                title: truncate(&i.title, 40),
+                kind: if i.is_bookmark { "bookmark" } else { "score" }.to_string(),
+                count: i.setlists.len(),
+                setlists: i.setlists.join(", "),
+            })
+            .collect();
+        Table::new(rows).to_string()
+    }
+}
+
+/// A setlist item as shown by `setlists show --layout compact`
+#[derive(Serialize)]
+pub struct CompactEntry {
+    pub position: usize,
+    pub id: i64,
+    pub title: String,
+    pub key: Option<String>,
+    pub pages: Option<i32>,
+    /// Pages from the start of the setlist through this item
+    pub cumulative_pages: Option<i32>,
+}
+
+/// A setlist item as shown by `setlists show --layout detailed`
+#[derive(Serialize)]
+pub struct DetailedEntry {
+    pub position: usize,
+    pub id: i64,
+    pub title: String,
+    pub composers: Vec<String>,
+    pub key: Option<String>,
+    pub pages: Option<i32>,
+    /// Pages from the start of the setlist through this item
+    pub cumulative_pages: Option<i32>,
+    /// Duration in seconds
+    pub duration: Option<i32>,
+    pub notes: Vec<String>,
+}
+
+/// One line per item: position, title, key and pages, with the running
+/// page total
+pub fn output_setlist_compact(entries: &[CompactEntry], json: bool) {
+    let _span = profile::span(Phase::Render);
+    if is_structured(json) {
+        write_structured(entries, format(json));
+        return;
+    }
+
+    for entry in entries {
+        let mut line = format!("{:>3}. {}", entry.position, entry.title);
+        if let Some(key) = &entry.key {
+            line.push_str(&format!("  [{}]", key));
+        }
+        match (entry.pages, entry.cumulative_pages) {
+            (Some(pages), Some(total)) => line.push_str(&format!("  {} pp. ({})", pages, total)),
+            (Some(pages), None) => line.push_str(&format!("  {} pp.", pages)),
+            _ => {}
+        }
+        println!("{}", line);
+    }
+}
+
+/// A block per item with composer, pages, duration and notes
+pub fn output_setlist_detailed(entries: &[DetailedEntry], json: bool) {
+    let _span = profile::span(Phase::Render);
+    if is_structured(json) {
+        write_structured(entries, format(json));
+        return;
+    }
+
+    for entry in entries {
+        println!("{}. {} (ID {})", entry.position, entry.title, entry.id);
+        if !entry.composers.is_empty() {
+            println!("   Composer: {}", entry.composers.join(", "));
+        }
+        if let Some(key) = &entry.key {
+            println!("   Key:      {}", key);
+        }
+        match (entry.pages, entry.cumulative_pages) {
+            (Some(pages), Some(total)) => {
+                println!("   Pages:    {} (running total {})", pages, total)
+            }
+            (Some(pages), None) => println!("   Pages:    {}", pages),
+            _ => {}
+        }
+        if let Some(duration) = entry.duration {
+            println!("   Duration: {}", format_duration(duration));
+        }
+        for note in &entry.notes {
+            println!("   Note:     {}", note.replace('\n', "\n             "));
+        }
+        println!();
+    }
+
+    let total: i32 = entries.iter().filter_map(|e| e.duration).sum();
+    if total > 0 {
+        println!("Total duration: {}", format_duration(total));
+    }
+}
+
+/// Format seconds as m:ss (or h:mm:ss)
+pub fn format_duration(seconds: i32) -> String {
+    let (h, m, s) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+/// A local calendar day relative to `today`: "today", "yesterday",
+/// "5 days ago", "3 weeks ago", or the date itself past eight weeks
+pub fn relative_day(date: DateTime<Utc>, today: NaiveDate) -> String {
+    let day = date.with_timezone(&Local).date_naive();
+    match (today - day).num_days() {
+        ..=-1 => day.format("%Y-%m-%d").to_string(),
+        0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        n @ 2..=13 => format!("{} days ago", n),
+        n @ 14..=55 => format!("{} weeks ago", n / 7),
+        _ => day.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// Render hierarchical names ("Jazz/Bebop") as an indented tree with counts.
+/// Parents that only exist implicitly are shown without a count.
+pub fn render_tree(items: &[(String, i32)]) -> String {
+    let mut sorted: Vec<(Vec<&str>, i32)> = items
+        .iter()
+        .map(|(name, count)| (name.split(HIERARCHY_SEPARATOR).collect(), *count))
+        .collect();
+    sorted.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut out = String::new();
+    let mut shown: Vec<&str> = Vec::new();
+
+    for (segments, count) in &sorted {
+        // Find how much of the path is already shown
+        let common = shown
+            .iter()
+            .zip(segments.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        for (depth, segment) in segments.iter().enumerate().skip(common) {
+            let indent = "  ".repeat(depth);
+            if depth == segments.len() - 1 {
+                out.push_str(&format!("{}{} ({})\n", indent, segment, count));
+            } else {
+                out.push_str(&format!("{}{}\n", indent, segment));
+            }
+        }
+        shown = segments.clone();
+    }
+
+    out
+}
+
+pub fn truncate(s: &str, max_len: usize) -> String {
     if s.chars().count() <= max_len {
         s.to_string()
     } else {
         format!("{}…", s.chars().take(max_len - 1).collect::<String>())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template() {
+        let template = Template::parse("{title} — {composer} ({key}) {{{id}}}").unwrap();
+        let fields = vec![
+            ("id".to_string(), Value::from(7)),
+            ("title".to_string(), Value::from("Fugue")),
+            (
+                "composers".to_string(),
+                serde_json::json!(["Bach", "Busoni"]),
+            ),
+            (
+                "key".to_string(),
+                serde_json::json!({"code": 501, "note": "G", "mode": "Minor"}),
+            ),
+        ];
+        assert_eq!(
+            template.render(&fields),
+            ("Fugue — Bach, Busoni (G Minor) {7}".to_string(), vec![])
+        );
+        let missing = Template::parse("{title}: {bpm}").unwrap();
+        assert_eq!(
+            missing.render(&fields),
+            ("Fugue: ".to_string(), vec!["bpm"])
+        );
+
+        assert!(Template::parse("{title").is_err());
+        assert!(Template::parse("title}").is_err());
+        assert!(Template::parse("{}").is_err());
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(
+            parse_columns("id, Title,genre,difficulty,pages,added").unwrap(),
+            vec![
+                Column::Id,
+                Column::Title,
+                Column::Genre,
+                Column::Difficulty,
+                Column::Pages,
+                Column::Added
+            ]
+        );
+        assert_eq!(
+            parse_columns("keywords,last_played").unwrap(),
+            vec![Column::Tags, Column::Played]
+        );
+        assert!(parse_columns("id,colour").is_err());
+        assert!(parse_columns(" , ").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(59), "0:59");
+        assert_eq!(format_duration(270), "4:30");
+        assert_eq!(format_duration(3725), "1:02:05");
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let items = vec![
+            ("Jazz/Bebop".to_string(), 2),
+            ("Warmup".to_string(), 4),
+            ("Jazz".to_string(), 1),
+            ("Latin/Salsa/Cuban".to_string(), 3),
+        ];
+        assert_eq!(
+            render_tree(&items),
+            "Jazz (1)\n  Bebop (2)\nLatin\n  Salsa\n    Cuban (3)\nWarmup (4)\n"
+        );
+    }
+
+    #[test]
+    fn test_relative_day() {
+        use chrono::TimeZone;
+        let today = NaiveDate::from_ymd_opt(2026, 3, 31).unwrap();
+        let at = |y, m, d| {
+            let noon = NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap();
+            Local
+                .from_local_datetime(&noon)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        assert_eq!(relative_day(at(2026, 3, 31), today), "today");
+        assert_eq!(relative_day(at(2026, 3, 30), today), "yesterday");
+        assert_eq!(relative_day(at(2026, 3, 26), today), "5 days ago");
+        assert_eq!(relative_day(at(2026, 3, 17), today), "2 weeks ago");
+        assert_eq!(relative_day(at(2026, 1, 5), today), "2026-01-05");
+        assert_eq!(relative_day(at(2026, 4, 2), today), "2026-04-02");
+    }
+}
@@ -0,0 +1,161 @@
+//! Platform-specific pieces
+//!
+//! forScore only runs on macOS. Everywhere else the CLI works on a copied
+//! library: `--db` and `--sync-dir` point at the copies, and the commands that
+//! need the app itself (opening scores, triggering a sync) report that they
+//! are unavailable.
+
+use crate::error::{ForScoreError, Result};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::OnceLock;
+
+/// Where forScore's sandbox container may be, relative to the home
+/// directory (the App Store Catalyst build's)
+const CONTAINERS: &[(&str, &str)] = &[(
+    "App Store container",
+    "Library/Containers/com.mgsdevelopment.forscore/Data",
+)];
+
+/// forScore's folder in iCloud Drive, relative to the home directory
+const ICLOUD_DRIVE_FOLDER: &str =
+    "Library/Mobile Documents/iCloud~com~mgsdevelopment~forscore/Documents";
+
+/// Paths given on the command line in place of forScore's own
+#[derive(Debug, Default)]
+pub struct PathOverrides {
+    /// A copied library.4sl
+    pub db: Option<PathBuf>,
+    /// A copied Sync folder
+    pub sync_dir: Option<PathBuf>,
+}
+
+static OVERRIDES: OnceLock<PathOverrides> = OnceLock::new();
+
+/// Record `--db` / `--sync-dir`; call once at startup
+pub fn set_path_overrides(overrides: PathOverrides) {
+    let _ = OVERRIDES.set(overrides);
+}
+
+fn overrides() -> &'static PathOverrides {
+    OVERRIDES.get_or_init(PathOverrides::default)
+}
+
+/// The `--db` path, if given
+pub fn db_override() -> Option<&'static Path> {
+    overrides().db.as_deref()
+}
+
+/// The `--sync-dir` path, if given
+pub fn sync_dir_override() -> Option<&'static Path> {
+    overrides().sync_dir.as_deref()
+}
+
+/// A place forScore's files may be, and whether they were found there
+#[derive(Debug)]
+pub struct Candidate {
+    pub label: &'static str,
+    pub path: PathBuf,
+    pub found: bool,
+}
+
+fn home_dir() -> Result<PathBuf> {
+    dirs::home_dir().ok_or_else(|| ForScoreError::Other("Cannot find home directory".into()))
+}
+
+/// Every container forScore may use, in the order they're tried; found
+/// means it holds a library.4sl
+pub fn container_candidates() -> Result<Vec<Candidate>> {
+    let home = home_dir()?;
+    Ok(CONTAINERS
+        .iter()
+        .map(|(label, relative)| {
+            let path = home.join(relative);
+            let found = path.join("Library/Preferences/library.4sl").exists();
+            Candidate { label, path, found }
+        })
+        .collect())
+}
+
+/// forScore's data container (only present on macOS): the first one holding
+/// a library, else the App Store build's
+pub fn container_path() -> Result<PathBuf> {
+    let mut candidates = container_candidates()?;
+    let index = candidates.iter().position(|c| c.found).unwrap_or(0);
+    Ok(candidates.swap_remove(index).path)
+}
+
+/// forScore's iCloud Drive folder, used for sync files when the container
+/// has no Sync folder; found means it holds .itm files
+pub fn icloud_drive_candidate() -> Result<Candidate> {
+    let path = home_dir()?.join(ICLOUD_DRIVE_FOLDER);
+    let found = std::fs::read_dir(&path).is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|e| e.path().extension().is_some_and(|ext| ext == "itm"))
+    });
+    Ok(Candidate {
+        label: "iCloud Drive folder",
+        path,
+        found,
+    })
+}
+
+/// The folder holding library.4sl, forScore's preferences and the Sync
+/// folder: the container's, or wherever `--db` points
+pub fn preferences_path() -> Result<PathBuf> {
+    match db_override() {
+        Some(db) => Ok(db
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."))),
+        None => Ok(container_path()?.join("Library/Preferences")),
+    }
+}
+
+/// Error for a missing database, pointing at `--db` where forScore can't be installed
+pub fn database_not_found() -> ForScoreError {
+    if cfg!(target_os = "macos") || db_override().is_some() {
+        ForScoreError::DatabaseNotFound
+    } else {
+        ForScoreError::Other(
+            "forScore only runs on macOS; use --db (and --sync-dir) to point at a copied library"
+                .into(),
+        )
+    }
+}
+
+/// Check if forScore is currently running (always false off macOS)
+pub fn is_forscore_running() -> bool {
+    cfg!(target_os = "macos")
+        && Command::new("pgrep")
+            .args(["-x", "forScore"])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+}
+
+/// Fail with a clear message for things only the macOS app can do
+pub fn require_macos(action: &str) -> Result<()> {
+    if cfg!(target_os = "macos") {
+        Ok(())
+    } else {
+        Err(ForScoreError::Other(format!(
+            "{} requires forScore on macOS",
+            action
+        )))
+    }
+}
+
+/// Open a forscore:// URL in the app
+pub fn open_url(url: &str) -> Result<()> {
+    require_macos("Opening scores")?;
+    Command::new("open").arg(url).spawn()?;
+    Ok(())
+}
+
+/// Run an AppleScript (used to drive forScore's UI)
+pub fn run_applescript(script: &str) -> Result<Output> {
+    require_macos("Controlling forScore")?;
+    Ok(Command::new("osascript").arg("-e").arg(script).output()?)
+}
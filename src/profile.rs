@@ -0,0 +1,183 @@
+//! Opt-in timing for `--profile`
+//!
+//! Records how long opening the database, each SQL statement, ITM reads and
+//! writes, and rendering took, and prints a breakdown to stderr when the
+//! command finishes. Recording is a no-op unless `enable` was called, so the
+//! hooks can stay in place on hot paths.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static START: OnceLock<Instant> = OnceLock::new();
+static RECORDS: Mutex<Vec<(Phase, String, Duration)>> = Mutex::new(Vec::new());
+
+/// How many of the slowest statements the report lists
+const SLOWEST: usize = 10;
+/// Statements are shortened to this many characters in the report
+const STATEMENT_WIDTH: usize = 90;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    OpenDatabase,
+    Sql,
+    ItmRead,
+    ItmWrite,
+    Render,
+}
+
+impl Phase {
+    const ALL: [Phase; 5] = [
+        Phase::OpenDatabase,
+        Phase::Sql,
+        Phase::ItmRead,
+        Phase::ItmWrite,
+        Phase::Render,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::OpenDatabase => "Open database",
+            Phase::Sql => "SQL",
+            Phase::ItmRead => "ITM reads",
+            Phase::ItmWrite => "ITM writes",
+            Phase::Render => "Rendering",
+        }
+    }
+}
+
+/// Start recording; the total is measured from here
+pub fn enable() {
+    START.get_or_init(Instant::now);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Only SQL keeps a label, the statement, for the slowest-statements list
+fn record(phase: Phase, label: &str, elapsed: Duration) {
+    if let Ok(mut records) = RECORDS.lock() {
+        records.push((phase, label.to_string(), elapsed));
+    }
+}
+
+/// Run `f`, recording how long it took
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if !enabled() {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    record(phase, "", start.elapsed());
+    result
+}
+
+/// Records the time until it is dropped, for timing a whole function body
+pub struct Span {
+    phase: Phase,
+    start: Option<Instant>,
+}
+
+pub fn span(phase: Phase) -> Span {
+    Span {
+        phase,
+        start: enabled().then(Instant::now),
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            record(self.phase, "", start.elapsed());
+        }
+    }
+}
+
+/// SQLite's profile callback: called once per statement with its run time
+pub fn sql(statement: &str, elapsed: Duration) {
+    record(Phase::Sql, &statement_label(statement), elapsed);
+}
+
+/// A statement on one line, shortened for the report
+fn statement_label(statement: &str) -> String {
+    let line = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= STATEMENT_WIDTH {
+        return line;
+    }
+    let mut short: String = line.chars().take(STATEMENT_WIDTH - 3).collect();
+    short.push_str("...");
+    short
+}
+
+fn ms(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Print the breakdown to stderr, if profiling is on
+pub fn report() {
+    let (Some(start), true) = (START.get(), enabled()) else {
+        return;
+    };
+    let total = start.elapsed();
+    let records = match RECORDS.lock() {
+        Ok(records) => records,
+        Err(_) => return,
+    };
+
+    eprintln!();
+    eprintln!("Profile (total {})", ms(total));
+    let mut accounted = Duration::ZERO;
+    for phase in Phase::ALL {
+        let (count, time) = records
+            .iter()
+            .filter(|(p, _, _)| *p == phase)
+            .fold((0, Duration::ZERO), |(n, sum), (_, _, d)| (n + 1, sum + *d));
+        accounted += time;
+        eprintln!("  {:<14} {:>6} x {:>10}", phase.name(), count, ms(time));
+    }
+    eprintln!(
+        "  {:<14} {:>6}   {:>10}",
+        "Other",
+        "",
+        ms(total.saturating_sub(accounted))
+    );
+
+    // Group identical statements, which a loop may run hundreds of times
+    let mut statements: HashMap<&str, (usize, Duration)> = HashMap::new();
+    for (_, label, elapsed) in records.iter().filter(|(p, _, _)| *p == Phase::Sql) {
+        let entry = statements.entry(label).or_default();
+        entry.0 += 1;
+        entry.1 += *elapsed;
+    }
+    if statements.is_empty() {
+        return;
+    }
+    let mut statements: Vec<_> = statements.into_iter().collect();
+    // SQLite only times statements to the millisecond, so break ties by runs
+    statements.sort_by(|a, b| (b.1 .1, b.1 .0).cmp(&(a.1 .1, a.1 .0)).then(a.0.cmp(b.0)));
+    eprintln!("Slowest SQL statements (total time, runs):");
+    for (statement, (count, time)) in statements.into_iter().take(SLOWEST) {
+        eprintln!("  {:>10} {:>6} x  {}", ms(time), count, statement);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_label() {
+        assert_eq!(
+            statement_label("SELECT Z_PK\n         FROM ZITEM\n   WHERE Z_ENT = ?"),
+            "SELECT Z_PK FROM ZITEM WHERE Z_ENT = ?"
+        );
+        let long = format!("SELECT {} FROM ZITEM", "ZTITLE, ".repeat(20));
+        let label = statement_label(&long);
+        assert_eq!(label.chars().count(), STATEMENT_WIDTH);
+        assert!(label.ends_with("..."));
+    }
+}
@@ -0,0 +1,119 @@
+//! Progress reporting for long-running operations
+//!
+//! Bars are drawn on stderr and hidden automatically when it isn't a
+//! terminal, so piped output stays clean.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use tabled::{Table, Tabled};
+
+/// A progress bar that lets callers print lines without garbling the bar
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    pub fn new(len: usize, message: &str) -> Self {
+        let bar = ProgressBar::new(len as u64);
+        bar.set_style(
+            ProgressStyle::with_template("{msg} [{bar:40}] {pos}/{len} ({eta})")
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        bar.set_message(message.to_string());
+        Self { bar }
+    }
+
+    /// A progress tracker that never draws (e.g. when the loop prints its own output)
+    pub fn hidden() -> Self {
+        Self {
+            bar: ProgressBar::hidden(),
+        }
+    }
+
+    /// Print a line above the bar
+    pub fn println(&self, line: impl AsRef<str>) {
+        self.bar.suspend(|| println!("{}", line.as_ref()));
+    }
+
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// Tally of what a bulk operation did, with reasons for anything not applied
+#[derive(Default)]
+pub struct Summary {
+    pub updated: usize,
+    pub skipped: Vec<(String, String)>,
+    pub errors: Vec<(String, String)>,
+}
+
+#[derive(Tabled)]
+struct CountRow {
+    #[tabled(rename = "Result")]
+    result: &'static str,
+    #[tabled(rename = "Count")]
+    count: usize,
+}
+
+#[derive(Tabled)]
+struct ReasonRow {
+    #[tabled(rename = "Result")]
+    result: &'static str,
+    #[tabled(rename = "Item")]
+    item: String,
+    #[tabled(rename = "Reason")]
+    reason: String,
+}
+
+impl Summary {
+    pub fn skip(&mut self, item: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push((item.into(), reason.into()));
+    }
+
+    pub fn error(&mut self, item: impl Into<String>, reason: impl Into<String>) {
+        self.errors.push((item.into(), reason.into()));
+    }
+
+    /// Print the counts table, followed by a table of skip/error reasons
+    pub fn print(&self, updated_label: &'static str) {
+        let counts = vec![
+            CountRow {
+                result: updated_label,
+                count: self.updated,
+            },
+            CountRow {
+                result: "Skipped",
+                count: self.skipped.len(),
+            },
+            CountRow {
+                result: "Errors",
+                count: self.errors.len(),
+            },
+        ];
+        println!("{}", Table::new(counts));
+
+        let reasons: Vec<ReasonRow> = self
+            .skipped
+            .iter()
+            .map(|(item, reason)| ReasonRow {
+                result: "Skipped",
+                item: item.clone(),
+                reason: reason.clone(),
+            })
+            .chain(self.errors.iter().map(|(item, reason)| ReasonRow {
+                result: "Error",
+                item: item.clone(),
+                reason: reason.clone(),
+            }))
+            .collect();
+
+        if !reasons.is_empty() {
+            println!("{}", Table::new(reasons));
+        }
+    }
+}
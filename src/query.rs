@@ -0,0 +1,721 @@
+//! Query mini-language for `scores query`
+//!
+//! An expression is field comparisons combined with AND, OR, NOT and
+//! parentheses, e.g. `rating>=4 AND key="E Minor" AND NOT genre:Baroque`.
+//! Adjacent comparisons without an operator are ANDed. `:` means "contains"
+//! for text fields and is the same as `=` everywhere else. Dates are
+//! `YYYY`, `YYYY-MM` or `YYYY-MM-DD` and compare as whole periods, so
+//! `added=2024` is anything added during 2024; `today`, `yesterday` and
+//! `"N days ago"` (or weeks, months, years) are single days. The value
+//! `none` matches items with the field unset.
+//!
+//! Expressions can be saved by name under `[searches]` in the config file.
+
+use crate::config::{config_path, load_config};
+use crate::db::datetime_to_core_data;
+use crate::error::{ForScoreError, Result};
+use crate::models::key::MusicalKey;
+use chrono::{Days, Local, Months, NaiveDate};
+use rusqlite::types::Value;
+
+/// A parsed query
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Operand),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Title,
+    Composer,
+    Genre,
+    Tag,
+    Label,
+    Library,
+    Key,
+    Rating,
+    Difficulty,
+    Bpm,
+    Added,
+    Modified,
+    Played,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+/// A comparison's right-hand side, already checked against its field
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Text(String),
+    Number(i64),
+    /// Start and end (exclusive) of a period, as Core Data timestamps
+    Period(f64, f64),
+    None,
+}
+
+impl Field {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name.to_lowercase().as_str() {
+            "title" => Field::Title,
+            "composer" => Field::Composer,
+            "genre" => Field::Genre,
+            "tag" | "tags" | "keyword" => Field::Tag,
+            "label" | "labels" => Field::Label,
+            "library" => Field::Library,
+            "key" => Field::Key,
+            "rating" => Field::Rating,
+            "difficulty" => Field::Difficulty,
+            "bpm" => Field::Bpm,
+            "added" => Field::Added,
+            "modified" => Field::Modified,
+            "played" => Field::Played,
+            _ => {
+                return Err(ForScoreError::Other(format!(
+                    "Unknown field '{}' (fields: title, composer, genre, tag, label, library, \
+                     key, rating, difficulty, bpm, added, modified, played)",
+                    name
+                )))
+            }
+        })
+    }
+
+    fn is_text(self) -> bool {
+        matches!(
+            self,
+            Field::Title
+                | Field::Composer
+                | Field::Genre
+                | Field::Tag
+                | Field::Label
+                | Field::Library
+        )
+    }
+}
+
+/// The expression saved under `name` in the config file's `[searches]`
+pub fn saved_search(name: &str) -> Result<Expr> {
+    let config = load_config()?;
+    let Some(expr) = config.searches.get(name) else {
+        let names: Vec<&str> = config.searches.keys().map(String::as_str).collect();
+        return Err(ForScoreError::Other(if names.is_empty() {
+            format!(
+                "No saved search '{}': add it under [searches] in {}",
+                name,
+                config_path()?.display()
+            )
+        } else {
+            format!("No saved search '{}' (saved: {})", name, names.join(", "))
+        }));
+    };
+    parse(expr).map_err(|e| ForScoreError::Other(format!("Saved search '{}': {}", name, e)))
+}
+
+/// A date comparison on its own, as for `--added-since` and friends
+pub fn date_comparison(field: Field, op: Op, value: &str) -> Result<Expr> {
+    Ok(Expr::Compare(field, op, operand(field, op, value)?))
+}
+
+/// Parse a query expression
+pub fn parse(input: &str) -> Result<Expr> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(syntax(format!("unexpected {}", token))),
+    }
+}
+
+impl Expr {
+    /// All of `exprs` ANDed together, or None if there are none
+    pub fn all(exprs: impl IntoIterator<Item = Expr>) -> Option<Expr> {
+        exprs
+            .into_iter()
+            .reduce(|a, b| Expr::And(Box::new(a), Box::new(b)))
+    }
+
+    /// A SQL condition over `ZITEM i` joined with rating `r` and difficulty
+    /// `d` (as in `search_scores`), plus its parameters
+    pub fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut params = Vec::new();
+        let sql = self.sql(&mut params);
+        (sql, params)
+    }
+
+    fn sql(&self, params: &mut Vec<Value>) -> String {
+        match self {
+            Expr::And(a, b) => format!("({} AND {})", a.sql(params), b.sql(params)),
+            Expr::Or(a, b) => format!("({} OR {})", a.sql(params), b.sql(params)),
+            Expr::Not(a) => format!("NOT {}", a.sql(params)),
+            Expr::Compare(field, op, operand) => compare_sql(*field, *op, operand, params),
+        }
+    }
+}
+
+/// SQL for one comparison. Unset values compare as false rather than NULL so
+/// that NOT includes them (`NOT rating>=4` finds unrated scores too)
+fn compare_sql(field: Field, op: Op, operand: &Operand, params: &mut Vec<Value>) -> String {
+    if let Some((table, item_column, meta_column, value_column)) = link_table(field) {
+        let exists = |condition: &str| {
+            format!(
+                "EXISTS (SELECT 1 FROM {table} x JOIN ZMETA m ON x.{meta_column} = m.Z_PK
+                         WHERE x.{item_column} = i.Z_PK{condition})"
+            )
+        };
+        return match (op, operand) {
+            (Op::Ne, Operand::None) => exists(""),
+            (_, Operand::None) => format!("NOT {}", exists("")),
+            (Op::Contains, Operand::Text(text)) => {
+                params.push(Value::Text(format!("%{}%", text)));
+                exists(&format!(" AND m.{value_column} LIKE ?"))
+            }
+            (op, Operand::Text(text)) => {
+                let condition = if field == Field::Tag {
+                    // Like --tag, "Jazz" also matches tags nested beneath it
+                    params.push(Value::Text(text.clone()));
+                    params.push(Value::Text(text.clone()));
+                    " AND (LOWER(m.ZVALUE) = LOWER(?) OR LOWER(m.ZVALUE) LIKE LOWER(?) || '/%')"
+                        .to_string()
+                } else {
+                    params.push(Value::Text(text.clone()));
+                    format!(" AND LOWER(m.{value_column}) = LOWER(?)")
+                };
+                if op == Op::Ne {
+                    format!("NOT {}", exists(&condition))
+                } else {
+                    exists(&condition)
+                }
+            }
+            _ => unreachable!("checked by the parser"),
+        };
+    }
+
+    if field == Field::Library {
+        let exists = |condition: &str| {
+            format!(
+                "EXISTS (SELECT 1 FROM Z_4LIBRARIES x JOIN ZLIBRARY l ON x.Z_7LIBRARIES = l.Z_PK
+                         WHERE x.Z_4ITEMS3 IN (i.Z_PK, i.ZSCORE){condition})"
+            )
+        };
+        return match (op, operand) {
+            (Op::Ne, Operand::None) => exists(""),
+            (_, Operand::None) => format!("NOT {}", exists("")),
+            (Op::Contains, Operand::Text(text)) => {
+                params.push(Value::Text(format!("%{}%", text)));
+                exists(" AND l.ZTITLE LIKE ?")
+            }
+            (op, Operand::Text(text)) => {
+                params.push(Value::Text(text.clone()));
+                let condition = exists(" AND LOWER(l.ZTITLE) = LOWER(?)");
+                if op == Op::Ne {
+                    format!("NOT {}", condition)
+                } else {
+                    condition
+                }
+            }
+            _ => unreachable!("checked by the parser"),
+        };
+    }
+
+    let column = match field {
+        Field::Title => "i.ZTITLE",
+        Field::Key => "i.ZKEY",
+        Field::Rating => "r.ZVALUE5",
+        Field::Difficulty => "d.ZVALUE1",
+        Field::Bpm => "i.ZBPM",
+        Field::Added => "i.ZADDED",
+        Field::Modified => "i.ZMODIFIED",
+        Field::Played => "i.ZLASTPLAYED",
+        _ => unreachable!("linked fields handled above"),
+    };
+    let unset = if field == Field::Key {
+        format!("({column} IS NULL OR {column} = 0)")
+    } else {
+        format!("{column} IS NULL")
+    };
+
+    match operand {
+        Operand::None if op == Op::Ne => format!("NOT {}", unset),
+        Operand::None => unset,
+        Operand::Text(text) => {
+            let condition = match op {
+                Op::Contains => {
+                    params.push(Value::Text(format!("%{}%", text)));
+                    format!("{column} LIKE ?")
+                }
+                Op::Ne => {
+                    params.push(Value::Text(text.clone()));
+                    format!("LOWER({column}) != LOWER(?)")
+                }
+                _ => {
+                    params.push(Value::Text(text.clone()));
+                    format!("LOWER({column}) = LOWER(?)")
+                }
+            };
+            format!("COALESCE({condition}, 0)")
+        }
+        Operand::Number(n) => {
+            params.push(Value::Integer(*n));
+            format!("COALESCE({column} {} ?, 0)", sql_op(op))
+        }
+        Operand::Period(start, end) => {
+            let condition = match op {
+                Op::Eq | Op::Contains => {
+                    params.push(Value::Real(*start));
+                    params.push(Value::Real(*end));
+                    format!("{column} >= ? AND {column} < ?")
+                }
+                Op::Ne => {
+                    params.push(Value::Real(*start));
+                    params.push(Value::Real(*end));
+                    format!("({column} < ? OR {column} >= ?)")
+                }
+                // A period is before another if it ends by its start, etc.
+                Op::Lt => {
+                    params.push(Value::Real(*start));
+                    format!("{column} < ?")
+                }
+                Op::Le => {
+                    params.push(Value::Real(*end));
+                    format!("{column} < ?")
+                }
+                Op::Gt => {
+                    params.push(Value::Real(*end));
+                    format!("{column} >= ?")
+                }
+                Op::Ge => {
+                    params.push(Value::Real(*start));
+                    format!("{column} >= ?")
+                }
+            };
+            format!("COALESCE({condition}, 0)")
+        }
+    }
+}
+
+/// Join table, its item and meta columns, and the ZMETA value column for
+/// fields stored as metadata links
+fn link_table(field: Field) -> Option<(&'static str, &'static str, &'static str, &'static str)> {
+    match field {
+        Field::Composer => Some(("Z_4COMPOSERS", "Z_4ITEMS1", "Z_10COMPOSERS", "ZVALUE")),
+        Field::Genre => Some(("Z_4GENRES", "Z_4ITEMS4", "Z_12GENRES", "ZVALUE2")),
+        Field::Tag => Some(("Z_4KEYWORDS", "Z_4ITEMS5", "Z_13KEYWORDS", "ZVALUE")),
+        Field::Label => Some(("Z_4LABELS", "Z_4ITEMS2", "Z_14LABELS", "ZVALUE")),
+        _ => None,
+    }
+}
+
+fn sql_op(op: Op) -> &'static str {
+    match op {
+        Op::Eq | Op::Contains => "=",
+        Op::Ne => "!=",
+        Op::Lt => "<",
+        Op::Le => "<=",
+        Op::Gt => ">",
+        Op::Ge => ">=",
+    }
+}
+
+/// Check a comparison's value against its field
+fn operand(field: Field, op: Op, value: &str) -> Result<Operand> {
+    let invalid = |what: &str| {
+        ForScoreError::Other(format!(
+            "Invalid value '{}' for {}: expected {}",
+            value,
+            format!("{:?}", field).to_lowercase(),
+            what
+        ))
+    };
+    if value.eq_ignore_ascii_case("none") {
+        return match op {
+            Op::Eq | Op::Ne | Op::Contains => Ok(Operand::None),
+            _ => Err(invalid("'none' only with = or !=")),
+        };
+    }
+
+    match field {
+        _ if field.is_text() => {
+            if !matches!(op, Op::Eq | Op::Ne | Op::Contains) {
+                return Err(invalid("=, != or : for text"));
+            }
+            Ok(Operand::Text(value.to_string()))
+        }
+        Field::Key => {
+            if !matches!(op, Op::Eq | Op::Ne | Op::Contains) {
+                return Err(invalid("= or != for keys"));
+            }
+            Ok(Operand::Number(MusicalKey::from_string(value)?.code as i64))
+        }
+        Field::Rating | Field::Difficulty | Field::Bpm => value
+            .parse()
+            .map(Operand::Number)
+            .map_err(|_| invalid("a number")),
+        _ => {
+            let (start, end) = period(value, Local::now().date_naive()).ok_or_else(|| {
+                invalid("YYYY, YYYY-MM, YYYY-MM-DD, today, yesterday or \"N days ago\"")
+            })?;
+            let timestamp = |date: NaiveDate| {
+                date.and_hms_opt(0, 0, 0)
+                    .and_then(|t| t.and_local_timezone(Local).earliest())
+                    .map(|t| datetime_to_core_data(t.into()))
+            };
+            match (timestamp(start), timestamp(end)) {
+                (Some(start), Some(end)) => Ok(Operand::Period(start, end)),
+                _ => Err(invalid("a representable date")),
+            }
+        }
+    }
+}
+
+/// First day of a year, month or day, and the first day after it.
+/// Relative dates count back from `today`
+fn period(value: &str, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    if let Some(day) = relative_day(value, today) {
+        return Some((day, day.succ_opt()?));
+    }
+    let parts: Vec<&str> = value.split('-').collect();
+    let number = |s: &str| s.parse::<u32>().ok();
+    match parts.as_slice() {
+        [year] if year.len() == 4 => {
+            let year = number(year)? as i32;
+            Some((
+                NaiveDate::from_ymd_opt(year, 1, 1)?,
+                NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+            ))
+        }
+        [year, month] if year.len() == 4 => {
+            let start = NaiveDate::from_ymd_opt(number(year)? as i32, number(month)?, 1)?;
+            Some((start, start.checked_add_months(Months::new(1))?))
+        }
+        [year, month, day] if year.len() == 4 => {
+            let start =
+                NaiveDate::from_ymd_opt(number(year)? as i32, number(month)?, number(day)?)?;
+            Some((start, start.succ_opt()?))
+        }
+        _ => None,
+    }
+}
+
+/// "today", "yesterday" or "N days/weeks/months/years ago"
+fn relative_day(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let value = value.trim().to_lowercase();
+    match value.as_str() {
+        "today" => return Some(today),
+        "yesterday" => return today.pred_opt(),
+        _ => {}
+    }
+    let words: Vec<&str> = value.split_whitespace().collect();
+    let [count, unit, "ago"] = words.as_slice() else {
+        return None;
+    };
+    let count: u32 = match *count {
+        "a" | "an" | "one" => 1,
+        n => n.parse().ok()?,
+    };
+    match unit.trim_end_matches('s') {
+        "day" => today.checked_sub_days(Days::new(count as u64)),
+        "week" => today.checked_sub_days(Days::new(count as u64 * 7)),
+        "month" => today.checked_sub_months(Months::new(count)),
+        "year" => today.checked_sub_months(Months::new(count.checked_mul(12)?)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Word(w) => write!(f, "'{}'", w),
+            Token::Quoted(s) => write!(f, "\"{}\"", s),
+            Token::Op(op) => write!(
+                f,
+                "'{}'",
+                if *op == Op::Contains {
+                    ":"
+                } else {
+                    sql_op(*op)
+                }
+            ),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+        }
+    }
+}
+
+fn syntax(message: String) -> ForScoreError {
+    ForScoreError::Other(format!("Invalid query: {}", message))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == c => break,
+                        Some(ch) => text.push(ch),
+                        None => return Err(syntax("unterminated quote".into())),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            '=' | '!' | '<' | '>' | ':' => {
+                chars.next();
+                let equals = chars.next_if_eq(&'=').is_some();
+                let op = match (c, equals) {
+                    ('=', _) => Op::Eq,
+                    ('!', true) => Op::Ne,
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    (':', false) => Op::Contains,
+                    _ => return Err(syntax(format!("unexpected '{}'", c))),
+                };
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || "()\"'=!<>:".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent: OR binds loosest, then AND, then NOT
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        loop {
+            if self.keyword("and") {
+                expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+                continue;
+            }
+            // Implicit AND between adjacent terms
+            match self.peek() {
+                Some(Token::Word(w)) if !w.eq_ignore_ascii_case("or") => {}
+                Some(Token::Open) => {}
+                _ => return Ok(expr),
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.term()
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    Some(token) => Err(syntax(format!("expected ')' but found {}", token))),
+                    None => Err(syntax("missing ')'".into())),
+                }
+            }
+            Some(Token::Word(name)) => {
+                let field = Field::parse(&name)?;
+                let op = match self.next() {
+                    Some(Token::Op(op)) => op,
+                    _ => {
+                        return Err(syntax(format!(
+                            "expected a comparison after '{}' (e.g. {}=...)",
+                            name, name
+                        )))
+                    }
+                };
+                let value = match self.next() {
+                    Some(Token::Word(value) | Token::Quoted(value)) => value,
+                    _ => return Err(syntax(format!("expected a value after '{}'", name))),
+                };
+                Ok(Expr::Compare(field, op, operand(field, op, &value)?))
+            }
+            Some(token) => Err(syntax(format!("unexpected {}", token))),
+            None => Err(syntax("unexpected end of query".into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(field: Field, op: Op, operand: Operand) -> Expr {
+        Expr::Compare(field, op, operand)
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let rating = compare(Field::Rating, Op::Ge, Operand::Number(4));
+        let key = compare(Field::Key, Op::Eq, Operand::Number(301));
+        let genre = compare(Field::Genre, Op::Contains, Operand::Text("Baroque".into()));
+
+        assert_eq!(
+            parse(r#"rating>=4 AND key="E Minor" AND NOT genre:Baroque"#).unwrap(),
+            Expr::And(
+                Box::new(Expr::And(Box::new(rating.clone()), Box::new(key.clone()))),
+                Box::new(Expr::Not(Box::new(genre.clone())))
+            )
+        );
+        // AND binds tighter than OR; adjacent terms are ANDed
+        assert_eq!(
+            parse("rating>=4 or key='E minor' not genre:Baroque").unwrap(),
+            Expr::Or(
+                Box::new(rating.clone()),
+                Box::new(Expr::And(
+                    Box::new(key.clone()),
+                    Box::new(Expr::Not(Box::new(genre.clone())))
+                ))
+            )
+        );
+        assert_eq!(
+            parse("(rating>=4 OR key=\"E Minor\") AND genre:Baroque").unwrap(),
+            Expr::And(
+                Box::new(Expr::Or(Box::new(rating), Box::new(key))),
+                Box::new(genre)
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse("colour=red").is_err());
+        assert!(parse("rating>=four").is_err());
+        assert!(parse("title>Bach").is_err());
+        assert!(parse("(rating>=4").is_err());
+        assert!(parse("rating>=4)").is_err());
+        assert!(parse("title=\"Bach").is_err());
+        assert!(parse("added>=2024-13").is_err());
+    }
+
+    #[test]
+    fn test_period() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let today = date(2024, 3, 31);
+        assert_eq!(
+            period("2024", today),
+            Some((date(2024, 1, 1), date(2025, 1, 1)))
+        );
+        assert_eq!(
+            period("2024-12", today),
+            Some((date(2024, 12, 1), date(2025, 1, 1)))
+        );
+        assert_eq!(
+            period("2024-02-29", today),
+            Some((date(2024, 2, 29), date(2024, 3, 1)))
+        );
+        assert_eq!(period("2023-02-29", today), None);
+        assert_eq!(period("24", today), None);
+    }
+
+    #[test]
+    fn test_relative_day() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let today = date(2024, 3, 31);
+        assert_eq!(relative_day("today", today), Some(today));
+        assert_eq!(relative_day("Yesterday", today), Some(date(2024, 3, 30)));
+        assert_eq!(relative_day("2 weeks ago", today), Some(date(2024, 3, 17)));
+        assert_eq!(relative_day("a day ago", today), Some(date(2024, 3, 30)));
+        // Month arithmetic clamps to the end of shorter months
+        assert_eq!(relative_day("1 month ago", today), Some(date(2024, 2, 29)));
+        assert_eq!(relative_day("3 years ago", today), Some(date(2021, 3, 31)));
+        assert_eq!(relative_day("2 fortnights ago", today), None);
+        assert_eq!(relative_day("2 weeks", today), None);
+        assert_eq!(relative_day("400000000 years ago", today), None);
+    }
+
+    #[test]
+    fn test_to_sql() {
+        let (sql, params) = parse("NOT rating>=4 OR composer=none").unwrap().to_sql();
+        assert!(sql.starts_with("(NOT COALESCE(r.ZVALUE5 >= ?, 0) OR NOT EXISTS"));
+        assert_eq!(params, vec![Value::Integer(4)]);
+
+        let (sql, params) = parse("title:prelude").unwrap().to_sql();
+        assert_eq!(sql, "COALESCE(i.ZTITLE LIKE ?, 0)");
+        assert_eq!(params, vec![Value::Text("%prelude%".into())]);
+    }
+}
@@ -0,0 +1,326 @@
+//! forScore schema detection
+//!
+//! forScore is a Core Data app, and Core Data numbers entities alphabetically,
+//! so an update that adds an entity can renumber the rest. The CLI reads the
+//! open database's Z_PRIMARYKEY for entity IDs (see `db::entity`) and compares
+//! its tables and columns with the fingerprint of the newest forScore it knows
+//! (tests/fingerprints/latest.fingerprint), warning once when they differ.
+
+use crate::error::{ForScoreError, Result};
+use rusqlite::Connection;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+/// Schema of the newest forScore release the CLI was checked against
+const KNOWN_FINGERPRINT: &str = include_str!("../tests/fingerprints/latest.fingerprint");
+
+/// Entities and table columns of a forScore database
+#[derive(Debug, Default)]
+pub struct Schema {
+    pub entities: BTreeMap<String, i32>,
+    pub tables: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// How a database's schema differs from the known one
+#[derive(Debug, Default, PartialEq)]
+pub struct SchemaDiff {
+    /// Entities and columns (as TABLE.COLUMN) the CLI doesn't know
+    pub added: Vec<String>,
+    /// Known entities, tables and columns the database doesn't have
+    pub missing: Vec<String>,
+}
+
+static SCHEMA: OnceLock<Schema> = OnceLock::new();
+
+/// The item-to-metadata link tables the CLI's SQL names directly, with their
+/// item and metadata columns. Core Data builds these names from entity
+/// numbers, so a renumbering forScore update renames them.
+pub const LINK_TABLES: &[(&str, &str, &str)] = &[
+    ("Z_4COMPOSERS", "Z_4ITEMS1", "Z_10COMPOSERS"),
+    ("Z_4GENRES", "Z_4ITEMS4", "Z_12GENRES"),
+    ("Z_4KEYWORDS", "Z_4ITEMS5", "Z_13KEYWORDS"),
+    ("Z_4LABELS", "Z_4ITEMS2", "Z_14LABELS"),
+    ("Z_4LIBRARIES", "Z_4ITEMS3", "Z_7LIBRARIES"),
+];
+
+impl Schema {
+    /// Parse a fingerprint generated by tests/fingerprint_forscore.sh
+    pub fn from_fingerprint(text: &str) -> Schema {
+        let mut schema = Schema::default();
+        let mut section = "";
+
+        for line in text.lines() {
+            if let Some(name) = line.strip_prefix("=== ") {
+                section = name.trim_end_matches(" ===");
+                continue;
+            }
+            match section {
+                "DATABASE SCHEMA" => {
+                    if let Some((table, columns)) = parse_create_table(line) {
+                        schema.tables.insert(table, columns);
+                    }
+                }
+                "ENTITY TYPES" => {
+                    let mut fields = line.split('|');
+                    if let (Some(id), Some(name)) = (fields.next(), fields.next()) {
+                        if let Ok(id) = id.parse() {
+                            schema.entities.insert(name.to_string(), id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        schema
+    }
+
+    /// Read the schema of an open database
+    pub fn read(conn: &Connection) -> Result<Schema> {
+        let mut schema = Schema::default();
+
+        let mut stmt = conn.prepare("SELECT Z_NAME, Z_ENT FROM Z_PRIMARYKEY")?;
+        schema.entities = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let mut stmt = conn.prepare(
+            "SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p
+             WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for (table, column) in rows.filter_map(|r: rusqlite::Result<(String, String)>| r.ok()) {
+            schema.tables.entry(table).or_default().insert(column);
+        }
+
+        Ok(schema)
+    }
+
+    /// Compare this (a database's) schema with a known one
+    pub fn diff(&self, known: &Schema) -> SchemaDiff {
+        let mut diff = SchemaDiff::default();
+
+        for name in self.entities.keys() {
+            if !known.entities.contains_key(name) {
+                diff.added.push(format!("entity {}", name));
+            }
+        }
+        for name in known.entities.keys() {
+            if !self.entities.contains_key(name) {
+                diff.missing.push(format!("entity {}", name));
+            }
+        }
+
+        for (table, known_columns) in &known.tables {
+            match self.tables.get(table) {
+                None => diff.missing.push(table.clone()),
+                Some(columns) => {
+                    for column in columns.difference(known_columns) {
+                        diff.added.push(format!("{}.{}", table, column));
+                    }
+                    for column in known_columns.difference(columns) {
+                        diff.missing.push(format!("{}.{}", table, column));
+                    }
+                }
+            }
+        }
+        for table in self.tables.keys() {
+            if !known.tables.contains_key(table) {
+                diff.added.push(table.clone());
+            }
+        }
+
+        diff
+    }
+
+    /// The link tables and columns (as TABLE.COLUMN) in `LINK_TABLES` this
+    /// database doesn't have
+    pub fn missing_links(&self) -> Vec<String> {
+        let mut missing = Vec::new();
+        for (table, item_column, meta_column) in LINK_TABLES {
+            match self.tables.get(*table) {
+                None => missing.push(table.to_string()),
+                Some(columns) => {
+                    for column in [item_column, meta_column] {
+                        if !columns.contains(*column) {
+                            missing.push(format!("{}.{}", table, column));
+                        }
+                    }
+                }
+            }
+        }
+        missing
+    }
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// "CREATE TABLE ZMETA ( Z_PK INTEGER PRIMARY KEY, ZVALUE VARCHAR );" -> (ZMETA, {Z_PK, ZVALUE})
+fn parse_create_table(line: &str) -> Option<(String, BTreeSet<String>)> {
+    let rest = line.strip_prefix("CREATE TABLE ")?;
+    let (name, rest) = rest.split_once('(')?;
+    let body = rest
+        .trim_end()
+        .trim_end_matches(';')
+        .trim_end()
+        .strip_suffix(')')?;
+
+    let columns = body
+        .split(',')
+        .filter_map(|def| def.split_whitespace().next())
+        .filter(|word| *word != "PRIMARY" && !word.ends_with(')'))
+        .map(str::to_string)
+        .collect();
+    Some((name.trim().to_string(), columns))
+}
+
+/// Version of forScore the known fingerprint was taken from
+pub fn known_version() -> &'static str {
+    KNOWN_FINGERPRINT
+        .lines()
+        .find_map(|line| line.strip_prefix("# Version: "))
+        .unwrap_or("unknown")
+}
+
+/// The known schema
+pub fn known_schema() -> Schema {
+    Schema::from_fingerprint(KNOWN_FINGERPRINT)
+}
+
+/// Create the known schema's tables, indexes and entity numbering in an
+/// empty database (see `fixture`)
+pub fn create_known_schema(conn: &Connection) -> Result<()> {
+    let mut section = "";
+    for line in KNOWN_FINGERPRINT.lines() {
+        if let Some(name) = line.strip_prefix("=== ") {
+            section = name.trim_end_matches(" ===");
+            continue;
+        }
+        match section {
+            "DATABASE SCHEMA" | "INDEXES" if line.starts_with("CREATE ") => {
+                conn.execute_batch(line)?;
+            }
+            "ENTITY TYPES" => {
+                let fields: Vec<&str> = line.split('|').collect();
+                if let [id, name, parent] = fields.as_slice() {
+                    conn.execute(
+                        "INSERT INTO Z_PRIMARYKEY (Z_ENT, Z_NAME, Z_SUPER, Z_MAX) VALUES (?, ?, ?, 0)",
+                        rusqlite::params![id, name, parent],
+                    )?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Read the database's schema the first time a connection is opened, and warn
+/// if it isn't the one the CLI knows. Fails if the link tables were renamed,
+/// since every metadata query would read or write the wrong ones.
+pub fn load(conn: &Connection) -> Result<()> {
+    if SCHEMA.get().is_some() {
+        return Ok(());
+    }
+
+    let schema = Schema::read(conn)?;
+    let missing = schema.missing_links();
+    if !missing.is_empty() {
+        return Err(ForScoreError::Other(format!(
+            "This library's metadata link tables differ from forScore {}'s (missing {}); \
+             forScore has renumbered its entities, and forscore-cli needs updating before it can read this library",
+            known_version(),
+            missing.join(", ")
+        )));
+    }
+    let diff = schema.diff(&known_schema());
+    if !diff.is_empty() {
+        eprintln!(
+            "WARNING: this library's schema differs from forScore {}, the newest version forscore-cli knows.",
+            known_version()
+        );
+        if !diff.added.is_empty() {
+            eprintln!("         New: {}", diff.added.join(", "));
+        }
+        if !diff.missing.is_empty() {
+            eprintln!("         Missing: {}", diff.missing.join(", "));
+        }
+        eprintln!("         Some commands may fail or miss data.\n");
+    }
+
+    let _ = SCHEMA.set(schema);
+    Ok(())
+}
+
+/// The open database's ID for an entity, or `default` before one is opened
+pub fn entity_id(name: &str, default: i32) -> i32 {
+    SCHEMA
+        .get()
+        .and_then(|schema| schema.entities.get(name).copied())
+        .unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_fingerprint() {
+        let known = known_schema();
+        assert_eq!(known.entities.get("Score"), Some(&6));
+        assert_eq!(known.entities.get("Setlist"), Some(&19));
+        let meta = known.tables.get("ZMETA").unwrap();
+        assert!(meta.contains("ZVALUE2"));
+        assert!(!known
+            .tables
+            .get("Z_4COMPOSERS")
+            .unwrap()
+            .contains("PRIMARY"));
+    }
+
+    #[test]
+    fn test_schema_diff() {
+        let known = Schema::from_fingerprint(
+            "=== DATABASE SCHEMA ===\n\
+             CREATE TABLE ZITEM ( Z_PK INTEGER PRIMARY KEY, ZTITLE VARCHAR );\n\
+             CREATE TABLE ZPAGE ( Z_PK INTEGER PRIMARY KEY );\n\
+             === ENTITY TYPES ===\n\
+             1|Item|0\n",
+        );
+        let mut actual = Schema::from_fingerprint(
+            "=== DATABASE SCHEMA ===\n\
+             CREATE TABLE ZITEM ( Z_PK INTEGER PRIMARY KEY, ZTITLE VARCHAR, ZCUE VARCHAR );\n",
+        );
+        actual.entities.insert("Annotation".into(), 1);
+        actual.entities.insert("Item".into(), 2);
+
+        assert_eq!(
+            actual.diff(&known),
+            SchemaDiff {
+                added: vec!["entity Annotation".into(), "ZITEM.ZCUE".into()],
+                missing: vec!["ZPAGE".into()],
+            }
+        );
+        assert!(known_schema().diff(&known_schema()).is_empty());
+    }
+
+    #[test]
+    fn test_missing_links() {
+        assert!(known_schema().missing_links().is_empty());
+
+        let renumbered = Schema::from_fingerprint(
+            &KNOWN_FINGERPRINT
+                .replace("Z_13KEYWORDS", "Z_14KEYWORDS")
+                .replace("TABLE Z_4LABELS", "TABLE Z_4TAGS"),
+        );
+        assert_eq!(
+            renumbered.missing_links(),
+            ["Z_4KEYWORDS.Z_13KEYWORDS", "Z_4LABELS"]
+        );
+    }
+}
@@ -5,14 +5,13 @@
 //! for changes to sync to other devices.
 
 use crate::error::{ForScoreError, Result};
-use crate::itm::sync_folder_path;
+use crate::itm::{sync_folder_path, write_plist_if_changed};
 use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use flate2::Compression;
 use plist::{Date, Dictionary, Value};
+use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 /// URL-encode a setlist name for the filename
@@ -63,17 +62,9 @@ fn read_setlist_file(path: &PathBuf) -> Result<Dictionary> {
     }
 }
 
-/// Write a setlist .set file
-fn write_setlist_file(path: &PathBuf, dict: &Dictionary) -> Result<()> {
-    let mut plist_data = Vec::new();
-    plist::to_writer_binary(&mut plist_data, &Value::Dictionary(dict.clone()))
-        .map_err(|e| ForScoreError::Other(format!("Failed to serialize setlist plist: {}", e)))?;
-
-    let file = File::create(path)?;
-    let mut encoder = GzEncoder::new(file, Compression::default());
-    encoder.write_all(&plist_data)?;
-    encoder.finish()?;
-
+/// Write a setlist .set file (skipped when the contents are unchanged)
+fn write_setlist_file(path: &Path, dict: &Dictionary) -> Result<()> {
+    write_plist_if_changed(path, &Value::Dictionary(dict.clone()))?;
     Ok(())
 }
 
@@ -140,6 +131,24 @@ pub fn rename_setlist_file(old_name: &str, new_name: &str) -> Result<bool> {
     Ok(true)
 }
 
+/// Set lastPlayed in a setlist .set file to now
+pub fn mark_setlist_file_played(name: &str) -> Result<bool> {
+    let path = setlist_file_path(name)?;
+
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let mut dict = read_setlist_file(&path)?;
+    dict.insert(
+        "lastPlayed".to_string(),
+        Value::Date(Date::from(SystemTime::now())),
+    );
+
+    write_setlist_file(&path, &dict)?;
+    Ok(true)
+}
+
 /// Delete a setlist .set file
 pub fn delete_setlist_file(name: &str) -> Result<bool> {
     let path = setlist_file_path(name)?;
@@ -198,6 +207,15 @@ pub fn add_item_to_setlist_file(setlist_name: &str, item: &SetlistItem) -> Resul
 
     // Create item dictionary
     let mut item_dict = Dictionary::new();
+    fill_item_dict(&mut item_dict, item);
+    items.push(Value::Dictionary(item_dict));
+
+    write_setlist_file(&path, &dict)?;
+    Ok(true)
+}
+
+/// Set the keys forScore reads for a setlist item
+fn fill_item_dict(item_dict: &mut Dictionary, item: &SetlistItem) {
     item_dict.insert(
         "FilePath".to_string(),
         Value::String(item.file_path.clone()),
@@ -217,11 +235,6 @@ pub fn add_item_to_setlist_file(setlist_name: &str, item: &SetlistItem) -> Resul
             item_dict.insert("Last Page".to_string(), Value::String(last.to_string()));
         }
     }
-
-    items.push(Value::Dictionary(item_dict));
-
-    write_setlist_file(&path, &dict)?;
-    Ok(true)
 }
 
 /// Remove an item from a setlist .set file by identifier
@@ -259,6 +272,51 @@ pub fn remove_item_from_setlist_file(setlist_name: &str, identifier: &str) -> Re
     Ok(true)
 }
 
+/// Set (or with None, clear) the page range of one item in a setlist .set
+/// file, returning false if the file or item isn't there
+pub fn set_item_pages_in_setlist_file(
+    setlist_name: &str,
+    identifier: &str,
+    pages: Option<(i64, i64)>,
+) -> Result<bool> {
+    let path = setlist_file_path(setlist_name)?;
+
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let mut dict = read_setlist_file(&path)?;
+
+    let Some(Value::Array(items)) = dict.get_mut("items") else {
+        return Ok(false);
+    };
+    let item = items.iter_mut().find_map(|item| match item {
+        Value::Dictionary(d)
+            if matches!(d.get("Identifier"), Some(Value::String(id)) if id == identifier) =>
+        {
+            Some(d)
+        }
+        _ => None,
+    });
+    let Some(item) = item else {
+        return Ok(false);
+    };
+
+    match pages {
+        Some((first, last)) => {
+            item.insert("First Page".to_string(), Value::String(first.to_string()));
+            item.insert("Last Page".to_string(), Value::String(last.to_string()));
+        }
+        None => {
+            item.remove("First Page");
+            item.remove("Last Page");
+        }
+    }
+
+    write_setlist_file(&path, &dict)?;
+    Ok(true)
+}
+
 /// Rebuild a setlist .set file with items in the specified order
 pub fn reorder_setlist_file(setlist_name: &str, items: &[SetlistItem]) -> Result<bool> {
     let path = setlist_file_path(setlist_name)?;
@@ -269,30 +327,23 @@ pub fn reorder_setlist_file(setlist_name: &str, items: &[SetlistItem]) -> Result
 
     let mut dict = read_setlist_file(&path)?;
 
-    // Rebuild items array
-    let mut new_items = Vec::new();
-    for item in items {
-        let mut item_dict = Dictionary::new();
-        item_dict.insert(
-            "FilePath".to_string(),
-            Value::String(item.file_path.clone()),
-        );
-        item_dict.insert("Title".to_string(), Value::String(item.title.clone()));
-        item_dict.insert(
-            "Identifier".to_string(),
-            Value::String(item.identifier.clone()),
-        );
-
-        if item.is_bookmark {
-            item_dict.insert("Bookmark".to_string(), Value::String("YES".to_string()));
-            if let Some(first) = item.first_page {
-                item_dict.insert("First Page".to_string(), Value::String(first.to_string()));
-            }
-            if let Some(last) = item.last_page {
-                item_dict.insert("Last Page".to_string(), Value::String(last.to_string()));
+    // Rebuild items array, starting each item from its existing entry so keys
+    // the CLI doesn't manage survive the reorder
+    let mut existing: HashMap<String, Dictionary> = HashMap::new();
+    if let Some(Value::Array(old_items)) = dict.remove("items") {
+        for old in old_items {
+            if let Value::Dictionary(d) = old {
+                if let Some(Value::String(id)) = d.get("Identifier") {
+                    existing.insert(id.clone(), d);
+                }
             }
         }
+    }
 
+    let mut new_items = Vec::new();
+    for item in items {
+        let mut item_dict = existing.remove(&item.identifier).unwrap_or_default();
+        fill_item_dict(&mut item_dict, item);
         new_items.push(Value::Dictionary(item_dict));
     }
 
@@ -302,6 +353,77 @@ pub fn reorder_setlist_file(setlist_name: &str, items: &[SetlistItem]) -> Result
     Ok(true)
 }
 
+/// List the .set files in the sync folder
+fn all_setlist_files() -> Result<Vec<PathBuf>> {
+    let sync_folder = sync_folder_path()?;
+    Ok(fs::read_dir(&sync_folder)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("set"))
+        .collect())
+}
+
+/// Collect every FilePath referenced by any .set file
+pub fn all_setlist_file_paths() -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+
+    for path in all_setlist_files()? {
+        let dict = match read_setlist_file(&path) {
+            Ok(d) => d,
+            Err(_) => continue, // Skip unreadable files
+        };
+        if let Some(Value::Array(items)) = dict.get("items") {
+            for item in items {
+                if let Some(Value::String(file_path)) =
+                    item.as_dictionary().and_then(|d| d.get("FilePath"))
+                {
+                    paths.push(file_path.clone());
+                }
+            }
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Rewrite FilePath entries across all .set files using `rename`, which returns
+/// the new path for entries that should change. Returns (files_modified, entries_fixed).
+pub fn rewrite_file_paths_in_all_setlists(
+    rename: impl Fn(&str) -> Option<String>,
+) -> Result<(usize, usize)> {
+    let mut files_modified = 0;
+    let mut entries_fixed = 0;
+
+    for path in all_setlist_files()? {
+        let mut dict = match read_setlist_file(&path) {
+            Ok(d) => d,
+            Err(_) => continue, // Skip unreadable files
+        };
+
+        let mut modified = false;
+        if let Some(Value::Array(items)) = dict.get_mut("items") {
+            for item in items.iter_mut() {
+                if let Some(Value::String(file_path)) =
+                    item.as_dictionary_mut().and_then(|d| d.get_mut("FilePath"))
+                {
+                    if let Some(new_path) = rename(file_path) {
+                        *file_path = new_path;
+                        entries_fixed += 1;
+                        modified = true;
+                    }
+                }
+            }
+        }
+
+        if modified {
+            write_setlist_file(&path, &dict)?;
+            files_modified += 1;
+        }
+    }
+
+    Ok((files_modified, entries_fixed))
+}
+
 /// Update folder .fld files that reference a renamed setlist
 fn update_folders_for_renamed_setlist(old_name: &str, new_name: &str) -> Result<()> {
     let sync_folder = sync_folder_path()?;
@@ -0,0 +1,230 @@
+//! Portable library snapshots, for `export snapshot` and `import snapshot`
+//!
+//! A snapshot is the `export json` document plus the library list and the
+//! time it was taken, gzipped. Given a directory, `export snapshot` writes
+//! a timestamped file into it and `import snapshot` reads the newest one,
+//! so a folder on a backup drive can collect them over time.
+
+use crate::error::{ForScoreError, Result};
+use crate::library_json::{read_library, ScoreJson, SetlistJson};
+use crate::models::library::list_libraries;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Written to every snapshot so other JSON files aren't mistaken for one
+const FORMAT: &str = "forscore-snapshot";
+
+/// Bumped when the snapshot changes shape
+pub const VERSION: u32 = 1;
+
+const FILE_PREFIX: &str = "forscore-snapshot-";
+const FILE_SUFFIX: &str = ".json.gz";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub format: String,
+    pub version: u32,
+    pub created: DateTime<Utc>,
+    /// Library names, including empty ones
+    pub libraries: Vec<String>,
+    pub scores: Vec<ScoreJson>,
+    pub setlists: Vec<SetlistJson>,
+}
+
+impl Snapshot {
+    pub fn bookmark_count(&self) -> usize {
+        self.scores.iter().map(|s| s.bookmarks.len()).sum()
+    }
+}
+
+/// Read the whole library
+pub fn read_snapshot(conn: &Connection) -> Result<Snapshot> {
+    let library = read_library(conn)?;
+    Ok(Snapshot {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        created: Utc::now(),
+        libraries: list_libraries(conn)?.into_iter().map(|l| l.title).collect(),
+        scores: library.scores,
+        setlists: library.setlists,
+    })
+}
+
+/// Write a snapshot to `target`: a timestamped file (UTC) if it's a
+/// directory, otherwise the file itself (gzipped unless it ends in `.json`).
+/// A target that doesn't exist is a directory, and is created, when it ends
+/// in `/` or has no extension. Existing files are never overwritten.
+pub fn write_snapshot(target: &Path, snapshot: &Snapshot) -> Result<PathBuf> {
+    if !target.exists() && names_directory(target) {
+        fs::create_dir_all(target)?;
+    }
+    let path = if target.is_dir() {
+        let timestamp = snapshot.created.format("%Y%m%d-%H%M%SZ");
+        target.join(format!("{}{}{}", FILE_PREFIX, timestamp, FILE_SUFFIX))
+    } else {
+        target.to_path_buf()
+    };
+
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                ForScoreError::Other(format!("{} already exists", path.display()))
+            }
+            _ => e.into(),
+        })?;
+    let mut text = serde_json::to_vec_pretty(snapshot)?;
+    text.push(b'\n');
+    if path.extension().is_some_and(|e| e == "json") {
+        let mut file = file;
+        file.write_all(&text)?;
+    } else {
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        encoder.write_all(&text)?;
+        encoder.finish()?;
+    }
+    Ok(path)
+}
+
+/// Whether a path that doesn't exist yet means a directory: "backups/" or
+/// "backups", but not "backup.json.gz"
+fn names_directory(path: &Path) -> bool {
+    path.as_os_str()
+        .to_string_lossy()
+        .ends_with(std::path::MAIN_SEPARATOR)
+        || path.extension().is_none()
+}
+
+/// Load a snapshot file, or the newest snapshot in a directory
+pub fn load_snapshot(source: &Path) -> Result<(PathBuf, Snapshot)> {
+    let path = if source.is_dir() {
+        let names: Vec<String> = fs::read_dir(source)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        let newest = latest_snapshot(&names)
+            .ok_or_else(|| ForScoreError::Other(format!("No snapshots in {}", source.display())))?;
+        source.join(newest)
+    } else {
+        source.to_path_buf()
+    };
+
+    let snapshot = parse_snapshot(&fs::read(&path)?)
+        .map_err(|e| ForScoreError::Other(format!("{}: {}", path.display(), e)))?;
+    Ok((path, snapshot))
+}
+
+/// The newest `export snapshot` file name; the timestamps sort as text
+fn latest_snapshot(names: &[String]) -> Option<&String> {
+    names
+        .iter()
+        .filter(|n| n.starts_with(FILE_PREFIX) && n.ends_with(FILE_SUFFIX))
+        .max()
+}
+
+/// Parse a snapshot, gzipped or not, refusing versions this build doesn't know
+fn parse_snapshot(bytes: &[u8]) -> Result<Snapshot> {
+    let mut text = Vec::new();
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(bytes).read_to_end(&mut text)?;
+    } else {
+        text.extend_from_slice(bytes);
+    }
+
+    let value: serde_json::Value = serde_json::from_slice(&text)
+        .map_err(|e| ForScoreError::Other(format!("not a snapshot: {}", e)))?;
+    if value.get("format").and_then(|f| f.as_str()) != Some(FORMAT) {
+        return Err(ForScoreError::Other("not a snapshot".to_string()));
+    }
+    let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+    if version > VERSION as u64 {
+        return Err(ForScoreError::Other(format!(
+            "snapshot version {}; this forscore-cli reads up to version {}",
+            version, VERSION
+        )));
+    }
+    serde_json::from_value(value)
+        .map_err(|e| ForScoreError::Other(format!("not a snapshot: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_snapshot() {
+        let names: Vec<String> = [
+            "forscore-snapshot-20260101-090000Z.json.gz",
+            "forscore-snapshot-20261015-183000Z.json.gz",
+            "notes.txt",
+            // Written before snapshots were timestamped in UTC
+            "forscore-snapshot-20260601-120000.json.gz",
+        ]
+        .iter()
+        .map(|n| n.to_string())
+        .collect();
+        assert_eq!(
+            latest_snapshot(&names).map(String::as_str),
+            Some("forscore-snapshot-20261015-183000Z.json.gz")
+        );
+        assert_eq!(
+            latest_snapshot(&[names[0].clone(), names[3].clone()]).map(String::as_str),
+            Some("forscore-snapshot-20260601-120000.json.gz")
+        );
+        assert_eq!(latest_snapshot(&names[2..3]), None);
+    }
+
+    #[test]
+    fn test_write_snapshot() {
+        let dir = std::env::temp_dir().join(format!("forscore-snapshot-{}", std::process::id()));
+        let snapshot = parse_snapshot(
+            br#"{"format": "forscore-snapshot", "version": 1,
+                "created": "2026-10-16T08:00:00Z", "libraries": [],
+                "scores": [], "setlists": []}"#,
+        )
+        .unwrap();
+
+        let written = write_snapshot(&dir.join("backups"), &snapshot).unwrap();
+        assert_eq!(
+            written,
+            dir.join("backups/forscore-snapshot-20261016-080000Z.json.gz")
+        );
+        assert!(write_snapshot(&dir.join("backups"), &snapshot).is_err());
+
+        let file = dir.join("library.json");
+        write_snapshot(&file, &snapshot).unwrap();
+        assert!(write_snapshot(&file, &snapshot).is_err());
+        assert_eq!(load_snapshot(&file).unwrap().1.version, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_snapshot() {
+        let text = r#"{"format": "forscore-snapshot", "version": 1,
+            "created": "2026-10-16T08:00:00Z", "libraries": ["Choir"],
+            "scores": [], "setlists": []}"#;
+        assert_eq!(
+            parse_snapshot(text.as_bytes()).unwrap().libraries,
+            ["Choir"]
+        );
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        assert_eq!(parse_snapshot(&gzipped).unwrap().version, 1);
+
+        assert!(parse_snapshot(b"{\"version\": 1, \"scores\": []}").is_err());
+        let newer = text.replace("\"version\": 1", "\"version\": 2");
+        assert!(parse_snapshot(newer.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Sort titles (ZSORTTITLE), derived from titles by the `[sort_title]` rules
+//! in the config file
+//!
+//! With no rules configured a sort title is the lowercased title, as
+//! forScore makes it. The rules can drop a leading article ("The", "Le",
+//! "Die", "L'"...), drop punctuation, and move an opus number ("Op. 9 No. 2")
+//! to the end, so titles sort by what they're called.
+
+use crate::config::{load_config, SortTitleConfig};
+use std::sync::OnceLock;
+
+/// Articles dropped by `strip_articles` unless `articles` replaces them:
+/// English, French, German, Italian and Spanish. Italian "i" is left out so
+/// "I Got Rhythm" keeps its first word
+const DEFAULT_ARTICLES: &[&str] = &[
+    "the", "a", "an", "le", "la", "les", "l'", "un", "une", "der", "die", "das", "ein", "eine",
+    "il", "lo", "gli", "uno", "una", "el", "los", "las",
+];
+
+/// The sort title for `title` under the configured rules. Config errors are
+/// reported by the commands that need the rest of the config, so here they
+/// mean no rules
+pub fn sort_title(title: &str) -> String {
+    static RULES: OnceLock<SortTitleConfig> = OnceLock::new();
+    let rules = RULES.get_or_init(|| load_config().map(|c| c.sort_title).unwrap_or_default());
+    derive(title, rules)
+}
+
+fn derive(title: &str, rules: &SortTitleConfig) -> String {
+    let mut sort = title.trim().to_lowercase();
+    if rules.opus_last {
+        sort = opus_last(&sort);
+    }
+    if rules.strip_articles {
+        let articles: Vec<&str> = match &rules.articles {
+            Some(list) => list.iter().map(String::as_str).collect(),
+            None => DEFAULT_ARTICLES.to_vec(),
+        };
+        sort = strip_article(&sort, &articles);
+    }
+    if rules.ignore_punctuation {
+        sort = strip_punctuation(&sort);
+    }
+    sort
+}
+
+/// Drop a leading article, unless it's the whole title. Articles ending in
+/// an apostrophe ("l'") are elided onto the next word
+fn strip_article(title: &str, articles: &[&str]) -> String {
+    let normalized = title.replace('’', "'");
+    for article in articles {
+        let article = article.trim().to_lowercase();
+        let rest = if article.ends_with('\'') {
+            normalized.strip_prefix(article.as_str())
+        } else {
+            normalized
+                .strip_prefix(article.as_str())
+                .filter(|rest| rest.starts_with(' '))
+        };
+        if let Some(rest) = rest.map(str::trim_start).filter(|r| !r.is_empty()) {
+            return rest.to_string();
+        }
+    }
+    title.to_string()
+}
+
+/// Apostrophes are dropped ("don't" -> "dont"), other punctuation separates
+/// words
+fn strip_punctuation(title: &str) -> String {
+    let kept: String = title
+        .chars()
+        .filter(|c| !matches!(c, '\'' | '’'))
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    kept.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Whether a word opens an opus number: "op.", "op", "opus", or "op.9"
+/// with the number attached
+fn opus_word(word: &str) -> Option<bool> {
+    let bare = word.trim_end_matches([',', '.']);
+    match bare {
+        "op" | "opus" => Some(false),
+        _ => {
+            let number = bare
+                .strip_prefix("op.")
+                .or_else(|| bare.strip_prefix("op"))?;
+            number
+                .starts_with(|c: char| c.is_ascii_digit())
+                .then_some(true)
+        }
+    }
+}
+
+fn starts_with_digit(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Move an opus number, with any "No. n" after it, to the end:
+/// "nocturne op. 9 no. 2 in e-flat" -> "nocturne in e-flat op. 9 no. 2"
+fn opus_last(title: &str) -> String {
+    let words: Vec<&str> = title.split_whitespace().collect();
+    for start in 0..words.len() {
+        let Some(attached) = opus_word(words[start]) else {
+            continue;
+        };
+        let mut end = start + 1;
+        if !attached {
+            if !words.get(end).is_some_and(|w| starts_with_digit(w)) {
+                continue;
+            }
+            end += 1;
+        }
+        // "No. 2", "Nr. 2", "No.2"
+        while let Some(word) = words.get(end) {
+            let bare = word.trim_end_matches([',', '.']);
+            if matches!(bare, "no" | "nr")
+                && words.get(end + 1).is_some_and(|w| starts_with_digit(w))
+            {
+                end += 2;
+            } else if ["no.", "nr."]
+                .iter()
+                .any(|p| word.strip_prefix(p).is_some_and(starts_with_digit))
+            {
+                end += 1;
+            } else {
+                break;
+            }
+        }
+        if start == 0 || end == words.len() {
+            return words.join(" ");
+        }
+
+        let opus = words[start..end].join(" ");
+        let mut rest: Vec<&str> = words[..start].to_vec();
+        rest.extend(&words[end..]);
+        let rest = rest.join(" ");
+        let rest = rest.trim_end_matches(',');
+        return format!("{} {}", rest, opus.trim_end_matches(','));
+    }
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive() {
+        let plain = SortTitleConfig::default();
+        assert_eq!(derive("The Entertainer", &plain), "the entertainer");
+
+        let rules = SortTitleConfig {
+            strip_articles: true,
+            articles: None,
+            ignore_punctuation: true,
+            opus_last: true,
+        };
+        assert_eq!(derive("The Entertainer", &rules), "entertainer");
+        assert_eq!(derive("L'Arlésienne", &rules), "arlésienne");
+        assert_eq!(derive("Die Forelle", &rules), "forelle");
+        assert_eq!(derive("I Got Rhythm", &rules), "i got rhythm");
+        assert_eq!(derive("The", &rules), "the");
+        assert_eq!(
+            derive("Theme and Variations", &rules),
+            "theme and variations"
+        );
+        assert_eq!(
+            derive("Nocturne Op. 9 No. 2 in E-flat", &rules),
+            "nocturne in e flat op 9 no 2"
+        );
+        assert_eq!(
+            derive("Sonata, Op.27 No.2 Moonlight", &rules),
+            "sonata moonlight op 27 no 2"
+        );
+        assert_eq!(derive("Don't Stop", &rules), "dont stop");
+
+        let custom = SortTitleConfig {
+            strip_articles: true,
+            articles: Some(vec!["a".into()]),
+            ..Default::default()
+        };
+        assert_eq!(derive("A Child Is Born", &custom), "child is born");
+        assert_eq!(derive("The Girl", &custom), "the girl");
+    }
+
+    #[test]
+    fn test_opus_last() {
+        assert_eq!(opus_last("etude op. 10 no. 3"), "etude op. 10 no. 3");
+        assert_eq!(
+            opus_last("waltz op. 64, no. 2 in c# minor"),
+            "waltz in c# minor op. 64, no. 2"
+        );
+        assert_eq!(opus_last("opus one"), "opus one");
+        assert_eq!(opus_last("sonata op. 2"), "sonata op. 2");
+    }
+}
@@ -0,0 +1,241 @@
+//! Sidecar store for metadata forScore has no field for, and for where
+//! archived scores used to be
+//!
+//! Kept in the user's data directory (unlike the cache, this is user data).
+//! Entries are keyed by name rather than ZMETA ID so they survive forScore
+//! renumbering its metadata.
+
+use crate::error::{ForScoreError, Result};
+use crate::models::meta::Composer;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Get the path to the sidecar store
+pub fn store_path() -> Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| ForScoreError::Other("Cannot find data directory".into()))?;
+    Ok(dir.join("forscore-cli").join("store.sqlite"))
+}
+
+/// Open (creating if needed) the sidecar store
+pub fn open_store() -> Result<Connection> {
+    let path = store_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let conn = Connection::open(&path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS composer_info (
+            name TEXT PRIMARY KEY COLLATE NOCASE,
+            born INTEGER,
+            died INTEGER,
+            nationality TEXT
+        );
+        CREATE TABLE IF NOT EXISTS archived_memberships (
+            item_uuid TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            name TEXT NOT NULL,
+            setlist_uuid TEXT,
+            position INTEGER,
+            archived_at TEXT NOT NULL
+        )",
+    )?;
+    Ok(conn)
+}
+
+/// Lifespan and nationality for a composer
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ComposerInfo {
+    pub born: Option<i32>,
+    pub died: Option<i32>,
+    pub nationality: Option<String>,
+}
+
+impl ComposerInfo {
+    /// Lifespan for display, e.g. "1685-1750" or "1962-"
+    pub fn years(&self) -> Option<String> {
+        match (self.born, self.died) {
+            (Some(b), Some(d)) => Some(format!("{}-{}", b, d)),
+            (Some(b), None) => Some(format!("{}-", b)),
+            (None, Some(d)) => Some(format!("-{}", d)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Parse a lifespan such as "1685-1750", "1962-" or "-1750"
+pub fn parse_years(s: &str) -> Result<(Option<i32>, Option<i32>)> {
+    let invalid =
+        || ForScoreError::Other(format!("Invalid years '{}' (expected e.g. 1685-1750)", s));
+    let (born, died) = s.split_once('-').unwrap_or((s, ""));
+
+    let parse = |part: &str| -> Result<Option<i32>> {
+        let part = part.trim();
+        if part.is_empty() {
+            Ok(None)
+        } else {
+            part.parse().map(Some).map_err(|_| invalid())
+        }
+    };
+
+    let years = (parse(born)?, parse(died)?);
+    if years == (None, None) {
+        return Err(invalid());
+    }
+    Ok(years)
+}
+
+/// Get the stored info for a composer
+pub fn get_composer_info(store: &Connection, name: &str) -> Result<ComposerInfo> {
+    let info = store
+        .query_row(
+            "SELECT born, died, nationality FROM composer_info WHERE name = ?",
+            [name],
+            |row| {
+                Ok(ComposerInfo {
+                    born: row.get(0)?,
+                    died: row.get(1)?,
+                    nationality: row.get(2)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(info.unwrap_or_default())
+}
+
+/// Store info for a composer (an empty info removes the entry)
+pub fn set_composer_info(store: &Connection, name: &str, info: &ComposerInfo) -> Result<()> {
+    if *info == ComposerInfo::default() {
+        store.execute("DELETE FROM composer_info WHERE name = ?", [name])?;
+    } else {
+        store.execute(
+            "INSERT OR REPLACE INTO composer_info (name, born, died, nationality) VALUES (?, ?, ?, ?)",
+            rusqlite::params![name, info.born, info.died, info.nationality],
+        )?;
+    }
+    Ok(())
+}
+
+/// Move info to a composer's new name, unless the new name already has some
+pub fn rename_composer_info(store: &Connection, old_name: &str, new_name: &str) -> Result<()> {
+    store.execute(
+        "UPDATE OR IGNORE composer_info SET name = ? WHERE name = ?",
+        [new_name, old_name],
+    )?;
+    store.execute("DELETE FROM composer_info WHERE name = ?", [old_name])?;
+    Ok(())
+}
+
+/// All stored composer info, keyed by lowercased name
+pub fn all_composer_info(store: &Connection) -> Result<HashMap<String, ComposerInfo>> {
+    let mut stmt = store.prepare("SELECT name, born, died, nationality FROM composer_info")?;
+    let info = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?.to_lowercase(),
+                ComposerInfo {
+                    born: row.get(1)?,
+                    died: row.get(2)?,
+                    nationality: row.get(3)?,
+                },
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(info)
+}
+
+/// Fill in lifespan and nationality for listed composers
+pub fn load_composer_info(composers: &mut [Composer]) -> Result<()> {
+    let info = all_composer_info(&open_store()?)?;
+    for composer in composers.iter_mut() {
+        if let Some(i) = info.get(&composer.name.to_lowercase()) {
+            composer.years = i.years();
+            composer.nationality = i.nationality.clone();
+        }
+    }
+    Ok(())
+}
+
+/// A library or setlist an archived item was removed from, so `scores
+/// unarchive` can put it back
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArchivedMembership {
+    /// "library" or "setlist"
+    pub kind: String,
+    /// Library or setlist name
+    pub name: String,
+    pub setlist_uuid: Option<String>,
+    /// 1-based position in the setlist
+    pub position: Option<i64>,
+}
+
+/// Remember where an item was before it was archived
+pub fn record_archived_membership(
+    store: &Connection,
+    item_uuid: &str,
+    membership: &ArchivedMembership,
+) -> Result<()> {
+    store.execute(
+        "INSERT INTO archived_memberships (item_uuid, kind, name, setlist_uuid, position, archived_at)
+         VALUES (?, ?, ?, ?, ?, datetime('now'))",
+        rusqlite::params![
+            item_uuid,
+            membership.kind,
+            membership.name,
+            membership.setlist_uuid,
+            membership.position
+        ],
+    )?;
+    Ok(())
+}
+
+/// Where an archived item was, in the order it was recorded
+pub fn archived_memberships(
+    store: &Connection,
+    item_uuid: &str,
+) -> Result<Vec<ArchivedMembership>> {
+    let mut stmt = store.prepare(
+        "SELECT kind, name, setlist_uuid, position FROM archived_memberships
+         WHERE item_uuid = ? ORDER BY rowid",
+    )?;
+    let memberships = stmt
+        .query_map([item_uuid], |row| {
+            Ok(ArchivedMembership {
+                kind: row.get(0)?,
+                name: row.get(1)?,
+                setlist_uuid: row.get(2)?,
+                position: row.get(3)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(memberships)
+}
+
+/// Forget an item's archived memberships (after it was restored)
+pub fn clear_archived_memberships(store: &Connection, item_uuid: &str) -> Result<()> {
+    store.execute(
+        "DELETE FROM archived_memberships WHERE item_uuid = ?",
+        [item_uuid],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_years() {
+        assert_eq!(parse_years("1685-1750").unwrap(), (Some(1685), Some(1750)));
+        assert_eq!(parse_years("1962-").unwrap(), (Some(1962), None));
+        assert_eq!(parse_years("1962").unwrap(), (Some(1962), None));
+        assert_eq!(parse_years("-1750").unwrap(), (None, Some(1750)));
+        assert!(parse_years("-").is_err());
+        assert!(parse_years("baroque").is_err());
+    }
+}
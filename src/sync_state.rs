@@ -0,0 +1,129 @@
+//! forScore's sync bookkeeping plists
+//!
+//! The sync settings live in the app's preferences plist and the record of
+//! synced files in Sync/.syncFolderState. Both are read with the plist crate,
+//! so `sync` works anywhere, including on copied files.
+
+use crate::error::{ForScoreError, Result};
+use crate::models::track::is_audio_file;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Sync settings from com.mgsdevelopment.forscore.plist
+#[derive(Debug, Default, Deserialize)]
+pub struct SyncPreferences {
+    #[serde(rename = "&SYNC;syncEnabled", default)]
+    pub sync_enabled: bool,
+    #[serde(rename = "&SYNC;lastSyncDate", default)]
+    pub last_sync_date: Option<plist::Date>,
+    #[serde(rename = "&SYNC;lastSyncErrorCode", default)]
+    pub last_sync_error_code: i64,
+}
+
+impl SyncPreferences {
+    pub fn last_sync_time(&self) -> Option<SystemTime> {
+        self.last_sync_date.map(SystemTime::from)
+    }
+}
+
+/// One file in .syncFolderState
+#[derive(Debug, Deserialize)]
+pub struct SyncStateEntry {
+    /// Unix timestamp
+    pub modified: f64,
+    /// Percent-encoded, prefixed with {%SYNC_DIR%}/
+    pub path: String,
+    #[serde(rename = "fileSize", default)]
+    pub file_size: i64,
+}
+
+impl SyncStateEntry {
+    /// The path relative to the Sync folder, decoded
+    pub fn display_path(&self) -> String {
+        let path = self
+            .path
+            .strip_prefix("{%SYNC_DIR%}/")
+            .unwrap_or(&self.path);
+        urlencoding::decode(path)
+            .map(|p| p.into_owned())
+            .unwrap_or_else(|_| path.to_string())
+    }
+
+    /// When the file was last synced
+    pub fn modified_time(&self) -> Option<DateTime<Utc>> {
+        let secs = self.modified.trunc() as i64;
+        let nanos = (self.modified.fract() * 1_000_000_000.0) as u32;
+        DateTime::from_timestamp(secs, nanos)
+    }
+}
+
+/// What a synced file is, by extension: "itm", "setlist", "pdf", "audio"
+/// or "other"
+pub fn file_kind(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+    match extension.as_deref() {
+        Some("itm") => "itm",
+        Some("set") => "setlist",
+        Some("pdf") => "pdf",
+        _ if is_audio_file(path) => "audio",
+        _ => "other",
+    }
+}
+
+/// Read the sync settings from forScore's preferences plist
+pub fn read_sync_preferences(path: &Path) -> Result<SyncPreferences> {
+    plist::from_file(path)
+        .map_err(|e| ForScoreError::Other(format!("Failed to read {}: {}", path.display(), e)))
+}
+
+/// Read the entries of a .syncFolderState file
+pub fn read_sync_state(path: &Path) -> Result<Vec<SyncStateEntry>> {
+    plist::from_file(path)
+        .map_err(|e| ForScoreError::Other(format!("Failed to read {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plist::{Dictionary, Value};
+
+    #[test]
+    fn test_read_sync_state() {
+        let mut entry = Dictionary::new();
+        entry.insert("modified".into(), Value::Integer(1_700_000_000.into()));
+        entry.insert(
+            "path".into(),
+            Value::String("{%SYNC_DIR%}/Prelude%20in%20C.pdf.itm".into()),
+        );
+        let path = std::env::temp_dir().join(format!("forscore-sync-state-{}", std::process::id()));
+        Value::Array(vec![Value::Dictionary(entry)])
+            .to_file_binary(&path)
+            .unwrap();
+
+        let entries = read_sync_state(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].modified, 1_700_000_000.0);
+        assert_eq!(entries[0].file_size, 0);
+        assert_eq!(entries[0].display_path(), "Prelude in C.pdf.itm");
+        assert_eq!(
+            entries[0].modified_time().map(|t| t.timestamp()),
+            Some(1_700_000_000)
+        );
+    }
+
+    #[test]
+    fn test_file_kind() {
+        assert_eq!(file_kind("Prelude in C.pdf.itm"), "itm");
+        assert_eq!(file_kind("Gig.SET"), "setlist");
+        assert_eq!(file_kind("Prelude in C.pdf"), "pdf");
+        assert_eq!(file_kind("audio/Take 1.m4a"), "audio");
+        assert_eq!(file_kind("README"), "other");
+    }
+}
@@ -0,0 +1,299 @@
+//! Setlist programs: built-in running orders and user templates
+//!
+//! Templates are Handlebars files rendered against a [`ProgramContext`].
+//! Output is only HTML-escaped when the template name contains ".htm"
+//! (e.g. `program.html.hbs`), so plain text and Markdown come out verbatim.
+
+use crate::cli::ProgramFormat;
+use crate::error::{ForScoreError, Result};
+use handlebars::{handlebars_helper, no_escape, Handlebars};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+/// Everything a program template can refer to
+#[derive(Debug, Serialize)]
+pub struct ProgramContext {
+    pub setlist: ProgramSetlist,
+    pub items: Vec<ProgramItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProgramSetlist {
+    pub id: i64,
+    pub title: String,
+    pub item_count: usize,
+    pub total_pages: i32,
+    /// Total duration as m:ss (empty if no item has a duration)
+    pub total_duration: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProgramItem {
+    pub position: usize,
+    pub id: i64,
+    pub title: String,
+    pub path: String,
+    pub is_bookmark: bool,
+    pub composers: Vec<ProgramComposer>,
+    pub genres: Vec<String>,
+    pub keywords: Vec<String>,
+    pub labels: Vec<String>,
+    pub key: Option<String>,
+    pub rating: Option<i32>,
+    pub difficulty: Option<i32>,
+    pub bpm: Option<i32>,
+    pub pages: Option<i32>,
+    /// Pages from the start of the setlist through this item
+    pub cumulative_pages: Option<i32>,
+    /// Duration as m:ss
+    pub duration: Option<String>,
+    pub duration_seconds: Option<i32>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProgramComposer {
+    pub name: String,
+    pub years: Option<String>,
+    pub nationality: Option<String>,
+}
+
+// {{join genres ", "}}, also accepting a list of objects with a name
+handlebars_helper!(join: |list: array, sep: str| {
+    list.iter()
+        .map(|v| match v {
+            Value::String(s) => s.clone(),
+            Value::Object(o) => o.get("name").and_then(Value::as_str).unwrap_or_default().to_string(),
+            other => other.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(sep)
+});
+
+/// Render the template at `path` with the given context
+pub fn render_template(path: &Path, context: &impl Serialize) -> Result<String> {
+    let source = std::fs::read_to_string(path)?;
+
+    let mut hb = Handlebars::new();
+    let name = path.to_string_lossy();
+    if !name.contains(".htm") {
+        hb.register_escape_fn(no_escape);
+    }
+    hb.register_helper("join", Box::new(join));
+
+    hb.render_template(&source, context)
+        .map_err(|e| ForScoreError::Other(format!("Template {}: {}", name, e)))
+}
+
+/// Columns of the built-in running order
+const PROGRAM_HEADERS: [&str; 6] = ["#", "Title", "Composer", "Key", "Pages", "Total"];
+
+/// An item's running-order row, matching `PROGRAM_HEADERS`
+fn program_row(item: &ProgramItem) -> [String; 6] {
+    let number = |n: Option<i32>| n.map(|n| n.to_string()).unwrap_or_default();
+    [
+        item.position.to_string(),
+        item.title.clone(),
+        item.composers
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        item.key.clone().unwrap_or_default(),
+        number(item.pages),
+        number(item.cumulative_pages),
+    ]
+}
+
+/// "5 items, 23 pages, 18:40"
+fn program_summary(setlist: &ProgramSetlist) -> String {
+    let mut parts = vec![format!("{} items", setlist.item_count)];
+    if setlist.total_pages > 0 {
+        parts.push(format!("{} pages", setlist.total_pages));
+    }
+    if !setlist.total_duration.is_empty() {
+        parts.push(setlist.total_duration.clone());
+    }
+    parts.join(", ")
+}
+
+/// Render a setlist's running order in one of the built-in layouts
+pub fn render_program(context: &ProgramContext, format: ProgramFormat) -> Result<String> {
+    let rows: Vec<[String; 6]> = context.items.iter().map(program_row).collect();
+    // Position, pages and running total are right-aligned
+    let numeric = |column: usize| matches!(column, 0 | 4 | 5);
+
+    Ok(match format {
+        ProgramFormat::Txt => {
+            let mut widths = PROGRAM_HEADERS.map(|h| h.chars().count());
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+            let line = |cells: &[String]| {
+                let padded: Vec<String> = cells
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| {
+                        if numeric(i) {
+                            format!("{:>1$}", cell, widths[i])
+                        } else {
+                            format!("{:<1$}", cell, widths[i])
+                        }
+                    })
+                    .collect();
+                format!("{}\n", padded.join("  ").trim_end())
+            };
+
+            let mut out = format!(
+                "{}\n{}\n\n",
+                context.setlist.title,
+                program_summary(&context.setlist)
+            );
+            out.push_str(&line(&PROGRAM_HEADERS.map(String::from)));
+            for row in &rows {
+                out.push_str(&line(row));
+            }
+            out
+        }
+        ProgramFormat::Md => {
+            let escape = |cell: &String| cell.replace('|', "\\|");
+            let mut out = format!("# {}\n\n", context.setlist.title);
+            out.push_str(&format!("| {} |\n", PROGRAM_HEADERS.join(" | ")));
+            let rules: Vec<&str> = (0..PROGRAM_HEADERS.len())
+                .map(|i| if numeric(i) { "--:" } else { "---" })
+                .collect();
+            out.push_str(&format!("| {} |\n", rules.join(" | ")));
+            for row in &rows {
+                let cells: Vec<String> = row.iter().map(escape).collect();
+                out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+            out.push_str(&format!("\n{}\n", program_summary(&context.setlist)));
+            out
+        }
+        ProgramFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(Vec::new());
+            wtr.write_record([
+                "position",
+                "title",
+                "composer",
+                "key",
+                "pages",
+                "cumulative_pages",
+            ])?;
+            for row in &rows {
+                wtr.write_record(row)?;
+            }
+            let bytes = wtr
+                .into_inner()
+                .map_err(|e| ForScoreError::Other(e.to_string()))?;
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_template() {
+        let dir = std::env::temp_dir().join(format!("forscore-template-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let context = json!({
+            "items": [{"title": "Prelude & Fugue", "composers": [{"name": "Bach"}, {"name": "Busoni"}]}]
+        });
+
+        let text = dir.join("program.txt.hbs");
+        std::fs::write(
+            &text,
+            "{{#each items}}{{title}} - {{join composers \"/\"}}{{/each}}",
+        )
+        .unwrap();
+        assert_eq!(
+            render_template(&text, &context).unwrap(),
+            "Prelude & Fugue - Bach/Busoni"
+        );
+
+        let html = dir.join("program.html.hbs");
+        std::fs::write(&html, "{{#each items}}{{title}}{{/each}}").unwrap();
+        assert_eq!(
+            render_template(&html, &context).unwrap(),
+            "Prelude &amp; Fugue"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_program() {
+        let item = |position: usize, title: &str, composer: Option<&str>, pages: Option<i32>| {
+            ProgramItem {
+                position,
+                id: position as i64,
+                title: title.to_string(),
+                path: format!("{}.pdf", title),
+                is_bookmark: false,
+                composers: composer
+                    .map(|name| ProgramComposer {
+                        name: name.to_string(),
+                        years: None,
+                        nationality: None,
+                    })
+                    .into_iter()
+                    .collect(),
+                genres: Vec::new(),
+                keywords: Vec::new(),
+                labels: Vec::new(),
+                key: (position == 1).then(|| "C Major".to_string()),
+                rating: None,
+                difficulty: None,
+                bpm: None,
+                pages,
+                cumulative_pages: pages.map(|p| p + 2 * (position as i32 - 1)),
+                duration: None,
+                duration_seconds: None,
+                notes: Vec::new(),
+            }
+        };
+        let context = ProgramContext {
+            setlist: ProgramSetlist {
+                id: 1,
+                title: "Recital".to_string(),
+                item_count: 2,
+                total_pages: 12,
+                total_duration: "9:30".to_string(),
+            },
+            items: vec![
+                item(1, "Prelude", Some("Bach"), Some(2)),
+                item(2, "Rock | Roll", None, Some(10)),
+            ],
+        };
+
+        assert_eq!(
+            render_program(&context, ProgramFormat::Txt).unwrap(),
+            "Recital\n2 items, 12 pages, 9:30\n\n\
+             #  Title        Composer  Key      Pages  Total\n\
+             1  Prelude      Bach      C Major      2      2\n\
+             2  Rock | Roll                        10     12\n"
+        );
+        assert_eq!(
+            render_program(&context, ProgramFormat::Md).unwrap(),
+            "# Recital\n\n\
+             | # | Title | Composer | Key | Pages | Total |\n\
+             | --: | --- | --- | --- | --: | --: |\n\
+             | 1 | Prelude | Bach | C Major | 2 | 2 |\n\
+             | 2 | Rock \\| Roll |  |  | 10 | 12 |\n\
+             \n2 items, 12 pages, 9:30\n"
+        );
+        assert_eq!(
+            render_program(&context, ProgramFormat::Csv).unwrap(),
+            "position,title,composer,key,pages,cumulative_pages\n\
+             1,Prelude,Bach,C Major,2,2\n\
+             2,Rock | Roll,,,10,12\n"
+        );
+    }
+}
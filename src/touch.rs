@@ -0,0 +1,86 @@
+//! Marking changes so forScore's sync notices them
+//!
+//! forScore's sync engine decides what changed from each row's ZMODIFIED and
+//! Z_OPT, and iCloud from the modification times of the sidecar files. Every
+//! command that changes a score, bookmark or setlist goes through these
+//! helpers, which bump all of them and record the change for `hooks`.
+
+use crate::db::{core_data_timestamp, entity};
+use crate::error::Result;
+use crate::hooks::{record_edit, record_setlist_change};
+use crate::itm::itm_path_for_score;
+use crate::setlist_sync::setlist_file_path;
+use rusqlite::{Connection, OptionalExtension};
+use std::fs::File;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Mark a score or bookmark as modified: its ZMODIFIED and Z_OPT, its
+/// score's too for a bookmark (forScore syncs bookmarks with their score),
+/// and the score's ITM file
+pub fn touch_item(conn: &Connection, item_id: i64) -> Result<()> {
+    let item: Option<(i32, Option<i64>, Option<String>)> = conn
+        .query_row(
+            "SELECT i.Z_ENT, i.ZSCORE, COALESCE(s.ZPATH, i.ZPATH)
+             FROM ZITEM i LEFT JOIN ZITEM s ON i.ZSCORE = s.Z_PK
+             WHERE i.Z_PK = ?",
+            [item_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+
+    let timestamp = core_data_timestamp();
+    let mut touched = vec![item_id];
+    if let Some((ent, Some(score_id), _)) = &item {
+        if *ent == entity::bookmark() {
+            touched.push(*score_id);
+        }
+    }
+    for id in touched {
+        conn.execute(
+            "UPDATE ZITEM SET ZMODIFIED = ?, Z_OPT = Z_OPT + 1 WHERE Z_PK = ?",
+            rusqlite::params![timestamp, id],
+        )?;
+    }
+
+    if let Some((_, _, Some(path))) = &item {
+        if let Ok(itm) = itm_path_for_score(path) {
+            touch_file(&itm);
+        }
+    }
+    record_edit(item_id);
+    Ok(())
+}
+
+/// Mark a setlist as modified: its Z_OPT and its .set file. A deleted
+/// setlist is only recorded
+pub fn touch_setlist(conn: &Connection, setlist_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE ZSETLIST SET Z_OPT = Z_OPT + 1 WHERE Z_PK = ?",
+        [setlist_id],
+    )?;
+    let title: Option<String> = conn
+        .query_row(
+            "SELECT ZTITLE FROM ZSETLIST WHERE Z_PK = ?",
+            [setlist_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+
+    if let Some(title) = title {
+        if let Ok(path) = setlist_file_path(&title) {
+            touch_file(&path);
+        }
+    }
+    record_setlist_change(setlist_id);
+    Ok(())
+}
+
+/// Set a sync file's modification time to now, if it exists. Best effort:
+/// the database change stands either way
+fn touch_file(path: &Path) {
+    if let Ok(file) = File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
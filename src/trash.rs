@@ -0,0 +1,444 @@
+//! Trash for deleted scores, bookmarks and setlists
+//!
+//! Before a delete removes anything, the rows it's about to remove are saved
+//! as a JSON bundle in the user's data directory, with copies of any sync
+//! files or PDFs it deletes in a folder of the same name. `trash restore`
+//! puts them back for `RETENTION_DAYS`; older bundles are cleared out the
+//! next time something is trashed.
+
+use crate::error::{ForScoreError, Result};
+use crate::itm::hex;
+use crate::models::score::{page_link_condition, ITEM_LINK_TABLES, PAGE_LINK_TABLES};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How long a trashed item can be restored
+pub const RETENTION_DAYS: i64 = 30;
+
+/// Columns pointing at a row in another table, as (table, column, target)
+const REFERENCES: &[(&str, &str, &str)] = &[
+    ("ZITEM", "ZSCORE", "ZITEM"),
+    ("ZCYLON", "ZITEM", "ZITEM"),
+    ("ZCYLON", "ZSETLIST", "ZSETLIST"),
+    ("ZTRACK", "ZITEM", "ZITEM"),
+    ("ZRECORDING", "ZITEM", "ZITEM"),
+    ("ZPAGE", "ZSCORE", "ZITEM"),
+    ("ZBUTTON", "ZPAGE", "ZPAGE"),
+    ("ZTEXTANNOTATION", "ZPAGE", "ZPAGE"),
+    ("ZLINK", "ZFROM", "ZPAGE"),
+    ("ZLINK", "ZTO", "ZPAGE"),
+    ("Z_4COMPOSERS", "Z_10COMPOSERS", "ZMETA"),
+    ("Z_4LABELS", "Z_14LABELS", "ZMETA"),
+    ("Z_4LIBRARIES", "Z_7LIBRARIES", "ZLIBRARY"),
+    ("Z_4GENRES", "Z_12GENRES", "ZMETA"),
+    ("Z_4KEYWORDS", "Z_13KEYWORDS", "ZMETA"),
+];
+
+/// Tables whose rows are the trashed items themselves; the rest are links
+/// that are dropped on restore if what they link to is gone
+const ITEM_TABLES: &[&str] = &["ZITEM", "ZSETLIST"];
+
+/// A column value, as JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Cell {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    /// Hex-encoded
+    Blob {
+        blob: String,
+    },
+}
+
+impl From<Value> for Cell {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => Cell::Null,
+            Value::Integer(i) => Cell::Integer(i),
+            Value::Real(f) => Cell::Real(f),
+            Value::Text(t) => Cell::Text(t),
+            Value::Blob(b) => Cell::Blob { blob: hex(&b) },
+        }
+    }
+}
+
+impl Cell {
+    fn to_value(&self) -> Result<Value> {
+        Ok(match self {
+            Cell::Null => Value::Null,
+            Cell::Integer(i) => Value::Integer(*i),
+            Cell::Real(f) => Value::Real(*f),
+            Cell::Text(t) => Value::Text(t.clone()),
+            Cell::Blob { blob } => Value::Blob(unhex(blob)?),
+        })
+    }
+}
+
+fn unhex(s: &str) -> Result<Vec<u8>> {
+    let invalid = || ForScoreError::Other(format!("Invalid hex in trash bundle: {}", s));
+    if !s.len().is_multiple_of(2) {
+        return Err(invalid());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(invalid)
+        })
+        .collect()
+}
+
+/// One deleted row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashRow {
+    pub table: String,
+    pub values: BTreeMap<String, Cell>,
+}
+
+impl TrashRow {
+    pub fn integer(&self, column: &str) -> Option<i64> {
+        match self.values.get(column) {
+            Some(Cell::Integer(i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn text(&self, column: &str) -> Option<&str> {
+        match self.values.get(column) {
+            Some(Cell::Text(t)) => Some(t),
+            _ => None,
+        }
+    }
+}
+
+/// Everything one delete removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    /// "score", "bookmark" or "setlist"
+    pub kind: String,
+    pub item_id: i64,
+    pub title: String,
+    pub deleted_at: DateTime<Utc>,
+    /// In restore order: the item's own rows first, then their links
+    pub rows: Vec<TrashRow>,
+    /// Original paths of files copied into the bundle's folder
+    pub files: Vec<PathBuf>,
+}
+
+impl Bundle {
+    pub fn new(kind: &str, item_id: i64, title: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            item_id,
+            title: title.to_string(),
+            deleted_at: Utc::now(),
+            rows: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// The bundle's name in the trash, e.g. "20261016-174149-score-12"
+    pub fn id(&self) -> String {
+        format!(
+            "{}-{}-{}",
+            self.deleted_at.format("%Y%m%d-%H%M%S"),
+            self.kind,
+            self.item_id
+        )
+    }
+
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.deleted_at + Duration::days(RETENTION_DAYS)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at()
+    }
+
+    /// Add the rows of `table` whose `column` is `id`
+    pub fn snapshot(
+        &mut self,
+        conn: &Connection,
+        table: &str,
+        column: &str,
+        id: i64,
+    ) -> Result<()> {
+        self.snapshot_where(conn, table, &format!("{} = ?1", column), id)
+    }
+
+    /// Add the rows of `table` matching `condition`, with `id` as `?1`
+    fn snapshot_where(
+        &mut self,
+        conn: &Connection,
+        table: &str,
+        condition: &str,
+        id: i64,
+    ) -> Result<()> {
+        let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE {}", table, condition))?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = stmt.query([id])?;
+        while let Some(row) = rows.next()? {
+            let mut values = BTreeMap::new();
+            for (i, column) in columns.iter().enumerate() {
+                values.insert(column.clone(), Cell::from(row.get::<_, Value>(i)?));
+            }
+            self.rows.push(TrashRow {
+                table: table.to_string(),
+                values,
+            });
+        }
+        Ok(())
+    }
+
+    /// Add a score or bookmark row with everything `delete_item` removes
+    /// along with it
+    pub fn snapshot_item(&mut self, conn: &Connection, item_id: i64) -> Result<()> {
+        self.snapshot(conn, "ZITEM", "Z_PK", item_id)?;
+        for (table, column) in ITEM_LINK_TABLES {
+            self.snapshot(conn, table, column, item_id)?;
+        }
+        Ok(())
+    }
+
+    /// Add a score's pages with everything `delete_score` removes along
+    /// with them
+    pub fn snapshot_pages(&mut self, conn: &Connection, score_id: i64) -> Result<()> {
+        self.snapshot(conn, "ZPAGE", "ZSCORE", score_id)?;
+        for (table, columns) in PAGE_LINK_TABLES {
+            self.snapshot_where(conn, table, &page_link_condition(columns), score_id)?;
+        }
+        Ok(())
+    }
+
+    /// Keep a copy of a file the delete is about to remove
+    pub fn add_file(&mut self, path: &Path) {
+        if path.exists() {
+            self.files.push(path.to_path_buf());
+        }
+    }
+
+    /// Write the bundle and copy its files into the trash, clearing out
+    /// expired bundles first
+    pub fn save(&mut self) -> Result<()> {
+        let dir = trash_dir()?;
+        fs::create_dir_all(&dir)?;
+        purge_expired(Utc::now())?;
+
+        // IDs are reused, so a script can delete the same one twice a second
+        while dir.join(format!("{}.json", self.id())).exists() {
+            self.deleted_at += Duration::seconds(1);
+        }
+        let id = self.id();
+        if !self.files.is_empty() {
+            let files_dir = dir.join(&id);
+            fs::create_dir_all(&files_dir)?;
+            for file in &self.files {
+                fs::copy(file, files_dir.join(stored_name(file)))?;
+            }
+        }
+        fs::write(
+            dir.join(format!("{}.json", id)),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Where a file of the bundle was copied to
+    pub fn stored_file(&self, original: &Path) -> Result<PathBuf> {
+        Ok(trash_dir()?.join(self.id()).join(stored_name(original)))
+    }
+}
+
+fn stored_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Get the path to the trash folder
+pub fn trash_dir() -> Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| ForScoreError::Other("Cannot find data directory".into()))?;
+    Ok(dir.join("forscore-cli").join("trash"))
+}
+
+/// Every bundle in the trash, most recently deleted first
+pub fn list_bundles() -> Result<Vec<Bundle>> {
+    let dir = trash_dir()?;
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut bundles: Vec<Bundle> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .filter_map(|p| fs::read_to_string(p).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .collect();
+    bundles.sort_by_key(|b| std::cmp::Reverse(b.deleted_at));
+    Ok(bundles)
+}
+
+/// A bundle by its id
+pub fn load_bundle(id: &str) -> Result<Bundle> {
+    let path = trash_dir()?.join(format!("{}.json", id));
+    let json = fs::read_to_string(&path).map_err(|_| {
+        ForScoreError::Other(format!(
+            "Nothing in the trash named '{}' (see `trash ls`)",
+            id
+        ))
+    })?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Delete a bundle and its files from the trash
+pub fn remove_bundle(bundle: &Bundle) -> Result<()> {
+    let dir = trash_dir()?;
+    let id = bundle.id();
+    fs::remove_file(dir.join(format!("{}.json", id)))?;
+    let files_dir = dir.join(&id);
+    if files_dir.exists() {
+        fs::remove_dir_all(files_dir)?;
+    }
+    Ok(())
+}
+
+/// Delete bundles past the retention window, returning how many
+pub fn purge_expired(now: DateTime<Utc>) -> Result<usize> {
+    let expired: Vec<Bundle> = list_bundles()?
+        .into_iter()
+        .filter(|b| b.is_expired(now))
+        .collect();
+    for bundle in &expired {
+        remove_bundle(bundle)?;
+    }
+    Ok(expired.len())
+}
+
+/// The row a column points at, if it's gone: (target table, ID)
+fn missing_reference(conn: &Connection, row: &TrashRow) -> Result<Option<(&'static str, i64)>> {
+    for (table, column, target) in REFERENCES {
+        if row.table != *table {
+            continue;
+        }
+        let Some(id) = row.integer(column) else {
+            continue;
+        };
+        if !row_exists(conn, target, id)? {
+            return Ok(Some((target, id)));
+        }
+    }
+    Ok(None)
+}
+
+fn row_exists(conn: &Connection, table: &str, id: i64) -> Result<bool> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT 1 FROM {} WHERE Z_PK = ?", table),
+            [id],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+/// Insert a bundle's rows back, returning the links that were dropped
+/// because what they linked to is gone. Fails without writing anything if
+/// one of the items can't go back under its old ID.
+pub fn restore_rows(conn: &Connection, rows: &[TrashRow]) -> Result<Vec<TrashRow>> {
+    let tx = conn.unchecked_transaction()?;
+    let mut dropped = Vec::new();
+    for row in rows {
+        let is_item = ITEM_TABLES.contains(&row.table.as_str());
+        let pk = row.integer("Z_PK");
+        if let Some(pk) = pk.filter(|_| is_item) {
+            if row_exists(&tx, &row.table, pk)? {
+                return Err(ForScoreError::Other(format!(
+                    "{} {} has been reused since, so it can't be restored",
+                    row.table, pk
+                )));
+            }
+        }
+        if let Some((target, id)) = missing_reference(&tx, row)? {
+            if is_item {
+                return Err(ForScoreError::Other(format!(
+                    "{} {} needs {} {}, which no longer exists",
+                    row.table,
+                    pk.unwrap_or_default(),
+                    target,
+                    id
+                )));
+            }
+            dropped.push(row.clone());
+            continue;
+        }
+        let columns: Vec<&String> = row.values.keys().collect();
+        let values = row
+            .values
+            .values()
+            .map(Cell::to_value)
+            .collect::<Result<Vec<Value>>>()?;
+        // A link whose ID was taken since is left out
+        let inserted = tx.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {} ({}) VALUES ({})",
+                row.table,
+                columns
+                    .iter()
+                    .map(|c| c.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                vec!["?"; columns.len()].join(", ")
+            ),
+            params_from_iter(values),
+        )?;
+        if inserted == 0 {
+            dropped.push(row.clone());
+        }
+    }
+    tx.commit()?;
+    Ok(dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_json() {
+        let cells = vec![
+            Cell::Null,
+            Cell::Integer(42),
+            Cell::Real(780000000.5),
+            Cell::Text("Prelude".into()),
+            Cell::from(Value::Blob(vec![0x00, 0xab, 0xff])),
+        ];
+        let json = serde_json::to_string(&cells).unwrap();
+        assert_eq!(json, r#"[null,42,780000000.5,"Prelude",{"blob":"00abff"}]"#);
+        let back: Vec<Cell> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, cells);
+        assert_eq!(
+            back[4].to_value().unwrap(),
+            Value::Blob(vec![0x00, 0xab, 0xff])
+        );
+        assert!(unhex("abc").is_err());
+        assert!(unhex("zz").is_err());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let mut bundle = Bundle::new("score", 12, "Prelude");
+        bundle.deleted_at = DateTime::from_timestamp(1_790_000_000, 0).unwrap();
+        assert_eq!(bundle.id(), "20260921-141320-score-12");
+        assert!(!bundle.is_expired(bundle.deleted_at + Duration::days(29)));
+        assert!(bundle.is_expired(bundle.deleted_at + Duration::days(RETENTION_DAYS)));
+    }
+}